@@ -1,21 +1,44 @@
 use anyhow::{Context, Result, anyhow};
 use matrix_sdk::{Client, config::SyncSettings};
+use notify::{RecursiveMode, Watcher};
 use std::sync::Arc;
 use tokio::fs;
+use tokio::sync::RwLock;
 use tracing::{debug, error, info, warn};
-use uuid::Uuid;
 
 use crate::BOT_CORE;
 use crate::BotCore;
-use crate::config::BotConfig;
+use crate::bot_commands::status::BotStatus;
+use crate::config::{APP_NAME, BotConfig, SharedLiveSettings, load_config_file};
+use crate::exit::ExitReason;
+use crate::logging::{self, LogReloadHandle};
+use crate::matrix_integration::presence::{PresenceTracker, SharedPresence};
 use crate::matrix_integration::{self, ClientStoreConfig};
-use crate::storage::StorageManager;
+use crate::storage::save_schedule::{SharedSaveSchedule, next_save_at};
+use crate::storage::{StorageManager, session_id};
+use crate::task_management::TodoList;
 
 pub struct AppContext {
     pub client: Client,
     pub initial_sync_token: Option<String>,
     pub storage_manager: Arc<StorageManager>,
     pub client_store_config: ClientStoreConfig, // Added for session persistence
+    pub command_prefix: String,
+    pub live_settings: SharedLiveSettings,
+    pub report_cooldown: std::time::Duration,
+    pub max_message_bytes: usize,
+    pub max_concurrent_commands: usize,
+    pub min_power_level: Option<i64>,
+    pub command_timeout: std::time::Duration,
+    pub thread_replies: bool,
+    pub max_tasks_per_room: usize,
+    // `None` when due date reminders are turned off
+    pub reminder_interval: Option<std::time::Duration>,
+    pub reminder_lead: chrono::Duration,
+    pub save_schedule: SharedSaveSchedule,
+    pub presence: SharedPresence,
+    pub active_rooms: Vec<matrix_sdk::ruma::OwnedRoomId>,
+    pub allowed_users: Vec<matrix_sdk::ruma::OwnedUserId>,
 }
 
 /// Ensures all required application directories exist
@@ -90,10 +113,23 @@ pub async fn init_matrix_client(config: &BotConfig) -> Result<AppContext> {
     }
 
     // --- Bot's Storage Manager Setup ---
-    let app_level_session_id = Uuid::new_v4();
+    // Reused across restarts so the saves of earlier runs stay discoverable
+    let app_level_session_id = session_id::load_or_create(&config.data_dir)
+        .await
+        .context(ExitReason::StorageFatal)?;
     let storage_manager = Arc::new(
-        StorageManager::new(config.data_dir.clone(), app_level_session_id)
-            .context("Failed to create bot's StorageManager")?,
+        StorageManager::new(
+            config.data_dir.clone(),
+            app_level_session_id,
+            config.history_offload_days,
+            config.per_room_storage,
+            (config.save_debounce_secs > 0)
+                .then(|| std::time::Duration::from_secs(config.save_debounce_secs)),
+            config.max_saved_files,
+            config.storage_format,
+        )
+        .context("Failed to create bot's StorageManager")
+        .context(ExitReason::StorageFatal)?,
     );
     info!(
         "Bot StorageManager initialized. App session ID: {}",
@@ -105,26 +141,76 @@ pub async fn init_matrix_client(config: &BotConfig) -> Result<AppContext> {
         initial_sync_token,
         storage_manager,
         client_store_config, // Pass the obtained store config
+        command_prefix: config.command_prefix.clone(),
+        live_settings: Arc::new(RwLock::new(config.live_settings())),
+        report_cooldown: std::time::Duration::from_secs(config.report_cooldown_secs),
+        max_message_bytes: config.max_message_bytes,
+        max_concurrent_commands: config.max_concurrent_commands,
+        min_power_level: config.min_power_level,
+        command_timeout: std::time::Duration::from_secs(config.command_timeout_secs),
+        thread_replies: config.thread_replies,
+        max_tasks_per_room: config.max_tasks_per_room,
+        reminder_interval: (config.reminder_interval_minutes > 0)
+            .then(|| std::time::Duration::from_secs(config.reminder_interval_minutes * 60)),
+        reminder_lead: chrono::Duration::hours(config.reminder_lead_hours as i64),
+        save_schedule: Arc::new(tokio::sync::Mutex::new(config.auto_save_time)),
+        active_rooms: config.active_rooms.clone(),
+        allowed_users: config.allowed_users.clone(),
+        presence: Arc::new(PresenceTracker::new(
+            config.activity_presence,
+            std::time::Duration::from_secs(config.presence_idle_minutes * 60),
+        )),
     })
 }
 
 /// Setup the BotCore singleton and register event handlers
 pub async fn setup_bot_core(context: &AppContext) -> Result<()> {
     // --- Initialize BotCore (singleton) ---
+    let session_user_id = context
+        .client
+        .user_id()
+        .map(|id| id.to_string())
+        .unwrap_or_else(|| "unknown".to_owned());
     let bot_core_instance = Arc::new(BotCore::new(
         context.client.clone(),
         context.storage_manager.clone(),
+        context.command_prefix.clone(),
+        context.report_cooldown,
+        context.max_message_bytes,
+        context.presence.clone(),
+        context.active_rooms.clone(),
+        context.min_power_level,
+        context.command_timeout,
+        Arc::new(BotStatus::new(session_user_id)),
+        context.thread_replies,
+        context.max_tasks_per_room,
+        context.save_schedule.clone(),
     ));
+    if let Some(interval) = context.reminder_interval {
+        spawn_reminder_scheduler(
+            bot_core_instance.todo_lists.clone(),
+            interval,
+            context.reminder_lead,
+        );
+    }
     BOT_CORE
         .set(bot_core_instance)
         .map_err(|_| anyhow!("Failed to set BOT_CORE singleton"))?;
     info!("BotCore initialized and set globally.");
 
     // --- Register Event Handlers ---
+    context
+        .client
+        .add_event_handler_context(context.live_settings.clone());
     context
         .client
         .add_event_handler(matrix_integration::on_stripped_state_member);
-    matrix_integration::register_message_handler(&context.client);
+    matrix_integration::register_message_handler(
+        &context.client,
+        context.command_prefix.clone(),
+        context.max_concurrent_commands,
+        context.allowed_users.clone(),
+    );
     info!("Matrix event handlers registered.");
 
     // --- Setup Verification Event Handlers ---
@@ -135,33 +221,262 @@ pub async fn setup_bot_core(context: &AppContext) -> Result<()> {
 
 /// Load the last saved bot state, if available
 pub async fn auto_load_bot_state(storage_manager: &Arc<StorageManager>) -> Result<()> {
-    match storage_manager.list_saved_files() {
-        Ok(files) => {
-            if let Some(most_recent_file) = files.last() {
-                info!(
-                    "Attempting to auto-load bot state from {}...",
-                    most_recent_file
+    // Unreadable saves are skipped while loading, so an error here means the data
+    // directory itself can't be used and running on would lose the saved tasks
+    if storage_manager.per_room {
+        return storage_manager
+            .load_or_migrate_rooms()
+            .await
+            .context("Failed to auto-load per-room bot state")
+            .context(ExitReason::StorageFatal);
+    }
+
+    info!("Attempting to auto-load bot state...");
+    // Each run starts a new session, so the previous runs' files are the ones to look at
+    match storage_manager
+        .load_latest_valid_any_session()
+        .await
+        .context("Failed to list saved bot state files")
+        .context(ExitReason::StorageFatal)?
+    {
+        Some(file) => info!("Successfully auto-loaded bot state from {}", file),
+        None => info!("No loadable saved bot state files found for auto-loading."),
+    }
+
+    Ok(())
+}
+
+/// Spawn the background job that writes debounced task changes to disk
+pub fn spawn_save_flusher(storage_manager: Arc<StorageManager>) {
+    let Some(interval) = storage_manager.save_debounce else {
+        return;
+    };
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        loop {
+            interval.tick().await;
+            if let Err(e) = storage_manager.flush_if_dirty().await {
+                error!("Failed to flush task changes: {}", e);
+            }
+        }
+    });
+}
+
+/// Resolves when the process is asked to stop (Ctrl-C, or SIGTERM on Unix)
+pub async fn shutdown_signal() {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            error!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(e) => {
+                error!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
+}
+
+/// Spawn the background job that reminds rooms of tasks coming due
+pub fn spawn_reminder_scheduler(
+    todo_lists: Arc<TodoList>,
+    interval: std::time::Duration,
+    lead: chrono::Duration,
+) {
+    tokio::spawn(async move {
+        // The first scan waits a full interval, giving startup time to load the saved lists
+        let start = tokio::time::Instant::now() + interval;
+        let mut interval = tokio::time::interval_at(start, interval);
+        loop {
+            interval.tick().await;
+            match todo_lists.send_due_reminders(lead).await {
+                Ok(0) => debug!("No tasks to remind about"),
+                Ok(count) => info!(count, "Sent due date reminders"),
+                Err(e) => error!("Failed to send due date reminders: {}", e),
+            }
+        }
+    });
+    info!(
+        "Due date reminder scheduler started (lead={}h)",
+        lead.num_hours()
+    );
+}
+
+/// Spawn the background job that brings snoozed tasks back once their time has come
+pub fn spawn_snooze_waker(todo_lists: Arc<TodoList>) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60));
+        loop {
+            interval.tick().await;
+            match todo_lists.wake_snoozed_tasks().await {
+                Ok(0) => {}
+                Ok(count) => info!(count, "Woke up snoozed tasks"),
+                Err(e) => error!("Failed to wake up snoozed tasks: {}", e),
+            }
+        }
+    });
+}
+
+/// Spawn the job behind `--auto-save-time` and `!bot schedule save`: a full save
+/// every day at the scheduled UTC time. The schedule is re-read at least every
+/// minute, so changes apply without a restart.
+pub fn spawn_save_scheduler(storage_manager: Arc<StorageManager>, schedule: SharedSaveSchedule) {
+    const POLL: std::time::Duration = std::time::Duration::from_secs(60);
+    tokio::spawn(async move {
+        loop {
+            let Some(time) = *schedule.lock().await else {
+                tokio::time::sleep(POLL).await;
+                continue;
+            };
+            let now = chrono::Utc::now();
+            let wait = (next_save_at(now, time) - now).to_std().unwrap_or_default();
+            if wait > POLL {
+                tokio::time::sleep(POLL).await;
+                continue;
+            }
+            tokio::time::sleep_until(tokio::time::Instant::now() + wait).await;
+            // Turned off or moved while waiting
+            if *schedule.lock().await != Some(time) {
+                continue;
+            }
+            // Like `!bot save`, this also writes out debounced changes
+            match storage_manager.flush().await {
+                Ok(filename) => info!(filename, "Scheduled save written"),
+                Err(e) => error!("Scheduled save failed: {}", e),
+            }
+        }
+    });
+}
+
+/// Spawn the background job that writes a weekly archival snapshot
+pub fn spawn_archive_scheduler(storage_manager: Arc<StorageManager>, compress: bool) {
+    tokio::spawn(async move {
+        // Checking hourly keeps the job cheap while still catching up soon after downtime
+        let mut interval = tokio::time::interval(std::time::Duration::from_secs(60 * 60));
+        loop {
+            interval.tick().await;
+            match storage_manager.archive_if_due(compress).await {
+                Ok(Some(entry)) => info!(
+                    archive = %entry.path,
+                    size = entry.size,
+                    "Weekly archival snapshot written"
+                ),
+                Ok(None) => debug!("Archival snapshot not due yet"),
+                Err(e) => error!("Failed to write archival snapshot: {}", e),
+            }
+        }
+    });
+    info!("Weekly archive scheduler started (compress={})", compress);
+}
+
+/// Watch the config file and apply changes to the reloadable settings
+/// (log level, retries, retry delay, welcome text) while the bot runs.
+/// Settings such as the homeserver or credentials are only read at startup.
+pub fn spawn_config_watcher(
+    config: &BotConfig,
+    live_settings: SharedLiveSettings,
+    log_handle: LogReloadHandle,
+) -> Result<()> {
+    let path = config
+        .config_file
+        .clone()
+        .ok_or_else(|| anyhow!("--watch-config requires --config"))?;
+    let file_name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Config path has no file name: {}", path.display()))?
+        .to_owned();
+    // Editors often replace the file instead of writing in place, so watch its directory
+    let watch_dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => std::path::PathBuf::from("."),
+    };
+
+    let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+    let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+        if let Ok(event) = res
+            && event
+                .paths
+                .iter()
+                .any(|p| p.file_name() == Some(file_name.as_os_str()))
+        {
+            let _ = tx.send(());
+        }
+    })
+    .context("Failed to create config file watcher")?;
+    watcher
+        .watch(&watch_dir, RecursiveMode::NonRecursive)
+        .with_context(|| format!("Failed to watch {}", watch_dir.display()))?;
+
+    let startup_settings = config.live_settings();
+    let debug = config.debug;
+    let mut current_file = load_config_file(&path)?;
+    info!("Watching config file {} for changes", path.display());
+
+    tokio::spawn(async move {
+        // Keep the watcher alive for as long as this task runs
+        let _watcher = watcher;
+        while rx.recv().await.is_some() {
+            // A single save usually produces a burst of events; let it settle
+            tokio::time::sleep(std::time::Duration::from_millis(500)).await;
+            while rx.try_recv().is_ok() {}
+
+            let new_file = match load_config_file(&path) {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("Ignoring config file change: {:#}", e);
+                    continue;
+                }
+            };
+
+            let restart_required = new_file.restart_required_changes(&current_file);
+            if !restart_required.is_empty() {
+                warn!(
+                    "Config file changed settings that require a restart: {}",
+                    restart_required.join(", ")
                 );
-                match storage_manager.load(most_recent_file).await {
-                    Ok(true) => info!(
-                        "Successfully auto-loaded bot state from {}",
-                        most_recent_file
-                    ),
-                    Ok(false) => warn!(
-                        "Failed to auto-load bot state (load returned false) from {}",
-                        most_recent_file
-                    ),
-                    Err(e) => error!(
-                        "Error auto-loading bot state from {}: {}",
-                        most_recent_file, e
-                    ),
+            }
+
+            let new_settings = startup_settings.with_file(&new_file);
+            let mut settings = live_settings.write().await;
+            let changes = new_settings.changes_from(&settings);
+
+            if new_settings.log_level != settings.log_level {
+                let filter =
+                    logging::build_filter(APP_NAME, debug, new_settings.log_level.as_deref())
+                        .and_then(|filter| {
+                            log_handle
+                                .reload(filter)
+                                .map_err(|e| anyhow!(e.to_string()))
+                        });
+                if let Err(e) = filter {
+                    error!("Failed to apply new log level: {}", e);
                 }
+            }
+
+            if changes.is_empty() {
+                debug!("Config file changed, no reloadable settings differ");
             } else {
-                info!("No saved bot state files found for auto-loading.");
+                info!("Reloaded config: {}", changes.join("; "));
             }
+            *settings = new_settings;
+            current_file = new_file;
         }
-        Err(e) => error!("Failed to list saved bot state files: {}", e),
-    }
+    });
 
     Ok(())
 }
@@ -169,29 +484,68 @@ pub async fn auto_load_bot_state(storage_manager: &Arc<StorageManager>) -> Resul
 /// Start the main sync loop with connection monitoring
 pub async fn start_sync_loop(context: &AppContext, config: &BotConfig) -> Result<()> {
     // --- Connection Monitor Setup ---
-    let mut connection_monitor = matrix_integration::ConnectionMonitor::new(config.max_retries);
+    let mut connection_monitor = matrix_integration::ConnectionMonitor::new(
+        config.max_retries,
+        config.retry_delay_secs * 1000,
+    );
     info!(
         "Connection monitor initialized with max_retries={}",
         config.max_retries
     );
     connection_monitor.connection_successful(); // Mark initial connection as successful
 
+    spawn_archive_scheduler(context.storage_manager.clone(), config.archive_compress);
+    spawn_save_flusher(context.storage_manager.clone());
+    matrix_integration::board::spawn_board_mirror(
+        context.client.clone(),
+        context.storage_manager.clone(),
+    );
+    matrix_integration::presence::spawn_presence_updater(
+        context.client.clone(),
+        context.presence.clone(),
+    );
+
     // --- Sync Loop ---
     let sync_settings = context
         .initial_sync_token
         .as_ref()
         .map(|token| SyncSettings::default().token(token.clone()))
-        .unwrap_or_default();
+        .unwrap_or_default()
+        .set_presence(context.presence.sync_presence());
 
     // Use modularized sync loop function with connection monitor
     let session_file_path = config.get_session_file_path(); // Get session file path
 
+    let mut sync_journal = matrix_integration::sync_journal::SyncJournal::open(&config.data_dir);
+
+    // The signal is forwarded so the loop can stop between steps, never mid session write
+    let (shutdown_tx, shutdown_rx) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        let _ = shutdown_tx.send(true);
+    });
+
+    let bot_core = BOT_CORE
+        .get()
+        .ok_or_else(|| anyhow!("BotCore must be set up before the sync loop starts"))?;
+    let status = bot_core.status.clone();
+    spawn_snooze_waker(bot_core.todo_lists.clone());
+    spawn_save_scheduler(
+        context.storage_manager.clone(),
+        context.save_schedule.clone(),
+    );
+
     matrix_integration::start_sync_loop(
         context.client.clone(),
         sync_settings,
         &mut connection_monitor,
         &session_file_path,           // Pass session file path
         &context.client_store_config, // Pass client store config
+        &mut sync_journal,
+        &context.live_settings,
+        &context.presence,
+        &status,
+        shutdown_rx,
     )
     .await
 }
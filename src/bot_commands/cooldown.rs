@@ -0,0 +1,190 @@
+use matrix_sdk::ruma::OwnedRoomId;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Commands that build whole-room reports and get a per-room cooldown.
+/// Entries with two words match a command plus its first argument.
+pub const REPORT_COMMANDS: &[&str] = &["stats", "export", "progress", "space list", "workload"];
+
+// Bound on remembered runs per room; the oldest is evicted first
+const MAX_RUNS_PER_ROOM: usize = 16;
+
+/// Cooldown key for a report command (the command with its normalized arguments),
+/// or None if the command isn't subject to cooldowns
+pub fn report_command_key(command: &str, args: &str) -> Option<String> {
+    let args = args.split_whitespace().collect::<Vec<&str>>().join(" ");
+    let with_first_arg = match args.split_whitespace().next() {
+        Some(first) => format!("{} {}", command, first.to_lowercase()),
+        None => command.to_owned(),
+    };
+    if !REPORT_COMMANDS.contains(&command) && !REPORT_COMMANDS.contains(&with_first_arg.as_str()) {
+        return None;
+    }
+    if args.is_empty() {
+        Some(command.to_owned())
+    } else {
+        Some(format!("{} {}", command, args.to_lowercase()))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CooldownCheck {
+    /// Run the command (and remember this run)
+    Run,
+    /// The same report ran this long ago and no task changed since
+    Recent(Duration),
+}
+
+#[derive(Debug)]
+struct LastRun {
+    key: String,
+    at: Instant,
+    generation: u64,
+}
+
+/// Source of the current time; tests substitute a fake clock
+pub type Clock = Arc<dyn Fn() -> Instant + Send + Sync>;
+
+/// Remembers when report commands last ran in each room so repeats within the
+/// window can be answered without recomputing. A run is only reused while the
+/// storage generation is unchanged, i.e. no task was modified in between.
+pub struct CommandCooldowns {
+    window: Duration,
+    runs: HashMap<OwnedRoomId, VecDeque<LastRun>>,
+    clock: Clock,
+}
+
+impl CommandCooldowns {
+    pub fn new(window: Duration) -> Self {
+        Self::with_clock(window, Arc::new(Instant::now))
+    }
+
+    pub fn with_clock(window: Duration, clock: Clock) -> Self {
+        Self {
+            window,
+            runs: HashMap::new(),
+            clock,
+        }
+    }
+
+    /// Cooldowns are per room: any user repeating the report in the room hits it
+    pub fn check(&mut self, room_id: &OwnedRoomId, key: &str, generation: u64) -> CooldownCheck {
+        if self.window.is_zero() {
+            return CooldownCheck::Run;
+        }
+
+        let now = (self.clock)();
+        let runs = self.runs.entry(room_id.clone()).or_default();
+        if let Some(position) = runs.iter().position(|run| run.key == key) {
+            let run = &runs[position];
+            let elapsed = now.saturating_duration_since(run.at);
+            if elapsed < self.window && run.generation == generation {
+                return CooldownCheck::Recent(elapsed);
+            }
+            runs.remove(position);
+        }
+
+        if runs.len() == MAX_RUNS_PER_ROOM {
+            runs.pop_front();
+        }
+        runs.push_back(LastRun {
+            key: key.to_owned(),
+            at: now,
+            generation,
+        });
+        CooldownCheck::Run
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+
+    /// Cooldowns over a clock that only moves when `advance` is called
+    fn fake_clock(window_secs: u64) -> (CommandCooldowns, impl Fn(u64)) {
+        let now = Arc::new(Mutex::new(Instant::now()));
+        let clock_now = now.clone();
+        let cooldowns = CommandCooldowns::with_clock(
+            Duration::from_secs(window_secs),
+            Arc::new(move || *clock_now.lock().unwrap()),
+        );
+        let advance = move |secs| *now.lock().unwrap() += Duration::from_secs(secs);
+        (cooldowns, advance)
+    }
+
+    fn room(id: &str) -> OwnedRoomId {
+        id.try_into().unwrap()
+    }
+
+    #[test]
+    fn a_repeat_runs_again_once_the_window_expires() {
+        let (mut cooldowns, advance) = fake_clock(30);
+        let room_id = room("!room:example.org");
+        assert_eq!(cooldowns.check(&room_id, "stats", 1), CooldownCheck::Run);
+        advance(29);
+        assert_eq!(
+            cooldowns.check(&room_id, "stats", 1),
+            CooldownCheck::Recent(Duration::from_secs(29))
+        );
+        advance(1);
+        assert_eq!(cooldowns.check(&room_id, "stats", 1), CooldownCheck::Run);
+        // The run that just happened starts a new window
+        advance(10);
+        assert_eq!(
+            cooldowns.check(&room_id, "stats", 1),
+            CooldownCheck::Recent(Duration::from_secs(10))
+        );
+    }
+
+    #[test]
+    fn a_task_change_ends_the_cooldown() {
+        let (mut cooldowns, advance) = fake_clock(30);
+        let room_id = room("!room:example.org");
+        assert_eq!(cooldowns.check(&room_id, "stats", 1), CooldownCheck::Run);
+        advance(5);
+        assert_eq!(cooldowns.check(&room_id, "stats", 2), CooldownCheck::Run);
+    }
+
+    #[test]
+    fn cooldowns_are_kept_per_room_and_report_key() {
+        let (mut cooldowns, _advance) = fake_clock(30);
+        let (first, second) = (room("!first:example.org"), room("!second:example.org"));
+        // Keys don't include the sender, so another user in the room gets the cooldown
+        let alice = report_command_key("stats", "").unwrap();
+        let bob = report_command_key("stats", "").unwrap();
+        assert_eq!(cooldowns.check(&first, &alice, 1), CooldownCheck::Run);
+        assert!(matches!(
+            cooldowns.check(&first, &bob, 1),
+            CooldownCheck::Recent(_)
+        ));
+        assert_eq!(cooldowns.check(&second, &bob, 1), CooldownCheck::Run);
+
+        let export = report_command_key("export", "CSV").unwrap();
+        assert_eq!(export, "export csv");
+        assert_eq!(cooldowns.check(&first, &export, 1), CooldownCheck::Run);
+        assert_eq!(
+            cooldowns.check(&first, "export json", 1),
+            CooldownCheck::Run
+        );
+    }
+
+    #[test]
+    fn only_report_commands_have_keys() {
+        assert_eq!(
+            report_command_key("space", "  LIST  all "),
+            Some("space list all".to_owned())
+        );
+        assert_eq!(report_command_key("space", "join"), None);
+        assert_eq!(report_command_key("add", "Buy milk"), None);
+    }
+
+    #[test]
+    fn a_zero_window_never_holds_back() {
+        let (mut cooldowns, _advance) = fake_clock(0);
+        let room_id = room("!room:example.org");
+        assert_eq!(cooldowns.check(&room_id, "stats", 1), CooldownCheck::Run);
+        assert_eq!(cooldowns.check(&room_id, "stats", 1), CooldownCheck::Run);
+    }
+}
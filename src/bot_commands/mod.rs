@@ -1,12 +1,47 @@
+use crate::matrix_integration::media::{MediaDeleter, SynapseAdminMedia};
+use crate::matrix_integration::presence::{PresenceTracker, SharedPresence};
+use crate::messaging::THREAD_ROOT;
+use crate::messaging::render::escape_html;
 use crate::storage::StorageManager;
-use crate::task_management::TodoList;
+use crate::storage::active_rooms::{admits_command, is_activate_command, is_room_active};
+use crate::storage::admins::AdminRemoval;
+use crate::storage::aliases::validate_alias_name;
+use crate::storage::cold_history::sanitize_room_id;
+use crate::storage::diff;
+use crate::storage::media::{MAX_MEDIA_AGE_DAYS, MediaPrune};
+use crate::storage::save_schedule::{SharedSaveSchedule, parse_save_time};
+use crate::storage::settings::{
+    QuickActionStyle, RenderStyle, ResponseFormat, format_utc_offset, parse_utc_offset,
+};
+use crate::task_management::import;
+use crate::task_management::list_view::ListFilter;
+use crate::task_management::undo::{UndoAction, UndoEntry, UndoHistory};
+use crate::task_management::{
+    ARCHIVE_LIST_DEFAULT, MoveTarget, RoomStats, Task, TaskStatus, TodoList,
+};
 use anyhow::Result;
 use async_trait::async_trait;
 use matrix_sdk::{
-    Client,
-    ruma::{OwnedRoomId, RoomId},
+    Client, RoomState,
+    attachment::AttachmentConfig,
+    ruma::{
+        OwnedEventId, OwnedRoomId, OwnedRoomOrAliasId, RoomId, UserId,
+        events::room::member::MembershipState,
+    },
 };
+use once_cell::sync::Lazy;
+use regex::Regex;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tracing::{debug, info, warn};
+use watchdog::{CommandWatchdog, LONG_RUNNING_AFTER};
+
+pub mod cooldown;
+pub mod status;
+pub mod watchdog;
+use cooldown::{CommandCooldowns, CooldownCheck, report_command_key};
+use status::BotStatus;
 
 #[async_trait]
 pub trait BotCommand: Send + Sync {
@@ -15,32 +50,67 @@ pub trait BotCommand: Send + Sync {
         room_id: &RoomId,
         message: &str,
         html_message: Option<String>,
-    ) -> Result<()>;
+    ) -> Result<OwnedEventId>;
 }
 
 #[derive(Clone)]
 pub struct BotManagement {
+    client: Client,
     message_sender: Arc<dyn crate::messaging::MessageSender>,
     pub storage: Arc<StorageManager>,
+    undo: UndoHistory,
+    media: Arc<dyn MediaDeleter>,
+    // Limit `!bot import` enforces on the size of a room's list
+    max_tasks_per_room: usize,
+    // Daily save time, read by `app::spawn_save_scheduler`
+    save_schedule: SharedSaveSchedule,
 }
 
 impl BotManagement {
-    pub fn new(client: Client, storage: Arc<StorageManager>) -> Self {
+    pub fn new(
+        client: Client,
+        storage: Arc<StorageManager>,
+        max_message_bytes: usize,
+        undo: UndoHistory,
+        max_tasks_per_room: usize,
+        save_schedule: SharedSaveSchedule,
+    ) -> Self {
         // Create a message sender for this instance
-        let message_sender = Arc::new(crate::messaging::MatrixMessageSender::new(client));
+        let media = Arc::new(SynapseAdminMedia::new(client.clone()));
+        let message_sender = Arc::new(crate::messaging::MatrixMessageSender::new(
+            client.clone(),
+            storage.clone(),
+            max_message_bytes,
+        ));
         Self {
+            client,
             message_sender,
             storage,
+            undo,
+            media,
+            max_tasks_per_room,
+            save_schedule,
         }
     }
 
     pub async fn clear_tasks(&self, room_id: &OwnedRoomId) -> Result<()> {
         let mut todo_lists = self.storage.todo_lists.lock().await;
         if todo_lists.contains_key(room_id) && !todo_lists[room_id].is_empty() {
-            todo_lists.insert(room_id.clone(), Vec::new());
+            // Keep the ID counter so cleared IDs are never handed out again
+            let previous = todo_lists
+                .get_mut(room_id)
+                .map(|list| std::mem::take(&mut list.tasks))
+                .unwrap_or_default();
             let message = "🗑️ List Cleared: The room's to-do list has been cleared.";
             self.send_matrix_message(room_id, message, None).await?;
-            self.storage.save().await?;
+            drop(todo_lists);
+            self.undo
+                .push(
+                    room_id,
+                    UndoEntry::new("clearing the list", vec![UndoAction::RestoreList(previous)]),
+                )
+                .await;
+            self.storage.save_room(room_id).await?;
         } else {
             let message = "ℹ️ Info: There are no tasks in this room's to-do list to clear.";
             self.send_matrix_message(room_id, message, None).await?;
@@ -48,8 +118,341 @@ impl BotManagement {
         Ok(())
     }
 
+    /// `!bot cleardone`: drop every done task. Remaining tasks keep their IDs,
+    /// other commands and task dependencies refer to them.
+    pub async fn clear_done_tasks(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let removed = self
+            .remove_tasks_where(room_id, "clearing done tasks", |task| {
+                task.status == TaskStatus::Done
+            })
+            .await?;
+        let message = if removed == 0 {
+            "ℹ️ Info: There are no done tasks to clear.".to_owned()
+        } else {
+            format!("🧹 Removed {} done task(s).", removed)
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// `!bot clearold <days>`: drop done and closed tasks whose last status change
+    /// is more than `days` days old. Tasks without a dated status change stay.
+    pub async fn clear_old_tasks(&self, room_id: &OwnedRoomId, days: &str) -> Result<()> {
+        let Some(cutoff) = clear_old_cutoff(days, chrono::Utc::now()) else {
+            let message =
+                "⚠️ Error: Usage: !bot clearold <days> (a number of days within the calendar)";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        };
+        let removed = self
+            .remove_tasks_where(room_id, "clearing old tasks", |task| {
+                task.completed_at.is_some_and(|at| at < cutoff)
+            })
+            .await?;
+        let message = if removed == 0 {
+            format!(
+                "ℹ️ Info: No done or closed tasks are older than {} day(s).",
+                days
+            )
+        } else {
+            format!(
+                "🧹 Removed {} task(s) finished more than {} day(s) ago.",
+                removed, days
+            )
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    // Remove the matching tasks, keeping the previous list for `!undo`
+    async fn remove_tasks_where(
+        &self,
+        room_id: &OwnedRoomId,
+        description: &str,
+        matches: impl Fn(&Task) -> bool,
+    ) -> Result<usize> {
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let Some(list) = todo_lists.get_mut(room_id) else {
+            return Ok(0);
+        };
+        let previous = list.tasks.clone();
+        list.tasks.retain(|task| !matches(task));
+        let removed = previous.len() - list.tasks.len();
+        drop(todo_lists);
+        if removed > 0 {
+            self.undo
+                .push(
+                    room_id,
+                    UndoEntry::new(description, vec![UndoAction::RestoreList(previous)]),
+                )
+                .await;
+            self.storage.save_room(room_id).await?;
+        }
+        Ok(removed)
+    }
+
+    /// `!dm`: open the sender's private list in a direct chat with the bot, or
+    /// point to the one they already have
+    pub async fn dm_command(&self, room_id: &OwnedRoomId, sender: &str) -> Result<()> {
+        if self.storage.dm_owner(room_id).await.as_deref() == Some(sender) {
+            let message = "ℹ️ Info: This room already is your private to-do list.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+        if let Some(dm_room_id) = self.storage.dm_room_of(sender).await
+            && self
+                .client
+                .get_room(&dm_room_id)
+                .is_some_and(|room| room.state() != RoomState::Left)
+        {
+            let message = format!(
+                "📬 You already have a private to-do list in {}.",
+                dm_room_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+        let user_id = UserId::parse(sender)?;
+        let dm_room = match self.client.create_dm(&user_id).await {
+            Ok(room) => room,
+            Err(e) => {
+                warn!(room_id = %room_id, sender, "Failed to create DM room: {}", e);
+                let message = format!("❌ Error: Could not open a direct message: {}", e);
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            }
+        };
+        self.setup_dm_room(&dm_room.room_id().to_owned(), sender)
+            .await?;
+        let message =
+            "📬 Opened a private to-do list for you, accept the invite to start using it.";
+        self.send_matrix_message(room_id, message, None).await?;
+        Ok(())
+    }
+
+    /// `!notify <id>`: send each user mentioned in the task a direct message about it
+    pub async fn notify_command(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: &str,
+        task_id: usize,
+    ) -> Result<()> {
+        let task = self
+            .storage
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .and_then(|tasks| tasks.find(task_id).cloned());
+        let Some(task) = task else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        // Neither the bot nor the person asking needs a message about it
+        let bot_id = self.client.user_id().map(|id| id.to_string());
+        let users: Vec<String> = task
+            .mention_users()
+            .into_iter()
+            .filter(|user| user != sender && bot_id.as_ref() != Some(user))
+            .collect();
+        if users.is_empty() {
+            let message = format!(
+                "ℹ️ Info: Task #{} doesn't mention anyone else to notify.",
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
+        let notice = format!(
+            "🔔 {} wants you to know about task #{} in {}: **{}**",
+            sender, task_id, room_id, task.title
+        );
+        let mut notified = Vec::new();
+        let mut failed = Vec::new();
+        for user in users {
+            match self.dm_room_with(&user).await {
+                Ok(dm_room_id) => {
+                    match self.send_matrix_message(&dm_room_id, &notice, None).await {
+                        Ok(_) => notified.push(user),
+                        Err(e) => {
+                            warn!(room_id = %room_id, user, "Failed to send task notification: {}", e);
+                            failed.push(user);
+                        }
+                    }
+                }
+                Err(e) => {
+                    warn!(room_id = %room_id, user, "Failed to open DM for notification: {}", e);
+                    failed.push(user);
+                }
+            }
+        }
+
+        let mut message = String::new();
+        if !notified.is_empty() {
+            message.push_str(&format!(
+                "📨 Notified about task #{}: {}",
+                task_id,
+                notified.join(", ")
+            ));
+        }
+        if !failed.is_empty() {
+            if !message.is_empty() {
+                message.push('\n');
+            }
+            message.push_str(&format!("⚠️ Error: Could not notify {}", failed.join(", ")));
+        }
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// Resolve the room `!moveto`/`!copyto` hands a task to. Refuses, with a reply
+    /// in `room_id`, unless both the bot and `sender` have joined it.
+    pub async fn transfer_target(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: &str,
+        target: &str,
+    ) -> Result<Option<OwnedRoomId>> {
+        let refusal = match self.joined_target(sender, target).await {
+            Ok(target_id) if &target_id == room_id => {
+                "⚠️ Error: The task is already in this room.".to_owned()
+            }
+            Ok(target_id) => return Ok(Some(target_id)),
+            Err(reason) => format!("❌ Error: Can't hand the task to {}: {}", target, reason),
+        };
+        self.send_matrix_message(room_id, &refusal, None).await?;
+        Ok(None)
+    }
+
+    async fn joined_target(&self, sender: &str, target: &str) -> Result<OwnedRoomId, String> {
+        let target = OwnedRoomOrAliasId::try_from(target)
+            .map_err(|_| "not a room ID or alias.".to_owned())?;
+        let target_id = match OwnedRoomId::try_from(target) {
+            Ok(room_id) => room_id,
+            Err(alias) => {
+                self.client
+                    .resolve_room_alias(&alias)
+                    .await
+                    .map_err(|e| format!("the alias doesn't resolve ({}).", e))?
+                    .room_id
+            }
+        };
+        let room = self
+            .client
+            .get_room(&target_id)
+            .filter(|room| room.state() == RoomState::Joined)
+            .ok_or_else(|| "I haven't joined that room.".to_owned())?;
+        let user_id = UserId::parse(sender).map_err(|e| e.to_string())?;
+        let member = room
+            .get_member(&user_id)
+            .await
+            .map_err(|e| format!("its members couldn't be checked ({}).", e))?;
+        if !member.is_some_and(|m| *m.membership() == MembershipState::Join) {
+            return Err("you aren't a member of that room.".to_owned());
+        }
+        Ok(target_id)
+    }
+
+    // The user's private list room, another direct chat with them, or a new one
+    async fn dm_room_with(&self, user: &str) -> Result<OwnedRoomId> {
+        if let Some(dm_room_id) = self.storage.dm_room_of(user).await
+            && self
+                .client
+                .get_room(&dm_room_id)
+                .is_some_and(|room| room.state() != RoomState::Left)
+        {
+            return Ok(dm_room_id);
+        }
+        let user_id = UserId::parse(user)?;
+        if let Some(room) = self.client.get_dm_room(&user_id) {
+            return Ok(room.room_id().to_owned());
+        }
+        let dm_room_id = self.client.create_dm(&user_id).await?.room_id().to_owned();
+        // A direct chat with the bot is the user's private list, same as with `!dm`
+        self.setup_dm_room(&dm_room_id, user).await?;
+        Ok(dm_room_id)
+    }
+
+    /// Mark a direct chat as `owner`'s private list, whether the bot or the user
+    /// started it. The owner administers the room.
+    pub async fn setup_dm_room(&self, room_id: &OwnedRoomId, owner: &str) -> Result<()> {
+        self.storage.register_dm_room(room_id, owner).await;
+        self.storage.claim_admin_if_unset(room_id, owner).await;
+        self.storage.save_room(room_id).await?;
+        let message = "🔒 This is your private to-do list. Tasks added here are only visible in this direct chat.";
+        self.send_matrix_message(room_id, message, None).await?;
+        Ok(())
+    }
+
+    pub async fn admin_command(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: &str,
+        action: &str,
+        user: Option<&str>,
+    ) -> Result<()> {
+        if action == "list" {
+            let admins = self.storage.room_admins(room_id).await;
+            let message = if admins.is_empty() {
+                "ℹ️ Info: This room has no admins yet.".to_owned()
+            } else {
+                format!("🛡️ Room Admins:\n{}", admins.join("\n"))
+            };
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
+        let user = match user.map(UserId::parse) {
+            Some(Ok(user)) => user.to_string(),
+            Some(Err(_)) | None => {
+                let message = "⚠️ Error: Missing or invalid user ID. Usage: !bot admin add|remove @user:server";
+                self.send_matrix_message(room_id, message, None).await?;
+                return Ok(());
+            }
+        };
+
+        if !self.storage.is_admin(room_id, sender).await {
+            self.permission_denied(room_id).await?;
+            return Ok(());
+        }
+
+        let message = match action {
+            "add" => {
+                if self.storage.add_admin(room_id, &user).await {
+                    format!("🛡️ {} is now an admin of this room.", user)
+                } else {
+                    format!("ℹ️ Info: {} is already an admin of this room.", user)
+                }
+            }
+            "remove" => match self.storage.remove_admin(room_id, &user).await {
+                AdminRemoval::Removed => format!("🛡️ {} is no longer an admin of this room.", user),
+                AdminRemoval::NotAdmin => {
+                    format!("ℹ️ Info: {} is not an admin of this room.", user)
+                }
+                AdminRemoval::LastAdmin => {
+                    "⚠️ Error: Cannot remove the last admin of this room.".to_owned()
+                }
+            },
+            _ => "⚠️ Error: Unknown admin action. Usage: !bot admin add|remove|list".to_owned(),
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await?;
+        Ok(())
+    }
+
+    pub async fn permission_denied(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let message = "🚫 Permission Denied: Only room admins can use this command.";
+        self.send_matrix_message(room_id, message, None).await?;
+        Ok(())
+    }
+
     pub async fn save_command(&self, room_id: &OwnedRoomId) -> Result<()> {
-        match self.storage.save().await {
+        match self.storage.flush().await {
             Ok(filename) => {
                 let message = format!(
                     "💾 Lists Saved: The to-do lists have been saved to `{}`.",
@@ -73,14 +476,59 @@ impl BotManagement {
         Ok(())
     }
 
-    pub async fn load_command(&self, room_id: &OwnedRoomId, filename: String) -> Result<()> {
+    /// `!bot schedule [save <HH:MM>|off]`: show or change the daily save time.
+    /// Changes last until a restart, `--auto-save-time` sets the one to start with.
+    pub async fn schedule_command(&self, room_id: &OwnedRoomId, args: &[&str]) -> Result<()> {
+        let message = match args {
+            [] => match *self.save_schedule.lock().await {
+                Some(time) => format!(
+                    "🕒 Lists are saved every day at {} UTC.",
+                    time.format("%H:%M")
+                ),
+                None => "ℹ️ Info: No daily save is scheduled.".to_owned(),
+            },
+            ["off"] => {
+                *self.save_schedule.lock().await = None;
+                "🕒 Daily save turned off.".to_owned()
+            }
+            ["save", time] => match parse_save_time(time) {
+                Some(time) => {
+                    *self.save_schedule.lock().await = Some(time);
+                    format!(
+                        "🕒 Lists will be saved every day at {} UTC.",
+                        time.format("%H:%M")
+                    )
+                }
+                None => format!(
+                    "⚠️ Error: Invalid time '{}'. Use HH:MM in UTC, e.g. 03:00.",
+                    time
+                ),
+            },
+            _ => "⚠️ Error: Usage: !bot schedule save <HH:MM>|off".to_owned(),
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// Load a save file of this session, or of any session with `any_session`
+    pub async fn load_command(
+        &self,
+        room_id: &OwnedRoomId,
+        filename: String,
+        any_session: bool,
+    ) -> Result<()> {
         if filename.contains("..") || filename.contains('/') {
             let message = "❌ Invalid Filename: Invalid characters detected in filename.";
             self.send_matrix_message(room_id, message, None).await?;
             return Ok(());
         }
 
-        if !self.storage.filename_pattern.is_match(&filename) {
+        let pattern = if any_session {
+            &self.storage.all_files_pattern
+        } else {
+            &self.storage.filename_pattern
+        };
+        if !pattern.is_match(&filename) {
             let message = format!(
                 "❌ Invalid Filename Format: Filename '{}' does not match the expected format.",
                 filename
@@ -94,7 +542,12 @@ impl BotManagement {
             return Ok(());
         }
 
-        match self.storage.load(&filename).await {
+        let loaded = if any_session {
+            self.storage.load_any_session(&filename).await
+        } else {
+            self.storage.load(&filename).await
+        };
+        match loaded {
             Ok(true) => {
                 let message = format!(
                     "📂 Lists Loaded: Successfully loaded to-do lists from `{}`.",
@@ -131,6 +584,16 @@ impl BotManagement {
     }
 
     pub async fn loadlast_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        // Room files are always current, "last" means reloading all of them
+        if self.storage.per_room {
+            let message = match self.storage.load_rooms().await {
+                Ok(rooms) => format!("📂 Rooms Reloaded: Loaded {} room file(s).", rooms),
+                Err(e) => format!("❌ Error Loading: Failed to reload room files: {}", e),
+            };
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
         let files = self.storage.list_saved_files()?;
 
         if files.is_empty() {
@@ -141,34 +604,84 @@ impl BotManagement {
 
         let most_recent_file = files.last().cloned().unwrap();
 
-        match self.storage.load(&most_recent_file).await {
-            Ok(true) => {
+        match self.storage.load_latest_valid().await {
+            Ok(Some(loaded_file)) => {
+                // Mention the fallback when the newest file was corrupted
+                let note = if loaded_file != most_recent_file {
+                    format!(" (`{}` failed verification)", most_recent_file)
+                } else {
+                    String::new()
+                };
                 let message = format!(
-                    "📂 Last List Loaded: Successfully loaded the most recent lists from `{}`.",
-                    most_recent_file
+                    "📂 Last List Loaded: Successfully loaded the most recent lists from `{}`{}.",
+                    loaded_file, note
                 );
                 let html_message = format!(
-                    "📂 Last List Loaded: Successfully loaded the most recent lists from <code>{}</code>.",
-                    most_recent_file
+                    "📂 Last List Loaded: Successfully loaded the most recent lists from <code>{}</code>{}.",
+                    loaded_file,
+                    note.replace('`', "")
                 );
                 self.send_matrix_message(room_id, &message, Some(html_message))
                     .await?;
             }
-            Ok(false) => {
+            Ok(None) => {
+                let message = "❌ Error Loading: None of the saved files could be loaded. They might be corrupted.";
+                self.send_matrix_message(room_id, message, None).await?;
+            }
+            Err(e) => {
                 let message = format!(
-                    "❌ Error Loading: Failed to load the most recent lists from `{}`. The file might be corrupted.",
-                    most_recent_file
-                );
-                let html_message = format!(
-                    "❌ Error Loading: Failed to load the most recent lists from <code>{}</code>. The file might be corrupted.",
-                    most_recent_file
+                    "❌ Error Loading: An error occurred while loading the most recent lists: {}",
+                    e
                 );
+                self.send_matrix_message(room_id, &message, None).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn list_archives_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        match self.storage.list_archives() {
+            Ok(entries) if entries.is_empty() => {
+                let message = "ℹ️ No Archives Found: No archival snapshots have been written yet.";
+                self.send_matrix_message(room_id, message, None).await?;
+            }
+            Ok(entries) => {
+                let describe = |size: u64, compressed: bool| {
+                    format!(
+                        "{:.1} KiB{}",
+                        size as f64 / 1024.0,
+                        if compressed { ", gzip" } else { "" }
+                    )
+                };
+                let archives_list = entries
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| {
+                        format!("{}. {} ({})", i + 1, e.date, describe(e.size, e.compressed))
+                    })
+                    .collect::<Vec<String>>()
+                    .join("\n");
+                let html_archives_list = entries
+                    .iter()
+                    .enumerate()
+                    .map(|(i, e)| {
+                        format!(
+                            "{}. <code>{}</code> ({})",
+                            i + 1,
+                            e.date,
+                            describe(e.size, e.compressed)
+                        )
+                    })
+                    .collect::<Vec<String>>()
+                    .join("<br>");
+                let message = format!("🗄️ Archival Snapshots:\n{}", archives_list);
+                let html_message = format!("🗄️ Archival Snapshots:<br>{}", html_archives_list);
                 self.send_matrix_message(room_id, &message, Some(html_message))
                     .await?;
             }
             Err(e) => {
                 let message = format!(
-                    "❌ Error Loading: An error occurred while loading the most recent lists: {}",
+                    "❌ Error Listing Archives: An error occurred while reading the archive manifest: {}",
                     e
                 );
                 self.send_matrix_message(room_id, &message, None).await?;
@@ -177,162 +690,1699 @@ impl BotManagement {
         Ok(())
     }
 
-    pub async fn list_files_command(&self, room_id: &OwnedRoomId) -> Result<()> {
-        match self.storage.list_saved_files() {
-            Ok(files) => {
-                if files.is_empty() {
-                    let message = "ℹ️ No Files Found: No saved to-do list files found.";
+    pub async fn quick_actions_command(&self, room_id: &OwnedRoomId, style: &str) -> Result<()> {
+        let Some(style) = QuickActionStyle::parse(style) else {
+            let message = "⚠️ Error: Unknown style. Usage: !bot actions off|commands|links";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        };
+
+        self.storage
+            .update_room_settings(room_id, |settings| settings.quick_actions = style)
+            .await;
+        let message = format!(
+            "⚙️ Quick actions in !list set to '{}' for this room.",
+            style.as_str()
+        );
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await?;
+        Ok(())
+    }
+
+    pub async fn response_format_command(&self, room_id: &OwnedRoomId, mode: &str) -> Result<()> {
+        let Some(format) = ResponseFormat::parse(mode) else {
+            let message = "⚠️ Error: Unknown format. Usage: !bot format plain|html|markdown";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        };
+
+        self.storage
+            .update_room_settings(room_id, |settings| settings.response_format = format)
+            .await;
+        let message = format!(
+            "⚙️ Response format set to '{}' for this room.",
+            format.as_str()
+        );
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await?;
+        Ok(())
+    }
+
+    /// `!bot set <key> <value>` for per-room display settings
+    pub async fn set_command(&self, room_id: &OwnedRoomId, key: &str, value: &str) -> Result<()> {
+        match key {
+            "style" => {
+                let Some(style) = RenderStyle::parse(value) else {
+                    let message = "⚠️ Error: Unknown style. Usage: !bot set style standard|plain";
                     self.send_matrix_message(room_id, message, None).await?;
-                } else {
-                    let files_list = files
-                        .iter()
-                        .enumerate()
-                        .map(|(i, f)| format!("{}. `{}`", i + 1, f))
-                        .collect::<Vec<String>>()
-                        .join("\n");
-                    let html_files_list = files
-                        .iter()
-                        .enumerate()
-                        .map(|(i, f)| format!("{}. <code>{}</code>", i + 1, f))
-                        .collect::<Vec<String>>()
-                        .join("<br>");
-                    let message = format!("📄 Available Save Files:\n{}", files_list);
-                    let html_message = format!("📄 Available Save Files:<br>{}", html_files_list);
-                    self.send_matrix_message(room_id, &message, Some(html_message))
-                        .await?;
-                }
+                    return Ok(());
+                };
+                self.storage
+                    .update_room_settings(room_id, |settings| settings.render_style = style)
+                    .await;
+                let message = format!(
+                    "⚙️ Rendering style set to '{}' for this room.",
+                    style.as_str()
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+                self.storage.save_room(room_id).await?;
             }
-            Err(e) => {
+            "timezone" | "tz" => {
+                let Some(offset) = parse_utc_offset(value) else {
+                    let message = "⚠️ Error: Invalid timezone. Usage: !bot set timezone <UTC offset, e.g. +02:00>";
+                    self.send_matrix_message(room_id, message, None).await?;
+                    return Ok(());
+                };
+                self.storage
+                    .update_room_settings(room_id, |settings| settings.utc_offset_minutes = offset)
+                    .await;
                 let message = format!(
-                    "❌ Error Listing Files: An error occurred while listing saved files: {}",
-                    e
+                    "⚙️ Timezone set to {} for this room.",
+                    format_utc_offset(offset)
                 );
                 self.send_matrix_message(room_id, &message, None).await?;
+                self.storage.save_room(room_id).await?;
+            }
+            _ => {
+                let message = "⚠️ Error: Unknown setting. Usage: !bot set style standard|plain or !bot set timezone <offset>";
+                self.send_matrix_message(room_id, message, None).await?;
             }
         }
         Ok(())
     }
-}
 
-#[async_trait]
-impl BotCommand for BotManagement {
-    async fn send_matrix_message(
+    pub async fn board_command(&self, room_id: &OwnedRoomId, mode: &str) -> Result<()> {
+        let enabled = match mode {
+            "on" => true,
+            "off" => false,
+            _ => {
+                let message = "⚠️ Error: Unknown mode. Usage: !bot board on|off";
+                self.send_matrix_message(room_id, message, None).await?;
+                return Ok(());
+            }
+        };
+
+        self.storage
+            .update_room_settings(room_id, |settings| settings.board_mirror = enabled)
+            .await;
+        let message = if enabled {
+            format!(
+                "📋 Task board mirror enabled. The list will be published as '{}' state events; the bot needs permission to send them.",
+                crate::matrix_integration::board::BOARD_EVENT_TYPE
+            )
+        } else {
+            "📋 Task board mirror disabled. The published board will be cleared.".to_owned()
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await?;
+        Ok(())
+    }
+
+    pub async fn sync_presence_command(
         &self,
-        room_id: &RoomId,
-        message: &str,
-        html_message: Option<String>,
+        room_id: &OwnedRoomId,
+        presence: &PresenceTracker,
+        mode: &str,
     ) -> Result<()> {
-        // Convert RoomId to OwnedRoomId for compatibility with MessageSender trait
-        let owned_room_id = room_id.to_owned();
-        // Use the MessageSender trait to send the message
-        self.message_sender
-            .send_response(&owned_room_id, message, html_message)
-            .await
+        let message = match mode {
+            "on" => {
+                presence.set_activity_based(true);
+                format!(
+                    "🟢 Presence follows activity: online after commands, unavailable after {} idle minute(s).",
+                    presence.idle_after().as_secs() / 60
+                )
+            }
+            "off" => {
+                presence.set_activity_based(false);
+                "🟢 Presence set to always online.".to_owned()
+            }
+            _ => "⚠️ Error: Unknown mode. Usage: !bot set-sync-presence on|off".to_owned(),
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
     }
-}
-// --- BotCore Struct ---
-#[derive(Clone)]
-pub struct BotCore {
-    pub todo_lists: Arc<TodoList>,
-    pub bot_management: Arc<BotManagement>,
-}
 
-impl BotCore {
-    pub fn new(client: Client, storage_manager: Arc<StorageManager>) -> Self {
-        // Create the message sender for all components
-        let message_sender = Arc::new(crate::messaging::MatrixMessageSender::new(client.clone()));
+    pub async fn join_template_command(&self, room_id: &OwnedRoomId, name: &str) -> Result<()> {
+        let mut store = self.storage.load_templates().await?;
+        let message = if name.is_empty() {
+            "⚠️ Error: Missing template name. Usage: !bot set-task-template-on-join <name>|off"
+                .to_owned()
+        } else if name.eq_ignore_ascii_case("off") {
+            store.on_join = None;
+            self.storage.save_templates(&store).await?;
+            "⚙️ No template will be applied when the bot joins a room.".to_owned()
+        } else if !store.templates.contains_key(name) {
+            let available = store
+                .templates
+                .keys()
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(", ");
+            format!(
+                "❌ Error: Template '{}' doesn't exist. Available templates: {}",
+                name,
+                if available.is_empty() {
+                    "none"
+                } else {
+                    &available
+                }
+            )
+        } else {
+            store.on_join = Some(name.to_owned());
+            self.storage.save_templates(&store).await?;
+            format!(
+                "⚙️ Template '{}' will be applied to new rooms the bot joins.",
+                name
+            )
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
 
-        // Initialize with the message sender
-        let todo_lists = Arc::new(TodoList::new(
-            message_sender.clone(),
-            storage_manager.clone(),
-        ));
-        let bot_management = Arc::new(BotManagement::new(client.clone(), storage_manager));
+    /// Task counts summed over every room with stored tasks. The per-user
+    /// breakdowns are left out so one room never learns who is active in another.
+    pub async fn global_stats_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let (mut stats, room_count) = {
+            let todo_lists = self.storage.todo_lists.lock().await;
+            let mut stats = RoomStats::default();
+            for tasks in todo_lists.values() {
+                stats.merge(&RoomStats::from_tasks(tasks));
+            }
+            (stats, todo_lists.len())
+        };
+        stats.by_creator.clear();
+        stats.activity.clear();
+        let (message, html_message) =
+            stats.render(&format!("Statistics across {} room(s)", room_count));
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        Ok(())
+    }
 
-        Self {
-            todo_lists,
-            bot_management,
+    /// Upload the room's tasks as a JSON file
+    pub async fn export_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let tasks = self
+            .storage
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .map(|list| list.tasks.clone())
+            .unwrap_or_default();
+        if tasks.is_empty() {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list to export.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
         }
+
+        let room_id_short = room_id
+            .as_str()
+            .trim_start_matches('!')
+            .split(':')
+            .next()
+            .unwrap_or_default();
+        let filename = format!(
+            "tasks_{}_{}.json",
+            room_id_short,
+            chrono::Utc::now().format("%Y%m%d_%H%M%S")
+        );
+        let data = serde_json::to_vec_pretty(&tasks)?;
+        self.message_sender
+            .send_file(room_id, "export", &filename, "application/json", data)
+            .await?;
+        debug!(room_id = %room_id, tasks = tasks.len(), "Exported tasks as JSON");
+        Ok(())
     }
 
-    pub async fn process_command(
+    /// `!bot backup <room>`: upload this room's data, in the per-room file format, to
+    /// another room. Anyone can become admin of a fresh room, so the global save file
+    /// with every other room's tasks is never sent.
+    pub async fn backup_command(
         &self,
-        room_id_str: &str,
-        sender: String,
-        command: &str,
-        args_str: String,
+        room_id: &OwnedRoomId,
+        destination: String,
+        status: &BotStatus,
     ) -> Result<()> {
-        let room_id = room_id_str.parse::<OwnedRoomId>()?;
+        let Ok(destination) = OwnedRoomId::try_from(destination.trim()) else {
+            let message = format!(
+                "⚠️ Error: Invalid room ID '{}'. Usage: !bot backup <!room:server>",
+                destination.trim()
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        let Some(room) = self.client.get_room(&destination) else {
+            let message = format!("❌ Error: The bot is not in room {}.", destination);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        let now = chrono::Utc::now();
+        if let Err(wait) = status.start_backup_at(now) {
+            let message = format!(
+                "⏳ A backup was made less than a minute ago, try again in {} seconds.",
+                wait.num_seconds().max(1)
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
 
-        match command.trim().to_lowercase().as_str() {
-            // Task management commands
-            "add" => {
-                self.todo_lists
-                    .add_task(&room_id, sender.clone(), args_str.clone())
-                    .await?
-            }
-            "list" => self.todo_lists.list_tasks(&room_id).await?,
-            "done" => {
-                if let Some(id) = parse_task_id(args_str.trim()) {
-                    self.todo_lists
-                        .done_task(&room_id, sender.clone(), id)
-                        .await?;
-                } else {
-                    let message = "⚠️ Error: Invalid task ID. Please provide a valid task number.";
-                    self.todo_lists
-                        .send_matrix_message(&room_id, message, None)
-                        .await?
-                }
+        let filename = format!(
+            "{}_room_backup_{}_{}.json",
+            env!("CARGO_PKG_NAME"),
+            sanitize_room_id(room_id),
+            now.format("%Y-%m-%d_%H-%M-%SZ")
+        );
+        let data = serde_json::to_vec_pretty(&self.storage.room_data(room_id).await)?;
+        let size = data.len();
+        room.send_attachment(
+            &filename,
+            &mime::APPLICATION_JSON,
+            data,
+            AttachmentConfig::new(),
+        )
+        .await?;
+        info!(
+            room_id = %room_id,
+            destination = %destination,
+            file_name = %filename,
+            bytes = size,
+            "Uploaded room backup"
+        );
+        let message = format!("💾 Backed up {} to {}", filename, destination);
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// `!bot import <json>`: append tasks described as JSON objects, created by the bot
+    pub async fn import_command(&self, room_id: &OwnedRoomId, payload: &str) -> Result<()> {
+        let entries = match import::parse_task_imports(payload) {
+            Ok(entries) => entries,
+            Err(reason) => {
+                let message = format!(
+                    "❌ Error: {} Usage: !bot import [{{\"title\": \"...\", \"status\": \"pending\", \"logs\": [\"...\"]}}]",
+                    reason
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
             }
-            "close" => {
-                if let Some(id) = parse_task_id(args_str.trim()) {
-                    self.todo_lists
-                        .close_task(&room_id, sender.clone(), id)
-                        .await?;
+        };
+        let creator = self
+            .client
+            .user_id()
+            .map(|id| id.to_string())
+            .ok_or_else(|| anyhow::anyhow!("Client has no user ID"))?;
+
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let list = todo_lists.entry(room_id.clone()).or_default();
+        let total = list.len() + entries.len();
+        if total > self.max_tasks_per_room {
+            let message = format!(
+                "❌ Error: Importing {} task(s) would bring this room to {} tasks, the limit is {}.",
+                entries.len(),
+                total,
+                self.max_tasks_per_room
+            );
+            drop(todo_lists);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+        let mut added = Vec::new();
+        for entry in entries {
+            let id = list.allocate_id();
+            list.push(entry.into_task(&creator, id));
+            added.push(id);
+        }
+        drop(todo_lists);
+
+        info!(room_id = %room_id, added = added.len(), "Imported tasks from JSON");
+        let message = format!("📥 Imported {} task(s).", added.len());
+        self.send_matrix_message(room_id, &message, None).await?;
+        let description = format!("import of {} task(s)", added.len());
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(description, vec![UndoAction::RemoveTasks(added)]),
+            )
+            .await;
+        self.storage.save_room(room_id).await
+    }
+
+    /// Repair duplicate task IDs and the ID counter of this room
+    pub async fn reindex_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let report = self.storage.reindex(room_id).await;
+        if report.is_clean() {
+            let message = "ℹ️ Info: Task IDs in this room are consistent, nothing to fix.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+
+        let mut lines = vec!["🔧 Reindexed this room's tasks:".to_owned()];
+        if let Some((old, new)) = report.counter_fixed {
+            lines.push(format!("• Next task ID moved from {} to {}", old, new));
+        }
+        for (old, new) in &report.renumbered {
+            lines.push(format!(
+                "• Task with duplicate ID {} is now task {}",
+                old, new
+            ));
+        }
+        self.send_matrix_message(room_id, &lines.join("\n"), None)
+            .await?;
+        self.storage.save_room(room_id).await
+    }
+
+    /// `!bot alias set <alias> <command>`
+    pub async fn alias_set(&self, room_id: &OwnedRoomId, alias: &str, command: &str) -> Result<()> {
+        let message = if let Err(reason) = validate_alias_name(alias) {
+            format!("❌ Error: Invalid alias '{}'. {}", alias, reason)
+        } else if BUILTIN_COMMANDS.contains(&alias) {
+            format!(
+                "❌ Error: '{}' is a built-in command and can't be an alias.",
+                alias
+            )
+        } else if !BUILTIN_COMMANDS.contains(&command) {
+            format!("❌ Error: '{}' is not a command.", command)
+        } else {
+            let previous = self.storage.set_alias(room_id, alias, command).await;
+            self.storage.save_room(room_id).await?;
+            match previous {
+                Some(previous) => format!(
+                    "🔗 Alias '{}' now runs '{}' instead of '{}'.",
+                    alias, command, previous
+                ),
+                None => format!("🔗 Alias '{}' now runs '{}'.", alias, command),
+            }
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// `!bot alias remove <alias>`
+    pub async fn alias_remove(&self, room_id: &OwnedRoomId, alias: &str) -> Result<()> {
+        let message = if self.storage.remove_alias(room_id, alias).await {
+            self.storage.save_room(room_id).await?;
+            format!("🔗 Alias '{}' removed.", alias)
+        } else {
+            format!("ℹ️ Info: This room has no alias '{}'.", alias)
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// `!bot alias list`
+    pub async fn alias_list(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let mut aliases: Vec<(String, String)> = self
+            .storage
+            .room_aliases(room_id)
+            .await
+            .into_iter()
+            .collect();
+        if aliases.is_empty() {
+            let message = "ℹ️ Info: This room has no command aliases.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+        aliases.sort();
+        let lines = aliases
+            .iter()
+            .map(|(alias, command)| format!("• {} → {}", alias, command))
+            .collect::<Vec<String>>();
+        let message = format!("🔗 Command aliases:\n{}", lines.join("\n"));
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// What changed in this room since a save file was written
+    pub async fn diff_command(&self, room_id: &OwnedRoomId, filename: &str) -> Result<()> {
+        if filename.is_empty() {
+            let message = "⚠️ Error: Missing filename. Usage: !bot diff <filename>";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+        if filename.contains("..") || filename.contains('/') {
+            let message = "❌ Invalid Filename: Invalid characters detected in filename.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+        let saved = match self.storage.read_save_file(filename).await {
+            Ok(Some(data)) => data,
+            Ok(None) => {
+                let message = format!(
+                    "❌ Error Loading: Failed to read `{}`. Check the filename and ensure it's a valid save file.",
+                    filename
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            }
+            Err(e) => {
+                let message = format!("❌ Error Loading: Failed to read `{}`: {}", filename, e);
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            }
+        };
+
+        // Other rooms' task titles stay out of this room, they only show up in the count
+        let diffs = diff::diff_storage(&saved, &self.storage.snapshot().await);
+        let other_rooms = diffs.iter().filter(|d| &d.room_id != room_id).count();
+        let mut lines = vec![format!("🔍 Changes since `{}`:", filename)];
+        match diffs.iter().find(|d| &d.room_id == room_id) {
+            Some(diff) => lines.push(diff.render()),
+            None => lines.push("This room is unchanged.".to_owned()),
+        }
+        if other_rooms > 0 {
+            lines.push(format!("{} other room(s) also differ.", other_rooms));
+        }
+        self.send_matrix_message(room_id, &lines.join("\n"), None)
+            .await?;
+        Ok(())
+    }
+
+    /// `!bot media list|prune <days>` for the files the bot uploaded to this room
+    pub async fn media_command(
+        &self,
+        room_id: &OwnedRoomId,
+        action: &str,
+        arg: &str,
+    ) -> Result<()> {
+        match action {
+            "list" => {
+                let registry = self.storage.load_media_registry().await?;
+                let uploads = registry
+                    .uploads
+                    .iter()
+                    .filter(|record| &record.room_id == room_id)
+                    .map(|record| {
+                        format!(
+                            "• {} {} ({}, {} bytes) {}",
+                            record.uploaded_at.format("%Y-%m-%d %H:%M"),
+                            record.filename,
+                            record.purpose,
+                            record.size,
+                            record.mxc_uri
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                let message = if uploads.is_empty() {
+                    "ℹ️ Info: The bot has no recorded uploads in this room.".to_owned()
+                } else {
+                    format!("📎 Uploads in this room:\n{}", uploads.join("\n"))
+                };
+                self.send_matrix_message(room_id, &message, None).await?;
+                Ok(())
+            }
+            "prune" => {
+                let Some(days) = arg
+                    .parse::<u32>()
+                    .ok()
+                    .filter(|days| *days <= MAX_MEDIA_AGE_DAYS)
+                else {
+                    let message = format!(
+                        "⚠️ Error: Missing or invalid age. Usage: !bot media prune <days> (up to {})",
+                        MAX_MEDIA_AGE_DAYS
+                    );
+                    self.send_matrix_message(room_id, &message, None).await?;
+                    return Ok(());
+                };
+                let outcome = self
+                    .storage
+                    .prune_media(self.media.as_ref(), room_id, days, chrono::Utc::now())
+                    .await?;
+                let message = match outcome {
+                    MediaPrune::NothingExpired => {
+                        format!("ℹ️ Info: No uploads older than {} day(s).", days)
+                    }
+                    MediaPrune::Deleted { deleted, failed } => {
+                        let mut message = format!(
+                            "🧹 Deleted {} upload(s) older than {} day(s) from the homeserver.",
+                            deleted, days
+                        );
+                        if failed > 0 {
+                            message.push_str(&format!(
+                                " {} could not be deleted and were kept.",
+                                failed
+                            ));
+                        }
+                        message
+                    }
+                    MediaPrune::Forgotten(forgotten) => format!(
+                        "⚠️ The homeserver doesn't let the bot delete media. Forgot {} upload record(s) older than {} day(s); the files remain on the homeserver.",
+                        forgotten, days
+                    ),
+                };
+                self.send_matrix_message(room_id, &message, None).await?;
+                Ok(())
+            }
+            _ => {
+                let message = "⚠️ Error: Unknown media action. Usage: !bot media list|prune <days>";
+                self.send_matrix_message(room_id, message, None).await?;
+                Ok(())
+            }
+        }
+    }
+
+    pub async fn activate_command(
+        &self,
+        room_id: &OwnedRoomId,
+        configured: &[OwnedRoomId],
+    ) -> Result<()> {
+        let message = if configured.contains(room_id) {
+            "ℹ️ Info: This room is already active through the bot's configuration."
+        } else if self.storage.activate_room(room_id).await {
+            self.storage.save_room(room_id).await?;
+            "🟢 Room activated: the bot will respond to commands here."
+        } else {
+            "ℹ️ Info: This room is already active."
+        };
+        self.send_matrix_message(room_id, message, None).await?;
+        Ok(())
+    }
+
+    pub async fn deactivate_command(
+        &self,
+        room_id: &OwnedRoomId,
+        configured: &[OwnedRoomId],
+    ) -> Result<()> {
+        let message = if configured.contains(room_id) {
+            "⚠️ Error: This room is listed with --active-room and can only be deactivated in the configuration."
+                .to_owned()
+        } else if self.storage.deactivate_room(room_id).await {
+            self.storage.save_room(room_id).await?;
+            let activated = self.storage.activated_rooms().await;
+            if configured.is_empty() && activated.is_empty() {
+                // An empty allowlist means "everywhere", which includes this room again
+                "⚠️ Room deactivated, but the allowlist is now empty so the bot responds in every room."
+                    .to_owned()
+            } else {
+                "🔴 Room deactivated: the bot will ignore commands here until an admin runs !bot activate."
+                    .to_owned()
+            }
+        } else if configured.is_empty() && self.storage.activated_rooms().await.is_empty() {
+            "ℹ️ Info: No allowlist is set, so the bot responds in every room. Run !bot activate in the rooms it should keep serving first."
+                .to_owned()
+        } else {
+            "ℹ️ Info: This room is not active.".to_owned()
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// Uptime and session, where the bot responds and how that relates to invites
+    pub async fn status_command(
+        &self,
+        room_id: &OwnedRoomId,
+        configured: &[OwnedRoomId],
+        is_admin: bool,
+        watchdog: &CommandWatchdog,
+        status: &BotStatus,
+    ) -> Result<()> {
+        let activated = self.storage.activated_rooms().await;
+        let allowlist = if configured.is_empty() && activated.is_empty() {
+            "Allowlist: empty, the bot responds in every room it has joined".to_owned()
+        } else {
+            format!(
+                "Allowlist: {} room(s) from the configuration, {} activated with !bot activate",
+                configured.len(),
+                activated.len()
+            )
+        };
+        let mut lines = vec![
+            "🤖 Bot Status".to_owned(),
+            format!("Logged in as: {}", status.session_user_id),
+            format!("Uptime: {}", status.uptime()),
+            format!("Syncs since startup: {}", status.syncs()),
+            format!(
+                "This room: {}",
+                if is_room_active(room_id, configured, &activated) {
+                    "active"
+                } else {
+                    "inactive"
+                }
+            ),
+            allowlist,
+            "Invites: every invite is accepted; in rooms outside the allowlist the bot stays silent until an admin runs !bot activate".to_owned(),
+            format!("You are {}an admin of this room", if is_admin { "" } else { "not " }),
+            format!(
+                "Commands stopped by the watchdog since startup: {}",
+                watchdog.stuck_count()
+            ),
+        ];
+        // Other rooms' commands are shown without saying where they run
+        for (command, elapsed) in watchdog.long_running(LONG_RUNNING_AFTER) {
+            let place = if &command.room_id == room_id {
+                "this room"
+            } else {
+                "another room"
+            };
+            lines.push(format!(
+                "Long-running: {} in {} for {} seconds",
+                command.command,
+                place,
+                elapsed.as_secs()
+            ));
+        }
+        lines.push("Connection Failures:".to_owned());
+        match status.connection_failures() {
+            Some(report) => lines.extend(report.lines().map(|line| format!("  {}", line))),
+            None => lines.push("  None since startup".to_owned()),
+        }
+        // Failure types quote server errors, which may contain markup
+        let html = lines
+            .iter()
+            .map(|line| escape_html(line))
+            .collect::<Vec<String>>()
+            .join("<br>");
+        self.send_matrix_message(room_id, &lines.join("\n"), Some(html))
+            .await?;
+        Ok(())
+    }
+
+    /// `!bot rooms [page]`: the rooms the bot has joined, 20 per page
+    pub async fn list_rooms_command(&self, room_id: &OwnedRoomId, page: &str) -> Result<()> {
+        let page = if page.is_empty() {
+            1
+        } else {
+            match page.parse::<usize>() {
+                Ok(page) if page > 0 => page,
+                _ => {
+                    let message = "⚠️ Error: Invalid page number. Usage: !bot rooms [page]";
+                    self.send_matrix_message(room_id, message, None).await?;
+                    return Ok(());
+                }
+            }
+        };
+
+        let mut rooms = Vec::new();
+        for room in self.client.joined_rooms() {
+            let name = match room.display_name().await {
+                Ok(name) => name.to_string(),
+                Err(e) => {
+                    warn!(room_id = %room.room_id(), error = %e, "Failed to compute room name");
+                    String::new()
+                }
+            };
+            rooms.push((name, room.room_id().to_owned()));
+        }
+        if rooms.is_empty() {
+            let message = "ℹ️ Info: The bot hasn't joined any rooms.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+        rooms.sort();
+
+        let pages = rooms.len().div_ceil(ROOMS_PER_PAGE);
+        if page > pages {
+            let message = format!(
+                "❌ Error: Page {} doesn't exist. The room list has {} page(s).",
+                page, pages
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+        let first = (page - 1) * ROOMS_PER_PAGE;
+        let lines: Vec<String> = rooms
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(ROOMS_PER_PAGE)
+            .map(|(idx, (name, id))| format!("{}. {} ({})", idx + 1, name, id))
+            .collect();
+        let mut message = format!("🏠 Joined rooms ({}):\n{}", rooms.len(), lines.join("\n"));
+        if pages > 1 {
+            let shown = format!("{}-{}", first + 1, first + lines.len());
+            let note = if page < pages {
+                format!(
+                    "\nShowing {} of {} — use !bot rooms {} for more",
+                    shown,
+                    rooms.len(),
+                    page + 1
+                )
+            } else {
+                format!("\nShowing {} of {}", shown, rooms.len())
+            };
+            message.push_str(&note);
+        }
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// `!bot ping`: reply, then edit the reply to show how long sending it took.
+    /// Only the SDK call is timed, the homeserver doesn't echo the event back to us.
+    pub async fn ping_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let started = Instant::now();
+        let event_id = self.send_matrix_message(room_id, "🏓 Pong!", None).await?;
+        let elapsed = started.elapsed().as_millis();
+        debug!(room_id = %room_id, elapsed_ms = elapsed, "Answered ping");
+        let message = format!("🏓 Pong! ({} ms)", elapsed);
+        self.message_sender
+            .edit_message(room_id, &event_id, &message, None)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn sync_log_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        // Only the most recent entries, the full journal is available via `asmith sync-log`
+        const SHOWN_ENTRIES: usize = 10;
+
+        match crate::matrix_integration::sync_journal::read_journal(&self.storage.data_dir) {
+            Ok(entries) if entries.is_empty() => {
+                let message = "ℹ️ No sync tokens have been recorded yet.";
+                self.send_matrix_message(room_id, message, None).await?;
+            }
+            Ok(entries) => {
+                let lines = entries
+                    .iter()
+                    .rev()
+                    .take(SHOWN_ENTRIES)
+                    .map(|e| {
+                        format!(
+                            "{} — events: {}, rooms: {}, limited: {}",
+                            e.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                            e.timeline_events,
+                            e.joined_rooms,
+                            e.limited_rooms
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                let message = format!(
+                    "🔄 Recent Syncs (newest first, {} recorded):\n{}",
+                    entries.len(),
+                    lines.join("\n")
+                );
+                let html_message = format!(
+                    "🔄 Recent Syncs (newest first, {} recorded):<br>{}",
+                    entries.len(),
+                    lines.join("<br>")
+                );
+                self.send_matrix_message(room_id, &message, Some(html_message))
+                    .await?;
+            }
+            Err(e) => {
+                let message = format!(
+                    "❌ Error Reading Sync Log: An error occurred while reading the sync journal: {}",
+                    e
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+            }
+        }
+        Ok(())
+    }
+
+    pub async fn list_files_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        match self.storage.list_saved_files() {
+            Ok(files) => {
+                // Room files of private lists are labelled with their owner
+                let dm_files = self.storage.dm_room_files().await;
+                let label = |f: &String| {
+                    dm_files
+                        .get(f)
+                        .map(|owner| format!(" (DM of {})", owner))
+                        .unwrap_or_default()
+                };
+                if files.is_empty() {
+                    let message = "ℹ️ No Files Found: No saved to-do list files found.";
+                    self.send_matrix_message(room_id, message, None).await?;
+                } else {
+                    let files_list = files
+                        .iter()
+                        .enumerate()
+                        .map(|(i, f)| format!("{}. `{}`{}", i + 1, f, label(f)))
+                        .collect::<Vec<String>>()
+                        .join("\n");
+                    let html_files_list = files
+                        .iter()
+                        .enumerate()
+                        .map(|(i, f)| format!("{}. <code>{}</code>{}", i + 1, f, label(f)))
+                        .collect::<Vec<String>>()
+                        .join("<br>");
+                    let message = format!("📄 Available Save Files:\n{}", files_list);
+                    let html_message = format!("📄 Available Save Files:<br>{}", html_files_list);
+                    self.send_matrix_message(room_id, &message, Some(html_message))
+                        .await?;
+                }
+            }
+            Err(e) => {
+                let message = format!(
+                    "❌ Error Listing Files: An error occurred while listing saved files: {}",
+                    e
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl BotCommand for BotManagement {
+    async fn send_matrix_message(
+        &self,
+        room_id: &RoomId,
+        message: &str,
+        html_message: Option<String>,
+    ) -> Result<OwnedEventId> {
+        // Convert RoomId to OwnedRoomId for compatibility with MessageSender trait
+        let owned_room_id = room_id.to_owned();
+        // Use the MessageSender trait to send the message
+        self.message_sender
+            .send_reply(&owned_room_id, message, html_message)
+            .await
+    }
+}
+// --- BotCore Struct ---
+#[derive(Clone)]
+pub struct BotCore {
+    pub todo_lists: Arc<TodoList>,
+    pub bot_management: Arc<BotManagement>,
+    pub command_prefix: String,
+    cooldowns: Arc<Mutex<CommandCooldowns>>,
+    presence: SharedPresence,
+    // Rooms from `--active-room`; runtime activations live in storage
+    configured_active_rooms: Vec<OwnedRoomId>,
+    // Power level needed for destructive commands; `None` leaves them to room admins
+    min_power_level: Option<i64>,
+    pub watchdog: Arc<CommandWatchdog>,
+    pub status: Arc<BotStatus>,
+    // Reply in a thread on the command message instead of the main timeline
+    thread_replies: bool,
+}
+
+impl BotCore {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        client: Client,
+        storage_manager: Arc<StorageManager>,
+        command_prefix: String,
+        report_cooldown: Duration,
+        max_message_bytes: usize,
+        presence: SharedPresence,
+        configured_active_rooms: Vec<OwnedRoomId>,
+        min_power_level: Option<i64>,
+        command_timeout: Duration,
+        status: Arc<BotStatus>,
+        thread_replies: bool,
+        max_tasks_per_room: usize,
+        save_schedule: SharedSaveSchedule,
+    ) -> Self {
+        // Create the message sender for all components
+        let message_sender = Arc::new(crate::messaging::MatrixMessageSender::new(
+            client.clone(),
+            storage_manager.clone(),
+            max_message_bytes,
+        ));
+
+        // Initialize with the message sender
+        let todo_lists = Arc::new(TodoList::new(
+            message_sender.clone(),
+            storage_manager.clone(),
+            max_tasks_per_room,
+        ));
+        let bot_management = Arc::new(BotManagement::new(
+            client.clone(),
+            storage_manager,
+            max_message_bytes,
+            todo_lists.undo.clone(),
+            max_tasks_per_room,
+            save_schedule,
+        ));
+
+        Self {
+            todo_lists,
+            bot_management,
+            command_prefix,
+            cooldowns: Arc::new(Mutex::new(CommandCooldowns::new(report_cooldown))),
+            presence,
+            configured_active_rooms,
+            min_power_level,
+            watchdog: Arc::new(CommandWatchdog::new(command_timeout)),
+            status,
+            thread_replies,
+        }
+    }
+
+    /// Help texts are written with the default `!` prefix; swap in the configured one
+    fn with_prefix(&self, text: &str) -> String {
+        with_command_prefix(text, &self.command_prefix)
+    }
+
+    /// Whether the sender may run destructive commands: by power level when
+    /// `--min-power-level` is set, otherwise by being a room admin
+    fn may_run_destructive(&self, is_admin: bool, power_level: Option<i64>) -> bool {
+        match self.min_power_level {
+            Some(min) => power_level.is_some_and(|level| level >= min),
+            None => is_admin,
+        }
+    }
+
+    async fn destructive_denied(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: &str,
+        command: &str,
+        power_level: Option<i64>,
+    ) -> Result<()> {
+        warn!(
+            room_id = %room_id,
+            sender,
+            command,
+            power_level,
+            min_power_level = self.min_power_level,
+            "Denied destructive command"
+        );
+        match self.min_power_level {
+            Some(min) => {
+                let message = format!(
+                    "🚫 You don't have permission to use this command. It needs power level {} or higher.",
+                    min
+                );
+                self.bot_management
+                    .send_matrix_message(room_id, &message, None)
+                    .await?;
+                Ok(())
+            }
+            None => self.bot_management.permission_denied(room_id).await,
+        }
+    }
+
+    /// `process_command` under the watchdog. A command that runs past the timeout
+    /// is cancelled and the room, including its admins, is told it may have failed.
+    pub async fn run_command(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        power_level: Option<i64>,
+        command: &str,
+        args_str: String,
+        event_id: OwnedEventId,
+    ) -> Result<()> {
+        let run = self.process_command(
+            room_id.as_str(),
+            sender.clone(),
+            power_level,
+            command,
+            args_str,
+            event_id,
+        );
+        let elapsed = match self.watchdog.run(room_id, &sender, command, run).await {
+            Ok(result) => return result,
+            Err(elapsed) => elapsed,
+        };
+
+        let admins = self.bot_management.storage.room_admins(room_id).await;
+        let mut message = format!(
+            "⏱️ {}{} from {} is taking too long and was stopped after {} seconds, it may have failed.",
+            self.command_prefix,
+            command,
+            sender,
+            elapsed.as_secs()
+        );
+        if !admins.is_empty() {
+            message.push_str(&format!(" Admins: {}", admins.join(", ")));
+        }
+        // The send itself may be what hangs, so it gets a bound of its own
+        let notify = self
+            .bot_management
+            .send_matrix_message(room_id, &message, None);
+        match tokio::time::timeout(STUCK_NOTICE_TIMEOUT, notify).await {
+            Ok(Ok(_)) => {}
+            Ok(Err(e)) => warn!(room_id = %room_id, "Failed to report stuck command: {}", e),
+            Err(_) => warn!(room_id = %room_id, "Timed out reporting stuck command"),
+        }
+        Ok(())
+    }
+
+    /// `power_level` is the sender's power level in the room, if it could be read.
+    /// With thread replies on, responses go in the thread of `event_id`, the
+    /// message that carried the command. Afterwards the room's list view is
+    /// edited to reflect any changes.
+    pub async fn process_command(
+        &self,
+        room_id_str: &str,
+        sender: String,
+        power_level: Option<i64>,
+        command: &str,
+        args_str: String,
+        event_id: OwnedEventId,
+    ) -> Result<()> {
+        let dispatch = async {
+            self.dispatch_command(room_id_str, sender, power_level, command, args_str)
+                .await?;
+            // Whatever the command changed shows up in the room's last `!list`
+            let room_id = room_id_str.parse::<OwnedRoomId>()?;
+            if let Err(e) = self.todo_lists.refresh_list_view(&room_id).await {
+                warn!(room_id = %room_id, "Failed to refresh list view: {}", e);
+            }
+            Ok(())
+        };
+        if self.thread_replies {
+            THREAD_ROOT.scope(event_id, dispatch).await
+        } else {
+            dispatch.await
+        }
+    }
+
+    async fn dispatch_command(
+        &self,
+        room_id_str: &str,
+        sender: String,
+        power_level: Option<i64>,
+        command: &str,
+        args_str: String,
+    ) -> Result<()> {
+        let room_id = room_id_str.parse::<OwnedRoomId>()?;
+        self.presence.record_activity();
+        crate::exit::RUN_STATS.record_command();
+
+        // A DM owner controls their private list regardless of power levels
+        let dm_owner = self.bot_management.storage.dm_owner(&room_id).await;
+        let owns_dm = dm_owner.as_deref() == Some(sender.as_str());
+
+        // Room aliases stand in for a built-in command
+        let mut command = command.trim().to_lowercase();
+        if let Some(target) = self
+            .bot_management
+            .storage
+            .resolve_alias(&room_id, &command)
+            .await
+        {
+            debug!(room_id = %room_id, alias = %command, command = %target, "Resolved command alias");
+            command = target;
+        }
+
+        let mut is_admin = self
+            .bot_management
+            .storage
+            .is_admin(&room_id, &sender)
+            .await;
+        let activated = self.bot_management.storage.activated_rooms().await;
+        if !admits_command(
+            &room_id,
+            &self.configured_active_rooms,
+            &activated,
+            dm_owner.is_some(),
+            is_activate_command(&command, &args_str),
+            is_admin,
+        ) {
+            debug!(room_id = %room_id, command = %command, "Ignoring command in inactive room");
+            return Ok(());
+        }
+
+        // The first user to talk to the bot in a room it answers becomes its admin.
+        // Only here, past the allowlist, or anyone could claim and activate a room.
+        if self
+            .bot_management
+            .storage
+            .claim_admin_if_unset(&room_id, &sender)
+            .await
+        {
+            self.bot_management.storage.save_room(&room_id).await?;
+            is_admin = true;
+        }
+        let may_destroy = owns_dm || self.may_run_destructive(is_admin, power_level);
+
+        // Report commands are expensive; don't recompute one that was just answered
+        if let Some(key) = report_command_key(&command, &args_str) {
+            let generation = self.bot_management.storage.generation();
+            let check = self
+                .cooldowns
+                .lock()
+                .await
+                .check(&room_id, &key, generation);
+            if let CooldownCheck::Recent(elapsed) = check {
+                let message = format!(
+                    "⏳ {}{} was already computed {} seconds ago and no task changed since; see the reply above.",
+                    self.command_prefix,
+                    key,
+                    elapsed.as_secs()
+                );
+                self.todo_lists
+                    .send_matrix_message(&room_id, &message, None)
+                    .await?;
+                return Ok(());
+            }
+        }
+
+        match command.as_str() {
+            // Task management commands
+            "add" => {
+                // `--force` adds the task even if an open one has the same title
+                let (title, force) = match args_str.trim_start().strip_prefix("--force") {
+                    Some(rest) if rest.is_empty() || rest.starts_with(char::is_whitespace) => {
+                        (rest.trim_start().to_owned(), true)
+                    }
+                    _ => (args_str.clone(), false),
+                };
+                self.todo_lists
+                    .add_task(&room_id, sender.clone(), title, force)
+                    .await?
+            }
+            "list" if args_str.trim().eq_ignore_ascii_case("snoozed") => {
+                self.todo_lists.list_snoozed_tasks(&room_id).await?
+            }
+            "list"
+                if args_str
+                    .split_whitespace()
+                    .next()
+                    .is_some_and(|word| word.eq_ignore_ascii_case("sort")) =>
+            {
+                let key = args_str.trim()[4..].trim();
+                self.todo_lists.sort_list(&room_id, key).await?
+            }
+            "list" => {
+                // `!list [all|<status>] [page]`
+                let args = args_str.trim();
+                let (first, rest) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                let (filter, page_str) = if first.is_empty() || first.parse::<usize>().is_ok() {
+                    (Some(ListFilter::Open), args)
+                } else {
+                    (ListFilter::parse(first), rest.trim())
+                };
+                match (filter, page_str) {
+                    (None, _) => {
+                        let message = format!(
+                            "⚠️ Error: Unknown list filter '{}'. Use all, snoozed or a status: {}.",
+                            first,
+                            TaskStatus::LIST_ORDER
+                                .iter()
+                                .map(|s| s.as_str())
+                                .collect::<Vec<_>>()
+                                .join(", ")
+                        );
+                        self.todo_lists
+                            .send_matrix_message(&room_id, &message, None)
+                            .await?;
+                    }
+                    (Some(filter), "") => self.todo_lists.list_tasks(&room_id, 1, filter).await?,
+                    (Some(filter), page_str) => match page_str.parse::<usize>() {
+                        Ok(page) => self.todo_lists.list_tasks(&room_id, page, filter).await?,
+                        Err(_) => {
+                            let message = "⚠️ Error: Invalid page number. Format: !list 2, !list all 2 or !list pending 2";
+                            self.todo_lists
+                                .send_matrix_message(&room_id, message, None)
+                                .await?;
+                        }
+                    },
+                }
+            }
+            "done" => {
+                // A trailing `force` marks tasks done even with unfinished dependencies
+                let (ids_str, force) = match args_str.trim_end().rsplit_once(char::is_whitespace) {
+                    Some((ids, last)) if last.eq_ignore_ascii_case("force") => (ids, true),
+                    _ => (args_str.as_str(), false),
+                };
+                match parse_task_ids(ids_str).as_deref() {
+                    Some([id]) => {
+                        self.todo_lists
+                            .done_task(&room_id, sender.clone(), *id, force, None)
+                            .await?
+                    }
+                    Some(ids) => {
+                        self.todo_lists
+                            .done_tasks(&room_id, sender.clone(), ids, force)
+                            .await?
+                    }
+                    None => match parse_id_and_note(&args_str) {
+                        Some((id, note)) => {
+                            self.todo_lists
+                                .done_task(&room_id, sender.clone(), id, false, Some(note))
+                                .await?
+                        }
+                        None => {
+                            self.todo_lists
+                                .send_matrix_message(&room_id, BULK_IDS_ERROR, None)
+                                .await?;
+                        }
+                    },
+                }
+            }
+            "close" if !may_destroy => {
+                self.destructive_denied(&room_id, &sender, "close", power_level)
+                    .await?
+            }
+            "close" => match parse_task_ids(&args_str).as_deref() {
+                Some([id]) => {
+                    self.todo_lists
+                        .close_task(&room_id, sender.clone(), *id, None)
+                        .await?
+                }
+                Some(ids) => {
+                    self.todo_lists
+                        .close_tasks(&room_id, sender.clone(), ids)
+                        .await?
+                }
+                None => match parse_id_and_note(&args_str) {
+                    Some((id, note)) => {
+                        self.todo_lists
+                            .close_task(&room_id, sender.clone(), id, Some(note))
+                            .await?
+                    }
+                    None => {
+                        self.todo_lists
+                            .send_matrix_message(&room_id, BULK_IDS_ERROR, None)
+                            .await?;
+                    }
+                },
+            },
+            "move" => {
+                let args = args_str.trim();
+                let parsed = args
+                    .split_once(char::is_whitespace)
+                    .and_then(|(id, target)| {
+                        Some((parse_task_id(id)?, MoveTarget::parse(target.trim())?))
+                    });
+                if let Some((id, target)) = parsed {
+                    self.todo_lists
+                        .move_task(&room_id, sender.clone(), id, target)
+                        .await?
+                } else {
+                    let message = "⚠️ Error: Unable to parse task ID and position. Format: !move 3 1, !move 3 top or !move 3 bottom";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "spin" => {
+                let mut parts = args_str.split_whitespace();
+                let ids = (
+                    parts.next().and_then(parse_task_id),
+                    parts.next().and_then(parse_task_id),
+                );
+                if let (Some(id), Some(log_number)) = ids {
+                    self.todo_lists
+                        .spinoff_from_log(&room_id, sender.clone(), id, log_number)
+                        .await?
+                } else {
+                    let message =
+                        "⚠️ Error: Unable to parse task ID and log number. Format: !spin 1 2";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "start" => {
+                if let Some(id) = parse_task_id(args_str.trim()) {
+                    self.todo_lists
+                        .start_task(&room_id, sender.clone(), id)
+                        .await?;
+                } else {
+                    let message = "⚠️ Error: Invalid task ID. Please provide a valid task number.";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "block" => {
+                let args = args_str.trim();
+                let (id_str, reason) = match args.split_once(char::is_whitespace) {
+                    Some((id, reason)) => (id, Some(reason.trim().to_owned())),
+                    None => (args, None),
+                };
+                if let Some(id) = parse_task_id(id_str) {
+                    self.todo_lists
+                        .block_task(&room_id, sender.clone(), id, reason)
+                        .await?;
+                } else {
+                    let message = "⚠️ Error: Invalid task ID. Format: !block <id> [reason]";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "reopen" => {
+                if let Some(id) = parse_task_id(args_str.trim()) {
+                    self.todo_lists
+                        .reopen_task(&room_id, sender.clone(), id)
+                        .await?;
+                } else {
+                    let message = "⚠️ Error: Invalid task ID. Please provide a valid task number.";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "log" => {
+                let args = args_str.trim();
+                if args.is_empty() {
+                    let message = "⚠️ Error: Missing task ID and log message.";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                } else if let Some((id_str, log_msg)) = args.split_once(char::is_whitespace) {
+                    if let Some(id) = parse_task_id(id_str) {
+                        self.todo_lists
+                            .log_task(&room_id, sender.clone(), id, log_msg.trim().to_string())
+                            .await?;
+                    } else {
+                        let message =
+                            "⚠️ Error: Invalid task ID. Please provide a valid task number.";
+                        self.todo_lists
+                            .send_matrix_message(&room_id, message, None)
+                            .await?;
+                    }
+                } else if let Some(id) = parse_task_id(args) {
+                    // Just the ID, but no log message - show the task details with logs
+                    self.todo_lists.details_task(&room_id, id).await?;
+                } else {
+                    let message = "⚠️ Error: Unable to parse task ID and log message. Format: !log 1 Your log message";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "details" => {
+                if let Some(id) = parse_task_id(args_str.trim()) {
+                    self.todo_lists.details_task(&room_id, id).await?;
+                } else {
+                    let message = "⚠️ Error: Invalid task ID. Please provide a valid task number.";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "tag" => {
+                let args = args_str.trim();
+                if let Some((id_str, tag)) = args.split_once(char::is_whitespace) {
+                    if let Some(id) = parse_task_id(id_str) {
+                        self.todo_lists
+                            .add_tag(&room_id, sender.clone(), id, tag.trim().to_string())
+                            .await?
+                    } else {
+                        let message =
+                            "⚠️ Error: Invalid task ID. Please provide a valid task number.";
+                        self.todo_lists
+                            .send_matrix_message(&room_id, message, None)
+                            .await?;
+                    }
+                } else {
+                    let message =
+                        "⚠️ Error: Unable to parse task ID and tag. Format: !tag 1 urgent";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "recur" => {
+                let args = args_str.trim();
+                if let Some((id_str, rule)) = args.split_once(char::is_whitespace) {
+                    if let Some(id) = parse_task_id(id_str) {
+                        self.todo_lists
+                            .recur_task(&room_id, sender.clone(), id, rule.trim().to_string())
+                            .await?
+                    } else {
+                        let message =
+                            "⚠️ Error: Invalid task ID. Please provide a valid task number.";
+                        self.todo_lists
+                            .send_matrix_message(&room_id, message, None)
+                            .await?;
+                    }
                 } else {
-                    let message = "⚠️ Error: Invalid task ID. Please provide a valid task number.";
+                    let message =
+                        "⚠️ Error: Unable to parse task ID and recurrence. Format: !recur 1 weekly";
                     self.todo_lists
                         .send_matrix_message(&room_id, message, None)
-                        .await?
+                        .await?;
                 }
             }
-            "log" => {
+            "assign" | "estimate" => {
                 let args = args_str.trim();
-                if args.is_empty() {
-                    let message = "⚠️ Error: Missing task ID and log message.";
+                let id_and_value = args
+                    .split_once(char::is_whitespace)
+                    .and_then(|(id_str, value)| Some((parse_task_id(id_str)?, value.trim())));
+                match (command.as_str(), id_and_value) {
+                    ("assign", Some((id, user))) => {
+                        self.todo_lists
+                            .assign_task(&room_id, sender.clone(), id, user)
+                            .await?
+                    }
+                    (_, Some((id, hours))) => {
+                        self.todo_lists
+                            .estimate_task(&room_id, sender.clone(), id, hours)
+                            .await?
+                    }
+                    (_, None) => {
+                        let message = if command == "assign" {
+                            "⚠️ Error: Unable to parse task ID and user. Format: !assign 1 @user:server"
+                        } else {
+                            "⚠️ Error: Unable to parse task ID and estimate. Format: !estimate 1 2.5"
+                        };
+                        self.todo_lists
+                            .send_matrix_message(&room_id, message, None)
+                            .await?;
+                    }
+                }
+            }
+            "spent" => {
+                let args = args_str.trim();
+                let (id_str, duration) = args.split_once(char::is_whitespace).unwrap_or((args, ""));
+                match parse_task_id(id_str) {
+                    Some(id) => {
+                        self.todo_lists
+                            .spent_command(&room_id, sender.clone(), id, duration)
+                            .await?
+                    }
+                    None => {
+                        self.todo_lists
+                            .send_matrix_message(
+                                &room_id,
+                                "⚠️ Error: Unable to parse task ID. Format: !spent 1 1h30m",
+                                None,
+                            )
+                            .await?;
+                    }
+                }
+            }
+            "snooze" => {
+                let args = args_str.trim();
+                let id_and_until = args
+                    .split_once(char::is_whitespace)
+                    .and_then(|(id_str, until)| Some((parse_task_id(id_str)?, until)));
+                match id_and_until {
+                    Some((id, until)) => {
+                        self.todo_lists
+                            .snooze_task(&room_id, sender.clone(), id, until)
+                            .await?
+                    }
+                    None => {
+                        self.todo_lists
+                            .send_matrix_message(
+                                &room_id,
+                                "⚠️ Error: Unable to parse task ID and time. Format: !snooze 1 2d",
+                                None,
+                            )
+                            .await?;
+                    }
+                }
+            }
+            "depends" => {
+                let words: Vec<&str> = args_str.split_whitespace().collect();
+                let ids = match words[..] {
+                    [id, on, other] if on.eq_ignore_ascii_case("on") => {
+                        parse_task_id(id).zip(parse_task_id(other))
+                    }
+                    _ => None,
+                };
+                match ids {
+                    Some((id, other)) => {
+                        self.todo_lists
+                            .add_dependency(&room_id, sender.clone(), id, other)
+                            .await?
+                    }
+                    None => {
+                        self.todo_lists
+                            .send_matrix_message(
+                                &room_id,
+                                "⚠️ Error: Unable to parse task IDs. Format: !depends 2 on 1",
+                                None,
+                            )
+                            .await?;
+                    }
+                }
+            }
+            "workload" => self.todo_lists.workload_command(&room_id).await?,
+            "mine" => {
+                let filter = args_str.trim();
+                let status = TaskStatus::parse(filter);
+                if filter.is_empty() || status.is_some() {
                     self.todo_lists
-                        .send_matrix_message(&room_id, message, None)
+                        .list_my_tasks(&room_id, &sender, status)
                         .await?
-                } else if let Some((id_str, log_msg)) = args.split_once(char::is_whitespace) {
-                    if let Some(id) = parse_task_id(id_str) {
+                } else {
+                    let message =
+                        "⚠️ Error: Usage: !mine [pending|in-progress|blocked|done|closed]";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "import" => {
+                self.todo_lists
+                    .import_command(&room_id, sender.clone(), &args_str)
+                    .await?
+            }
+            "undo" => {
+                self.todo_lists
+                    .undo_command(&room_id, sender.clone())
+                    .await?
+            }
+            "template" => {
+                let args = args_str.split_whitespace().collect::<Vec<&str>>();
+                let action = args.first().map(|a| a.to_lowercase()).unwrap_or_default();
+                match (action.as_str(), args.get(1..).unwrap_or_default()) {
+                    ("apply", [name, vars @ ..]) => {
                         self.todo_lists
-                            .log_task(&room_id, sender.clone(), id, log_msg.trim().to_string())
-                            .await?;
-                    } else {
-                        let message =
-                            "⚠️ Error: Invalid task ID. Please provide a valid task number.";
+                            .apply_template(&room_id, sender.clone(), name, vars)
+                            .await?
+                    }
+                    ("save", [name]) => self.todo_lists.save_template(&room_id, name).await?,
+                    ("delete", [name]) => self.todo_lists.delete_template(&room_id, name).await?,
+                    ("list", []) => self.todo_lists.list_templates(&room_id).await?,
+                    _ => {
+                        let message = "⚠️ Error: Usage: !template save|delete <name>, !template list or !template apply <name> [key=value ...]";
                         self.todo_lists
                             .send_matrix_message(&room_id, message, None)
+                            .await?;
+                    }
+                }
+            }
+            "archive" => {
+                let args = args_str.split_whitespace().collect::<Vec<&str>>();
+                let action = args.first().map(|a| a.to_lowercase()).unwrap_or_default();
+                let usage = "⚠️ Error: Usage: !archive, !archive list [n] or !archive restore <id>";
+                match (action.as_str(), args.get(1..).unwrap_or_default()) {
+                    ("", []) => {
+                        self.todo_lists
+                            .archive_tasks(&room_id, sender.clone())
                             .await?
                     }
-                } else if let Some(id) = parse_task_id(args) {
-                    // Just the ID, but no log message - show the task details with logs
-                    self.todo_lists.details_task(&room_id, id).await?;
+                    ("list", []) => {
+                        self.todo_lists
+                            .list_archived(&room_id, ARCHIVE_LIST_DEFAULT)
+                            .await?
+                    }
+                    ("list", [count]) => match count.parse::<usize>() {
+                        Ok(count) if count > 0 => {
+                            self.todo_lists.list_archived(&room_id, count).await?
+                        }
+                        _ => {
+                            self.todo_lists
+                                .send_matrix_message(&room_id, usage, None)
+                                .await?;
+                        }
+                    },
+                    ("restore", [id]) => match parse_task_id(id) {
+                        Some(id) => {
+                            self.todo_lists
+                                .restore_archived(&room_id, sender.clone(), id)
+                                .await?
+                        }
+                        None => {
+                            self.todo_lists
+                                .send_matrix_message(&room_id, usage, None)
+                                .await?;
+                        }
+                    },
+                    _ => {
+                        self.todo_lists
+                            .send_matrix_message(&room_id, usage, None)
+                            .await?;
+                    }
+                }
+            }
+            "filter" => {
+                let tag = args_str.trim();
+                if tag.is_empty() {
+                    let message = "⚠️ Error: Missing tag. Format: !filter urgent";
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
                 } else {
-                    let message = "⚠️ Error: Unable to parse task ID and log message. Format: !log 1 Your log message";
+                    self.todo_lists
+                        .filter_tasks(&room_id, tag.to_string())
+                        .await?
+                }
+            }
+            "tags" => self.todo_lists.list_tags(&room_id).await?,
+            "stats" => self.todo_lists.stats_command(&room_id).await?,
+            "dm" => self.bot_management.dm_command(&room_id, &sender).await?,
+            "moveto" | "copyto" => {
+                let copy = command == "copyto";
+                let args = args_str.trim();
+                let id_and_room = args
+                    .split_once(char::is_whitespace)
+                    .and_then(|(id_str, room)| Some((parse_task_id(id_str)?, room.trim())));
+                match id_and_room {
+                    Some((id, target)) => {
+                        if let Some(target_id) = self
+                            .bot_management
+                            .transfer_target(&room_id, &sender, target)
+                            .await?
+                        {
+                            self.todo_lists
+                                .transfer_task(&room_id, &target_id, sender.clone(), id, copy)
+                                .await?
+                        }
+                    }
+                    None => {
+                        let message =
+                            format!("⚠️ Error: Usage: !{} <id> <room ID or alias>", command);
+                        self.todo_lists
+                            .send_matrix_message(&room_id, &message, None)
+                            .await?;
+                    }
+                }
+            }
+            "notify" => {
+                if let Some(id) = parse_task_id(args_str.trim()) {
+                    self.bot_management
+                        .notify_command(&room_id, &sender, id)
+                        .await?;
+                } else {
+                    let message = "⚠️ Error: Usage: !notify <id>";
                     self.todo_lists
                         .send_matrix_message(&room_id, message, None)
+                        .await?;
+                }
+            }
+            "watch" | "unwatch" => {
+                let args = args_str.trim().to_lowercase();
+                let auto = match args.split_whitespace().collect::<Vec<_>>().as_slice() {
+                    ["auto", "on"] if command == "watch" => Some(true),
+                    ["auto", "off"] if command == "watch" => Some(false),
+                    _ => None,
+                };
+                if let Some(enabled) = auto {
+                    self.todo_lists
+                        .auto_watch_command(&room_id, &sender, enabled)
+                        .await?
+                } else if let Some(id) = parse_task_id(&args) {
+                    self.todo_lists
+                        .watch_task(&room_id, sender.clone(), id, command == "watch")
                         .await?
+                } else {
+                    let message = if command == "watch" {
+                        "⚠️ Error: Usage: !watch <id> or !watch auto on|off"
+                    } else {
+                        "⚠️ Error: Usage: !unwatch <id>"
+                    };
+                    self.todo_lists
+                        .send_matrix_message(&room_id, message, None)
+                        .await?;
                 }
             }
-            "details" => {
+            "personal" => {
+                self.todo_lists
+                    .personal_command(&room_id, sender.clone(), &args_str)
+                    .await?
+            }
+            "export" => self.todo_lists.export_command(&room_id, &args_str).await?,
+            "search" => self.todo_lists.search_command(&room_id, &args_str).await?,
+            "history" => {
                 if let Some(id) = parse_task_id(args_str.trim()) {
-                    self.todo_lists.details_task(&room_id, id).await?;
+                    self.todo_lists.history_task(&room_id, id).await?;
                 } else {
                     let message = "⚠️ Error: Invalid task ID. Please provide a valid task number.";
                     self.todo_lists
                         .send_matrix_message(&room_id, message, None)
-                        .await?
+                        .await?;
                 }
             }
             "edit" => {
@@ -341,7 +2391,7 @@ impl BotCore {
                     let message = "⚠️ Error: Missing task ID and new description.";
                     self.todo_lists
                         .send_matrix_message(&room_id, message, None)
-                        .await?
+                        .await?;
                 } else if let Some((id_str, new_description)) = args.split_once(char::is_whitespace)
                 {
                     if let Some(id) = parse_task_id(id_str) {
@@ -358,13 +2408,13 @@ impl BotCore {
                             "⚠️ Error: Invalid task ID. Please provide a valid task number.";
                         self.todo_lists
                             .send_matrix_message(&room_id, message, None)
-                            .await?
+                            .await?;
                     }
                 } else {
                     let message = "⚠️ Error: Unable to parse task ID and new description. Format: !edit 1 New task description";
                     self.todo_lists
                         .send_matrix_message(&room_id, message, None)
-                        .await?
+                        .await?;
                 }
             }
 
@@ -373,9 +2423,35 @@ impl BotCore {
                 let args = args_str.trim().to_lowercase();
                 let args_parts: Vec<&str> = args.split_whitespace().collect();
                 let bot_command = args_parts.first().cloned().unwrap_or("");
+                let denial = bot_command_denial(
+                    bot_command,
+                    is_admin,
+                    self.may_run_destructive(is_admin, power_level),
+                    may_destroy,
+                );
 
                 match bot_command {
+                    _ if denial == Some(BotCommandDenial::Destructive) => {
+                        self.destructive_denied(&room_id, &sender, bot_command, power_level)
+                            .await?
+                    }
+                    _ if denial == Some(BotCommandDenial::NotAdmin) => {
+                        self.bot_management.permission_denied(&room_id).await?
+                    }
+                    "admin" => {
+                        // User IDs keep their original case, `args` is lowercased
+                        let raw_parts: Vec<&str> = args_str.split_whitespace().collect();
+                        let action = args_parts.get(1).cloned().unwrap_or("");
+                        self.bot_management
+                            .admin_command(&room_id, &sender, action, raw_parts.get(2).cloned())
+                            .await?
+                    }
                     "save" => self.bot_management.save_command(&room_id).await?,
+                    "schedule" => {
+                        self.bot_management
+                            .schedule_command(&room_id, &args_parts[1..])
+                            .await?
+                    }
                     "load" => {
                         if args_parts.len() < 2 {
                             let message = "⚠️ Error: Missing filename. Usage: !bot load <filename>";
@@ -384,22 +2460,193 @@ impl BotCore {
                                 .await?;
                         } else {
                             let filename = args_parts[1].to_string();
-                            self.bot_management.load_command(&room_id, filename).await?
+                            self.bot_management
+                                .load_command(&room_id, filename, false)
+                                .await?
+                        }
+                    }
+                    "loadany" => {
+                        // Save file names are case sensitive, `args` is lowercased
+                        match args_str.split_whitespace().nth(1) {
+                            Some(filename) => {
+                                self.bot_management
+                                    .load_command(&room_id, filename.to_owned(), true)
+                                    .await?
+                            }
+                            None => {
+                                let message =
+                                    "⚠️ Error: Missing filename. Usage: !bot loadany <filename>";
+                                self.bot_management
+                                    .send_matrix_message(&room_id, message, None)
+                                    .await?;
+                            }
                         }
                     }
                     "loadlast" => self.bot_management.loadlast_command(&room_id).await?,
                     "listfiles" => self.bot_management.list_files_command(&room_id).await?,
+                    "archives" => self.bot_management.list_archives_command(&room_id).await?,
+                    "synclog" => self.bot_management.sync_log_command(&room_id).await?,
+                    "globalstats" => self.bot_management.global_stats_command(&room_id).await?,
+                    "export" => self.bot_management.export_command(&room_id).await?,
+                    "reindex" => self.bot_management.reindex_command(&room_id).await?,
+                    "backup" => {
+                        // Room IDs are case sensitive, `args` is lowercased
+                        let destination = args_str.split_whitespace().nth(1).unwrap_or("");
+                        self.bot_management
+                            .backup_command(&room_id, destination.to_owned(), &self.status)
+                            .await?
+                    }
+                    "import" => {
+                        // The JSON keeps its case and line breaks, `args` is lowercased
+                        let payload = args_str
+                            .trim_start()
+                            .split_once(char::is_whitespace)
+                            .map_or("", |(_, rest)| rest);
+                        self.bot_management
+                            .import_command(&room_id, payload)
+                            .await?
+                    }
+                    "diff" => {
+                        // Save file names are case sensitive, `args` is lowercased
+                        let filename = args_str.split_whitespace().nth(1).unwrap_or("");
+                        self.bot_management.diff_command(&room_id, filename).await?
+                    }
+                    "media" => {
+                        let action = args_parts.get(1).cloned().unwrap_or("");
+                        let arg = args_parts.get(2).cloned().unwrap_or("");
+                        self.bot_management
+                            .media_command(&room_id, action, arg)
+                            .await?
+                    }
+                    "format" => {
+                        let mode = args_parts.get(1).cloned().unwrap_or("");
+                        self.bot_management
+                            .response_format_command(&room_id, mode)
+                            .await?
+                    }
+                    "actions" => {
+                        let style = args_parts.get(1).cloned().unwrap_or("");
+                        self.bot_management
+                            .quick_actions_command(&room_id, style)
+                            .await?
+                    }
+                    "set-task-template-on-join" => {
+                        // Template names are case sensitive, `args` is lowercased
+                        let name = args_str.split_whitespace().nth(1).unwrap_or("");
+                        self.bot_management
+                            .join_template_command(&room_id, name)
+                            .await?
+                    }
+                    "set" => {
+                        let key = args_parts.get(1).cloned().unwrap_or("");
+                        let value = args_parts.get(2).cloned().unwrap_or("");
+                        self.bot_management
+                            .set_command(&room_id, key, value)
+                            .await?
+                    }
+                    "board" => {
+                        let mode = args_parts.get(1).cloned().unwrap_or("");
+                        self.bot_management.board_command(&room_id, mode).await?
+                    }
+                    "set-sync-presence" => {
+                        let mode = args_parts.get(1).cloned().unwrap_or("");
+                        self.bot_management
+                            .sync_presence_command(&room_id, &self.presence, mode)
+                            .await?
+                    }
+                    "activate" => {
+                        self.bot_management
+                            .activate_command(&room_id, &self.configured_active_rooms)
+                            .await?
+                    }
+                    "deactivate" => {
+                        self.bot_management
+                            .deactivate_command(&room_id, &self.configured_active_rooms)
+                            .await?
+                    }
+                    "ping" => self.bot_management.ping_command(&room_id).await?,
+                    "rooms" => {
+                        let page = args_parts.get(1).cloned().unwrap_or("");
+                        self.bot_management
+                            .list_rooms_command(&room_id, page)
+                            .await?
+                    }
+                    "status" => {
+                        self.bot_management
+                            .status_command(
+                                &room_id,
+                                &self.configured_active_rooms,
+                                is_admin,
+                                &self.watchdog,
+                                &self.status,
+                            )
+                            .await?
+                    }
+                    "alias" => match args_parts.get(1..).unwrap_or_default() {
+                        ["list"] => self.bot_management.alias_list(&room_id).await?,
+                        ["set", ..] | ["remove", ..] if !is_admin => {
+                            self.bot_management.permission_denied(&room_id).await?
+                        }
+                        ["set", alias, command] => {
+                            self.bot_management
+                                .alias_set(&room_id, alias, command)
+                                .await?
+                        }
+                        ["remove", alias] => {
+                            self.bot_management.alias_remove(&room_id, alias).await?
+                        }
+                        _ => {
+                            let message = "⚠️ Error: Usage: !bot alias set <alias> <command>, !bot alias remove <alias> or !bot alias list";
+                            self.bot_management
+                                .send_matrix_message(&room_id, message, None)
+                                .await?;
+                        }
+                    },
                     "cleartasks" => self.bot_management.clear_tasks(&room_id).await?,
+                    "cleardone" => self.bot_management.clear_done_tasks(&room_id).await?,
+                    "clearold" => {
+                        let days = args_parts.get(1).copied().unwrap_or("");
+                        self.bot_management.clear_old_tasks(&room_id, days).await?
+                    }
                     _ => {
                         let usage = "Bot Commands Usage:\n\n\
                         !bot save - Save all lists\n\
+                        !bot schedule save <HH:MM>|off - Save every day at a UTC time, or stop\n\
                         !bot load <filename> - Load lists from file\n\
                         !bot loadlast - Load most recent save file\n\
+                        !bot loadany <filename> - Load a save file written by any session\n\
                         !bot listfiles - List all save files\n\
-                        !bot cleartasks - Clear the current room's list";
+                        !bot archives - List weekly archival snapshots\n\
+                        !bot synclog - Show recently persisted sync tokens\n\
+                        !bot globalstats - Show task statistics across all rooms\n\
+                        !bot export - Upload this room's tasks as a JSON file\n\
+                        !bot media list|prune <days> - List or delete files the bot uploaded here\n\
+                        !bot rooms [page] - List the rooms the bot has joined\n\
+                        !bot ping - Reply with how long sending a message takes\n\
+                        !bot status - Show uptime, syncs, the bot account and whether it is active here\n\
+                        !bot alias set <alias> <command> - Add a command alias for this room\n\
+                        !bot alias remove <alias> - Remove a command alias\n\
+                        !bot alias list - List this room's command aliases\n\
+                        !bot activate|deactivate - Add or remove this room from the allowlist\n\
+                        !bot admin add|remove|list [@user] - Manage room admins\n\
+                        !bot actions off|commands|links - Quick actions shown in !list\n\
+                        !bot format plain|html|markdown - How responses are formatted\n\
+                        !bot set style standard|plain - Plain style drops emoji and markup for screen readers\n\
+                        !bot set timezone <offset> - Room timezone as a UTC offset, e.g. +02:00\n\
+                        !bot board on|off - Mirror the list into room state for board widgets\n\
+                        !bot set-task-template-on-join <name>|off - Template applied to rooms the bot joins\n\
+                        !bot set-sync-presence on|off - Show as online only while the bot is in use\n\
+                        !bot reindex - Repair duplicate task IDs and the ID counter\n\
+                        !bot diff <filename> - Show what changed since a save file\n\
+                        !bot backup <room id> - Upload this room's data to another room\n\
+                        !bot import <json> - Add tasks from a JSON array of objects with a title and optional status and logs\n\
+                        !bot cleartasks - Clear the current room's list\n\
+                        !bot cleardone - Remove all done tasks\n\
+                        !bot clearold <days> - Remove tasks done or closed more than <days> days ago";
 
+                        let usage = self.with_prefix(usage);
                         self.bot_management
-                            .send_matrix_message(&room_id, usage, None)
+                            .send_matrix_message(&room_id, &usage, None)
                             .await?;
                     }
                 }
@@ -409,52 +2656,194 @@ impl BotCore {
             "help" => {
                 let help_text = "Matrix ToDo Bot Help:\n\n\
                 **Task Commands:**\n\
-                !add <task description> - Add a new task\n\
-                !list - List all tasks\n\
-                !done <id> - Mark a task as done\n\
-                !close <id> - Mark a task as closed/completed\n\
+                !add <task description> - Add a new task; lines after the first become its description\n\
+                !add --force <task description> - Add a task even if an open one has the same title\n\
+                !list [all|<status>] [page] - List tasks, 20 per page (all includes closed ones, a status such as pending shows only those), with a count per status; the last list is kept up to date as tasks change\n\
+                !list snoozed - List snoozed tasks and when they come back\n\
+                !list sort status|priority|age|due|assignee - Change and remember the order of this room's list\n\
+                !done <id> [<id>|<from>-<to> ...] [force] - Mark one or more tasks as done; force skips the dependency check\n\
+                !done <id> <note> - Mark a task as done and log a completion note\n\
+                !close <id> [<id>|<from>-<to> ...] - Mark one or more tasks as closed/completed\n\
+                !close <id> <note> - Close a task and log why\n\
+                !reopen <id> - Set a done or closed task back to pending\n\
+                !start <id> - Mark a task as in progress\n\
+                !block <id> [reason] - Mark a task as blocked\n\
+                !move <id> <position>|top|bottom - Reorder a task in the list\n\
+                !moveto <id> <room> - Move a task to another room's list, by room ID or alias\n\
+                !copyto <id> <room> - Copy a task to another room's list\n\
+                !spin <id> <log#> - Create a new task from a task's log entry\n\
                 !log <id> <message> - Add a log entry to a task\n\
                 !log <id> - Show logs for a task\n\
                 !details <id> - Show full task details\n\
-                !edit <id> <new description> - Edit a task description\n\n\
+                !history <id> - Show the complete history of a task\n\
+                !tag <id> <tag> - Tag a task\n\
+                !filter <tag> - List tasks with a tag\n\
+                !tags - List all tags in use\n\
+                !stats - Show task statistics for this room\n\
+                !assign <id> @user|me|off - Assign a task\n\
+                !estimate <id> <hours>|off - Set a task's estimated effort\n\
+                !depends <id> on <other id> - Require another task to be finished first\n\
+                !snooze <id> <time>|<date>|off - Hide a task from !list for a while, e.g. 2d or 2024-05-01\n\
+                !spent <id> [<time>] - Log time on a task, e.g. 1h30m or 2d, or show its total\n\
+                !workload - Rank users by open assigned tasks\n\
+                !mine [status] - List tasks you created or are assigned to, optionally only those with a status\n\
+                !template save|delete <name> - Save this room's task titles as a template, or delete one\n\
+                !template list - List saved templates\n\
+                !template apply <name> [key=value ...] - Add a template's tasks, filling {{key}} and {{date+7d}}\n\
+                !export md|csv - Post this room's tasks as a Markdown checklist, or save and upload them as a CSV file\n\
+                !import <JSON array or Markdown list> - Add tasks from a list of titles, skipping duplicates\n\
+                !archive - Move done and closed tasks to the archive\n\
+                !archive list [n] - Show the most recently archived tasks\n\
+                !archive restore <id> - Move an archived task back to the list\n\
+                !recur <id> daily|weekly|monthly|every N days|off - Make a task repeat when done\n\
+                !search <query> [status:<status>] - Search tasks by title, logs or creator\n\
+                !edit <id> <new description> - Edit a task description\n\
+                !dm - Open your own private to-do list in a direct chat with the bot\n\
+                !notify <id> - Send users mentioned in a task's title or logs a direct message about it\n\
+                !watch <id> - Get mentioned whenever a task's status, logs or title change\n\
+                !unwatch <id> - Stop watching a task\n\
+                !watch auto on|off - Whether you watch the tasks you create or get assigned (on by default)\n\
+                !personal add <task>|list|done <id> - Your own list, available in every room you share with the bot\n\
+                !undo - Revert the last change to this room's tasks, up to 10 steps back\n\n\
                 **Bot Commands:**\n\
                 !bot save - Save all lists\n\
+                !bot schedule save <HH:MM>|off - Save every day at a UTC time, or stop\n\
                 !bot load <filename> - Load lists from file\n\
                 !bot loadlast - Load most recent save file\n\
+                !bot loadany <filename> - Load a save file written by any session\n\
                 !bot listfiles - List all save files\n\
-                !bot cleartasks - Clear the current room's list\n\n\
+                !bot archives - List weekly archival snapshots\n\
+                !bot synclog - Show recently persisted sync tokens\n\
+                !bot globalstats - Show task statistics across all rooms\n\
+                !bot export - Upload this room's tasks as a JSON file\n\
+                !bot media list|prune <days> - List or delete files the bot uploaded here\n\
+                !bot rooms [page] - List the rooms the bot has joined\n\
+                !bot ping - Reply with how long sending a message takes\n\
+                !bot status - Show uptime, syncs, the bot account and whether it is active here\n\
+                !bot alias set <alias> <command> - Add a command alias for this room\n\
+                !bot alias remove <alias> - Remove a command alias\n\
+                !bot alias list - List this room's command aliases\n\
+                !bot activate|deactivate - Add or remove this room from the allowlist\n\
+                !bot admin add|remove|list [@user] - Manage room admins\n\
+                !bot actions off|commands|links - Quick actions shown in !list\n\
+                !bot format plain|html|markdown - How responses are formatted\n\
+                !bot set style standard|plain - Plain style drops emoji and markup for screen readers\n\
+                !bot set timezone <offset> - Room timezone as a UTC offset, e.g. +02:00\n\
+                !bot board on|off - Mirror the list into room state for board widgets\n\
+                !bot set-task-template-on-join <name>|off - Template applied to rooms the bot joins\n\
+                !bot set-sync-presence on|off - Show as online only while the bot is in use\n\
+                !bot reindex - Repair duplicate task IDs and the ID counter\n\
+                !bot diff <filename> - Show what changed since a save file\n\
+                !bot backup <room id> - Upload this room's data to another room\n\
+                !bot import <json> - Add tasks from a JSON array of objects with a title and optional status and logs\n\
+                !bot cleartasks - Clear the current room's list\n\
+                !bot cleardone - Remove all done tasks\n\
+                !bot clearold <days> - Remove tasks done or closed more than <days> days ago\n\n\
                 **Other Commands:**\n\
                 !help - Show this help message";
 
                 let html_help = "<h4>Matrix ToDo Bot Help</h4>\
                 <strong>Task Commands:</strong><br>\
-                <code>!add &lt;task description&gt;</code> - Add a new task<br>\
-                <code>!list</code> - List all tasks<br>\
-                <code>!done &lt;id&gt;</code> - Mark a task as done<br>\
-                <code>!close &lt;id&gt;</code> - Mark a task as closed/completed<br>\
+                <code>!add &lt;task description&gt;</code> - Add a new task; lines after the first become its description<br>\
+                <code>!add --force &lt;task description&gt;</code> - Add a task even if an open one has the same title<br>\
+                <code>!list [all|&lt;status&gt;] [page]</code> - List tasks, 20 per page (all includes closed ones, a status such as pending shows only those), with a count per status; the last list is kept up to date as tasks change<br>\
+                <code>!list snoozed</code> - List snoozed tasks and when they come back<br>\
+                <code>!list sort status|priority|age|due|assignee</code> - Change and remember the order of this room's list<br>\
+                <code>!done &lt;id&gt; [&lt;id&gt;|&lt;from&gt;-&lt;to&gt; ...] [force]</code> - Mark one or more tasks as done; force skips the dependency check<br>\
+                <code>!done &lt;id&gt; &lt;note&gt;</code> - Mark a task as done and log a completion note<br>\
+                <code>!close &lt;id&gt; [&lt;id&gt;|&lt;from&gt;-&lt;to&gt; ...]</code> - Mark one or more tasks as closed/completed<br>\
+                <code>!close &lt;id&gt; &lt;note&gt;</code> - Close a task and log why<br>\
+                <code>!reopen &lt;id&gt;</code> - Set a done or closed task back to pending<br>\
+                <code>!start &lt;id&gt;</code> - Mark a task as in progress<br>\
+                <code>!block &lt;id&gt; [reason]</code> - Mark a task as blocked<br>\
+                <code>!move &lt;id&gt; &lt;position&gt;|top|bottom</code> - Reorder a task in the list<br>\
+                <code>!moveto &lt;id&gt; &lt;room&gt;</code> - Move a task to another room's list, by room ID or alias<br>\
+                <code>!copyto &lt;id&gt; &lt;room&gt;</code> - Copy a task to another room's list<br>\
+                <code>!spin &lt;id&gt; &lt;log#&gt;</code> - Create a new task from a task's log entry<br>\
                 <code>!log &lt;id&gt; &lt;message&gt;</code> - Add a log entry to a task<br>\
                 <code>!log &lt;id&gt;</code> - Show logs for a task<br>\
                 <code>!details &lt;id&gt;</code> - Show full task details<br>\
-                <code>!edit &lt;id&gt; &lt;new description&gt;</code> - Edit a task description<br><br>\
+                <code>!history &lt;id&gt;</code> - Show the complete history of a task<br>\
+                <code>!tag &lt;id&gt; &lt;tag&gt;</code> - Tag a task<br>\
+                <code>!filter &lt;tag&gt;</code> - List tasks with a tag<br>\
+                <code>!tags</code> - List all tags in use<br>\
+                <code>!stats</code> - Show task statistics for this room<br>\
+                <code>!assign &lt;id&gt; @user|me|off</code> - Assign a task<br>\
+                <code>!estimate &lt;id&gt; &lt;hours&gt;|off</code> - Set a task's estimated effort<br>\
+                <code>!depends &lt;id&gt; on &lt;other id&gt;</code> - Require another task to be finished first<br>\
+                <code>!snooze &lt;id&gt; &lt;time&gt;|&lt;date&gt;|off</code> - Hide a task from !list for a while, e.g. 2d or 2024-05-01<br>\
+                <code>!spent &lt;id&gt; [&lt;time&gt;]</code> - Log time on a task, e.g. 1h30m or 2d, or show its total<br>\
+                <code>!workload</code> - Rank users by open assigned tasks<br>\
+                <code>!mine [status]</code> - List tasks you created or are assigned to, optionally only those with a status<br>\
+                <code>!template save|delete &lt;name&gt;</code> - Save this room's task titles as a template, or delete one<br>\
+                <code>!template list</code> - List saved templates<br>\
+                <code>!template apply &lt;name&gt; [key=value ...]</code> - Add a template's tasks, filling {{key}} and {{date+7d}}<br>\
+                <code>!export md|csv</code> - Post this room's tasks as a Markdown checklist, or save and upload them as a CSV file<br>\
+                <code>!import &lt;JSON array or Markdown list&gt;</code> - Add tasks from a list of titles, skipping duplicates<br>\
+                <code>!archive</code> - Move done and closed tasks to the archive<br>\
+                <code>!archive list [n]</code> - Show the most recently archived tasks<br>\
+                <code>!archive restore &lt;id&gt;</code> - Move an archived task back to the list<br>\
+                <code>!recur &lt;id&gt; daily|weekly|monthly|every N days|off</code> - Make a task repeat when done<br>\
+                <code>!search &lt;query&gt; [status:&lt;status&gt;]</code> - Search tasks by title, logs or creator<br>\
+                <code>!edit &lt;id&gt; &lt;new description&gt;</code> - Edit a task description<br>\
+                <code>!dm</code> - Open your own private to-do list in a direct chat with the bot<br>\
+                <code>!notify &lt;id&gt;</code> - Send users mentioned in a task's title or logs a direct message about it<br>\
+                <code>!watch &lt;id&gt;</code> - Get mentioned whenever a task's status, logs or title change<br>\
+                <code>!unwatch &lt;id&gt;</code> - Stop watching a task<br>\
+                <code>!watch auto on|off</code> - Whether you watch the tasks you create or get assigned (on by default)<br>\
+                <code>!personal add &lt;task&gt;|list|done &lt;id&gt;</code> - Your own list, available in every room you share with the bot<br>\
+                <code>!undo</code> - Revert the last change to this room's tasks, up to 10 steps back<br><br>\
                 <strong>Bot Commands:</strong><br>\
                 <code>!bot save</code> - Save all lists<br>\
+                <code>!bot schedule save &lt;HH:MM&gt;|off</code> - Save every day at a UTC time, or stop<br>\
                 <code>!bot load &lt;filename&gt;</code> - Load lists from file<br>\
                 <code>!bot loadlast</code> - Load most recent save file<br>\
+                <code>!bot loadany &lt;filename&gt;</code> - Load a save file written by any session<br>\
                 <code>!bot listfiles</code> - List all save files<br>\
-                <code>!bot cleartasks</code> - Clear the current room's list<br><br>\
+                <code>!bot archives</code> - List weekly archival snapshots<br>\
+                <code>!bot synclog</code> - Show recently persisted sync tokens<br>\
+                <code>!bot globalstats</code> - Show task statistics across all rooms<br>\
+                <code>!bot export</code> - Upload this room's tasks as a JSON file<br>\
+                <code>!bot media list|prune &lt;days&gt;</code> - List or delete files the bot uploaded here<br>\
+                <code>!bot rooms [page]</code> - List the rooms the bot has joined<br>\
+                <code>!bot ping</code> - Reply with how long sending a message takes<br>\
+                <code>!bot status</code> - Show uptime, syncs, the bot account and whether it is active here<br>\
+                <code>!bot alias set &lt;alias&gt; &lt;command&gt;</code> - Add a command alias for this room<br>\
+                <code>!bot alias remove &lt;alias&gt;</code> - Remove a command alias<br>\
+                <code>!bot alias list</code> - List this room's command aliases<br>\
+                <code>!bot activate|deactivate</code> - Add or remove this room from the allowlist<br>\
+                <code>!bot admin add|remove|list [@user]</code> - Manage room admins<br>\
+                <code>!bot actions off|commands|links</code> - Quick actions shown in !list<br>\
+                <code>!bot format plain|html|markdown</code> - How responses are formatted<br>\
+                <code>!bot set style standard|plain</code> - Plain style drops emoji and markup for screen readers<br>\
+                <code>!bot set timezone &lt;offset&gt;</code> - Room timezone as a UTC offset, e.g. +02:00<br>\
+                <code>!bot board on|off</code> - Mirror the list into room state for board widgets<br>\
+                <code>!bot set-task-template-on-join &lt;name&gt;|off</code> - Template applied to rooms the bot joins<br>\
+                <code>!bot set-sync-presence on|off</code> - Show as online only while the bot is in use<br>\
+                <code>!bot reindex</code> - Repair duplicate task IDs and the ID counter<br>\
+                <code>!bot diff &lt;filename&gt;</code> - Show what changed since a save file<br>\
+                <code>!bot backup &lt;room id&gt;</code> - Upload this room's data to another room<br>\
+                <code>!bot import &lt;json&gt;</code> - Add tasks from a JSON array of objects with a title and optional status and logs<br>\
+                <code>!bot cleartasks</code> - Clear the current room's list<br>\
+                <code>!bot cleardone</code> - Remove all done tasks<br>\
+                <code>!bot clearold &lt;days&gt;</code> - Remove tasks done or closed more than &lt;days&gt; days ago<br><br>\
                 <strong>Other Commands:</strong><br>\
                 <code>!help</code> - Show this help message";
 
                 self.todo_lists
-                    .send_matrix_message(&room_id, help_text, Some(html_help.to_string()))
+                    .send_matrix_message(
+                        &room_id,
+                        &self.with_prefix(help_text),
+                        Some(self.with_prefix(html_help)),
+                    )
                     .await?;
             }
 
             // Unknown command
             _ => {
                 let message = format!(
-                    "⚠️ Unknown command: '{}'. Type !help for available commands.",
-                    command
+                    "⚠️ Unknown command: '{}'. Type {}help for available commands.",
+                    command, self.command_prefix
                 );
                 self.todo_lists
                     .send_matrix_message(&room_id, &message, None)
@@ -469,3 +2858,189 @@ impl BotCore {
 fn parse_task_id(id_str: &str) -> Option<usize> {
     id_str.parse::<usize>().ok()
 }
+
+/// `<id> <note>` as taken by `!done` and `!close` when the rest isn't more IDs
+fn parse_id_and_note(input: &str) -> Option<(usize, String)> {
+    let (id, note) = input.trim().split_once(char::is_whitespace)?;
+    Some((parse_task_id(id)?, note.trim().to_owned()))
+}
+
+/// A `!` starting a command name in help text, after a line break, space, tag or quote
+static HELP_COMMAND: Lazy<Regex> = Lazy::new(|| Regex::new(r"(^|[\s>`(])!([a-z])").unwrap());
+
+/// `text` with the `!` of every command replaced by `prefix`; other exclamation
+/// marks stay
+fn with_command_prefix(text: &str, prefix: &str) -> String {
+    HELP_COMMAND
+        .replace_all(text, |caps: &regex::Captures| {
+            format!("{}{}{}", &caps[1], prefix, &caps[2])
+        })
+        .into_owned()
+}
+
+/// Commands handled by `BotCore::process_command`; aliases may point at these but not shadow them
+pub const BUILTIN_COMMANDS: &[&str] = &[
+    "add", "list", "done", "close", "move", "spin", "start", "block", "reopen", "log", "details",
+    "tag", "recur", "assign", "estimate", "depends", "spent", "snooze", "workload", "import",
+    "undo", "template", "archive", "filter", "tags", "stats", "export", "search", "history",
+    "edit", "bot", "help", "dm", "mine", "personal", "notify", "moveto", "copyto", "watch",
+    "unwatch",
+];
+
+// How long reporting a stuck command may take before it is given up
+const STUCK_NOTICE_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Rooms per page of `!bot rooms`
+const ROOMS_PER_PAGE: usize = 20;
+
+// Upper bound on the tasks one bulk command may touch, so `!done 1-100000` stays cheap
+const MAX_BULK_TASK_IDS: usize = 100;
+
+const BULK_IDS_ERROR: &str =
+    "⚠️ Error: Invalid task IDs. Use numbers and ranges, e.g. 3, 1 3 5 or 2-6.";
+
+/// Parse task IDs separated by spaces or commas, where `a-b` is an inclusive range.
+/// Duplicates are dropped, keeping the first occurrence.
+fn parse_task_ids(input: &str) -> Option<Vec<usize>> {
+    let mut ids = Vec::new();
+    for token in input
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|t| !t.is_empty())
+    {
+        let range = match token.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse_task_id(start)?, parse_task_id(end)?);
+                if start > end || end - start >= MAX_BULK_TASK_IDS {
+                    return None;
+                }
+                start..=end
+            }
+            None => {
+                let id = parse_task_id(token)?;
+                id..=id
+            }
+        };
+        for id in range {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+        }
+        if ids.len() > MAX_BULK_TASK_IDS {
+            return None;
+        }
+    }
+    (!ids.is_empty()).then_some(ids)
+}
+
+/// Why the sender may not run `!bot <command>`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BotCommandDenial {
+    /// Needs the destructive permission, see `BotCore::may_run_destructive`
+    Destructive,
+    /// Needs a room admin
+    NotAdmin,
+}
+
+/// Permission check for a `!bot` subcommand. `may_destroy` also lets a DM's owner
+/// clear their own list; loading replaces every room, so being a DM owner isn't enough.
+fn bot_command_denial(
+    command: &str,
+    is_admin: bool,
+    may_run_destructive: bool,
+    may_destroy: bool,
+) -> Option<BotCommandDenial> {
+    match command {
+        "load" | "loadlast" | "loadany" if !may_run_destructive => {
+            Some(BotCommandDenial::Destructive)
+        }
+        "cleartasks" if !may_destroy => Some(BotCommandDenial::Destructive),
+        "set-task-template-on-join"
+        | "set-sync-presence"
+        | "globalstats"
+        | "media"
+        | "reindex"
+        | "diff"
+        | "rooms"
+        | "backup"
+        | "import"
+        | "cleardone"
+        | "clearold"
+        | "activate"
+        | "deactivate"
+        | "schedule"
+            if !is_admin =>
+        {
+            Some(BotCommandDenial::NotAdmin)
+        }
+        _ => None,
+    }
+}
+
+/// The cutoff for `!bot clearold <days>`, or `None` when `days` isn't a number
+/// or reaches past the calendar
+fn clear_old_cutoff(
+    days: &str,
+    now: chrono::DateTime<chrono::Utc>,
+) -> Option<chrono::DateTime<chrono::Utc>> {
+    let days = days.parse::<i64>().ok()?.max(0);
+    now.checked_sub_signed(chrono::Duration::try_days(days)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn loading_needs_the_destructive_permission_even_for_admins() {
+        for command in ["load", "loadlast", "loadany"] {
+            assert_eq!(
+                bot_command_denial(command, true, false, true),
+                Some(BotCommandDenial::Destructive),
+                "{command}"
+            );
+            assert_eq!(bot_command_denial(command, false, true, false), None);
+        }
+        // A DM owner may clear their own list, but not load over every room
+        assert_eq!(bot_command_denial("cleartasks", false, false, true), None);
+        assert_eq!(
+            bot_command_denial("loadany", false, false, true),
+            Some(BotCommandDenial::Destructive)
+        );
+    }
+
+    #[test]
+    fn backup_and_clearold_need_a_room_admin() {
+        for command in ["backup", "clearold", "cleardone"] {
+            assert_eq!(
+                bot_command_denial(command, false, true, true),
+                Some(BotCommandDenial::NotAdmin),
+                "{command}"
+            );
+            assert_eq!(bot_command_denial(command, true, false, false), None);
+        }
+        assert_eq!(bot_command_denial("help", false, false, false), None);
+    }
+
+    #[test]
+    fn clear_old_cutoff_rejects_out_of_range_days() {
+        let now = chrono::Utc::now();
+        assert_eq!(
+            clear_old_cutoff("30", now),
+            Some(now - chrono::Duration::days(30))
+        );
+        assert_eq!(clear_old_cutoff("-5", now), Some(now));
+        assert_eq!(clear_old_cutoff("999999999999", now), None);
+        assert_eq!(clear_old_cutoff(&i64::MAX.to_string(), now), None);
+        assert_eq!(clear_old_cutoff("soon", now), None);
+    }
+
+    #[test]
+    fn prefix_replaces_only_command_names() {
+        let help = "!add <task> - Add a task\n<code>!list</code> - See `!list all`!\nDone! (!undo)";
+        assert_eq!(
+            with_command_prefix(help, "todo:"),
+            "todo:add <task> - Add a task\n<code>todo:list</code> - See `todo:list all`!\nDone! (todo:undo)"
+        );
+        assert_eq!(with_command_prefix(help, "!"), help);
+    }
+}
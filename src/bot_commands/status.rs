@@ -0,0 +1,103 @@
+use chrono::{DateTime, Duration, Utc};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// Shortest time between two `!bot backup` runs
+pub const BACKUP_INTERVAL: Duration = Duration::seconds(60);
+
+/// Runtime information shown by `!bot status`
+#[derive(Debug)]
+pub struct BotStatus {
+    pub started_at: DateTime<Utc>,
+    /// Successful syncs since startup, counted by the sync loop
+    pub sync_count: Arc<AtomicU64>,
+    pub session_user_id: String,
+    last_backup: Mutex<Option<DateTime<Utc>>>,
+    // Latest `ConnectionMonitor::failure_report`, set by the sync loop
+    connection_failures: Mutex<Option<String>>,
+}
+
+impl BotStatus {
+    pub fn new(session_user_id: String) -> Self {
+        Self {
+            started_at: Utc::now(),
+            sync_count: Arc::new(AtomicU64::new(0)),
+            session_user_id,
+            last_backup: Mutex::new(None),
+            connection_failures: Mutex::new(None),
+        }
+    }
+
+    pub fn record_sync(&self) {
+        self.sync_count.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn syncs(&self) -> u64 {
+        self.sync_count.load(Ordering::Relaxed)
+    }
+
+    pub fn set_connection_failures(&self, report: String) {
+        *self.connection_failures.lock().unwrap() = Some(report);
+    }
+
+    /// Connection failure report, `None` while syncing never failed
+    pub fn connection_failures(&self) -> Option<String> {
+        self.connection_failures.lock().unwrap().clone()
+    }
+
+    /// Time since startup as `<X>h <Y>m <Z>s`
+    pub fn uptime_at(&self, now: DateTime<Utc>) -> String {
+        let secs = (now - self.started_at).num_seconds().max(0);
+        format!("{}h {}m {}s", secs / 3600, secs / 60 % 60, secs % 60)
+    }
+
+    pub fn uptime(&self) -> String {
+        self.uptime_at(Utc::now())
+    }
+
+    /// Claim a backup slot at `now`, or how long to wait when the previous
+    /// backup was less than `BACKUP_INTERVAL` ago
+    pub fn start_backup_at(&self, now: DateTime<Utc>) -> Result<(), Duration> {
+        let mut last_backup = self.last_backup.lock().unwrap();
+        if let Some(last) = *last_backup {
+            let wait = last + BACKUP_INTERVAL - now;
+            if wait > Duration::zero() {
+                return Err(wait);
+            }
+        }
+        *last_backup = Some(now);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn uptime_is_formatted_in_hours_minutes_seconds() {
+        let mut status = BotStatus::new("@bot:example.org".to_owned());
+        let now = Utc::now();
+        status.started_at = now - Duration::seconds(26 * 3600 + 5 * 60 + 9);
+
+        let uptime = status.uptime_at(now);
+        assert!(!uptime.is_empty());
+        assert_eq!(uptime, "26h 5m 9s");
+        assert!(!status.uptime().is_empty());
+
+        status.record_sync();
+        assert_eq!(status.syncs(), 1);
+    }
+
+    #[test]
+    fn backups_are_rate_limited() {
+        let status = BotStatus::new("@bot:example.org".to_owned());
+        let now = Utc::now();
+        assert!(status.start_backup_at(now).is_ok());
+        let wait = status
+            .start_backup_at(now + Duration::seconds(20))
+            .unwrap_err();
+        assert_eq!(wait, Duration::seconds(40));
+        assert!(status.start_backup_at(now + BACKUP_INTERVAL).is_ok());
+    }
+}
@@ -0,0 +1,172 @@
+use matrix_sdk::ruma::OwnedRoomId;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tracing::error;
+
+/// In-flight commands running at least this long are listed by `!bot status`
+pub const LONG_RUNNING_AFTER: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone)]
+pub struct InFlightCommand {
+    pub room_id: OwnedRoomId,
+    pub sender: String,
+    pub command: String,
+    pub started: Instant,
+}
+
+/// Runs commands under a timeout and keeps track of the ones still running.
+/// A zero timeout disables the limit but commands are still tracked.
+#[derive(Debug)]
+pub struct CommandWatchdog {
+    timeout: Duration,
+    next_id: AtomicU64,
+    in_flight: Mutex<HashMap<u64, InFlightCommand>>,
+    stuck: AtomicU64,
+}
+
+impl CommandWatchdog {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            timeout,
+            next_id: AtomicU64::new(0),
+            in_flight: Mutex::new(HashMap::new()),
+            stuck: AtomicU64::new(0),
+        }
+    }
+
+    /// Run `command_future`, dropping it if it outlives the timeout. The future's
+    /// own output is returned as is; `Err` means the watchdog cancelled it after that long.
+    pub async fn run<F: Future>(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: &str,
+        command: &str,
+        command_future: F,
+    ) -> Result<F::Output, Duration> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        let entry = InFlightCommand {
+            room_id: room_id.clone(),
+            sender: sender.to_owned(),
+            command: command.to_owned(),
+            started: Instant::now(),
+        };
+        self.in_flight.lock().unwrap().insert(id, entry.clone());
+        // Untracked however the command ends, even if the caller is cancelled
+        let tracked = Tracked { watchdog: self, id };
+
+        let result = if self.timeout.is_zero() {
+            Ok(command_future.await)
+        } else {
+            tokio::time::timeout(self.timeout, command_future).await
+        };
+        drop(tracked);
+
+        result.map_err(|_| {
+            let elapsed = entry.started.elapsed();
+            self.stuck.fetch_add(1, Ordering::Relaxed);
+            crate::exit::RUN_STATS.record_stuck_command();
+            error!(
+                room_id = %entry.room_id,
+                sender = %entry.sender,
+                command = %entry.command,
+                elapsed_ms = elapsed.as_millis() as u64,
+                "Command timed out and was cancelled"
+            );
+            elapsed
+        })
+    }
+
+    /// Commands running for at least `min_age`, longest first
+    pub fn long_running(&self, min_age: Duration) -> Vec<(InFlightCommand, Duration)> {
+        let mut commands: Vec<(InFlightCommand, Duration)> = self
+            .in_flight
+            .lock()
+            .unwrap()
+            .values()
+            .map(|c| (c.clone(), c.started.elapsed()))
+            .filter(|(_, elapsed)| *elapsed >= min_age)
+            .collect();
+        commands.sort_by_key(|(_, elapsed)| std::cmp::Reverse(*elapsed));
+        commands
+    }
+
+    /// Commands the watchdog has cancelled since startup
+    pub fn stuck_count(&self) -> u64 {
+        self.stuck.load(Ordering::Relaxed)
+    }
+}
+
+struct Tracked<'a> {
+    watchdog: &'a CommandWatchdog,
+    id: u64,
+}
+
+impl Drop for Tracked<'_> {
+    fn drop(&mut self) {
+        self.watchdog.in_flight.lock().unwrap().remove(&self.id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room() -> OwnedRoomId {
+        OwnedRoomId::try_from("!room:example.org").unwrap()
+    }
+
+    #[tokio::test]
+    async fn never_completing_command_times_out() {
+        let watchdog = CommandWatchdog::new(Duration::from_millis(20));
+        let result = watchdog
+            .run(
+                &room(),
+                "@alice:example.org",
+                "list",
+                std::future::pending::<()>(),
+            )
+            .await;
+
+        let elapsed = result.unwrap_err();
+        assert!(elapsed >= Duration::from_millis(20));
+        assert_eq!(watchdog.stuck_count(), 1);
+        assert!(watchdog.long_running(Duration::ZERO).is_empty());
+    }
+
+    #[tokio::test]
+    async fn finished_command_returns_its_output() {
+        let watchdog = CommandWatchdog::new(Duration::from_secs(5));
+        let result = watchdog
+            .run(&room(), "@alice:example.org", "add", async { 42 })
+            .await;
+        assert_eq!(result.unwrap(), 42);
+        assert_eq!(watchdog.stuck_count(), 0);
+    }
+
+    #[tokio::test]
+    async fn running_commands_are_listed() {
+        let watchdog = std::sync::Arc::new(CommandWatchdog::new(Duration::ZERO));
+        let running = watchdog.clone();
+        let handle = tokio::spawn(async move {
+            running
+                .run(
+                    &room(),
+                    "@alice:example.org",
+                    "export",
+                    std::future::pending::<()>(),
+                )
+                .await
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let long = watchdog.long_running(Duration::from_millis(10));
+        assert_eq!(long.len(), 1);
+        assert_eq!(long[0].0.command, "export");
+        handle.abort();
+        let _ = handle.await;
+        assert!(watchdog.long_running(Duration::ZERO).is_empty());
+    }
+}
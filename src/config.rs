@@ -1,20 +1,49 @@
 use std::env;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 // App constants
 pub const APP_NAME: &str = env!("CARGO_PKG_NAME");
 pub const APP_VERSION: &str = env!("CARGO_PKG_VERSION");
+pub const MAX_COMMAND_PREFIX_LEN: usize = 8;
+pub const DEFAULT_MAX_RETRIES: usize = 3;
+pub const DEFAULT_RETRY_DELAY_SECS: u64 = 1;
+pub const DEFAULT_SYNC_TOKEN_MAX_AGE_HOURS: u64 = 24;
+pub const DEFAULT_REPORT_COOLDOWN_SECS: u64 = 30;
+pub const DEFAULT_COMMAND_PREFIX: &str = "!";
+pub const DEFAULT_PRESENCE_IDLE_MINUTES: u64 = 10;
+pub const DEFAULT_SAVE_DEBOUNCE_SECS: u64 = 5;
+pub const DEFAULT_MAX_CONCURRENT_COMMANDS: usize = 64;
+pub const DEFAULT_MAX_SAVED_FILES: usize = 20;
+pub const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 30;
+pub const DEFAULT_MAX_TASKS_PER_ROOM: usize = 500;
+pub const DEFAULT_REMINDER_INTERVAL_MINUTES: u64 = 15;
+pub const DEFAULT_REMINDER_LEAD_HOURS: u64 = 24;
 
-use anyhow::{Result, anyhow};
-use clap::Parser;
-use matrix_sdk::ruma::{OwnedUserId, UserId};
+use anyhow::{Context, Result, anyhow};
+use chrono::NaiveTime;
+use clap::{Parser, Subcommand};
+use matrix_sdk::ruma::{OwnedRoomId, OwnedUserId, RoomId, UserId};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
 use tracing::{info, warn};
 use url::Url;
 
+use crate::storage::format::StorageFormat;
+use crate::storage::save_schedule::parse_save_time;
+
 // Define the CLI arguments using clap
 #[derive(Parser, Debug, Clone)]
 #[command(author, version, about)]
 pub struct Args {
+    /// Path to a TOML config file; command-line flags take precedence over it
+    #[clap(long)]
+    pub config: Option<PathBuf>,
+
+    /// Watch the config file and apply changes to reloadable settings without a restart
+    #[clap(long, requires = "config")]
+    pub watch_config: bool,
+
     /// Directory to store data files (default: platform-specific data directory + /asmith_bot)
     #[clap(long)]
     pub data_dir: Option<PathBuf>,
@@ -39,26 +68,387 @@ pub struct Args {
     #[clap(long)]
     pub debug: bool,
 
+    /// Log filter directive, e.g. "asmith=debug,matrix_sdk=warn" (default depends on --debug)
+    #[clap(long)]
+    pub log_level: Option<String>,
+
+    /// Also write logs to this file, as one JSON object per line
+    #[clap(long)]
+    pub log_file: Option<PathBuf>,
+
     /// Maximum number of consecutive connection failures before exiting (default: 3)
-    #[clap(long, default_value_t = 3)]
+    #[clap(long)]
+    pub max_retries: Option<usize>,
+
+    /// Base delay in seconds before retrying a failed sync; doubles per consecutive failure up to 60s (default: 1)
+    #[clap(long)]
+    pub retry_delay_secs: Option<u64>,
+
+    /// Message the bot sends after joining a room on invite
+    #[clap(long)]
+    pub welcome_text: Option<String>,
+
+    /// Warn when resuming from a sync token older than this many hours (default: 24)
+    #[clap(long)]
+    pub sync_token_max_age_hours: Option<u64>,
+
+    /// Per-room cooldown in seconds for report commands like !stats and !export (default: 30, 0 disables)
+    #[clap(long)]
+    pub report_cooldown_secs: Option<u64>,
+
+    /// Messages with a plain or HTML body above this many bytes are split into several events
+    #[clap(long)]
+    pub max_message_bytes: Option<usize>,
+
+    /// Prefix that marks a message as a bot command (default: "!", at most 8 characters such as "todo:", no whitespace)
+    #[clap(long)]
+    pub command_prefix: Option<String>,
+
+    /// Compress the weekly archival snapshots with gzip
+    #[clap(long)]
+    pub archive_compress: bool,
+
+    /// Move task history entries older than this many days into per-room history files
+    #[clap(long)]
+    pub history_offload_days: Option<u32>,
+
+    /// Store each room in its own file under `rooms/` instead of one file for all rooms
+    #[clap(long)]
+    pub per_room_storage: bool,
+
+    /// Write task changes to disk at most once per this many seconds (default: 5, 0 writes on every change)
+    #[clap(long)]
+    pub save_debounce_secs: Option<u64>,
+
+    /// Keep only this many timestamped save files, deleting the oldest (default: 20, 0 keeps all)
+    #[clap(long)]
+    pub max_saved_files: Option<usize>,
+
+    /// Encoding of new save files (default: json-pretty); files in any format can be loaded
+    #[clap(long, value_enum)]
+    pub storage_format: Option<StorageFormat>,
+
+    /// Only respond in this room (repeatable); with none given the bot responds everywhere
+    #[clap(long = "active-room")]
+    pub active_rooms: Vec<OwnedRoomId>,
+
+    /// Only take commands from this user (repeatable, or comma-separated in the
+    /// MATRIX_ALLOWED_USERS env variable); with none given everyone may use the bot
+    #[clap(long = "allowed-user")]
+    pub allowed_users: Vec<OwnedUserId>,
+
+    /// Show as online only after recent commands instead of always online
+    #[clap(long)]
+    pub activity_presence: bool,
+
+    /// Minutes without commands after which the bot shows as unavailable (default: 10)
+    #[clap(long)]
+    pub presence_idle_minutes: Option<u64>,
+
+    /// Commands processed at the same time; further commands get a busy reply (default: 64)
+    #[clap(long)]
+    pub max_concurrent_commands: Option<usize>,
+
+    /// Matrix power level needed for destructive commands (cleartasks, load, close).
+    /// Unset leaves them to the bot's room admins.
+    #[clap(long)]
+    pub min_power_level: Option<i64>,
+
+    /// Seconds a command may run before it is cancelled and reported as stuck (default: 30, 0 disables)
+    #[clap(long)]
+    pub command_timeout_secs: Option<u64>,
+
+    /// Send command responses as replies in a thread on the command message
+    #[clap(long)]
+    pub thread_replies: bool,
+
+    /// Most tasks a room's list may hold, checked by !add, !import and !bot import (default: 500)
+    #[clap(long)]
+    pub max_tasks_per_room: Option<usize>,
+
+    /// Minutes between scans for tasks to send due date reminders for (default: 15, 0 disables)
+    #[clap(long)]
+    pub reminder_interval_minutes: Option<u64>,
+
+    /// Remind a room about tasks due within this many hours, and overdue ones (default: 24)
+    #[clap(long)]
+    pub reminder_lead_hours: Option<u64>,
+
+    /// Save every day at this UTC time, as HH:MM; `!bot schedule` changes it while running
+    #[clap(long)]
+    pub auto_save_time: Option<String>,
+
+    /// Offline command to run instead of starting the bot
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+// Offline subcommands that operate on the data directory without logging in
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Print stored task data as JSON to stdout
+    Export {
+        /// Date (YYYY-MM-DD) of the archival snapshot to export
+        #[clap(long)]
+        from_archive: String,
+    },
+    /// Print the journal of recently persisted sync tokens
+    SyncLog,
+    /// Print per-room differences between two save files
+    Diff {
+        /// Older save file, as a path or a name in the data directory
+        a: PathBuf,
+        /// Newer save file, as a path or a name in the data directory
+        b: PathBuf,
+    },
+}
+
+/// Contents of the optional `--config` TOML file, mirroring `BotConfig`.
+/// Every key is optional; values set on the command line or via env vars win.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BotConfigFile {
+    // Only read at startup, changing these requires a restart
+    pub data_dir: Option<PathBuf>,
+    pub homeserver: Option<String>,
+    pub user_id: Option<String>,
+    pub password: Option<String>,
+    pub access_token: Option<String>,
+    pub debug: Option<bool>,
+    pub log_file: Option<PathBuf>,
+    pub sync_token_max_age_hours: Option<u64>,
+    pub report_cooldown_secs: Option<u64>,
+    pub max_message_bytes: Option<usize>,
+    pub command_prefix: Option<String>,
+    pub archive_compress: Option<bool>,
+    pub history_offload_days: Option<u32>,
+    pub per_room_storage: Option<bool>,
+    pub save_debounce_secs: Option<u64>,
+    pub max_saved_files: Option<usize>,
+    pub storage_format: Option<StorageFormat>,
+    pub active_rooms: Option<Vec<String>>,
+    pub allowed_users: Option<Vec<String>>,
+    pub activity_presence: Option<bool>,
+    pub presence_idle_minutes: Option<u64>,
+    pub max_concurrent_commands: Option<usize>,
+    pub min_power_level: Option<i64>,
+    pub command_timeout_secs: Option<u64>,
+    pub thread_replies: Option<bool>,
+    pub max_tasks_per_room: Option<usize>,
+    pub reminder_interval_minutes: Option<u64>,
+    pub reminder_lead_hours: Option<u64>,
+    pub auto_save_time: Option<String>,
+
+    // Reloadable while running with --watch-config
+    pub log_level: Option<String>,
+    pub max_retries: Option<usize>,
+    pub retry_delay_secs: Option<u64>,
+    pub welcome_text: Option<String>,
+}
+
+pub fn load_config_file(path: &Path) -> Result<BotConfigFile> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read config file: {:?}", path))?;
+    toml::from_str(&content).with_context(|| format!("Failed to parse config file: {:?}", path))
+}
+
+impl BotConfigFile {
+    /// Names of the settings that changed compared to `other` but only take effect after a restart
+    pub fn restart_required_changes(&self, other: &BotConfigFile) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        if self.data_dir != other.data_dir {
+            changed.push("data_dir");
+        }
+        if self.homeserver != other.homeserver {
+            changed.push("homeserver");
+        }
+        if self.user_id != other.user_id {
+            changed.push("user_id");
+        }
+        if self.log_file != other.log_file {
+            changed.push("log_file");
+        }
+        if self.password != other.password {
+            changed.push("password");
+        }
+        if self.access_token != other.access_token {
+            changed.push("access_token");
+        }
+        if self.debug != other.debug {
+            changed.push("debug");
+        }
+        if self.sync_token_max_age_hours != other.sync_token_max_age_hours {
+            changed.push("sync_token_max_age_hours");
+        }
+        if self.report_cooldown_secs != other.report_cooldown_secs {
+            changed.push("report_cooldown_secs");
+        }
+        if self.max_message_bytes != other.max_message_bytes {
+            changed.push("max_message_bytes");
+        }
+        if self.command_prefix != other.command_prefix {
+            changed.push("command_prefix");
+        }
+        if self.archive_compress != other.archive_compress {
+            changed.push("archive_compress");
+        }
+        if self.history_offload_days != other.history_offload_days {
+            changed.push("history_offload_days");
+        }
+        if self.per_room_storage != other.per_room_storage {
+            changed.push("per_room_storage");
+        }
+        if self.save_debounce_secs != other.save_debounce_secs {
+            changed.push("save_debounce_secs");
+        }
+        if self.max_saved_files != other.max_saved_files {
+            changed.push("max_saved_files");
+        }
+        if self.storage_format != other.storage_format {
+            changed.push("storage_format");
+        }
+        if self.active_rooms != other.active_rooms {
+            changed.push("active_rooms");
+        }
+        if self.allowed_users != other.allowed_users {
+            changed.push("allowed_users");
+        }
+        if self.activity_presence != other.activity_presence {
+            changed.push("activity_presence");
+        }
+        if self.presence_idle_minutes != other.presence_idle_minutes {
+            changed.push("presence_idle_minutes");
+        }
+        if self.max_concurrent_commands != other.max_concurrent_commands {
+            changed.push("max_concurrent_commands");
+        }
+        if self.min_power_level != other.min_power_level {
+            changed.push("min_power_level");
+        }
+        if self.command_timeout_secs != other.command_timeout_secs {
+            changed.push("command_timeout_secs");
+        }
+        if self.thread_replies != other.thread_replies {
+            changed.push("thread_replies");
+        }
+        if self.max_tasks_per_room != other.max_tasks_per_room {
+            changed.push("max_tasks_per_room");
+        }
+        if self.reminder_interval_minutes != other.reminder_interval_minutes {
+            changed.push("reminder_interval_minutes");
+        }
+        if self.reminder_lead_hours != other.reminder_lead_hours {
+            changed.push("reminder_lead_hours");
+        }
+        if self.auto_save_time != other.auto_save_time {
+            changed.push("auto_save_time");
+        }
+        changed
+    }
+}
+
+/// The subset of the configuration that can change while the bot is running
+#[derive(Debug, Clone, PartialEq)]
+pub struct LiveSettings {
+    pub log_level: Option<String>,
     pub max_retries: usize,
+    pub retry_delay_secs: u64,
+    pub welcome_text: Option<String>,
+}
+
+pub type SharedLiveSettings = Arc<RwLock<LiveSettings>>;
+
+impl LiveSettings {
+    /// Overlay the reloadable keys present in `file`; absent keys keep the startup values
+    pub fn with_file(&self, file: &BotConfigFile) -> Self {
+        Self {
+            log_level: file.log_level.clone().or_else(|| self.log_level.clone()),
+            max_retries: file.max_retries.unwrap_or(self.max_retries),
+            retry_delay_secs: file.retry_delay_secs.unwrap_or(self.retry_delay_secs),
+            welcome_text: file
+                .welcome_text
+                .clone()
+                .or_else(|| self.welcome_text.clone()),
+        }
+    }
+
+    /// Human readable list of the settings that differ from `other`
+    pub fn changes_from(&self, other: &LiveSettings) -> Vec<String> {
+        let mut changes = Vec::new();
+        if self.log_level != other.log_level {
+            changes.push(format!(
+                "log_level: {:?} -> {:?}",
+                other.log_level, self.log_level
+            ));
+        }
+        if self.max_retries != other.max_retries {
+            changes.push(format!(
+                "max_retries: {} -> {}",
+                other.max_retries, self.max_retries
+            ));
+        }
+        if self.retry_delay_secs != other.retry_delay_secs {
+            changes.push(format!(
+                "retry_delay_secs: {} -> {}",
+                other.retry_delay_secs, self.retry_delay_secs
+            ));
+        }
+        if self.welcome_text != other.welcome_text {
+            changes.push("welcome_text updated".to_owned());
+        }
+        changes
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct BotConfig {
+    pub config_file: Option<PathBuf>,
+    pub watch_config: bool,
     pub data_dir: PathBuf,
     pub homeserver: Option<Url>,
     pub user_id: Option<OwnedUserId>,
     pub password: Option<String>,
     pub access_token: Option<String>,
     pub debug: bool,
+    pub log_level: Option<String>,
+    pub log_file: Option<PathBuf>,
     pub max_retries: usize,
+    pub retry_delay_secs: u64,
+    pub welcome_text: Option<String>,
+    pub sync_token_max_age_hours: u64,
+    pub report_cooldown_secs: u64,
+    pub max_message_bytes: usize,
+    pub command_prefix: String,
+    pub archive_compress: bool,
+    pub history_offload_days: Option<u32>,
+    pub per_room_storage: bool,
+    pub save_debounce_secs: u64,
+    pub max_saved_files: usize,
+    pub storage_format: StorageFormat,
+    pub active_rooms: Vec<OwnedRoomId>,
+    pub allowed_users: Vec<OwnedUserId>,
+    pub activity_presence: bool,
+    pub presence_idle_minutes: u64,
+    pub max_concurrent_commands: usize,
+    pub min_power_level: Option<i64>,
+    pub command_timeout_secs: u64,
+    pub thread_replies: bool,
+    pub max_tasks_per_room: usize,
+    pub reminder_interval_minutes: u64,
+    pub reminder_lead_hours: u64,
+    pub auto_save_time: Option<NaiveTime>,
+    pub command: Option<Command>,
 }
 
 impl BotConfig {
     pub fn from_args(args: Args) -> Result<Self> {
+        let file = match &args.config {
+            Some(path) => load_config_file(path)?,
+            None => BotConfigFile::default(),
+        };
+
         // Get data directory or use platform default
-        let data_dir = if let Some(dir) = args.data_dir {
+        let data_dir = if let Some(dir) = args.data_dir.or(file.data_dir) {
             dir
         } else {
             let mut dir = dirs::data_dir()
@@ -74,16 +464,80 @@ impl BotConfig {
         }
 
         // Check for environment variables for sensitive data
-        let password = args.password.or_else(|| env::var("MATRIX_PASSWORD").ok());
+        let password = args
+            .password
+            .or_else(|| env::var("MATRIX_PASSWORD").ok())
+            .or(file.password);
         let access_token = args
             .access_token
-            .or_else(|| env::var("MATRIX_ACCESS_TOKEN").ok());
+            .or_else(|| env::var("MATRIX_ACCESS_TOKEN").ok())
+            .or(file.access_token);
+
+        let homeserver = match (args.homeserver, file.homeserver) {
+            (Some(url), _) => Some(url),
+            (None, Some(raw)) => Some(
+                Url::parse(&raw)
+                    .with_context(|| format!("Invalid homeserver URL in config file: {}", raw))?,
+            ),
+            (None, None) => None,
+        };
+        let user_id = match (args.user_id, file.user_id) {
+            (Some(id), _) => Some(id),
+            (None, Some(raw)) => Some(
+                UserId::parse(raw.as_str())
+                    .with_context(|| format!("Invalid user ID in config file: {}", raw))?,
+            ),
+            (None, None) => None,
+        };
+
+        let active_rooms = if !args.active_rooms.is_empty() {
+            args.active_rooms
+        } else {
+            file.active_rooms
+                .unwrap_or_default()
+                .iter()
+                .map(|raw| {
+                    RoomId::parse(raw.as_str())
+                        .with_context(|| format!("Invalid room ID in config file: {}", raw))
+                })
+                .collect::<Result<Vec<OwnedRoomId>>>()?
+        };
+
+        let allowed_users = if !args.allowed_users.is_empty() {
+            args.allowed_users
+        } else {
+            let raw_users = match env::var("MATRIX_ALLOWED_USERS") {
+                Ok(list) => list
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|u| !u.is_empty())
+                    .map(str::to_owned)
+                    .collect(),
+                Err(_) => file.allowed_users.unwrap_or_default(),
+            };
+            raw_users
+                .iter()
+                .map(|raw| {
+                    UserId::parse(raw.as_str())
+                        .with_context(|| format!("Invalid allowed user ID: {}", raw))
+                })
+                .collect::<Result<Vec<OwnedUserId>>>()?
+        };
+
+        let auto_save_time = args
+            .auto_save_time
+            .or(file.auto_save_time)
+            .map(|raw| {
+                parse_save_time(&raw)
+                    .ok_or_else(|| anyhow!("Invalid auto save time '{}', expected HH:MM", raw))
+            })
+            .transpose()?;
 
-        if args.homeserver.is_none() {
+        if homeserver.is_none() {
             warn!("No homeserver URL specified. Login will not be possible without it.");
         }
 
-        if args.user_id.is_none() {
+        if user_id.is_none() {
             warn!("No user ID specified. Login will not be possible without it.");
         }
 
@@ -93,17 +547,125 @@ impl BotConfig {
             );
         }
 
+        let max_message_bytes = args
+            .max_message_bytes
+            .or(file.max_message_bytes)
+            .unwrap_or(crate::messaging::DEFAULT_MAX_MESSAGE_BYTES);
+        if max_message_bytes < 1024 {
+            return Err(anyhow!("max_message_bytes must be at least 1024"));
+        }
+
+        let max_concurrent_commands = args
+            .max_concurrent_commands
+            .or(file.max_concurrent_commands)
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_COMMANDS);
+        if max_concurrent_commands == 0 {
+            return Err(anyhow!("max_concurrent_commands must be at least 1"));
+        }
+
+        let command_prefix = args
+            .command_prefix
+            .or(file.command_prefix)
+            .unwrap_or_else(|| DEFAULT_COMMAND_PREFIX.to_owned());
+        if command_prefix.is_empty() {
+            return Err(anyhow!("Command prefix cannot be empty"));
+        }
+        if command_prefix.chars().count() > MAX_COMMAND_PREFIX_LEN {
+            return Err(anyhow!(
+                "Command prefix '{}' is longer than {} characters",
+                command_prefix,
+                MAX_COMMAND_PREFIX_LEN
+            ));
+        }
+        if command_prefix.chars().any(char::is_whitespace) {
+            return Err(anyhow!("Command prefix cannot contain whitespace"));
+        }
+
         Ok(Self {
+            config_file: args.config,
+            watch_config: args.watch_config,
             data_dir,
-            homeserver: args.homeserver,
-            user_id: args.user_id,
+            homeserver,
+            user_id,
             password,
             access_token,
-            debug: args.debug,
-            max_retries: args.max_retries,
+            debug: args.debug || file.debug.unwrap_or(false),
+            log_level: args.log_level.or(file.log_level),
+            log_file: args.log_file.or(file.log_file),
+            max_retries: args
+                .max_retries
+                .or(file.max_retries)
+                .unwrap_or(DEFAULT_MAX_RETRIES),
+            retry_delay_secs: args
+                .retry_delay_secs
+                .or(file.retry_delay_secs)
+                .unwrap_or(DEFAULT_RETRY_DELAY_SECS),
+            welcome_text: args.welcome_text.or(file.welcome_text),
+            sync_token_max_age_hours: args
+                .sync_token_max_age_hours
+                .or(file.sync_token_max_age_hours)
+                .unwrap_or(DEFAULT_SYNC_TOKEN_MAX_AGE_HOURS),
+            report_cooldown_secs: args
+                .report_cooldown_secs
+                .or(file.report_cooldown_secs)
+                .unwrap_or(DEFAULT_REPORT_COOLDOWN_SECS),
+            max_message_bytes,
+            command_prefix,
+            archive_compress: args.archive_compress || file.archive_compress.unwrap_or(false),
+            history_offload_days: args.history_offload_days.or(file.history_offload_days),
+            per_room_storage: args.per_room_storage || file.per_room_storage.unwrap_or(false),
+            save_debounce_secs: args
+                .save_debounce_secs
+                .or(file.save_debounce_secs)
+                .unwrap_or(DEFAULT_SAVE_DEBOUNCE_SECS),
+            max_saved_files: args
+                .max_saved_files
+                .or(file.max_saved_files)
+                .unwrap_or(DEFAULT_MAX_SAVED_FILES),
+            storage_format: args
+                .storage_format
+                .or(file.storage_format)
+                .unwrap_or_default(),
+            active_rooms,
+            allowed_users,
+            activity_presence: args.activity_presence || file.activity_presence.unwrap_or(false),
+            presence_idle_minutes: args
+                .presence_idle_minutes
+                .or(file.presence_idle_minutes)
+                .unwrap_or(DEFAULT_PRESENCE_IDLE_MINUTES),
+            max_concurrent_commands,
+            min_power_level: args.min_power_level.or(file.min_power_level),
+            command_timeout_secs: args
+                .command_timeout_secs
+                .or(file.command_timeout_secs)
+                .unwrap_or(DEFAULT_COMMAND_TIMEOUT_SECS),
+            thread_replies: args.thread_replies || file.thread_replies.unwrap_or(false),
+            max_tasks_per_room: args
+                .max_tasks_per_room
+                .or(file.max_tasks_per_room)
+                .unwrap_or(DEFAULT_MAX_TASKS_PER_ROOM),
+            reminder_interval_minutes: args
+                .reminder_interval_minutes
+                .or(file.reminder_interval_minutes)
+                .unwrap_or(DEFAULT_REMINDER_INTERVAL_MINUTES),
+            reminder_lead_hours: args
+                .reminder_lead_hours
+                .or(file.reminder_lead_hours)
+                .unwrap_or(DEFAULT_REMINDER_LEAD_HOURS),
+            auto_save_time,
+            command: args.command,
         })
     }
 
+    pub fn live_settings(&self) -> LiveSettings {
+        LiveSettings {
+            log_level: self.log_level.clone(),
+            max_retries: self.max_retries,
+            retry_delay_secs: self.retry_delay_secs,
+            welcome_text: self.welcome_text.clone(),
+        }
+    }
+
     pub fn get_session_file_path(&self) -> PathBuf {
         self.data_dir.join("session.json")
     }
@@ -134,3 +696,76 @@ pub fn init_config() -> Result<BotConfig> {
     let args = Args::parse();
     BotConfig::from_args(args)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A config file with every key set
+    fn full_config_file() -> BotConfigFile {
+        BotConfigFile {
+            data_dir: Some("/var/lib/asmith".into()),
+            homeserver: Some("https://matrix.example.org".to_owned()),
+            user_id: Some("@asmith:example.org".to_owned()),
+            password: Some("hunter2".to_owned()),
+            access_token: Some("syt_token".to_owned()),
+            debug: Some(true),
+            log_file: Some("/var/log/asmith.jsonl".into()),
+            sync_token_max_age_hours: Some(12),
+            report_cooldown_secs: Some(30),
+            max_message_bytes: Some(32_000),
+            command_prefix: Some("?".to_owned()),
+            archive_compress: Some(false),
+            history_offload_days: Some(30),
+            per_room_storage: Some(true),
+            save_debounce_secs: Some(5),
+            max_saved_files: Some(7),
+            storage_format: Some(StorageFormat::JsonLines),
+            active_rooms: Some(vec!["!room:example.org".to_owned()]),
+            allowed_users: Some(vec!["@alice:example.org".to_owned()]),
+            activity_presence: Some(true),
+            presence_idle_minutes: Some(15),
+            max_concurrent_commands: Some(4),
+            min_power_level: Some(50),
+            command_timeout_secs: Some(20),
+            thread_replies: Some(true),
+            max_tasks_per_room: Some(500),
+            reminder_interval_minutes: Some(60),
+            reminder_lead_hours: Some(24),
+            auto_save_time: Some("03:00".to_owned()),
+            log_level: Some("debug".to_owned()),
+            max_retries: Some(5),
+            retry_delay_secs: Some(2),
+            welcome_text: Some("Hi! Try !help".to_owned()),
+        }
+    }
+
+    #[test]
+    fn config_file_round_trips_through_toml() {
+        for config in [BotConfigFile::default(), full_config_file()] {
+            let text = toml::to_string(&config).unwrap();
+            let parsed: BotConfigFile = toml::from_str(&text).unwrap();
+            assert_eq!(parsed, config, "{text}");
+        }
+    }
+
+    #[test]
+    fn unknown_config_keys_are_rejected() {
+        let err =
+            toml::from_str::<BotConfigFile>("max_retries = 3\nretry_delay = 5\n").unwrap_err();
+        assert!(
+            err.to_string().contains("unknown field `retry_delay`"),
+            "{err}"
+        );
+
+        let path =
+            std::env::temp_dir().join(format!("asmith-config-{}.toml", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "[storage]\nformat = \"json-lines\"\n").unwrap();
+        let err = load_config_file(&path).unwrap_err();
+        assert!(
+            format!("{:#}", err).contains("unknown field `storage`"),
+            "{err:#}"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+}
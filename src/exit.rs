@@ -0,0 +1,200 @@
+//! Process exit codes and the final report logged on shutdown.
+//!
+//! Subsystems tag fatal errors with an [`ExitReason`] as anyhow context; `main`
+//! classifies the terminal error so supervisors can tell a bad configuration
+//! (don't restart) from a network outage (restart).
+
+use anyhow::Error;
+use matrix_sdk::ruma::api::client::error::ErrorKind;
+use once_cell::sync::Lazy;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Instant;
+use tracing::{error, info};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExitReason {
+    Clean,
+    Unexpected,
+    ConfigInvalid,
+    AuthFailure,
+    RetriesExhausted,
+    StorageFatal,
+}
+
+impl ExitReason {
+    pub fn code(&self) -> u8 {
+        match self {
+            ExitReason::Clean => 0,
+            ExitReason::Unexpected => 1,
+            ExitReason::ConfigInvalid => 2,
+            ExitReason::AuthFailure => 3,
+            ExitReason::RetriesExhausted => 4,
+            ExitReason::StorageFatal => 5,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExitReason::Clean => "clean_shutdown",
+            ExitReason::Unexpected => "unexpected_error",
+            ExitReason::ConfigInvalid => "config_invalid",
+            ExitReason::AuthFailure => "auth_failure",
+            ExitReason::RetriesExhausted => "connection_retries_exhausted",
+            ExitReason::StorageFatal => "storage_fatal",
+        }
+    }
+}
+
+impl std::fmt::Display for ExitReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let description = match self {
+            ExitReason::Clean => "Clean shutdown",
+            ExitReason::Unexpected => "Unexpected error",
+            ExitReason::ConfigInvalid => "Invalid configuration",
+            ExitReason::AuthFailure => "Authentication failed",
+            ExitReason::RetriesExhausted => "Connection retries exhausted",
+            ExitReason::StorageFatal => "Storage failure",
+        };
+        f.write_str(description)
+    }
+}
+
+/// Whether a homeserver error means our credentials are bad rather than the network
+pub fn is_auth_error_kind(kind: &ErrorKind) -> bool {
+    matches!(
+        kind,
+        ErrorKind::Forbidden { .. }
+            | ErrorKind::UnknownToken { .. }
+            | ErrorKind::MissingToken
+            | ErrorKind::UserDeactivated
+            | ErrorKind::InvalidUsername
+    )
+}
+
+/// Map the error a run ended with to its exit reason. An explicit `ExitReason`
+/// tag anywhere in the chain wins; otherwise homeserver auth errors are recognized.
+pub fn classify(result: &anyhow::Result<()>) -> ExitReason {
+    let Err(err) = result else {
+        return ExitReason::Clean;
+    };
+    if let Some(reason) = err.downcast_ref::<ExitReason>() {
+        return *reason;
+    }
+    let auth_error = err.chain().any(|cause| {
+        let kind = cause
+            .downcast_ref::<matrix_sdk::Error>()
+            .and_then(|e| e.client_api_error_kind())
+            .or_else(|| {
+                cause
+                    .downcast_ref::<matrix_sdk::HttpError>()
+                    .and_then(|e| e.client_api_error_kind())
+            });
+        kind.is_some_and(is_auth_error_kind)
+    });
+    if auth_error {
+        ExitReason::AuthFailure
+    } else {
+        ExitReason::Unexpected
+    }
+}
+
+/// Counters for the shutdown report, updated while the bot runs
+pub struct RunStats {
+    started: Instant,
+    commands: AtomicU64,
+    stuck_commands: AtomicU64,
+    last_sync: Mutex<Option<Instant>>,
+}
+
+pub static RUN_STATS: Lazy<RunStats> = Lazy::new(|| RunStats {
+    started: Instant::now(),
+    commands: AtomicU64::new(0),
+    stuck_commands: AtomicU64::new(0),
+    last_sync: Mutex::new(None),
+});
+
+impl RunStats {
+    pub fn record_command(&self) {
+        self.commands.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// A command the watchdog cancelled after it ran past the timeout
+    pub fn record_stuck_command(&self) {
+        self.stuck_commands.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_sync(&self) {
+        *self.last_sync.lock().unwrap() = Some(Instant::now());
+    }
+}
+
+/// Emit the single structured line summarizing the run
+pub fn log_shutdown_report(reason: ExitReason, error: Option<&Error>) {
+    let stats = &*RUN_STATS;
+    let uptime_secs = stats.started.elapsed().as_secs();
+    let commands = stats.commands.load(Ordering::Relaxed);
+    let stuck_commands = stats.stuck_commands.load(Ordering::Relaxed);
+    // -1 when no sync ever succeeded
+    let last_sync_age_secs = stats
+        .last_sync
+        .lock()
+        .unwrap()
+        .map(|at| at.elapsed().as_secs() as i64)
+        .unwrap_or(-1);
+
+    match error {
+        None => info!(
+            exit_code = reason.code(),
+            exit_reason = reason.as_str(),
+            uptime_secs,
+            commands,
+            stuck_commands,
+            last_sync_age_secs,
+            "Shutdown report"
+        ),
+        Some(e) => error!(
+            exit_code = reason.code(),
+            exit_reason = reason.as_str(),
+            uptime_secs,
+            commands,
+            stuck_commands,
+            last_sync_age_secs,
+            error = format!("{:#}", e),
+            "Shutdown report"
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::{Context, anyhow};
+
+    #[test]
+    fn errors_are_classified_by_their_exit_reason() {
+        let tagged = |reason: ExitReason| Err(anyhow!("boom").context(reason));
+        let cases = [
+            (Ok(()), ExitReason::Clean),
+            (Err(anyhow!("boom")), ExitReason::Unexpected),
+            (tagged(ExitReason::Unexpected), ExitReason::Unexpected),
+            (tagged(ExitReason::ConfigInvalid), ExitReason::ConfigInvalid),
+            (tagged(ExitReason::AuthFailure), ExitReason::AuthFailure),
+            (
+                tagged(ExitReason::RetriesExhausted),
+                ExitReason::RetriesExhausted,
+            ),
+            (tagged(ExitReason::StorageFatal), ExitReason::StorageFatal),
+            // A tag deeper in the chain still counts
+            (
+                tagged(ExitReason::StorageFatal)
+                    .context("Failed to auto-load bot state")
+                    .context("Startup failed"),
+                ExitReason::StorageFatal,
+            ),
+        ];
+        for (result, expected) in cases {
+            assert_eq!(classify(&result), expected, "{:?}", result);
+        }
+    }
+}
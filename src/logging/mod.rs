@@ -1,8 +1,20 @@
-use anyhow::Result;
-use tracing_subscriber::EnvFilter;
+use anyhow::{Context, Result, anyhow};
+use std::path::Path;
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::{EnvFilter, Registry, prelude::*, reload};
+
+/// Handle used to swap the log filter at runtime (see `--watch-config`)
+pub type LogReloadHandle = reload::Handle<EnvFilter, Registry>;
+
+/// Build the log filter. An explicit `log_level` directive wins, then `RUST_LOG`,
+/// then the defaults based on the debug setting.
+pub fn build_filter(app_name: &str, debug: bool, log_level: Option<&str>) -> Result<EnvFilter> {
+    if let Some(directive) = log_level {
+        return EnvFilter::try_new(directive)
+            .map_err(|e| anyhow!("Invalid log level '{}': {}", directive, e));
+    }
 
-/// Initialize logging with the appropriate filter level based on debug setting
-pub fn init_logging(app_name: &str, debug: bool) -> Result<()> {
     // Create the filter based on debug flag
     let filter = if debug {
         EnvFilter::try_from_default_env()
@@ -11,12 +23,52 @@ pub fn init_logging(app_name: &str, debug: bool) -> Result<()> {
         EnvFilter::try_from_default_env()
             .unwrap_or_else(|_| EnvFilter::new(format!("{},matrix_sdk=info", app_name)))
     };
+    Ok(filter)
+}
+
+/// Initialize logging with the appropriate filter level based on debug setting.
+/// With a `log_file` the same events are also appended to it as JSON lines; keep
+/// the returned guard alive until exit so buffered lines get flushed.
+pub fn init_logging(
+    app_name: &str,
+    debug: bool,
+    log_level: Option<&str>,
+    log_file: Option<&Path>,
+) -> Result<(LogReloadHandle, Option<WorkerGuard>)> {
+    let filter = build_filter(app_name, debug, log_level)?;
+    let (filter_layer, handle) = reload::Layer::new(filter);
+
+    let (file_layer, guard) = match log_file {
+        Some(path) => {
+            let (writer, guard) = tracing_appender::non_blocking(open_log_file(path)?);
+            let layer = tracing_subscriber::fmt::layer().json().with_writer(writer);
+            (Some(layer), Some(guard))
+        }
+        None => (None, None),
+    };
 
     // Initialize the tracing subscriber with the filter
-    tracing_subscriber::fmt()
-        .with_target(true)
-        .with_env_filter(filter)
+    tracing_subscriber::registry()
+        .with(filter_layer)
+        .with(tracing_subscriber::fmt::layer().with_target(true))
+        .with(file_layer)
         .init();
 
-    Ok(())
+    Ok((handle, guard))
+}
+
+// Same as `rolling::never`, which panics when the file can't be created
+fn open_log_file(path: &Path) -> Result<RollingFileAppender> {
+    let file_name = path
+        .file_name()
+        .with_context(|| format!("Log file path has no file name: {:?}", path))?;
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    RollingFileAppender::builder()
+        .rotation(Rotation::NEVER)
+        .filename_prefix(file_name.to_string_lossy())
+        .build(dir)
+        .with_context(|| format!("Failed to open log file: {:?}", path))
 }
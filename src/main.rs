@@ -1,8 +1,11 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 
 use once_cell::sync::OnceCell;
+use std::path::PathBuf;
+use std::process::ExitCode;
 use std::sync::Arc;
-use tracing::{debug, info};
+use tracing::{debug, error, info};
+use tracing_appender::non_blocking::WorkerGuard;
 
 // Import app constants from config module
 use crate::config::{APP_NAME, APP_VERSION};
@@ -11,6 +14,7 @@ use crate::config::{APP_NAME, APP_VERSION};
 mod app;
 mod bot_commands;
 mod config;
+mod exit;
 mod logging;
 mod matrix_integration;
 mod messaging;
@@ -19,24 +23,55 @@ mod task_management;
 
 // Module components we need to use
 use crate::bot_commands::BotCore;
-use config::init_config;
+use config::{Command, init_config};
+use exit::ExitReason;
 
 // Global access to BotCore
 static BOT_CORE: OnceCell<Arc<BotCore>> = OnceCell::new();
 
 #[tokio::main]
-async fn main() -> Result<()> {
+async fn main() -> ExitCode {
+    // Held here so the shutdown report still reaches the log file before it is flushed
+    let mut log_guard = None;
+    let result = run(&mut log_guard).await;
+    let reason = exit::classify(&result);
+    exit::log_shutdown_report(reason, result.as_ref().err());
+    drop(log_guard);
+    if let Err(e) = &result {
+        // Logging may not be set up yet (e.g. a config error), so always print the cause
+        eprintln!("Error: {:?}", e);
+    }
+    ExitCode::from(reason.code())
+}
+
+async fn run(log_guard: &mut Option<WorkerGuard>) -> Result<()> {
+    // Start the uptime clock for the shutdown report
+    once_cell::sync::Lazy::force(&exit::RUN_STATS);
+
     // Initialize configuration from arguments and environment variables
-    let config = init_config()?;
+    let config = init_config().context(ExitReason::ConfigInvalid)?;
 
     // Initialize logging
-    logging::init_logging(APP_NAME, config.debug)?;
+    let (log_handle, guard) = logging::init_logging(
+        APP_NAME,
+        config.debug,
+        config.log_level.as_deref(),
+        config.log_file.as_deref(),
+    )?;
+    *log_guard = guard;
 
     info!("Starting {} v{}...", APP_NAME, APP_VERSION);
     debug!("Configuration: {:?}", config);
 
+    // Offline subcommands run against the data directory and exit without logging in
+    if let Some(command) = &config.command {
+        return run_command(command, &config);
+    }
+
     // Ensure required directories exist
-    app::ensure_directories(&config).await?;
+    app::ensure_directories(&config)
+        .await
+        .context(ExitReason::StorageFatal)?;
 
     // Initialize Matrix client, session, and storage manager
     let context = app::init_matrix_client(&config).await?;
@@ -44,11 +79,79 @@ async fn main() -> Result<()> {
     // Setup BotCore and event handlers
     app::setup_bot_core(&context).await?;
 
+    // Apply config file edits to the reloadable settings while running
+    if config.watch_config {
+        app::spawn_config_watcher(&config, context.live_settings.clone(), log_handle)
+            .context(ExitReason::ConfigInvalid)?;
+    }
+
     // Auto-load previous bot state if available
     app::auto_load_bot_state(&context.storage_manager).await?;
 
-    // Start the main sync loop
-    app::start_sync_loop(&context, &config).await?;
+    // Start the main sync loop; it returns Ok on Ctrl-C or SIGTERM
+    let result = app::start_sync_loop(&context, &config).await;
+
+    // Don't lose changes still waiting for the debounced save
+    match context.storage_manager.flush_if_dirty().await {
+        Ok(true) => info!("Saved pending task changes before exit"),
+        Ok(false) => {}
+        Err(e) => error!("Failed to save pending task changes before exit: {}", e),
+    }
+
+    result
+}
 
-    Ok(())
+fn run_command(command: &Command, config: &config::BotConfig) -> Result<()> {
+    match command {
+        Command::Export { from_archive } => {
+            let date =
+                chrono::NaiveDate::parse_from_str(from_archive, "%Y-%m-%d").map_err(|e| {
+                    anyhow::anyhow!(
+                        "Invalid archive date '{}' (expected YYYY-MM-DD): {}",
+                        from_archive,
+                        e
+                    )
+                })?;
+            let data = storage::archive::read_archive(&config.data_dir, date)?;
+            println!("{}", serde_json::to_string_pretty(&data)?);
+            Ok(())
+        }
+        Command::SyncLog => {
+            let entries = matrix_integration::sync_journal::read_journal(&config.data_dir)?;
+            if entries.is_empty() {
+                println!("No sync tokens recorded yet.");
+            }
+            for entry in entries {
+                println!(
+                    "{}  {}  rooms={} events={} limited={}",
+                    entry.timestamp.format("%Y-%m-%d %H:%M:%S"),
+                    entry.token,
+                    entry.joined_rooms,
+                    entry.timeline_events,
+                    entry.limited_rooms
+                );
+            }
+            Ok(())
+        }
+        Command::Diff { a, b } => {
+            // Bare file names are looked up in the data directory
+            let resolve = |path: &PathBuf| {
+                if path.exists() {
+                    path.clone()
+                } else {
+                    config.data_dir.join(path)
+                }
+            };
+            let before = storage::diff::read_storage_file(&resolve(a))?;
+            let after = storage::diff::read_storage_file(&resolve(b))?;
+            let diffs = storage::diff::diff_storage(&before, &after);
+            if diffs.is_empty() {
+                println!("No differences.");
+            }
+            for diff in diffs {
+                println!("{}", diff.render());
+            }
+            Ok(())
+        }
+    }
 }
@@ -0,0 +1,216 @@
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedRoomId;
+use matrix_sdk::ruma::events::StateEventType;
+use matrix_sdk::ruma::events::room::message::RoomMessageEventContent;
+use matrix_sdk::{Client, Room};
+use serde::Serialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{debug, error, info, warn};
+
+use crate::storage::StorageManager;
+use crate::task_management::Task;
+
+/// Custom state event type board frontends can read, one state key per page
+pub const BOARD_EVENT_TYPE: &str = "io.asmith.tasklist";
+pub const BOARD_FORMAT_VERSION: u32 = 1;
+// Stay well below the 64 KiB event limit once the event envelope is added
+pub const MAX_BOARD_PAGE_BYTES: usize = 48 * 1024;
+pub const MAX_BOARD_PAGES: usize = 10;
+const MAX_BOARD_TITLE_CHARS: usize = 200;
+// Changes are batched: the board is only republished this often, and only if it changed
+const BOARD_PUBLISH_INTERVAL_SECS: u64 = 10;
+
+/// Compact task representation used on the board
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct BoardTask {
+    pub id: usize,
+    pub title: String,
+    pub status: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub due: Option<String>,
+}
+
+impl From<&Task> for BoardTask {
+    fn from(task: &Task) -> Self {
+        Self {
+            id: task.id,
+            title: task.title.chars().take(MAX_BOARD_TITLE_CHARS).collect(),
+            status: task.status.to_string(),
+            tags: task.tags.clone(),
+            due: task.due_date.map(|d| d.format("%Y-%m-%d").to_string()),
+        }
+    }
+}
+
+/// Serialize a room's tasks into board pages, each the content of one state event.
+/// Tasks that don't fit in `MAX_BOARD_PAGES` are dropped and the pages marked truncated.
+pub fn board_pages(tasks: &[Task]) -> Vec<serde_json::Value> {
+    let mut chunks: Vec<Vec<BoardTask>> = vec![Vec::new()];
+    let mut chunk_bytes = 0;
+    let mut truncated = false;
+
+    for task in tasks {
+        let board_task = BoardTask::from(task);
+        let task_bytes = serde_json::to_vec(&board_task).map_or(0, |v| v.len()) + 1;
+        if chunk_bytes + task_bytes > MAX_BOARD_PAGE_BYTES && !chunks[chunks.len() - 1].is_empty() {
+            if chunks.len() == MAX_BOARD_PAGES {
+                truncated = true;
+                break;
+            }
+            chunks.push(Vec::new());
+            chunk_bytes = 0;
+        }
+        chunk_bytes += task_bytes;
+        if let Some(chunk) = chunks.last_mut() {
+            chunk.push(board_task);
+        }
+    }
+
+    let pages = chunks.len();
+    chunks
+        .into_iter()
+        .enumerate()
+        .map(|(page, tasks)| {
+            json!({
+                "version": BOARD_FORMAT_VERSION,
+                "page": page,
+                "pages": pages,
+                "truncated": truncated,
+                "tasks": tasks,
+            })
+        })
+        .collect()
+}
+
+/// Turn the mirror off for a room the bot can't write state to, and tell the room why
+async fn disable_mirror(storage: &StorageManager, room: &Room, reason: &str) {
+    let room_id = room.room_id().to_owned();
+    warn!(room_id = %room_id, reason, "Disabling task board mirror");
+    storage
+        .update_room_settings(&room_id, |settings| settings.board_mirror = false)
+        .await;
+    if let Err(e) = storage.save_room(&room_id).await {
+        error!(room_id = %room_id, "Failed to save after disabling board mirror: {}", e);
+    }
+    let notice = format!(
+        "⚠️ Task board mirror disabled: {}. Grant the bot permission to send '{}' state events and run !bot board on again.",
+        reason, BOARD_EVENT_TYPE
+    );
+    if let Err(e) = room
+        .send(RoomMessageEventContent::notice_plain(notice))
+        .await
+    {
+        error!(room_id = %room_id, "Failed to send board mirror notice: {}", e);
+    }
+}
+
+/// Publish `pages` and blank out state keys left over from a previous, longer board
+async fn publish(room: &Room, pages: &[String], previous_count: usize) -> Result<()> {
+    for (page, content) in pages.iter().enumerate() {
+        let content: serde_json::Value = serde_json::from_str(content)?;
+        room.send_state_event_raw(BOARD_EVENT_TYPE, &page.to_string(), content)
+            .await?;
+    }
+    for page in pages.len()..previous_count {
+        room.send_state_event_raw(BOARD_EVENT_TYPE, &page.to_string(), json!({}))
+            .await?;
+    }
+    Ok(())
+}
+
+/// Spawn the background job mirroring task lists into `io.asmith.tasklist` state
+/// events for rooms that opted in with `!bot board on`.
+pub fn spawn_board_mirror(client: Client, storage: Arc<StorageManager>) {
+    tokio::spawn(async move {
+        // Last published pages per room, serialized, so unchanged boards aren't resent
+        let mut published: HashMap<OwnedRoomId, Vec<String>> = HashMap::new();
+        let mut interval =
+            tokio::time::interval(std::time::Duration::from_secs(BOARD_PUBLISH_INTERVAL_SECS));
+        let Some(user_id) = client.user_id().map(|id| id.to_owned()) else {
+            error!("Task board mirror not started: client has no user ID");
+            return;
+        };
+
+        loop {
+            interval.tick().await;
+
+            let enabled: Vec<OwnedRoomId> = storage
+                .room_settings
+                .lock()
+                .await
+                .iter()
+                .filter(|(_, settings)| settings.board_mirror)
+                .map(|(room_id, _)| room_id.clone())
+                .collect();
+
+            // Rooms that switched the mirror off get their board cleared once
+            let disabled: Vec<OwnedRoomId> = published
+                .keys()
+                .filter(|room_id| !enabled.contains(room_id))
+                .cloned()
+                .collect();
+            for room_id in disabled {
+                let previous = published.remove(&room_id).unwrap_or_default();
+                if let Some(room) = client.get_room(&room_id)
+                    && let Err(e) = publish(&room, &[], previous.len()).await
+                {
+                    warn!(room_id = %room_id, "Failed to clear task board: {}", e);
+                }
+            }
+
+            for room_id in enabled {
+                let Some(room) = client.get_room(&room_id) else {
+                    continue;
+                };
+
+                let pages: Vec<String> = {
+                    let todo_lists = storage.todo_lists.lock().await;
+                    let tasks = todo_lists
+                        .get(&room_id)
+                        .map(|list| list.as_slice())
+                        .unwrap_or(&[]);
+                    board_pages(tasks).iter().map(|p| p.to_string()).collect()
+                };
+                let previous_count = published.get(&room_id).map_or(0, Vec::len);
+                if published.get(&room_id) == Some(&pages) {
+                    continue;
+                }
+
+                match room
+                    .can_user_send_state(&user_id, StateEventType::from(BOARD_EVENT_TYPE))
+                    .await
+                {
+                    Ok(true) => {}
+                    Ok(false) => {
+                        disable_mirror(&storage, &room, "the bot lacks the required power level")
+                            .await;
+                        continue;
+                    }
+                    Err(e) => {
+                        warn!(room_id = %room_id, "Could not read power levels: {}", e);
+                        continue;
+                    }
+                }
+
+                match publish(&room, &pages, previous_count).await {
+                    Ok(()) => {
+                        debug!(room_id = %room_id, pages = pages.len(), "Published task board");
+                        published.insert(room_id, pages);
+                    }
+                    Err(e) => {
+                        disable_mirror(&storage, &room, &format!("publishing failed ({})", e))
+                            .await;
+                    }
+                }
+            }
+        }
+    });
+    info!(
+        "Task board mirror started (interval={}s)",
+        BOARD_PUBLISH_INTERVAL_SECS
+    );
+}
@@ -0,0 +1,98 @@
+use anyhow::{Result, anyhow};
+use async_trait::async_trait;
+use matrix_sdk::Client;
+use matrix_sdk::reqwest::{Method, StatusCode};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+/// Deleting uploaded media from the homeserver. The client-server API has no
+/// endpoint for it, so this depends on what the homeserver offers.
+#[async_trait]
+pub trait MediaDeleter: Send + Sync {
+    /// Whether `delete` can work with this homeserver and account
+    async fn can_delete(&self) -> bool;
+
+    async fn delete(&self, mxc_uri: &str) -> Result<()>;
+}
+
+/// Split `mxc://server/media_id` into its server name and media ID
+pub fn parse_mxc_uri(mxc_uri: &str) -> Option<(&str, &str)> {
+    let (server_name, media_id) = mxc_uri.strip_prefix("mxc://")?.split_once('/')?;
+    if server_name.is_empty() || media_id.is_empty() || media_id.contains('/') {
+        return None;
+    }
+    Some((server_name, media_id))
+}
+
+/// Deletes media through the Synapse admin API, which needs the bot account to be a server admin
+pub struct SynapseAdminMedia {
+    client: Client,
+}
+
+#[derive(Deserialize)]
+struct AdminStatus {
+    admin: bool,
+}
+
+impl SynapseAdminMedia {
+    pub fn new(client: Client) -> Self {
+        Self { client }
+    }
+
+    async fn admin_request(&self, method: Method, path: &str) -> Result<(StatusCode, Vec<u8>)> {
+        let token = self
+            .client
+            .access_token()
+            .ok_or_else(|| anyhow!("Client has no access token"))?;
+        let url = self.client.homeserver().join(path)?;
+        let response = matrix_sdk::reqwest::Client::new()
+            .request(method, url)
+            .bearer_auth(token)
+            .send()
+            .await?;
+        let status = response.status();
+        Ok((status, response.bytes().await?.to_vec()))
+    }
+}
+
+#[async_trait]
+impl MediaDeleter for SynapseAdminMedia {
+    async fn can_delete(&self) -> bool {
+        let Some(user_id) = self.client.user_id() else {
+            return false;
+        };
+        let path = format!("_synapse/admin/v1/users/{}/admin", user_id);
+        match self.admin_request(Method::GET, &path).await {
+            Ok((status, body)) if status.is_success() => {
+                serde_json::from_slice::<AdminStatus>(&body).is_ok_and(|s| s.admin)
+            }
+            Ok((status, _)) => {
+                // Not Synapse, or the bot isn't a server admin
+                debug!(%status, "Media admin API not available");
+                false
+            }
+            Err(e) => {
+                warn!("Failed to check media admin API: {}", e);
+                false
+            }
+        }
+    }
+
+    async fn delete(&self, mxc_uri: &str) -> Result<()> {
+        let (server_name, media_id) =
+            parse_mxc_uri(mxc_uri).ok_or_else(|| anyhow!("Invalid mxc URI: {}", mxc_uri))?;
+        let path = format!("_synapse/admin/v1/media/{}/{}", server_name, media_id);
+        let (status, _) = self.admin_request(Method::DELETE, &path).await?;
+        // Already gone counts as deleted
+        if status.is_success() || status == StatusCode::NOT_FOUND {
+            info!(mxc_uri, "Deleted uploaded media");
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "Homeserver refused to delete {}: {}",
+                mxc_uri,
+                status
+            ))
+        }
+    }
+}
@@ -1,9 +1,9 @@
-use anyhow::{Context, Result, anyhow, bail};
+use anyhow::{Context, Result, anyhow};
 use futures_util::stream::StreamExt;
 use matrix_sdk::encryption::verification::Verification;
-use matrix_sdk::ruma::OwnedDeviceId;
 use matrix_sdk::ruma::events::room::{
-    member::StrippedRoomMemberEvent, message::OriginalSyncRoomMessageEvent,
+    member::StrippedRoomMemberEvent,
+    message::{OriginalSyncRoomMessageEvent, RoomMessageEventContent},
 };
 use matrix_sdk::ruma::events::{
     ToDeviceEvent,
@@ -15,19 +15,33 @@ use matrix_sdk::ruma::events::{
         start::ToDeviceKeyVerificationStartEventContent,
     },
 };
+use matrix_sdk::ruma::{OwnedDeviceId, OwnedUserId};
 use matrix_sdk::{
     Client, Room, RoomState, SessionMeta, SessionTokens, authentication::matrix::MatrixSession,
-    config::SyncSettings,
+    config::SyncSettings, event_handler::Ctx,
 };
 use ruma::DeviceId;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore, watch};
 
 use std::path::{Path, PathBuf};
 use tokio::time::Duration;
 use tracing::{debug, error, info, warn};
 
-use crate::config::APP_NAME;
+use crate::bot_commands::status::BotStatus;
+use crate::config::{APP_NAME, SharedLiveSettings};
+use crate::exit::{ExitReason, RUN_STATS, is_auth_error_kind};
+
+pub mod board;
+pub mod media;
+pub mod presence;
+pub mod sync_journal;
+
+use presence::PresenceTracker;
+use sync_journal::{SyncJournal, SyncJournalEntry};
 
 use rand::{Rng, rngs::ThreadRng};
 use rand_distr::Alphanumeric;
@@ -46,6 +60,8 @@ pub struct PersistedSession {
     client_store_config: ClientStoreConfig,
     matrix_session: MatrixSession, // The SDK's session object
     sync_token: Option<String>,
+    #[serde(default)]
+    sync_token_saved_at: Option<chrono::DateTime<chrono::Utc>>,
 }
 
 pub async fn restore_session(
@@ -71,6 +87,24 @@ pub async fn restore_session(
     let matrix_session = persisted_session.matrix_session;
     let sync_token = persisted_session.sync_token;
 
+    // A stale token means the server may have dropped timeline events in between
+    if let (Some(_), Some(saved_at)) = (&sync_token, persisted_session.sync_token_saved_at) {
+        let age = chrono::Utc::now() - saved_at;
+        let max_age = chrono::Duration::hours(config.sync_token_max_age_hours as i64);
+        if age > max_age {
+            warn!(
+                "Resuming from a sync token saved {} hours ago (threshold: {}h); messages sent while offline may be missing from the timeline",
+                age.num_hours(),
+                config.sync_token_max_age_hours
+            );
+        } else {
+            info!(
+                "Resuming from a sync token saved {} minutes ago",
+                age.num_minutes()
+            );
+        }
+    }
+
     let homeserver_url = config
         .homeserver
         .as_ref()
@@ -113,7 +147,7 @@ pub async fn login_and_save_session(
 ) -> Result<(Client, Option<String>, ClientStoreConfig)> {
     info!("Performing new login and creating new session store.");
 
-    let homeserver_url_str = config.get_homeserver()?;
+    let homeserver_url_str = config.get_homeserver().context(ExitReason::ConfigInvalid)?;
 
     // Create a unique directory for this session's store
     let mut rng = ThreadRng::default();
@@ -152,7 +186,10 @@ pub async fn login_and_save_session(
     // Perform login
     if let Some(token) = &config.access_token {
         tracing::info!("Attempting to log in with access token.");
-        let user_id = config.get_user_id().context("User ID not found in config, but access token is present. User ID is required for token login.")?;
+        let user_id = config
+            .get_user_id()
+            .context("User ID not found in config, but access token is present. User ID is required for token login.")
+            .context(ExitReason::ConfigInvalid)?;
 
         let device_id: OwnedDeviceId = DeviceId::new();
         tracing::info!(
@@ -185,9 +222,10 @@ pub async fn login_and_save_session(
             .await
             .context("Login with username and password failed")?;
     } else {
-        bail!(
+        return Err(anyhow!(
             "Login failed: Ensure homeserver, user ID, and either password or access token are correctly configured."
-        );
+        )
+        .context(ExitReason::ConfigInvalid));
     }
 
     info!(
@@ -211,6 +249,7 @@ pub async fn login_and_save_session(
         client_store_config: client_store_config.clone(),
         matrix_session,
         sync_token: None, // Sync token is obtained after the first sync
+        sync_token_saved_at: None,
     };
 
     let session_json = serde_json::to_string_pretty(&persisted_session_data)
@@ -243,10 +282,12 @@ pub async fn save_current_session(
         .session()
         .ok_or_else(|| anyhow!("Failed to get MatrixSession from client for saving"))?;
 
+    let sync_token_saved_at = current_sync_token.as_ref().map(|_| chrono::Utc::now());
     let persisted_session_data = PersistedSession {
         client_store_config: client_store_config.clone(),
         matrix_session,
         sync_token: current_sync_token,
+        sync_token_saved_at,
     };
 
     let session_json = serde_json::to_string_pretty(&persisted_session_data)
@@ -265,21 +306,33 @@ pub async fn save_current_session(
     Ok(())
 }
 
+/// Upper bound of the retry backoff, before jitter
+pub const MAX_RETRY_DELAY_MS: u64 = 60_000;
+/// Relative jitter applied to every retry delay (±20%)
+const RETRY_JITTER: f64 = 0.2;
+
 pub struct ConnectionMonitor {
     pub max_retries: usize,
+    pub base_retry_delay_ms: u64,
+    pub retry_delay_ms: u64, // Delay before the next retry, doubles on each failure
     pub consecutive_failures: usize,
     pub total_failures: usize, // This field was present and should remain
     pub failure_types: HashMap<String, usize>, // This field was present and should remain
-                               // last_failure_time and first_failure_time were intentionally removed
+    pub first_failure_time: Option<Instant>,
+    pub last_failure_time: Option<Instant>,
 }
 
 impl ConnectionMonitor {
-    pub fn new(max_retries: usize) -> Self {
+    pub fn new(max_retries: usize, base_retry_delay_ms: u64) -> Self {
         Self {
             max_retries,
+            base_retry_delay_ms,
+            retry_delay_ms: base_retry_delay_ms,
             consecutive_failures: 0,
             total_failures: 0,
             failure_types: HashMap::new(),
+            first_failure_time: None,
+            last_failure_time: None,
         }
     }
 
@@ -291,12 +344,38 @@ impl ConnectionMonitor {
             );
         }
         self.consecutive_failures = 0;
+        self.retry_delay_ms = self.base_retry_delay_ms;
+    }
+
+    /// Apply a reloaded base delay. Reloads that leave it unchanged keep the
+    /// current backoff, and a new base takes over right away only between failures.
+    pub fn set_base_retry_delay(&mut self, base_retry_delay_ms: u64) {
+        if base_retry_delay_ms == self.base_retry_delay_ms {
+            return;
+        }
+        self.base_retry_delay_ms = base_retry_delay_ms;
+        if self.consecutive_failures == 0 {
+            self.retry_delay_ms = base_retry_delay_ms;
+        }
+    }
+
+    /// Delay to wait before the next retry: truncated binary exponential backoff
+    /// (doubling up to `MAX_RETRY_DELAY_MS`) with ±20% random jitter
+    pub fn next_retry_delay(&mut self) -> Duration {
+        let delay_ms = self.retry_delay_ms.min(MAX_RETRY_DELAY_MS);
+        self.retry_delay_ms = delay_ms.saturating_mul(2).min(MAX_RETRY_DELAY_MS);
+
+        let jitter = rand::thread_rng().gen_range(-RETRY_JITTER..=RETRY_JITTER);
+        Duration::from_millis((delay_ms as f64 * (1.0 + jitter)) as u64)
     }
 
     pub fn connection_failed(&mut self, error_type: String) -> bool {
         self.total_failures += 1;
         *self.failure_types.entry(error_type.clone()).or_insert(0) += 1;
         self.consecutive_failures += 1;
+        let now = Instant::now();
+        self.first_failure_time.get_or_insert(now);
+        self.last_failure_time = Some(now);
 
         if self.consecutive_failures >= self.max_retries {
             warn!(
@@ -319,6 +398,34 @@ impl ConnectionMonitor {
             false // Indicate that max retries have not been reached
         }
     }
+
+    /// Failures per error type as `<type>: <count>` lines, most frequent first,
+    /// followed by the total and when the first and last failure happened
+    pub fn failure_report(&self) -> String {
+        if self.total_failures == 0 {
+            return "No connection failures".to_owned();
+        }
+        let mut types: Vec<(&String, &usize)> = self.failure_types.iter().collect();
+        types.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        let mut lines: Vec<String> = types
+            .into_iter()
+            .map(|(error_type, count)| format!("{}: {}", error_type, count))
+            .collect();
+        lines.push(format!("Total: {}", self.total_failures));
+        if let Some(first) = self.first_failure_time {
+            lines.push(format!(
+                "First failure: {} seconds ago",
+                first.elapsed().as_secs()
+            ));
+        }
+        if let Some(last) = self.last_failure_time {
+            lines.push(format!(
+                "Last failure: {} seconds ago",
+                last.elapsed().as_secs()
+            ));
+        }
+        lines.join("\n")
+    }
 }
 
 pub async fn handle_verification_events(client: Client) {
@@ -518,6 +625,7 @@ pub async fn on_stripped_state_member(
     room_member: StrippedRoomMemberEvent,
     client: Client,
     room: Room,
+    Ctx(live_settings): Ctx<SharedLiveSettings>,
 ) {
     if room_member.state_key != client.user_id().unwrap() {
         return;
@@ -529,81 +637,224 @@ pub async fn on_stripped_state_member(
         error!("Failed to join room {}: {}", room_id, e);
     } else {
         info!("Successfully joined room {}", room_id);
+
+        // Direct chats become the inviter's private list instead of a shared room
+        if room_member.content.is_direct == Some(true) {
+            if let Some(bot_core) = crate::BOT_CORE.get()
+                && let Err(e) = bot_core
+                    .bot_management
+                    .setup_dm_room(&room_id.to_owned(), room_member.sender.as_str())
+                    .await
+            {
+                error!("Failed to set up DM room {}: {}", room_id, e);
+            }
+            return;
+        }
+
+        let welcome_text = live_settings.read().await.welcome_text.clone();
+        if let Some(text) = welcome_text
+            && let Err(e) = room.send(RoomMessageEventContent::text_plain(text)).await
+        {
+            warn!("Failed to send welcome message to room {}: {}", room_id, e);
+        }
+
+        // Opt-in: bootstrap the room with the configured template
+        let Some(bot_user_id) = client.user_id() else {
+            warn!(
+                "Not logged in, skipping the on-join template for room {}",
+                room_id
+            );
+            return;
+        };
+        if let Some(bot_core) = crate::BOT_CORE.get()
+            && let Err(e) = bot_core
+                .todo_lists
+                .apply_join_template(&room_id.to_owned(), bot_user_id.to_string())
+                .await
+        {
+            error!(
+                "Failed to apply on-join template to room {}: {}",
+                room_id, e
+            );
+        }
+    }
+}
+
+/// Split a message into its lowercased command and the raw arguments, or `None` if it
+/// isn't a command. The prefix may be several characters (`todo:`) and be followed by
+/// spaces. Any whitespace ends the command so multi-line payloads keep their lines.
+pub fn split_command(body: &str, prefix: &str) -> Option<(String, String)> {
+    let command_and_args = body.strip_prefix(prefix)?.trim();
+    let (command, args) = command_and_args
+        .split_once(char::is_whitespace)
+        .unwrap_or((command_and_args, ""));
+    if command.is_empty() {
+        return None;
     }
+    Some((command.to_lowercase(), args.to_owned()))
+}
+
+/// Take one of the command permits, or None when `max_concurrent` commands are
+/// already running and this one should get the busy reply instead. The permit
+/// is held until the command finishes.
+pub fn try_admit_command(permits: &Arc<Semaphore>) -> Option<OwnedSemaphorePermit> {
+    permits.clone().try_acquire_owned().ok()
 }
 
-pub fn register_message_handler(client: &Client) {
+/// `allowed_users` limits who may run commands; empty lets everyone
+pub fn register_message_handler(
+    client: &Client,
+    command_prefix: String,
+    max_concurrent: usize,
+    allowed_users: Vec<OwnedUserId>,
+) {
+    let allowed_users = Arc::new(allowed_users);
+    // Bounds how many commands run at once so a flood can't pile up unbounded tasks
+    let permits = Arc::new(Semaphore::new(max_concurrent));
     // Register handler for room messages to process bot commands
     client.add_event_handler(
         // Closure for room messages
-        move |ev: OriginalSyncRoomMessageEvent, room: Room, _client_clone: Client| async move {
-            if room.state() != RoomState::Joined {
-                return;
-            }
+        move |ev: OriginalSyncRoomMessageEvent, room: Room, _client_clone: Client| {
+            let command_prefix = command_prefix.clone();
+            let permits = permits.clone();
+            let allowed_users = allowed_users.clone();
+            async move {
+                if room.state() != RoomState::Joined {
+                    return;
+                }
 
-            let bot_core_ref = crate::BOT_CORE
-                .get()
-                .expect("BOT_CORE not initialized")
-                .clone();
-            tokio::spawn(async move {
+                let matrix_sdk::ruma::events::room::message::MessageType::Text(text_content) =
+                    ev.content.msgtype
+                else {
+                    return;
+                };
+                let body = text_content.body;
+                let Some((command, args_str)) = split_command(&body, &command_prefix) else {
+                    return;
+                };
                 let room_id_owned = room.room_id().to_owned();
+                if !allowed_users.is_empty() && !allowed_users.contains(&ev.sender) {
+                    debug!(
+                        room_id = %room_id_owned,
+                        sender = %ev.sender,
+                        "Ignoring command from user outside the allowlist"
+                    );
+                    return;
+                }
                 let sender = ev.sender.to_string();
+                let event_id = ev.event_id.clone();
+                debug!(
+                    "Received command: {} from {} in room {}",
+                    body, sender, room_id_owned
+                );
+
+                let power_level = match room.get_user_power_level(&ev.sender).await {
+                    Ok(level) => Some(level),
+                    Err(e) => {
+                        warn!(room_id = %room_id_owned, sender = %sender, "Failed to read power level: {}", e);
+                        None
+                    }
+                };
+
+                let Some(permit) = try_admit_command(&permits) else {
+                    warn!(
+                        room_id = %room_id_owned,
+                        command = %command,
+                        max_concurrent,
+                        "Too many commands in progress, rejecting command"
+                    );
+                    let busy = RoomMessageEventContent::notice_plain(
+                        "⏳ The bot is busy with other commands right now. Please try again in a moment.",
+                    );
+                    if let Err(e) = room.send(busy).await {
+                        error!("Failed to send busy reply: {}", e);
+                    }
+                    return;
+                };
 
-                if let matrix_sdk::ruma::events::room::message::MessageType::Text(text_content) =
-                    ev.content.msgtype
-                {
-                    let body = text_content.body;
-                    if body.starts_with('!') {
-                        debug!(
-                            "Received command: {} from {} in room {}",
-                            body, sender, room_id_owned
+                let bot_core_ref = crate::BOT_CORE
+                    .get()
+                    .expect("BOT_CORE not initialized")
+                    .clone();
+                tokio::spawn(async move {
+                    // Held until the command finishes
+                    let _permit = permit;
+                    if let Err(e) = bot_core_ref
+                        .run_command(
+                            &room_id_owned,
+                            sender.clone(),
+                            power_level,
+                            &command,
+                            args_str,
+                            event_id,
+                        )
+                        .await
+                    {
+                        error!(
+                            "Error processing command '{}' from sender {}: {:?}",
+                            command, sender, e
                         );
-
-                        // Remove the leading '!' before splitting command and args
-                        let command_and_args = body.strip_prefix('!').unwrap_or_default().trim();
-                        let mut command_parts = command_and_args.splitn(2, ' ');
-                        let command = command_parts.next().unwrap_or("").to_lowercase();
-                        let args_str = command_parts.next().unwrap_or("").to_owned();
-
-                        if !command.is_empty() {
-                            if let Err(e) = bot_core_ref
-                                .process_command(
-                                    room_id_owned.as_str(),
-                                    sender.clone(),
-                                    &command,
-                                    args_str,
-                                )
-                                .await
-                            {
-                                error!(
-                                    "Error processing command '{}' from sender {}: {:?}",
-                                    command, sender, e
-                                );
-                            }
-                        }
                     }
-                }
-            });
+                });
+            }
         },
     );
-    info!("Room message handler registered for command processing");
+    info!(
+        max_concurrent,
+        "Room message handler registered for command processing"
+    );
 }
 
+#[allow(clippy::too_many_arguments)]
 pub async fn start_sync_loop(
     client: Client,
     initial_sync_settings: SyncSettings, // Renamed for clarity
     connection_monitor: &mut ConnectionMonitor,
     session_file_path: &PathBuf,             // Added
     client_store_config: &ClientStoreConfig, // Added
+    sync_journal: &mut SyncJournal,
+    live_settings: &SharedLiveSettings,
+    presence: &PresenceTracker,
+    status: &BotStatus,
+    mut shutdown: watch::Receiver<bool>,
 ) -> Result<()> {
     info!("Starting Matrix sync loop...");
     let mut current_sync_settings = initial_sync_settings;
+    let mut last_sync_token = None;
 
     loop {
+        // Pick up settings reloaded from the config file
+        {
+            let settings = live_settings.read().await;
+            connection_monitor.max_retries = settings.max_retries;
+            connection_monitor.set_base_retry_delay(settings.retry_delay_secs * 1000);
+        }
+
         info!("Initiating a sync cycle...");
-        match client.sync_once(current_sync_settings.clone()).await {
+        // Only the sync request is raced against shutdown, never the session write below
+        let sync_result = tokio::select! {
+            _ = shutdown_requested(&mut shutdown) => {
+                info!("Graceful shutdown initiated");
+                if let Some(token) = last_sync_token
+                    && let Err(e) =
+                        save_current_session(&client, session_file_path, client_store_config, Some(token))
+                            .await
+                {
+                    error!("Failed to save session on shutdown: {:?}", e);
+                }
+                return Ok(());
+            }
+            result = client.sync_once(current_sync_settings.clone()) => result,
+        };
+        match sync_result {
             Ok(sync_response) => {
                 connection_monitor.connection_successful();
+                RUN_STATS.record_sync();
+                status.record_sync();
+                // Refreshed on every sync so the "ago" times in `!bot status` stay current
+                if connection_monitor.total_failures > 0 {
+                    status.set_connection_failures(connection_monitor.failure_report());
+                }
                 let new_sync_token = sync_response.next_batch;
                 info!("Sync successful. New sync token: {}", new_sync_token);
 
@@ -617,31 +868,170 @@ pub async fn start_sync_loop(
                 {
                     error!("Failed to save current session after sync: {:?}", save_err);
                     // Decide if this is a critical error. For now, we'll log and continue.
+                } else {
+                    let joined = &sync_response.rooms.join;
+                    sync_journal.record(SyncJournalEntry {
+                        timestamp: chrono::Utc::now(),
+                        token: new_sync_token.clone(),
+                        joined_rooms: joined.len(),
+                        timeline_events: joined.values().map(|r| r.timeline.events.len()).sum(),
+                        limited_rooms: joined.values().filter(|r| r.timeline.limited).count(),
+                    });
                 }
 
-                current_sync_settings = SyncSettings::default().token(new_sync_token);
+                last_sync_token = Some(new_sync_token.clone());
+                current_sync_settings = SyncSettings::default()
+                    .token(new_sync_token)
+                    .set_presence(presence.sync_presence());
+            }
+            // Retrying with revoked credentials can't succeed
+            Err(e) if e.client_api_error_kind().is_some_and(is_auth_error_kind) => {
+                return Err(anyhow::Error::new(e)
+                    .context("Homeserver rejected the bot's credentials during sync")
+                    .context(ExitReason::AuthFailure));
             }
             Err(e) => {
-                error!("Sync loop exited with error: {}", e);
-                let should_exit =
-                    connection_monitor.connection_failed(format!("Sync loop error: {}", e));
-                if should_exit {
-                    return Err(anyhow!(
-                        "Connection monitor recommended exit due to critical errors"
-                    ));
-                }
-                // Original error handling for sync failure from client.sync() is adapted here
                 error!("Sync cycle failed: {}", e);
                 let error_details = format!("Sync cycle error: {}", e);
-                if connection_monitor.connection_failed(error_details) {
+                let should_exit = connection_monitor.connection_failed(error_details);
+                status.set_connection_failures(connection_monitor.failure_report());
+                if should_exit {
                     return Err(anyhow!(
                         "Connection monitor recommended exit due to critical sync errors."
-                    ));
+                    )
+                    .context(ExitReason::RetriesExhausted));
                 }
                 // If not exiting, the loop will continue, implicitly retrying the sync on the next iteration.
-                // A delay might be useful here depending on the nature of expected errors.
-                tokio::time::sleep(tokio::time::Duration::from_secs(5)).await; // Brief pause before retrying
+                let delay = connection_monitor.next_retry_delay();
+                info!("Retrying sync in {:.1}s", delay.as_secs_f64());
+                // A shutdown during the wait is picked up at the top of the next cycle
+                tokio::select! {
+                    _ = tokio::time::sleep(delay) => {}
+                    _ = shutdown_requested(&mut shutdown) => {}
+                }
             }
         }
     }
 }
+
+/// Resolves once shutdown was requested. Never resolves if the sender is gone.
+async fn shutdown_requested(shutdown: &mut watch::Receiver<bool>) {
+    if shutdown.wait_for(|&stop| stop).await.is_err() {
+        std::future::pending::<()>().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failure_report_lists_types_by_count() {
+        let mut monitor = ConnectionMonitor::new(10, 1000);
+        assert_eq!(monitor.failure_report(), "No connection failures");
+        monitor.connection_failed("timeout".to_owned());
+        monitor.connection_failed("dns".to_owned());
+        monitor.connection_failed("timeout".to_owned());
+        assert!(monitor.first_failure_time <= monitor.last_failure_time);
+
+        let report = monitor.failure_report();
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines[..3], ["timeout: 2", "dns: 1", "Total: 3"]);
+        assert!(lines[3].starts_with("First failure: "));
+        assert!(lines[4].starts_with("Last failure: "));
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let mut monitor = ConnectionMonitor::new(20, 1000);
+        let expected_ms = [
+            1_000, 2_000, 4_000, 8_000, 16_000, 32_000, 60_000, 60_000, 60_000, 60_000,
+        ];
+        for expected in expected_ms {
+            monitor.connection_failed("timeout".to_owned());
+            let delay = monitor.next_retry_delay().as_millis() as f64;
+            let expected = expected as f64;
+            assert!(
+                delay + 1.0 >= expected * (1.0 - RETRY_JITTER)
+                    && delay <= expected * (1.0 + RETRY_JITTER),
+                "{delay}ms is not within 20% of {expected}ms"
+            );
+        }
+
+        monitor.connection_successful();
+        let delay = monitor.next_retry_delay().as_millis();
+        assert!((799..=1200).contains(&delay), "{delay}ms after a success");
+    }
+
+    #[test]
+    fn reloading_the_same_base_keeps_the_backoff() {
+        let mut monitor = ConnectionMonitor::new(20, 1000);
+        for _ in 0..3 {
+            monitor.connection_failed("timeout".to_owned());
+            monitor.next_retry_delay();
+        }
+        monitor.set_base_retry_delay(1000);
+        assert_eq!(monitor.retry_delay_ms, 8000);
+
+        // A new base waits for the next success while failures are ongoing
+        monitor.set_base_retry_delay(2000);
+        assert_eq!(monitor.retry_delay_ms, 8000);
+        monitor.connection_successful();
+        assert_eq!(monitor.retry_delay_ms, 2000);
+    }
+
+    #[test]
+    fn commands_past_the_limit_are_turned_away_until_one_finishes() {
+        let permits = Arc::new(Semaphore::new(2));
+        let first = try_admit_command(&permits).unwrap();
+        let _second = try_admit_command(&permits).unwrap();
+        assert!(try_admit_command(&permits).is_none());
+        assert!(try_admit_command(&permits).is_none());
+
+        drop(first);
+        let _third = try_admit_command(&permits).unwrap();
+        assert!(try_admit_command(&permits).is_none());
+    }
+
+    #[test]
+    fn splits_single_character_prefix() {
+        assert_eq!(
+            split_command("!ADD Buy milk", "!"),
+            Some(("add".into(), "Buy milk".into()))
+        );
+        assert_eq!(
+            split_command("!list", "!"),
+            Some(("list".into(), "".into()))
+        );
+        assert_eq!(split_command("!", "!"), None);
+        assert_eq!(split_command("hello !add", "!"), None);
+    }
+
+    #[test]
+    fn splits_multi_character_prefix() {
+        assert_eq!(
+            split_command("todo:add Buy milk", "todo:"),
+            Some(("add".into(), "Buy milk".into()))
+        );
+        assert_eq!(
+            split_command("todo: done 3", "todo:"),
+            Some(("done".into(), "3".into()))
+        );
+        assert_eq!(split_command("todo add", "todo:"), None);
+        assert_eq!(
+            split_command("todo:import\n- one\n- two", "todo:"),
+            Some(("import".into(), "- one\n- two".into()))
+        );
+    }
+
+    #[test]
+    fn keeps_every_line_of_the_arguments() {
+        assert_eq!(
+            split_command("!add Buy milk\nFrom the corner shop\n\nTwo litres", "!"),
+            Some((
+                "add".into(),
+                "Buy milk\nFrom the corner shop\n\nTwo litres".into()
+            ))
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use matrix_sdk::Client;
+use matrix_sdk::ruma::api::client::error::ErrorKind;
+use matrix_sdk::ruma::api::client::presence::set_presence;
+use matrix_sdk::ruma::presence::PresenceState;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
+
+// How often the updater re-evaluates the bot's presence
+const PRESENCE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Tracks command activity so the bot only shows as online while it is being used.
+/// When disabled the bot stays online, which is the default.
+#[derive(Debug)]
+pub struct PresenceTracker {
+    activity_based: AtomicBool,
+    idle_after: Duration,
+    last_activity: Mutex<Instant>,
+}
+
+pub type SharedPresence = Arc<PresenceTracker>;
+
+impl PresenceTracker {
+    pub fn new(activity_based: bool, idle_after: Duration) -> Self {
+        Self {
+            activity_based: AtomicBool::new(activity_based),
+            idle_after,
+            last_activity: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn record_activity(&self) {
+        *self.last_activity.lock().unwrap() = Instant::now();
+    }
+
+    pub fn is_activity_based(&self) -> bool {
+        self.activity_based.load(Ordering::SeqCst)
+    }
+
+    pub fn set_activity_based(&self, enabled: bool) {
+        self.activity_based.store(enabled, Ordering::SeqCst);
+    }
+
+    pub fn idle_after(&self) -> Duration {
+        self.idle_after
+    }
+
+    /// The presence the bot should currently have
+    pub fn desired_state(&self) -> PresenceState {
+        if !self.is_activity_based() {
+            return PresenceState::Online;
+        }
+        if self.last_activity.lock().unwrap().elapsed() < self.idle_after {
+            PresenceState::Online
+        } else {
+            PresenceState::Unavailable
+        }
+    }
+
+    /// Presence to pass along with `/sync`. Syncing would otherwise mark the bot
+    /// online every cycle and override what the updater set.
+    pub fn sync_presence(&self) -> PresenceState {
+        if self.is_activity_based() {
+            PresenceState::Offline
+        } else {
+            PresenceState::Online
+        }
+    }
+}
+
+/// Spawn the background job that keeps the bot's presence in line with its activity
+pub fn spawn_presence_updater(client: Client, tracker: SharedPresence) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PRESENCE_CHECK_INTERVAL);
+        let mut current: Option<PresenceState> = None;
+        loop {
+            interval.tick().await;
+            let desired = tracker.desired_state();
+            if current.as_ref() == Some(&desired) {
+                continue;
+            }
+            let Some(user_id) = client.user_id().map(ToOwned::to_owned) else {
+                debug!("Not logged in yet, skipping presence update");
+                continue;
+            };
+
+            let request = set_presence::v3::Request::new(user_id, desired.clone());
+            match client.send(request).await {
+                Ok(_) => {
+                    debug!(presence = %desired, "Updated bot presence");
+                    current = Some(desired);
+                }
+                Err(e)
+                    if matches!(
+                        e.client_api_error_kind(),
+                        Some(
+                            ErrorKind::Forbidden { .. }
+                                | ErrorKind::Unrecognized
+                                | ErrorKind::NotFound
+                        )
+                    ) =>
+                {
+                    info!(
+                        "Homeserver does not accept presence updates, stopping presence updater: {}",
+                        e
+                    );
+                    return;
+                }
+                Err(e) => warn!("Failed to update bot presence: {}", e),
+            }
+        }
+    });
+}
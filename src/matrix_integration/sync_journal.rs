@@ -0,0 +1,86 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use tracing::{debug, warn};
+
+pub const SYNC_JOURNAL_FILE: &str = "sync_journal.jsonl";
+pub const SYNC_JOURNAL_MAX_ENTRIES: usize = 100;
+
+/// One persisted sync token, with enough context to spot gaps after restarts
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct SyncJournalEntry {
+    pub timestamp: DateTime<Utc>,
+    pub token: String,
+    pub joined_rooms: usize,
+    pub timeline_events: usize,
+    // Rooms whose timeline was truncated by the server, i.e. events may have been skipped
+    pub limited_rooms: usize,
+}
+
+/// Bounded on-disk journal (JSON Lines) of the sync tokens the bot has persisted
+pub struct SyncJournal {
+    path: PathBuf,
+    entries: VecDeque<SyncJournalEntry>,
+}
+
+pub fn journal_path(data_dir: &Path) -> PathBuf {
+    data_dir.join(SYNC_JOURNAL_FILE)
+}
+
+/// Read the journal, skipping any lines that fail to parse
+pub fn read_journal(data_dir: &Path) -> Result<Vec<SyncJournalEntry>> {
+    let path = journal_path(data_dir);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read sync journal: {:?}", path))?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+impl SyncJournal {
+    pub fn open(data_dir: &Path) -> Self {
+        let entries = read_journal(data_dir).unwrap_or_else(|e| {
+            warn!("Starting with an empty sync journal: {}", e);
+            Vec::new()
+        });
+        let mut entries: VecDeque<SyncJournalEntry> = entries.into();
+        while entries.len() > SYNC_JOURNAL_MAX_ENTRIES {
+            entries.pop_front();
+        }
+        Self {
+            path: journal_path(data_dir),
+            entries,
+        }
+    }
+
+    /// Record an entry. Never fails: the journal is a debugging aid and must not
+    /// interfere with syncing, so write errors are only logged.
+    pub fn record(&mut self, entry: SyncJournalEntry) {
+        self.entries.push_back(entry);
+        while self.entries.len() > SYNC_JOURNAL_MAX_ENTRIES {
+            self.entries.pop_front();
+        }
+
+        let mut content = String::new();
+        for entry in &self.entries {
+            match serde_json::to_string(entry) {
+                Ok(line) => {
+                    content.push_str(&line);
+                    content.push('\n');
+                }
+                Err(e) => warn!("Failed to serialize sync journal entry: {}", e),
+            }
+        }
+        if let Err(e) = std::fs::write(&self.path, content) {
+            warn!(file_path = %self.path.display(), "Failed to write sync journal: {}", e);
+        } else {
+            debug!(entries = self.entries.len(), "Sync journal updated");
+        }
+    }
+}
@@ -0,0 +1,164 @@
+//! Test double for `MessageSender` that records messages instead of sending them.
+//! Each message gets a made-up event ID, `$mock<n>`, counting from 1.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use super::{EditOutcome, MessageSender};
+
+/// A message the code under test tried to send
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SentMessage {
+    pub room_id: OwnedRoomId,
+    pub text: String,
+    pub html: Option<String>,
+    /// Set for replies sent in a thread
+    pub thread_root: Option<OwnedEventId>,
+    /// The ID returned to the sender
+    pub event_id: OwnedEventId,
+}
+
+/// A replacement the code under test sent for an earlier message
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EditedMessage {
+    pub event_id: OwnedEventId,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MockMessageSender {
+    sent: Arc<Mutex<Vec<SentMessage>>>,
+    edits: Arc<Mutex<Vec<EditedMessage>>>,
+    redacted: Arc<Mutex<HashSet<OwnedEventId>>>,
+}
+
+impl MockMessageSender {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn sent_messages(&self) -> Vec<SentMessage> {
+        self.sent.lock().unwrap().clone()
+    }
+
+    pub fn edits(&self) -> Vec<EditedMessage> {
+        self.edits.lock().unwrap().clone()
+    }
+
+    /// Pretend the message was redacted, edits of it are sent fresh instead
+    pub fn redact(&self, event_id: &OwnedEventId) {
+        self.redacted.lock().unwrap().insert(event_id.clone());
+    }
+
+    /// Event IDs handed back to the caller, in sending order
+    pub fn returned_ids(&self) -> Vec<OwnedEventId> {
+        self.sent
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|m| m.event_id.clone())
+            .collect()
+    }
+
+    fn record(&self, room_id: &OwnedRoomId, text: &str, html: Option<String>) -> OwnedEventId {
+        self.record_in_thread(room_id, None, text, html)
+    }
+
+    fn record_in_thread(
+        &self,
+        room_id: &OwnedRoomId,
+        thread_root: Option<&OwnedEventId>,
+        text: &str,
+        html: Option<String>,
+    ) -> OwnedEventId {
+        let mut sent = self.sent.lock().unwrap();
+        let event_id =
+            OwnedEventId::try_from(format!("$mock{}", sent.len() + 1)).expect("valid event ID");
+        sent.push(SentMessage {
+            room_id: room_id.clone(),
+            text: text.to_owned(),
+            html,
+            thread_root: thread_root.cloned(),
+            event_id: event_id.clone(),
+        });
+        event_id
+    }
+}
+
+#[async_trait]
+impl MessageSender for MockMessageSender {
+    async fn send_text_message(
+        &self,
+        room_id: &OwnedRoomId,
+        message: &str,
+    ) -> Result<OwnedEventId> {
+        Ok(self.record(room_id, message, None))
+    }
+
+    async fn send_formatted_message(
+        &self,
+        room_id: &OwnedRoomId,
+        text: &str,
+        html: &str,
+    ) -> Result<OwnedEventId> {
+        Ok(self.record(room_id, text, Some(html.to_owned())))
+    }
+
+    // Same routing as the default HTML response format
+    async fn send_response(
+        &self,
+        room_id: &OwnedRoomId,
+        message: &str,
+        html_message: Option<String>,
+    ) -> Result<OwnedEventId> {
+        match html_message {
+            Some(html) => self.send_formatted_message(room_id, message, &html).await,
+            None => self.send_text_message(room_id, message).await,
+        }
+    }
+
+    async fn send_threaded_response(
+        &self,
+        room_id: &OwnedRoomId,
+        thread_root: &OwnedEventId,
+        message: &str,
+        html_message: Option<String>,
+    ) -> Result<OwnedEventId> {
+        Ok(self.record_in_thread(room_id, Some(thread_root), message, html_message))
+    }
+
+    async fn edit_message(
+        &self,
+        room_id: &OwnedRoomId,
+        event_id: &OwnedEventId,
+        new_text: &str,
+        new_html: Option<String>,
+    ) -> Result<EditOutcome> {
+        if self.redacted.lock().unwrap().contains(event_id) {
+            let new_event_id = self.record(room_id, new_text, new_html);
+            return Ok(EditOutcome::Resent(new_event_id));
+        }
+        self.edits.lock().unwrap().push(EditedMessage {
+            event_id: event_id.clone(),
+            text: new_text.to_owned(),
+            html: new_html,
+        });
+        Ok(EditOutcome::Edited)
+    }
+
+    async fn send_file(
+        &self,
+        room_id: &OwnedRoomId,
+        _purpose: &str,
+        filename: &str,
+        _content_type: &str,
+        _data: Vec<u8>,
+    ) -> Result<()> {
+        self.record(room_id, filename, None);
+        Ok(())
+    }
+}
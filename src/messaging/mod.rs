@@ -1,13 +1,59 @@
 use anyhow::Result;
 use async_trait::async_trait;
-use matrix_sdk::ruma::OwnedRoomId;
+use matrix_sdk::ruma::events::relation::Thread;
+use matrix_sdk::ruma::events::room::message::{
+    FileInfo, FileMessageEventContent, MessageType, Relation, ReplacementMetadata,
+    RoomMessageEventContent,
+};
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId, UInt};
+use std::sync::Arc;
+use tracing::{debug, warn};
+
+#[cfg(test)]
+pub mod mock;
+pub mod render;
+pub mod split;
+
+use crate::storage::StorageManager;
+use crate::storage::media::MediaRecord;
+use crate::storage::settings::{RenderStyle, ResponseFormat};
+
+/// Practical upper bound for the combined plain + HTML body of one event.
+/// Matrix caps whole events at 65536 bytes, this leaves room for the envelope.
+pub const MAX_EVENT_CONTENT_BYTES: usize = 60 * 1024;
+
+/// Default size at which a single body (plain or HTML) is split into several events
+pub const DEFAULT_MAX_MESSAGE_BYTES: usize = 28 * 1024;
+
+tokio::task_local! {
+    /// Event that triggered the command being processed when replies go in its
+    /// thread, set by `BotCore::process_command`
+    pub static THREAD_ROOT: OwnedEventId;
+}
+
+/// Root of the thread the current command's replies belong in, if any
+pub fn current_thread_root() -> Option<OwnedEventId> {
+    THREAD_ROOT.try_with(|root| root.clone()).ok()
+}
+
+/// What `MessageSender::edit_message` did
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EditOutcome {
+    /// The message now shows the new content
+    Edited,
+    /// The message was redacted or couldn't be edited, so the content went out
+    /// as this new message
+    Resent(OwnedEventId),
+}
 
 /// MessageSender trait provides an abstraction for sending messages to rooms
 /// This decouples the task management logic from matrix-specific implementation details
 #[async_trait]
 pub trait MessageSender: Send + Sync {
-    /// Send a plain text message to a room
-    async fn send_text_message(&self, room_id: &OwnedRoomId, message: &str) -> Result<()>;
+    /// Send a plain text message to a room. Every send returns the ID of the
+    /// event, the first one when a long message went out in several parts.
+    async fn send_text_message(&self, room_id: &OwnedRoomId, message: &str)
+    -> Result<OwnedEventId>;
 
     /// Send a formatted HTML message to a room
     async fn send_formatted_message(
@@ -15,44 +61,163 @@ pub trait MessageSender: Send + Sync {
         room_id: &OwnedRoomId,
         text: &str,
         html: &str,
-    ) -> Result<()>;
+    ) -> Result<OwnedEventId>;
 
-    /// Send a response message that can be either plain text or HTML
+    /// Send a response message that can be either plain text or HTML,
+    /// honoring the room's configured response format
     async fn send_response(
         &self,
         room_id: &OwnedRoomId,
         message: &str,
         html_message: Option<String>,
+    ) -> Result<OwnedEventId>;
+
+    /// Like `send_response`, as a reply in the thread rooted at `thread_root`
+    async fn send_threaded_response(
+        &self,
+        room_id: &OwnedRoomId,
+        thread_root: &OwnedEventId,
+        message: &str,
+        html_message: Option<String>,
+    ) -> Result<OwnedEventId>;
+
+    /// Reply to the command being processed: in its thread when `THREAD_ROOT`
+    /// is set, in the main timeline otherwise
+    async fn send_reply(
+        &self,
+        room_id: &OwnedRoomId,
+        message: &str,
+        html_message: Option<String>,
+    ) -> Result<OwnedEventId> {
+        match current_thread_root() {
+            Some(root) => {
+                self.send_threaded_response(room_id, &root, message, html_message)
+                    .await
+            }
+            None => self.send_response(room_id, message, html_message).await,
+        }
+    }
+
+    /// Replace the content of a message the bot sent earlier, falling back to a
+    /// fresh send when the original was redacted
+    async fn edit_message(
+        &self,
+        room_id: &OwnedRoomId,
+        event_id: &OwnedEventId,
+        new_text: &str,
+        new_html: Option<String>,
+    ) -> Result<EditOutcome>;
+
+    /// Upload `data` to a room as a file attachment. `purpose` is kept in the
+    /// media registry so the upload can be found and pruned later.
+    async fn send_file(
+        &self,
+        room_id: &OwnedRoomId,
+        purpose: &str,
+        filename: &str,
+        content_type: &str,
+        data: Vec<u8>,
     ) -> Result<()>;
 }
 
 /// Implements the MessageSender trait for Matrix client
 pub struct MatrixMessageSender {
     client: matrix_sdk::Client,
+    storage: Arc<StorageManager>,
+    max_message_bytes: usize,
 }
 
 impl MatrixMessageSender {
-    pub fn new(client: matrix_sdk::Client) -> Self {
-        Self { client }
+    pub fn new(
+        client: matrix_sdk::Client,
+        storage: Arc<StorageManager>,
+        max_message_bytes: usize,
+    ) -> Self {
+        Self {
+            client,
+            storage,
+            max_message_bytes,
+        }
     }
-}
 
-#[async_trait]
-impl MessageSender for MatrixMessageSender {
-    async fn send_text_message(&self, room_id: &OwnedRoomId, message: &str) -> Result<()> {
+    /// Send events one after another so the parts of a split message stay in order
+    async fn send_contents(
+        &self,
+        room_id: &OwnedRoomId,
+        contents: Vec<RoomMessageEventContent>,
+    ) -> Result<OwnedEventId> {
         let room = self
             .client
             .get_room(room_id)
             .ok_or_else(|| anyhow::anyhow!("Room not found"))?;
 
-        // Create a plain text message type
-        let content =
-            matrix_sdk::ruma::events::room::message::RoomMessageEventContent::notice_plain(message);
-        room.send(content)
-            .await
-            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        if contents.len() > 1 {
+            debug!(room_id = %room_id, parts = contents.len(), "Sending oversized message in parts");
+        }
+        let mut first_event_id = None;
+        for content in contents {
+            let response = room
+                .send(content)
+                .await
+                .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+            first_event_id.get_or_insert(response.event_id);
+        }
 
-        Ok(())
+        first_event_id.ok_or_else(|| anyhow::anyhow!("Message had no content to send"))
+    }
+
+    fn text_contents(&self, message: &str) -> Vec<RoomMessageEventContent> {
+        // Plain text notices, split into parts if it's too long
+        split::split_text(message, self.max_message_bytes)
+            .into_iter()
+            .map(RoomMessageEventContent::notice_plain)
+            .collect()
+    }
+
+    fn formatted_contents(&self, text: &str, html: &str) -> Vec<RoomMessageEventContent> {
+        // Each part's text and HTML come from the same lines, so they never drift apart
+        split::split_formatted(text, html, self.max_message_bytes)
+            .into_iter()
+            .map(|(text, html)| RoomMessageEventContent::notice_html(text, html))
+            .collect()
+    }
+
+    fn markdown_contents(&self, markdown: &str) -> Vec<RoomMessageEventContent> {
+        split::split_text(markdown, self.max_message_bytes)
+            .into_iter()
+            .map(RoomMessageEventContent::notice_markdown)
+            .collect()
+    }
+
+    /// Events for a response in the room's configured format, see `send_response`
+    async fn response_contents(
+        &self,
+        room_id: &OwnedRoomId,
+        message: &str,
+        html_message: Option<String>,
+    ) -> Vec<RoomMessageEventContent> {
+        let settings = self.storage.room_settings(room_id).await;
+        // The plain style always goes out as clean text so clients don't render markup
+        if settings.render_style == RenderStyle::Plain {
+            return self.text_contents(&render::plain_style(message));
+        }
+        match (settings.response_format, html_message) {
+            (ResponseFormat::Html, Some(html)) => self.formatted_contents(message, &html),
+            (ResponseFormat::Markdown, _) => self.markdown_contents(message),
+            _ => self.text_contents(message),
+        }
+    }
+}
+
+#[async_trait]
+impl MessageSender for MatrixMessageSender {
+    async fn send_text_message(
+        &self,
+        room_id: &OwnedRoomId,
+        message: &str,
+    ) -> Result<OwnedEventId> {
+        self.send_contents(room_id, self.text_contents(message))
+            .await
     }
 
     async fn send_formatted_message(
@@ -60,37 +225,134 @@ impl MessageSender for MatrixMessageSender {
         room_id: &OwnedRoomId,
         text: &str,
         html: &str,
-    ) -> Result<()> {
+    ) -> Result<OwnedEventId> {
+        self.send_contents(room_id, self.formatted_contents(text, html))
+            .await
+    }
+
+    async fn send_response(
+        &self,
+        room_id: &OwnedRoomId,
+        message: &str,
+        html_message: Option<String>,
+    ) -> Result<OwnedEventId> {
+        let settings = self.storage.room_settings(room_id).await;
+        // The plain style always goes out as clean text so clients don't render markup
+        if settings.render_style == RenderStyle::Plain {
+            return self
+                .send_text_message(room_id, &render::plain_style(message))
+                .await;
+        }
+        match (settings.response_format, html_message) {
+            (ResponseFormat::Html, Some(html)) => {
+                self.send_formatted_message(room_id, message, &html).await
+            }
+            (ResponseFormat::Markdown, _) => {
+                self.send_contents(room_id, self.markdown_contents(message))
+                    .await
+            }
+            _ => self.send_text_message(room_id, message).await,
+        }
+    }
+
+    async fn send_threaded_response(
+        &self,
+        room_id: &OwnedRoomId,
+        thread_root: &OwnedEventId,
+        message: &str,
+        html_message: Option<String>,
+    ) -> Result<OwnedEventId> {
+        let mut contents = self.response_contents(room_id, message, html_message).await;
+        // Clients without thread support show the parts as replies to the command
+        for content in &mut contents {
+            content.relates_to = Some(Relation::Thread(Thread::plain(
+                thread_root.clone(),
+                thread_root.clone(),
+            )));
+        }
+        self.send_contents(room_id, contents).await
+    }
+
+    async fn edit_message(
+        &self,
+        room_id: &OwnedRoomId,
+        event_id: &OwnedEventId,
+        new_text: &str,
+        new_html: Option<String>,
+    ) -> Result<EditOutcome> {
         let room = self
             .client
             .get_room(room_id)
             .ok_or_else(|| anyhow::anyhow!("Room not found"))?;
-
-        // Create HTML formatted message content
-        let content_type = matrix_sdk::ruma::events::room::message::MessageType::notice_html(
-            text.to_string(),
-            html.to_string(),
-        );
-        let content =
-            matrix_sdk::ruma::events::room::message::RoomMessageEventContent::new(content_type);
-
-        room.send(content)
+        let redacted = match room.event(event_id, None).await {
+            Ok(event) => event
+                .raw()
+                .get_field::<serde_json::Value>("unsigned")
+                .ok()
+                .flatten()
+                .is_some_and(|unsigned| unsigned.get("redacted_because").is_some()),
+            Err(e) => {
+                debug!(room_id = %room_id, event_id = %event_id, "Couldn't fetch message to edit: {}", e);
+                true
+            }
+        };
+        let mut contents = self.response_contents(room_id, new_text, new_html).await;
+        // An edit replaces a single event, content that needs splitting goes out fresh
+        if redacted || contents.len() != 1 {
+            let new_event_id = self.send_contents(room_id, contents).await?;
+            return Ok(EditOutcome::Resent(new_event_id));
+        }
+        let replacement = contents
+            .remove(0)
+            .make_replacement(ReplacementMetadata::new(event_id.clone(), None), None);
+        room.send(replacement)
             .await
             .map_err(|e| anyhow::anyhow!("{:?}", e))?;
-
-        Ok(())
+        Ok(EditOutcome::Edited)
     }
 
-    async fn send_response(
+    async fn send_file(
         &self,
         room_id: &OwnedRoomId,
-        message: &str,
-        html_message: Option<String>,
+        purpose: &str,
+        filename: &str,
+        content_type: &str,
+        data: Vec<u8>,
     ) -> Result<()> {
-        if let Some(html) = html_message {
-            self.send_formatted_message(room_id, message, &html).await
-        } else {
-            self.send_text_message(room_id, message).await
+        let room = self
+            .client
+            .get_room(room_id)
+            .ok_or_else(|| anyhow::anyhow!("Room not found"))?;
+        let mime_type: mime::Mime = content_type.parse()?;
+        let size = data.len() as u64;
+        // Uploaded separately from the message so the mxc URI can be recorded
+        let response = self
+            .client
+            .media()
+            .upload(&mime_type, data, None)
+            .await
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+        let mut info = FileInfo::new();
+        info.mimetype = Some(content_type.to_owned());
+        info.size = UInt::new(size);
+        let file =
+            FileMessageEventContent::plain(filename.to_owned(), response.content_uri.clone())
+                .info(Box::new(info));
+        room.send(RoomMessageEventContent::new(MessageType::File(file)))
+            .await
+            .map_err(|e| anyhow::anyhow!("{:?}", e))?;
+
+        let record = MediaRecord {
+            mxc_uri: response.content_uri.to_string(),
+            room_id: room_id.clone(),
+            purpose: purpose.to_owned(),
+            filename: filename.to_owned(),
+            uploaded_at: chrono::Utc::now(),
+            size,
+        };
+        if let Err(e) = self.storage.record_media_upload(record).await {
+            warn!(room_id = %room_id, "Failed to record media upload: {}", e);
         }
+        Ok(())
     }
 }
@@ -0,0 +1,124 @@
+//! Rendering of outgoing text for the accessibility-friendly plain style.
+
+/// Emoji and pictographs used as decoration in bot messages
+fn is_decorative(c: char) -> bool {
+    matches!(c,
+        '\u{1F000}'..='\u{1FAFF}'   // emoji and pictographs
+        | '\u{2600}'..='\u{27BF}'   // misc symbols and dingbats (✅ ❌ ⚠ ⚙ ✖ ➡)
+        | '\u{2300}'..='\u{23FF}'   // misc technical (⏳)
+        | '\u{2190}'..='\u{21FF}'   // arrows (↕)
+        | '\u{2B00}'..='\u{2BFF}'
+        | '\u{2139}'                // ℹ
+        | '\u{FE0F}'                // emoji presentation selector
+        | '\u{200D}'                // zero width joiner
+    )
+}
+
+/// Rewrite a composed message for the plain style: drop decorative emoji,
+/// Markdown emphasis and box-drawing characters, and use simple status markers
+pub fn plain_style(text: &str) -> String {
+    let text = text
+        .replace("**", "")
+        .replace('`', "")
+        .replace("[pending]", "[open]")
+        .replace(['•', '—'], "-");
+    text.lines()
+        .map(|line| {
+            let mut plain = String::with_capacity(line.len());
+            let mut dropped = false;
+            for c in line.chars() {
+                if is_decorative(c) {
+                    dropped = true;
+                    continue;
+                }
+                // Don't leave a double space where an emoji was
+                if dropped && c == ' ' && (plain.is_empty() || plain.ends_with(' ')) {
+                    continue;
+                }
+                dropped = false;
+                plain.push(match c {
+                    // Box drawing characters
+                    '\u{2500}'..='\u{257F}' => ' ',
+                    c => c,
+                });
+            }
+            plain.trim().to_owned()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+/// Escape user-provided text before it is placed inside an HTML body
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Escape user-provided text so Markdown punctuation in it renders literally
+pub fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(
+            c,
+            '\\' | '`'
+                | '*'
+                | '_'
+                | '{'
+                | '}'
+                | '['
+                | ']'
+                | '<'
+                | '>'
+                | '('
+                | ')'
+                | '#'
+                | '+'
+                | '-'
+                | '.'
+                | '!'
+                | '|'
+                | '~'
+        ) {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plain_style_keeps_only_the_words() {
+        for (styled, plain) in [
+            (
+                "✅ Task 1 marked as done: **Buy milk**",
+                "Task 1 marked as done: Buy milk",
+            ),
+            (
+                "1. ⏳ **[pending] Buy milk** (3d 2h)",
+                "1. [open] Buy milk (3d 2h)",
+            ),
+            ("✖️ Closed: 2", "Closed: 2"),
+            ("👨‍👩‍👧 Family list", "Family list"),
+            ("• Run `!help` — or ask", "- Run !help - or ask"),
+            ("│ done │ 3 │", "done   3"),
+            ("Plain text stays as it is", "Plain text stays as it is"),
+        ] {
+            assert_eq!(plain_style(styled), plain);
+        }
+    }
+
+    #[test]
+    fn escaping_covers_html_and_markdown_punctuation() {
+        assert_eq!(
+            escape_html(r#"<b>"Milk" & eggs</b>"#),
+            "&lt;b&gt;&quot;Milk&quot; &amp; eggs&lt;/b&gt;"
+        );
+        assert_eq!(escape_markdown("**v1.4** [x]"), r"\*\*v1\.4\*\* \[x\]");
+    }
+}
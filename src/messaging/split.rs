@@ -0,0 +1,317 @@
+//! Splitting of oversized message bodies into several events.
+
+// Elements that never have a closing tag
+const VOID_ELEMENTS: &[&str] = &["br", "hr", "img", "input", "wbr"];
+
+/// Largest index <= `index` that falls on a char boundary of `text`
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+/// Length of `window` without a trailing tag or entity that it cuts in half, so a
+/// cut never lands inside `<a href=...>` or `&amp;`
+fn html_safe_len(window: &str) -> usize {
+    let mut len = window.len();
+    if let Some(start) = window.rfind('<')
+        && !window[start..].contains('>')
+    {
+        len = len.min(start);
+    }
+    if let Some(start) = window.rfind('&')
+        && window[start + 1..]
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '#')
+    {
+        len = len.min(start);
+    }
+    len
+}
+
+/// Split `text` into chunks of at most `max_bytes`, preferring to break right after
+/// `separator`. Pieces without a separator in range are cut at a char boundary, backed
+/// up to `safe_len` of the window so the cut doesn't split markup.
+fn split_on(
+    text: &str,
+    separator: &str,
+    max_bytes: usize,
+    safe_len: fn(&str) -> usize,
+) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while rest.len() > max_bytes {
+        let window = &rest[..floor_char_boundary(rest, max_bytes)];
+        let cut = match window.rfind(separator) {
+            Some(pos) if pos > 0 => pos + separator.len(),
+            // Always make progress, even if a single char is above the limit
+            _ if window.is_empty() => rest.chars().next().map_or(rest.len(), char::len_utf8),
+            _ => match safe_len(window) {
+                // Markup longer than the whole window can only be cut
+                0 => window.len(),
+                len => len,
+            },
+        };
+        chunks.push(rest[..cut].to_owned());
+        rest = &rest[cut..];
+    }
+    if !rest.is_empty() || chunks.is_empty() {
+        chunks.push(rest.to_owned());
+    }
+    chunks
+}
+
+/// Split a plain text body on line boundaries
+pub fn split_text(text: &str, max_bytes: usize) -> Vec<String> {
+    split_on(text, "\n", max_bytes, str::len)
+        .into_iter()
+        .map(|chunk| chunk.trim_end_matches('\n').to_owned())
+        .collect()
+}
+
+/// Name of the tag in `<name ...>` / `</name>`, lowercased
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('<')
+        .trim_start_matches('/')
+        .chars()
+        .take_while(|c| c.is_ascii_alphanumeric())
+        .collect::<String>()
+        .to_lowercase()
+}
+
+/// Tags still open at the end of `html`, outermost first, as their original opening text
+fn open_tags(html: &str, mut stack: Vec<String>) -> Vec<String> {
+    let mut rest = html;
+    while let Some(start) = rest.find('<') {
+        let Some(len) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start..start + len + 1];
+        let name = tag_name(tag);
+        if tag.starts_with("</") {
+            if let Some(pos) = stack.iter().rposition(|open| tag_name(open) == name) {
+                stack.truncate(pos);
+            }
+        } else if !tag.ends_with("/>") && !VOID_ELEMENTS.contains(&name.as_str()) {
+            stack.push(tag.to_owned());
+        }
+        rest = &rest[start + len + 1..];
+    }
+    stack
+}
+
+// Leave headroom for the closing/reopening tags added around each HTML chunk
+fn html_budget(max_bytes: usize) -> usize {
+    (max_bytes * 9 / 10).max(1)
+}
+
+/// Close tags that are still open at the end of a piece and reopen them at the
+/// start of the next, so every chunk is well-formed
+fn balance_html(pieces: Vec<String>) -> Vec<String> {
+    let mut chunks = Vec::new();
+    let mut carried: Vec<String> = Vec::new();
+
+    for piece in pieces {
+        let mut chunk = carried.concat();
+        chunk.push_str(piece.trim_end_matches("<br>"));
+        carried = open_tags(&piece, carried);
+        for tag in carried.iter().rev() {
+            chunk.push_str(&format!("</{}>", tag_name(tag)));
+        }
+        chunks.push(chunk);
+    }
+    chunks
+}
+
+/// Split an HTML body on `<br>` boundaries into well-formed chunks
+pub fn split_html(html: &str, max_bytes: usize) -> Vec<String> {
+    balance_html(split_on(
+        html,
+        "<br>",
+        html_budget(max_bytes),
+        html_safe_len,
+    ))
+}
+
+/// Split a text body and its HTML rendering into `(text, html)` parts that carry
+/// the same lines: line `i` of the text goes with the `i`th `<br>`-separated line
+/// of the HTML. When the lines don't pair up, or one line alone is over the limit,
+/// the HTML is split and each part's text is stripped from its own chunk.
+pub fn split_formatted(text: &str, html: &str, max_bytes: usize) -> Vec<(String, String)> {
+    let text_lines: Vec<&str> = text.split('\n').collect();
+    let html_lines: Vec<&str> = html.split("<br>").collect();
+    let html_max = html_budget(max_bytes);
+    let paired = text_lines.len() == html_lines.len()
+        && text_lines.iter().all(|line| line.len() <= max_bytes)
+        && html_lines.iter().all(|line| line.len() <= html_max);
+    if !paired {
+        return split_html(html, max_bytes)
+            .into_iter()
+            .map(|html| (strip_tags(&html), html))
+            .collect();
+    }
+
+    let mut texts = vec![String::new()];
+    let mut htmls = vec![String::new()];
+    for (i, (text_line, html_line)) in text_lines.iter().zip(&html_lines).enumerate() {
+        let (text, html) = (texts.last_mut().unwrap(), htmls.last_mut().unwrap());
+        if i > 0 {
+            if text.len() + 1 + text_line.len() <= max_bytes
+                && html.len() + 4 + html_line.len() <= html_max
+            {
+                text.push('\n');
+                html.push_str("<br>");
+            } else {
+                texts.push(String::new());
+                htmls.push(String::new());
+            }
+        }
+        texts.last_mut().unwrap().push_str(text_line);
+        htmls.last_mut().unwrap().push_str(html_line);
+    }
+    texts.into_iter().zip(balance_html(htmls)).collect()
+}
+
+/// Plain fallback for an HTML chunk that has no matching text chunk
+pub fn strip_tags(html: &str) -> String {
+    let html = html.replace("<br>", "\n");
+    let mut text = String::with_capacity(html.len());
+    let mut in_tag = false;
+    for c in html.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => in_tag = false,
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    // `&amp;` last so an escaped entity like `&amp;lt;` stays literal
+    text.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether every tag opened in `html` is closed again, in order
+    fn balanced(html: &str) -> bool {
+        let mut stack = Vec::new();
+        let mut rest = html;
+        while let Some(start) = rest.find('<') {
+            let Some(len) = rest[start..].find('>') else {
+                return false;
+            };
+            let tag = &rest[start..start + len + 1];
+            let name = tag_name(tag);
+            if tag.starts_with("</") {
+                if stack.pop() != Some(name) {
+                    return false;
+                }
+            } else if !VOID_ELEMENTS.contains(&name.as_str()) {
+                stack.push(name);
+            }
+            rest = &rest[start + len + 1..];
+        }
+        stack.is_empty()
+    }
+
+    #[test]
+    fn cuts_never_land_inside_a_tag_or_entity() {
+        let html = "<b>Milk &amp; eggs</b> <a href=\"https://example.org\">link</a>";
+        // From the longest tag up, every cut can fall between markup
+        for max_bytes in 30..html.len() {
+            let chunks = split_on(html, "<br>", max_bytes, html_safe_len);
+            assert_eq!(chunks.concat(), html);
+            for chunk in chunks {
+                assert_eq!(
+                    chunk.matches('<').count(),
+                    chunk.matches('>').count(),
+                    "{chunk}"
+                );
+                assert_eq!(
+                    chunk.matches('&').count(),
+                    chunk.matches("&amp;").count(),
+                    "{chunk}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_long_list_is_sent_in_ordered_balanced_chunks() {
+        let max_bytes = 4000;
+        let html = (1..=1500)
+            .map(|i| {
+                format!("<b>{i}.</b> Task &amp; <a href=\"https://example.org/{i}\">notes</a>")
+            })
+            .collect::<Vec<_>>()
+            .join("<br>");
+        let html = format!("<p>{}</p>", html);
+        assert!(html.len() > 100_000);
+
+        let chunks = split_html(&html, max_bytes);
+        assert!(chunks.len() > 1);
+        let mut next = 1;
+        for chunk in &chunks {
+            assert!(chunk.len() <= max_bytes, "{} bytes", chunk.len());
+            assert!(balanced(chunk), "{chunk}");
+            for line in chunk.split("<br>") {
+                assert!(line.contains(&format!("<b>{next}.</b>")), "{line}");
+                next += 1;
+            }
+        }
+        assert_eq!(next, 1501);
+    }
+
+    #[test]
+    fn formatted_parts_keep_text_and_html_on_the_same_lines() {
+        // The HTML lines are far longer, so splitting each body on its own drifts apart
+        let lines = 1..=400;
+        let text = lines
+            .clone()
+            .map(|i| format!("{i}. Task"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        let html = lines
+            .map(|i| format!("<b>{i}.</b> <a href=\"https://example.org/{i}\">Task</a>"))
+            .collect::<Vec<_>>()
+            .join("<br>");
+
+        let parts = split_formatted(&text, &html, 2000);
+        assert!(parts.len() > 1);
+        let mut next = 1;
+        for (text, html) in &parts {
+            assert!(text.len() <= 2000 && html.len() <= 2000);
+            assert!(balanced(html), "{html}");
+            let text_lines: Vec<&str> = text.split('\n').collect();
+            let html_lines: Vec<&str> = html.split("<br>").collect();
+            assert_eq!(text_lines.len(), html_lines.len());
+            for (text_line, html_line) in text_lines.iter().zip(html_lines) {
+                assert_eq!(*text_line, format!("{next}. Task"));
+                assert!(
+                    html_line.contains(&format!("<b>{next}.</b>")),
+                    "{html_line}"
+                );
+                next += 1;
+            }
+        }
+        assert_eq!(next, 401);
+    }
+
+    #[test]
+    fn unpaired_lines_take_their_text_from_the_html_chunk() {
+        let parts = split_formatted("one line", "<b>Milk &amp; eggs</b><br>more", 100);
+        assert_eq!(
+            parts,
+            [(
+                "Milk & eggs\nmore".to_owned(),
+                "<b>Milk &amp; eggs</b><br>more".to_owned()
+            )]
+        );
+    }
+}
@@ -0,0 +1,114 @@
+use matrix_sdk::ruma::OwnedRoomId;
+use std::collections::HashSet;
+
+use super::StorageManager;
+
+/// Whether the bot should respond in `room_id`. Rooms from `--active-room` and rooms
+/// activated at runtime together form the allowlist; while both are empty the bot
+/// is active everywhere.
+pub fn is_room_active(
+    room_id: &OwnedRoomId,
+    configured: &[OwnedRoomId],
+    activated: &HashSet<OwnedRoomId>,
+) -> bool {
+    if configured.is_empty() && activated.is_empty() {
+        return true;
+    }
+    configured.contains(room_id) || activated.contains(room_id)
+}
+
+/// Whether `command` with `args` is `!bot activate`
+pub fn is_activate_command(command: &str, args: &str) -> bool {
+    command == "bot"
+        && args
+            .split_whitespace()
+            .next()
+            .is_some_and(|arg| arg.eq_ignore_ascii_case("activate"))
+}
+
+/// Whether a command in `room_id` gets a response: always in DM rooms and rooms on
+/// the allowlist, elsewhere only when an admin activates the room
+pub fn admits_command(
+    room_id: &OwnedRoomId,
+    configured: &[OwnedRoomId],
+    activated: &HashSet<OwnedRoomId>,
+    is_dm: bool,
+    activating: bool,
+    is_admin: bool,
+) -> bool {
+    is_dm || is_room_active(room_id, configured, activated) || (activating && is_admin)
+}
+
+impl StorageManager {
+    pub async fn activated_rooms(&self) -> HashSet<OwnedRoomId> {
+        self.active_rooms.lock().await.clone()
+    }
+
+    /// Returns false if the room was already activated
+    pub async fn activate_room(&self, room_id: &OwnedRoomId) -> bool {
+        self.active_rooms.lock().await.insert(room_id.clone())
+    }
+
+    /// Returns false if the room wasn't activated at runtime
+    pub async fn deactivate_room(&self, room_id: &OwnedRoomId) -> bool {
+        self.active_rooms.lock().await.remove(room_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(id: &str) -> OwnedRoomId {
+        OwnedRoomId::try_from(id).unwrap()
+    }
+
+    #[test]
+    fn allowlist_gates_commands_by_room() {
+        let configured = vec![room("!configured:example.org")];
+        let activated = HashSet::from([room("!activated:example.org")]);
+        let admits = |id: &str, is_dm, activating, is_admin| {
+            admits_command(
+                &room(id),
+                &configured,
+                &activated,
+                is_dm,
+                activating,
+                is_admin,
+            )
+        };
+
+        assert!(admits("!configured:example.org", false, false, false));
+        assert!(admits("!activated:example.org", false, false, false));
+        // Private lists work wherever their owner is
+        assert!(admits("!dm:example.org", true, false, false));
+        assert!(!admits("!other:example.org", false, false, false));
+        assert!(!admits("!other:example.org", false, false, true));
+
+        // Outside the allowlist only an admin may activate the room
+        assert!(admits("!other:example.org", false, true, true));
+        assert!(!admits("!other:example.org", false, true, false));
+    }
+
+    #[test]
+    fn empty_allowlist_admits_every_room() {
+        let other = room("!other:example.org");
+        assert!(admits_command(
+            &other,
+            &[],
+            &HashSet::new(),
+            false,
+            false,
+            false
+        ));
+    }
+
+    #[test]
+    fn only_bot_activate_activates() {
+        assert!(is_activate_command("bot", "activate"));
+        assert!(is_activate_command("bot", "ACTIVATE now"));
+        assert!(!is_activate_command("bot", "deactivate"));
+        assert!(!is_activate_command("bot", ""));
+        assert!(!is_activate_command("add", "activate"));
+    }
+}
@@ -0,0 +1,108 @@
+use matrix_sdk::ruma::OwnedRoomId;
+use tracing::info;
+
+use super::StorageManager;
+
+/// Outcome of `StorageManager::remove_admin`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AdminRemoval {
+    Removed,
+    NotAdmin,
+    LastAdmin,
+}
+
+impl StorageManager {
+    pub async fn is_admin(&self, room_id: &OwnedRoomId, user: &str) -> bool {
+        self.admins
+            .lock()
+            .await
+            .get(room_id)
+            .is_some_and(|admins| admins.iter().any(|a| a == user))
+    }
+
+    pub async fn room_admins(&self, room_id: &OwnedRoomId) -> Vec<String> {
+        self.admins
+            .lock()
+            .await
+            .get(room_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Make `user` the room's first admin if the room has none yet.
+    /// Returns true when the user was granted admin by this call.
+    pub async fn claim_admin_if_unset(&self, room_id: &OwnedRoomId, user: &str) -> bool {
+        let mut admins = self.admins.lock().await;
+        let room_admins = admins.entry(room_id.clone()).or_default();
+        if !room_admins.is_empty() {
+            return false;
+        }
+        room_admins.push(user.to_owned());
+        info!(room_id = %room_id, user, "Granted admin to the first user of the room");
+        true
+    }
+
+    /// Returns false if the user already was an admin
+    pub async fn add_admin(&self, room_id: &OwnedRoomId, user: &str) -> bool {
+        let mut admins = self.admins.lock().await;
+        let room_admins = admins.entry(room_id.clone()).or_default();
+        if room_admins.iter().any(|a| a == user) {
+            return false;
+        }
+        room_admins.push(user.to_owned());
+        true
+    }
+
+    /// Remove `user` from the room's admins. The last admin stays, as without any
+    /// the next user to run a command would claim the room.
+    pub async fn remove_admin(&self, room_id: &OwnedRoomId, user: &str) -> AdminRemoval {
+        let mut admins = self.admins.lock().await;
+        let Some(room_admins) = admins.get_mut(room_id) else {
+            return AdminRemoval::NotAdmin;
+        };
+        if !room_admins.iter().any(|a| a == user) {
+            return AdminRemoval::NotAdmin;
+        }
+        if room_admins.len() == 1 {
+            return AdminRemoval::LastAdmin;
+        }
+        room_admins.retain(|a| a != user);
+        AdminRemoval::Removed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_storage;
+
+    #[tokio::test]
+    async fn the_last_admin_cannot_be_removed() {
+        let storage = test_storage(0);
+        let room_id = OwnedRoomId::try_from("!room:example.org").unwrap();
+        assert!(
+            storage
+                .claim_admin_if_unset(&room_id, "@alice:example.org")
+                .await
+        );
+        assert_eq!(
+            storage.remove_admin(&room_id, "@alice:example.org").await,
+            AdminRemoval::LastAdmin
+        );
+
+        assert!(storage.add_admin(&room_id, "@bob:example.org").await);
+        assert_eq!(
+            storage.remove_admin(&room_id, "@carol:example.org").await,
+            AdminRemoval::NotAdmin
+        );
+        assert_eq!(
+            storage.remove_admin(&room_id, "@alice:example.org").await,
+            AdminRemoval::Removed
+        );
+        assert_eq!(
+            storage.remove_admin(&room_id, "@bob:example.org").await,
+            AdminRemoval::LastAdmin
+        );
+        assert_eq!(storage.room_admins(&room_id).await, ["@bob:example.org"]);
+    }
+}
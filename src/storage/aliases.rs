@@ -0,0 +1,74 @@
+use matrix_sdk::ruma::OwnedRoomId;
+use std::collections::HashMap;
+use tracing::info;
+
+use super::StorageManager;
+
+pub const MAX_ALIAS_LEN: usize = 16;
+
+/// Alias names are ASCII letters and digits
+pub fn validate_alias_name(alias: &str) -> Result<(), String> {
+    if alias.is_empty() {
+        return Err("Aliases cannot be empty.".to_owned());
+    }
+    if alias.len() > MAX_ALIAS_LEN {
+        return Err(format!(
+            "Aliases can be at most {} characters long.",
+            MAX_ALIAS_LEN
+        ));
+    }
+    if !alias.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err("Aliases may only contain letters and digits.".to_owned());
+    }
+    Ok(())
+}
+
+impl StorageManager {
+    /// The command an alias stands for in this room, if it is one
+    pub async fn resolve_alias(&self, room_id: &OwnedRoomId, alias: &str) -> Option<String> {
+        self.aliases
+            .lock()
+            .await
+            .get(room_id)
+            .and_then(|aliases| aliases.get(alias))
+            .cloned()
+    }
+
+    pub async fn room_aliases(&self, room_id: &OwnedRoomId) -> HashMap<String, String> {
+        self.aliases
+            .lock()
+            .await
+            .get(room_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Returns the command the alias pointed to before, if it existed
+    pub async fn set_alias(
+        &self,
+        room_id: &OwnedRoomId,
+        alias: &str,
+        command: &str,
+    ) -> Option<String> {
+        info!(room_id = %room_id, alias, command, "Set command alias");
+        self.aliases
+            .lock()
+            .await
+            .entry(room_id.clone())
+            .or_default()
+            .insert(alias.to_owned(), command.to_owned())
+    }
+
+    /// Returns false if the room had no such alias
+    pub async fn remove_alias(&self, room_id: &OwnedRoomId, alias: &str) -> bool {
+        let mut aliases = self.aliases.lock().await;
+        let Some(room_aliases) = aliases.get_mut(room_id) else {
+            return false;
+        };
+        let removed = room_aliases.remove(alias).is_some();
+        if room_aliases.is_empty() {
+            aliases.remove(room_id);
+        }
+        removed
+    }
+}
@@ -0,0 +1,330 @@
+use anyhow::{Context, Result, anyhow};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use serde::{Deserialize, Serialize};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tracing::{debug, info, warn};
+
+use super::atomic::write_atomic;
+use super::checksum::sha256_hex;
+use super::{StorageData, StorageManager};
+
+// Archives live in their own subdirectory so that anything cleaning up the
+// operational save files in `data_dir` never sees (or prunes) them.
+pub const ARCHIVE_DIR: &str = "archive";
+const MANIFEST_FILE: &str = "manifest.json";
+pub const ARCHIVE_INTERVAL_DAYS: i64 = 7;
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ArchiveEntry {
+    pub date: NaiveDate,
+    pub created_at: DateTime<Utc>,
+    pub path: String, // relative to the archive directory, e.g. "2024/asmith_archive_2024-05-01.json.gz"
+    pub size: u64,
+    pub sha256: String,
+    pub compressed: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ArchiveManifest {
+    pub entries: Vec<ArchiveEntry>,
+}
+
+impl ArchiveManifest {
+    pub fn last(&self) -> Option<&ArchiveEntry> {
+        self.entries.iter().max_by_key(|e| e.created_at)
+    }
+
+    pub fn find(&self, date: NaiveDate) -> Option<&ArchiveEntry> {
+        self.entries.iter().find(|e| e.date == date)
+    }
+
+    /// Whether a new snapshot should be written. A missed week (bot offline) does
+    /// not produce back-filled entries, only a single snapshot on the next check.
+    pub fn is_due(&self, now: DateTime<Utc>) -> bool {
+        match self.last() {
+            Some(entry) => now - entry.created_at >= Duration::days(ARCHIVE_INTERVAL_DAYS),
+            None => true,
+        }
+    }
+
+    /// Insert an entry, replacing any previous snapshot for the same date
+    pub fn upsert(&mut self, entry: ArchiveEntry) {
+        self.entries.retain(|e| e.date != entry.date);
+        self.entries.push(entry);
+        self.entries.sort_by_key(|e| e.date);
+    }
+}
+
+pub fn archive_dir(data_dir: &Path) -> PathBuf {
+    data_dir.join(ARCHIVE_DIR)
+}
+
+pub fn load_manifest(data_dir: &Path) -> Result<ArchiveManifest> {
+    let path = archive_dir(data_dir).join(MANIFEST_FILE);
+    if !path.exists() {
+        return Ok(ArchiveManifest::default());
+    }
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read archive manifest: {:?}", path))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse archive manifest: {:?}", path))
+}
+
+async fn write_manifest(data_dir: &Path, manifest: &ArchiveManifest) -> Result<()> {
+    let path = archive_dir(data_dir).join(MANIFEST_FILE);
+    let json = serde_json::to_string_pretty(manifest)?;
+    write_atomic(&path, json.as_bytes())
+        .await
+        .with_context(|| format!("Failed to write archive manifest: {:?}", path))
+}
+
+/// Write an archival snapshot of `data` for the date of `now` and record it in the manifest.
+/// Both files are replaced atomically, so an interrupted write keeps the previous ones.
+pub async fn write_archive(
+    data_dir: &Path,
+    data: &StorageData,
+    now: DateTime<Utc>,
+    compress: bool,
+) -> Result<ArchiveEntry> {
+    let date = now.date_naive();
+    let year_dir = archive_dir(data_dir).join(date.format("%Y").to_string());
+    tokio::fs::create_dir_all(&year_dir)
+        .await
+        .with_context(|| format!("Failed to create archive directory: {:?}", year_dir))?;
+
+    let json = serde_json::to_vec_pretty(data)?;
+    let (bytes, extension) = if compress {
+        let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&json)?;
+        (encoder.finish()?, "json.gz")
+    } else {
+        (json, "json")
+    };
+
+    let filename = format!(
+        "{}_archive_{}.{}",
+        env!("CARGO_PKG_NAME"),
+        date.format("%Y-%m-%d"),
+        extension
+    );
+    let filepath = year_dir.join(&filename);
+    write_atomic(&filepath, &bytes)
+        .await
+        .with_context(|| format!("Failed to write archive file: {:?}", filepath))?;
+
+    let entry = ArchiveEntry {
+        date,
+        created_at: now,
+        path: format!("{}/{}", date.format("%Y"), filename),
+        size: bytes.len() as u64,
+        sha256: sha256_hex(&bytes),
+        compressed: compress,
+    };
+
+    let mut manifest = load_manifest(data_dir)?;
+    // Drop a stale entry for the same date if it pointed at the other (compressed/plain) file
+    if let Some(old) = manifest.find(date)
+        && old.path != entry.path
+    {
+        let old_path = archive_dir(data_dir).join(&old.path);
+        if let Err(e) = tokio::fs::remove_file(&old_path).await {
+            warn!(file_path = %old_path.display(), error = %e, "Failed to remove replaced archive file");
+        }
+    }
+    manifest.upsert(entry.clone());
+    write_manifest(data_dir, &manifest).await?;
+
+    info!(
+        file_path = %filepath.display(),
+        size = entry.size,
+        compressed = compress,
+        "Wrote archival snapshot"
+    );
+    Ok(entry)
+}
+
+/// Read the archival snapshot for `date`, verifying it against the manifest checksum
+pub fn read_archive(data_dir: &Path, date: NaiveDate) -> Result<StorageData> {
+    let manifest = load_manifest(data_dir)?;
+    let entry = manifest
+        .find(date)
+        .ok_or_else(|| anyhow!("No archival snapshot found for {}", date))?;
+    let filepath = archive_dir(data_dir).join(&entry.path);
+    let bytes = std::fs::read(&filepath)
+        .with_context(|| format!("Failed to read archive file: {:?}", filepath))?;
+
+    if sha256_hex(&bytes) != entry.sha256 {
+        return Err(anyhow!(
+            "Checksum mismatch for archive {:?}; the file may be corrupted",
+            filepath
+        ));
+    }
+
+    let json = if entry.compressed {
+        let mut decoded = Vec::new();
+        GzDecoder::new(bytes.as_slice()).read_to_end(&mut decoded)?;
+        decoded
+    } else {
+        bytes
+    };
+    debug!(file_path = %filepath.display(), "Read archival snapshot");
+    Ok(serde_json::from_slice(&json)?)
+}
+
+impl StorageManager {
+    /// Write a weekly archival snapshot if the last one is at least a week old
+    pub async fn archive_if_due(&self, compress: bool) -> Result<Option<ArchiveEntry>> {
+        self.archive_if_due_at(Utc::now(), compress).await
+    }
+
+    async fn archive_if_due_at(
+        &self,
+        now: DateTime<Utc>,
+        compress: bool,
+    ) -> Result<Option<ArchiveEntry>> {
+        if !load_manifest(&self.data_dir)?.is_due(now) {
+            return Ok(None);
+        }
+        let data = self.snapshot().await;
+        write_archive(&self.data_dir, &data, now, compress)
+            .await
+            .map(Some)
+    }
+
+    pub fn list_archives(&self) -> Result<Vec<ArchiveEntry>> {
+        Ok(load_manifest(&self.data_dir)?.entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_storage;
+    use crate::task_management::Task;
+    use matrix_sdk::ruma::OwnedRoomId;
+
+    async fn add_task(storage: &StorageManager, room_id: &OwnedRoomId, title: &str) {
+        let mut todo_lists = storage.todo_lists.lock().await;
+        let tasks = todo_lists.entry(room_id.clone()).or_default();
+        let id = tasks.allocate_id();
+        tasks.push(Task::new("@alice:example.org".into(), id, title.into()));
+    }
+
+    fn titles(data: &StorageData, room_id: &OwnedRoomId) -> Vec<String> {
+        data.todo_lists[room_id]
+            .iter()
+            .map(|task| task.title.clone())
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn a_gap_of_weeks_archives_once_and_reads_back() {
+        let storage = test_storage(10);
+        let room_id: OwnedRoomId = "!room:example.org".try_into().unwrap();
+        add_task(&storage, &room_id, "Buy milk").await;
+        let start = "2024-05-01T10:00:00Z".parse::<DateTime<Utc>>().unwrap();
+
+        let first = storage.archive_if_due_at(start, true).await.unwrap();
+        assert!(first.is_some());
+        // Within the week nothing new is written
+        let later = start + Duration::days(ARCHIVE_INTERVAL_DAYS - 1);
+        assert!(
+            storage
+                .archive_if_due_at(later, true)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        // The bot was offline for five weeks: one snapshot, no back-filled ones
+        add_task(&storage, &room_id, "Call Bob").await;
+        let back = start + Duration::weeks(5);
+        assert!(
+            storage
+                .archive_if_due_at(back, false)
+                .await
+                .unwrap()
+                .is_some()
+        );
+        assert!(
+            storage
+                .archive_if_due_at(back, false)
+                .await
+                .unwrap()
+                .is_none()
+        );
+
+        let entries = storage.list_archives().unwrap();
+        let dates: Vec<NaiveDate> = entries.iter().map(|e| e.date).collect();
+        assert_eq!(dates, [start.date_naive(), back.date_naive()]);
+        assert_eq!(entries[0].path, "2024/asmith_archive_2024-05-01.json.gz");
+        assert!(entries[0].compressed);
+        assert_eq!(entries[1].path, "2024/asmith_archive_2024-06-05.json");
+        assert!(!entries[1].compressed);
+        for entry in &entries {
+            let bytes = std::fs::read(archive_dir(&storage.data_dir).join(&entry.path)).unwrap();
+            assert_eq!(entry.size, bytes.len() as u64);
+            assert_eq!(entry.sha256, sha256_hex(&bytes));
+        }
+
+        let data = read_archive(&storage.data_dir, start.date_naive()).unwrap();
+        assert_eq!(titles(&data, &room_id), ["Buy milk"]);
+        let data = read_archive(&storage.data_dir, back.date_naive()).unwrap();
+        assert_eq!(titles(&data, &room_id), ["Buy milk", "Call Bob"]);
+        assert!(read_archive(&storage.data_dir, later.date_naive()).is_err());
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn rewriting_a_date_replaces_its_files_without_leftovers() {
+        let storage = test_storage(10);
+        let room_id: OwnedRoomId = "!room:example.org".try_into().unwrap();
+        let now = Utc::now();
+        add_task(&storage, &room_id, "Buy milk").await;
+        let data = storage.snapshot().await;
+        write_archive(&storage.data_dir, &data, now, false)
+            .await
+            .unwrap();
+        let entry = write_archive(&storage.data_dir, &data, now, true)
+            .await
+            .unwrap();
+
+        let manifest = load_manifest(&storage.data_dir).unwrap();
+        assert_eq!(manifest.entries.len(), 1);
+        assert!(manifest.entries[0].compressed);
+        let year_dir = archive_dir(&storage.data_dir).join(now.format("%Y").to_string());
+        let files: Vec<_> = std::fs::read_dir(&year_dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(files.len(), 1, "{files:?}");
+        assert!(entry.path.ends_with(files[0].to_str().unwrap()));
+        assert!(
+            !archive_dir(&storage.data_dir)
+                .join("manifest.json.tmp")
+                .exists()
+        );
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn a_tampered_archive_is_rejected() {
+        let storage = test_storage(10);
+        let now = Utc::now();
+        let entry = storage
+            .archive_if_due_at(now, false)
+            .await
+            .unwrap()
+            .unwrap();
+        let path = archive_dir(&storage.data_dir).join(&entry.path);
+        let mut bytes = std::fs::read(&path).unwrap();
+        bytes.push(b' ');
+        std::fs::write(&path, bytes).unwrap();
+
+        let err = read_archive(&storage.data_dir, now.date_naive()).unwrap_err();
+        assert!(err.to_string().contains("Checksum mismatch"), "{err}");
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::{Context, Result};
+use std::path::{Path, PathBuf};
+use tracing::{info, warn};
+
+/// Sibling `<file>.tmp` that `write_atomic` writes before renaming it into place
+pub fn tmp_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".tmp");
+    PathBuf::from(name)
+}
+
+/// Replace `path` with `contents` through a temporary file in the same directory,
+/// so a crash mid-write leaves either the old file or the new one, never half of it
+pub async fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    let tmp = tmp_path(path);
+    tokio::fs::write(&tmp, contents)
+        .await
+        .with_context(|| format!("Failed to write temporary file: {:?}", tmp))?;
+    if let Err(e) = tokio::fs::rename(&tmp, path).await {
+        let _ = tokio::fs::remove_file(&tmp).await;
+        return Err(e).with_context(|| format!("Failed to replace file: {:?}", path));
+    }
+    Ok(())
+}
+
+/// Delete the temporary files interrupted writes left in `dir`, returning how
+/// many went. A missing directory has none.
+pub fn remove_leftover_tmp_files(dir: &Path) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    let mut removed = 0;
+    for path in entries.filter_map(|e| e.ok()).map(|e| e.path()) {
+        if !path.is_file() || path.extension().is_none_or(|ext| ext != "tmp") {
+            continue;
+        }
+        match std::fs::remove_file(&path) {
+            Ok(()) => {
+                info!(path = %path.display(), "Removed leftover temporary file");
+                removed += 1;
+            }
+            Err(e) => {
+                warn!(path = %path.display(), error = %e, "Failed to remove leftover temporary file")
+            }
+        }
+    }
+    removed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{LATEST_FILE, StorageManager};
+
+    #[tokio::test]
+    async fn interrupted_save_keeps_the_previous_file_loadable() {
+        let dir = std::env::temp_dir().join(format!("asmith-atomic-{}", uuid::Uuid::new_v4()));
+        let storage = StorageManager::new(
+            dir.clone(),
+            uuid::Uuid::new_v4(),
+            None,
+            false,
+            None,
+            0,
+            Default::default(),
+        )
+        .unwrap();
+        storage.save().await.unwrap();
+        let latest = dir.join(LATEST_FILE);
+
+        // A process killed mid-write leaves only a truncated temporary file behind
+        tokio::fs::write(tmp_path(&latest), br#"{"todo_li"#)
+            .await
+            .unwrap();
+        assert!(storage.load(LATEST_FILE).await.unwrap());
+
+        // The next save overwrites the leftover
+        storage.save().await.unwrap();
+        assert!(!tmp_path(&latest).exists());
+        assert!(storage.load(LATEST_FILE).await.unwrap());
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_writes_leave_the_target_as_it_was() {
+        let dir = std::env::temp_dir().join(format!("asmith-atomic-{}", uuid::Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+        let target = dir.join("tasks.json");
+        tokio::fs::write(&target, "old").await.unwrap();
+
+        // Writing the temporary file fails: a directory is in its way
+        tokio::fs::create_dir(tmp_path(&target)).await.unwrap();
+        assert!(write_atomic(&target, b"new").await.is_err());
+        assert_eq!(tokio::fs::read_to_string(&target).await.unwrap(), "old");
+        tokio::fs::remove_dir(tmp_path(&target)).await.unwrap();
+
+        // Renaming fails: the target is a directory with content. The
+        // temporary file doesn't stay behind.
+        let blocked = dir.join("blocked.json");
+        tokio::fs::create_dir_all(blocked.join("inner"))
+            .await
+            .unwrap();
+        assert!(write_atomic(&blocked, b"new").await.is_err());
+        assert!(!tmp_path(&blocked).exists());
+
+        // Leftovers from a crash are cleaned up at startup, other files stay
+        tokio::fs::write(dir.join("latest.json.tmp"), "{")
+            .await
+            .unwrap();
+        assert_eq!(remove_leftover_tmp_files(&dir), 1);
+        assert!(target.exists());
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
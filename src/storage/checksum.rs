@@ -0,0 +1,62 @@
+use anyhow::{Context, Result, anyhow};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use tracing::debug;
+
+// Save files get a sidecar `<file>.sha256` holding the hex digest of their contents
+const CHECKSUM_EXTENSION: &str = "sha256";
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+pub fn checksum_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(CHECKSUM_EXTENSION);
+    PathBuf::from(name)
+}
+
+pub async fn write_checksum(path: &Path, contents: &[u8]) -> Result<()> {
+    let sidecar = checksum_path(path);
+    super::atomic::write_atomic(&sidecar, sha256_hex(contents).as_bytes())
+        .await
+        .with_context(|| format!("Failed to write checksum file: {:?}", sidecar))
+}
+
+/// Replace `path` with `contents` and its checksum. The stale sidecar is removed
+/// first and the new one written last, so a crash at any point leaves a file
+/// without a sidecar (accepted unverified) rather than a checksum mismatch.
+pub async fn write_with_checksum(path: &Path, contents: &[u8]) -> Result<()> {
+    let sidecar = checksum_path(path);
+    match tokio::fs::remove_file(&sidecar).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => {
+            return Err(e)
+                .with_context(|| format!("Failed to remove old checksum file: {:?}", sidecar));
+        }
+    }
+    super::atomic::write_atomic(path, contents).await?;
+    write_checksum(path, contents).await
+}
+
+/// Check `contents` of the save file at `path` against its checksum. Files written
+/// before checksums existed have no sidecar and are accepted as they are.
+pub async fn verify_checksum(path: &Path, contents: &[u8]) -> Result<()> {
+    let sidecar = checksum_path(path);
+    if !sidecar.exists() {
+        debug!(file_path = %path.display(), "No checksum file, skipping verification");
+        return Ok(());
+    }
+    let expected = tokio::fs::read_to_string(&sidecar)
+        .await
+        .with_context(|| format!("Failed to read checksum file: {:?}", sidecar))?;
+    if sha256_hex(contents) != expected.trim() {
+        return Err(anyhow!(
+            "Checksum mismatch for {:?}; the file may be corrupted or modified",
+            path
+        ));
+    }
+    Ok(())
+}
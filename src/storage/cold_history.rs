@@ -0,0 +1,274 @@
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
+use matrix_sdk::ruma::OwnedRoomId;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tracing::{debug, info};
+
+use super::StorageManager;
+use crate::task_management::{RoomTaskList, Task};
+
+pub const HISTORY_DIR: &str = "history";
+
+// (timestamp, user, log), same shape as `Task::internal_logs`
+pub type HistoryEntry = (String, String, String);
+
+/// Offloaded history for one room, keyed by task id
+pub type RoomHistory = HashMap<usize, Vec<HistoryEntry>>;
+
+/// Turn a room id into something safe to use as a file name
+pub fn sanitize_room_id(room_id: &OwnedRoomId) -> String {
+    room_id
+        .as_str()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect()
+}
+
+/// Move the history of renumbered tasks to their new IDs. `moves` holds
+/// (old ID, new ID) pairs; an old ID shared by several tasks goes to the first.
+/// Returns whether anything moved.
+pub fn rekey(cold: &mut RoomHistory, moves: &[(usize, usize)]) -> bool {
+    // Take everything out first, so a new ID can be an old ID of another task
+    let mut taken: HashMap<usize, Vec<HistoryEntry>> = moves
+        .iter()
+        .filter_map(|&(old, _)| Some((old, cold.remove(&old)?)))
+        .collect();
+    let mut moved = false;
+    for &(old, new) in moves {
+        if let Some(entries) = taken.remove(&old) {
+            moved |= old != new;
+            cold.insert(new, entries);
+        }
+    }
+    moved
+}
+
+fn is_older_than(entry: &HistoryEntry, cutoff: NaiveDateTime) -> bool {
+    NaiveDateTime::parse_from_str(&entry.0, "%Y-%m-%d %H:%M:%S")
+        .map(|ts| ts < cutoff)
+        .unwrap_or(false)
+}
+
+impl StorageManager {
+    fn cold_history_path(&self, room_id: &OwnedRoomId) -> PathBuf {
+        self.data_dir
+            .join(HISTORY_DIR)
+            .join(format!("{}.json", sanitize_room_id(room_id)))
+    }
+
+    pub async fn read_cold_history(&self, room_id: &OwnedRoomId) -> Result<RoomHistory> {
+        let path = self.cold_history_path(room_id);
+        if !path.exists() {
+            return Ok(RoomHistory::new());
+        }
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read history file: {:?}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse history file: {:?}", path))
+    }
+
+    async fn write_cold_history(&self, room_id: &OwnedRoomId, cold: &RoomHistory) -> Result<()> {
+        let path = self.cold_history_path(room_id);
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(&path, serde_json::to_string_pretty(cold)?)
+            .await
+            .with_context(|| format!("Failed to write history file: {:?}", path))
+    }
+
+    /// Keep a room's offloaded history with its tasks after they were renumbered,
+    /// see `rekey`
+    pub async fn rekey_cold_history(
+        &self,
+        room_id: &OwnedRoomId,
+        moves: &[(usize, usize)],
+    ) -> Result<()> {
+        if moves.is_empty() || !self.cold_history_path(room_id).exists() {
+            return Ok(());
+        }
+        let mut cold = self.read_cold_history(room_id).await?;
+        if rekey(&mut cold, moves) {
+            self.write_cold_history(room_id, &cold).await?;
+            info!(room_id = %room_id, tasks = moves.len(), "Moved offloaded history to renumbered tasks");
+        }
+        Ok(())
+    }
+
+    /// Timestamps of loaded tasks that were filled in from their history while part
+    /// of it was offloaded, corrected with the offloaded entries
+    pub(super) async fn backfill_from_cold_history(&self) -> Result<()> {
+        let mut todo_lists = self.todo_lists.lock().await;
+        for (room_id, tasks) in todo_lists.iter_mut() {
+            if !self.cold_history_path(room_id).exists() {
+                continue;
+            }
+            let cold = self.read_cold_history(room_id).await?;
+            for task in tasks.iter_mut() {
+                if let Some(entries) = cold.get(&task.id) {
+                    task.backfill_timestamps(entries);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Full history of a task: offloaded entries first, then the ones still kept in memory
+    pub async fn task_history(
+        &self,
+        room_id: &OwnedRoomId,
+        task: &Task,
+    ) -> Result<Vec<HistoryEntry>> {
+        let mut history = self
+            .read_cold_history(room_id)
+            .await?
+            .remove(&task.id)
+            .unwrap_or_default();
+        history.extend(task.internal_logs.iter().cloned());
+        Ok(history)
+    }
+
+    /// Move internal log entries older than the configured threshold out of the
+    /// in-memory tasks and append them to each room's cold history file.
+    /// Does nothing unless history offloading was enabled.
+    pub(super) async fn offload_history(
+        &self,
+        todo_lists: &mut HashMap<OwnedRoomId, RoomTaskList>,
+    ) -> Result<()> {
+        let Some(days) = self.history_offload_days else {
+            return Ok(());
+        };
+        let cutoff = (Utc::now() - Duration::days(days as i64)).naive_utc();
+
+        for (room_id, tasks) in todo_lists.iter_mut() {
+            let mut offloaded: RoomHistory = HashMap::new();
+            for task in tasks.iter_mut() {
+                // Always keep the most recent entry in the task so details stay meaningful
+                let keep_from = task
+                    .internal_logs
+                    .iter()
+                    .position(|entry| !is_older_than(entry, cutoff))
+                    .unwrap_or(task.internal_logs.len().saturating_sub(1));
+                if keep_from > 0 {
+                    let old: Vec<HistoryEntry> = task.internal_logs.drain(..keep_from).collect();
+                    offloaded.entry(task.id).or_default().extend(old);
+                }
+            }
+
+            if offloaded.is_empty() {
+                continue;
+            }
+
+            let mut cold = self.read_cold_history(room_id).await?;
+            let moved: usize = offloaded.values().map(Vec::len).sum();
+            for (task_id, entries) in offloaded {
+                cold.entry(task_id).or_default().extend(entries);
+            }
+
+            self.write_cold_history(room_id, &cold).await?;
+            info!(room_id = %room_id, entries = moved, "Offloaded old task history to cold store");
+        }
+        debug!("History offload pass complete");
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_storage;
+    use crate::task_management::TaskStatus;
+
+    fn entry(timestamp: &str, action: &str) -> HistoryEntry {
+        (
+            timestamp.to_owned(),
+            "@alice:example.org".to_owned(),
+            action.to_owned(),
+        )
+    }
+
+    fn room() -> OwnedRoomId {
+        OwnedRoomId::try_from("!room:example.org").unwrap()
+    }
+
+    #[test]
+    fn rekey_follows_renumbered_tasks() {
+        let a = vec![entry("2024-01-01 10:00:00", "a")];
+        let b = vec![entry("2024-01-02 10:00:00", "b")];
+        let c = vec![entry("2024-01-03 10:00:00", "c")];
+        let mut cold = RoomHistory::from([(0, a.clone()), (3, b.clone()), (4, c.clone())]);
+
+        // 3 and 4 swap places, an old ID shared by two tasks goes to the first
+        assert!(rekey(&mut cold, &[(0, 5), (3, 4), (4, 3), (7, 8), (7, 9)]));
+        assert_eq!(cold, RoomHistory::from([(5, a), (4, b), (3, c)]));
+        assert!(!rekey(&mut cold, &[(5, 5)]));
+    }
+
+    #[tokio::test]
+    async fn reindex_moves_offloaded_history_with_the_task() {
+        let storage = test_storage(0);
+        let tasks =
+            [1, 1, 0].map(|id| Task::new("@alice:example.org".into(), id, format!("Task {}", id)));
+        storage
+            .todo_lists
+            .lock()
+            .await
+            .insert(room(), RoomTaskList::new(tasks.to_vec(), 2));
+        let kept = vec![entry("2024-01-01 10:00:00", "first task with ID 1")];
+        let zero = vec![entry("2024-01-02 10:00:00", "task with ID 0")];
+        storage
+            .write_cold_history(
+                &room(),
+                &RoomHistory::from([(1, kept.clone()), (0, zero.clone())]),
+            )
+            .await
+            .unwrap();
+
+        let report = storage.reindex(&room()).await;
+        assert_eq!(report.renumbered, vec![(1, 2), (0, 3)]);
+        assert_eq!(
+            storage.read_cold_history(&room()).await.unwrap(),
+            RoomHistory::from([(1, kept), (3, zero)])
+        );
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn loading_backfills_timestamps_from_offloaded_history() {
+        let storage = test_storage(0);
+        // As loaded from a file without the timestamp fields whose early history
+        // was offloaded: creation fell back to the load time, completion is unknown
+        let mut task = Task::new("@alice:example.org".into(), 1, "Task".into());
+        task.status = TaskStatus::Done;
+        task.internal_logs = vec![entry("2024-01-03 10:00:00", "Updated title")];
+        storage
+            .todo_lists
+            .lock()
+            .await
+            .insert(room(), RoomTaskList::new(vec![task], 2));
+        let offloaded = vec![
+            entry("2024-01-01 10:00:00", "Created task"),
+            entry(
+                "2024-01-02 10:00:00",
+                "Updated status: from 'pending' to 'done'",
+            ),
+        ];
+        storage
+            .write_cold_history(&room(), &RoomHistory::from([(1, offloaded)]))
+            .await
+            .unwrap();
+
+        storage.backfill_from_cold_history().await.unwrap();
+        let todo_lists = storage.todo_lists.lock().await;
+        let task = todo_lists[&room()].find(1).unwrap();
+        assert_eq!(task.created_at.to_string(), "2024-01-01 10:00:00 UTC");
+        assert_eq!(
+            task.completed_at.unwrap().to_string(),
+            "2024-01-02 10:00:00 UTC"
+        );
+        drop(todo_lists);
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+}
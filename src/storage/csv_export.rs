@@ -0,0 +1,120 @@
+//! CSV export of a room's tasks for spreadsheets.
+
+use anyhow::{Context, Result};
+use matrix_sdk::ruma::OwnedRoomId;
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufWriter};
+use tracing::info;
+
+use super::StorageManager;
+use crate::task_management::Task;
+
+pub const CSV_HEADER: &str = "id,title,status,creator,created,last_modified,log_count";
+
+impl StorageManager {
+    /// Write the room's tasks as CSV next to the save files, row by row.
+    /// Returns the file name and the number of tasks written.
+    pub async fn export_csv(&self, room_id: &OwnedRoomId) -> Result<(String, usize)> {
+        let tasks = self
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .map(|list| list.tasks.clone())
+            .unwrap_or_default();
+        let filename = Self::export_filename(room_id, "csv");
+        let filepath = self.data_dir.join(&filename);
+        let file = tokio::fs::File::create(&filepath)
+            .await
+            .with_context(|| format!("Failed to create export file: {:?}", filepath))?;
+        let mut writer = BufWriter::new(file);
+        write_csv(&mut writer, &tasks)
+            .await
+            .with_context(|| format!("Failed to write export file: {:?}", filepath))?;
+        writer.shutdown().await?;
+        info!(room_id = %room_id, file_path = %filepath.display(), rows = tasks.len(), "Wrote CSV export");
+        Ok((filename, tasks.len()))
+    }
+}
+
+/// Header and one RFC 4180 row per task, in list order. Last modified comes from
+/// the last entry of the task's history.
+pub async fn write_csv<W: AsyncWrite + Unpin>(writer: &mut W, tasks: &[Task]) -> Result<()> {
+    writer.write_all(CSV_HEADER.as_bytes()).await?;
+    writer.write_all(b"\r\n").await?;
+    for task in tasks {
+        let row = [
+            task.id.to_string(),
+            task.title.clone(),
+            task.status.to_string(),
+            task.creator.clone(),
+            task.created_at.format("%Y-%m-%d %H:%M:%S").to_string(),
+            task.internal_logs
+                .last()
+                .map(|(timestamp, _, _)| timestamp.clone())
+                .unwrap_or_default(),
+            task.logs.len().to_string(),
+        ];
+        let fields: Vec<String> = row.iter().map(|f| csv_field(f)).collect();
+        writer.write_all(fields.join(",").as_bytes()).await?;
+        writer.write_all(b"\r\n").await?;
+    }
+    writer.flush().await?;
+    Ok(())
+}
+
+/// Quote a CSV field when it holds a comma, quote or line break, doubling inner quotes
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn csv_of(tasks: &[Task]) -> String {
+        let mut out = Vec::new();
+        write_csv(&mut out, tasks).await.unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn fields_are_quoted_only_when_needed() {
+        assert_eq!(csv_field("plain title"), "plain title");
+        assert_eq!(csv_field("a, b"), "\"a, b\"");
+        assert_eq!(csv_field("say \"hi\""), "\"say \"\"hi\"\"\"");
+        assert_eq!(csv_field("two\nlines"), "\"two\nlines\"");
+        assert_eq!(csv_field("cr\r"), "\"cr\r\"");
+    }
+
+    #[tokio::test]
+    async fn nasty_titles_stay_in_one_field() {
+        let mut task = Task::new(
+            "@alice:example.org".to_owned(),
+            7,
+            "Fix \"quoted\", comma\nand newline".to_owned(),
+        );
+        task.add_log("@alice:example.org".to_owned(), "a note".to_owned());
+        let csv = csv_of(&[task.clone()]).await;
+
+        let (header, row) = csv.split_once("\r\n").unwrap();
+        assert_eq!(header, CSV_HEADER);
+        let created = task.created_at.format("%Y-%m-%d %H:%M:%S");
+        let modified = &task.internal_logs.last().unwrap().0;
+        assert_eq!(
+            row,
+            format!(
+                "7,\"Fix \"\"quoted\"\", comma\nand newline\",pending,@alice:example.org,{},{},1\r\n",
+                created, modified
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn empty_room_has_only_the_header() {
+        assert_eq!(csv_of(&[]).await, format!("{}\r\n", CSV_HEADER));
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedRoomId;
+use std::sync::atomic::Ordering;
+use tracing::{debug, info};
+
+use super::StorageManager;
+
+impl StorageManager {
+    /// Record that a room changed so the next flush writes it
+    pub async fn mark_dirty(&self, room_id: &OwnedRoomId) {
+        self.dirty_rooms.lock().await.insert(room_id.clone());
+        // Caches keyed on the generation must see the change before it reaches disk
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    /// Write the rooms changed since the last flush, if any. Rooms stay dirty when
+    /// the write fails so the next flush retries them. Returns whether anything was written.
+    pub async fn flush_if_dirty(&self) -> Result<bool> {
+        let rooms: Vec<OwnedRoomId> = self.dirty_rooms.lock().await.drain().collect();
+        if rooms.is_empty() {
+            return Ok(false);
+        }
+        debug!(rooms = rooms.len(), "Flushing dirty rooms");
+
+        let result = if self.per_room {
+            let mut result = Ok(());
+            for room_id in &rooms {
+                result = self.write_room(room_id).await;
+                if result.is_err() {
+                    break;
+                }
+            }
+            result
+        } else {
+            self.save().await.map(|_| ())
+        };
+
+        if let Err(e) = result {
+            self.dirty_rooms.lock().await.extend(rooms);
+            return Err(e);
+        }
+        Ok(true)
+    }
+
+    /// Write everything immediately, regardless of the debounce interval.
+    /// Used by `!bot save`; returns the name of what was written.
+    pub async fn flush(&self) -> Result<String> {
+        let pending: Vec<OwnedRoomId> = self.dirty_rooms.lock().await.drain().collect();
+        match self.save().await {
+            Ok(filename) => {
+                if !pending.is_empty() {
+                    info!(rooms = pending.len(), "Flushed pending changes");
+                }
+                Ok(filename)
+            }
+            Err(e) => {
+                self.dirty_rooms.lock().await.extend(pending);
+                Err(e)
+            }
+        }
+    }
+}
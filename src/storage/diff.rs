@@ -0,0 +1,371 @@
+//! Comparing two sets of stored task data, e.g. a save file against the current state.
+
+use anyhow::{Context, Result, anyhow};
+use matrix_sdk::ruma::OwnedRoomId;
+use std::collections::{BTreeSet, HashSet};
+use std::path::Path;
+
+use super::StorageData;
+use super::checksum::{checksum_path, sha256_hex};
+use crate::task_management::{Task, TaskStatus};
+
+/// A task as it appears on one side of the diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct TaskRef {
+    pub id: usize,
+    pub title: String,
+    pub status: TaskStatus,
+}
+
+impl From<&Task> for TaskRef {
+    fn from(task: &Task) -> Self {
+        Self {
+            id: task.id,
+            title: task.title.clone(),
+            status: task.status,
+        }
+    }
+}
+
+/// The same task on both sides with a different ID, title or status
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangedTask {
+    pub before: TaskRef,
+    pub after: TaskRef,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct RoomDiff {
+    pub room_id: OwnedRoomId,
+    pub tasks_before: usize,
+    pub tasks_after: usize,
+    pub added: Vec<TaskRef>,
+    pub removed: Vec<TaskRef>,
+    pub changed: Vec<ChangedTask>,
+}
+
+impl RoomDiff {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+
+    /// Summary line followed by one line per differing task
+    pub fn render(&self) -> String {
+        let mut lines = vec![format!(
+            "🏠 {}: {} → {} tasks (+{} −{} ~{})",
+            self.room_id,
+            self.tasks_before,
+            self.tasks_after,
+            self.added.len(),
+            self.removed.len(),
+            self.changed.len()
+        )];
+        for task in &self.added {
+            lines.push(format!("  + #{} {} [{}]", task.id, task.title, task.status));
+        }
+        for task in &self.removed {
+            lines.push(format!("  − #{} {} [{}]", task.id, task.title, task.status));
+        }
+        for ChangedTask { before, after } in &self.changed {
+            let mut parts = Vec::new();
+            if before.id != after.id {
+                parts.push(format!("#{} → #{}", before.id, after.id));
+            }
+            if before.title != after.title {
+                parts.push(format!("\"{}\" → \"{}\"", before.title, after.title));
+            }
+            if before.status != after.status {
+                parts.push(format!("{} → {}", before.status, after.status));
+            }
+            lines.push(format!(
+                "  ~ #{} {}: {}",
+                after.id,
+                after.title,
+                parts.join(", ")
+            ));
+        }
+        lines.join("\n")
+    }
+}
+
+fn title_key(title: &str) -> String {
+    title.trim().to_lowercase()
+}
+
+/// Pair tasks by ID, then pair what is left by title (first match wins)
+pub fn diff_room(room_id: &OwnedRoomId, before: &[Task], after: &[Task]) -> RoomDiff {
+    let mut diff = RoomDiff {
+        room_id: room_id.clone(),
+        tasks_before: before.len(),
+        tasks_after: after.len(),
+        added: Vec::new(),
+        removed: Vec::new(),
+        changed: Vec::new(),
+    };
+
+    let mut pairs = Vec::new();
+    let mut matched_after = HashSet::new();
+    let mut unmatched_before = Vec::new();
+    for old in before {
+        match after
+            .iter()
+            .enumerate()
+            .find(|(i, new)| new.id == old.id && !matched_after.contains(i))
+        {
+            Some((i, new)) => {
+                matched_after.insert(i);
+                pairs.push((old, new));
+            }
+            None => unmatched_before.push(old),
+        }
+    }
+    for old in unmatched_before {
+        let key = title_key(&old.title);
+        match after
+            .iter()
+            .enumerate()
+            .find(|(i, new)| !matched_after.contains(i) && title_key(&new.title) == key)
+        {
+            Some((i, new)) => {
+                matched_after.insert(i);
+                pairs.push((old, new));
+            }
+            None => diff.removed.push(old.into()),
+        }
+    }
+    diff.added = after
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !matched_after.contains(i))
+        .map(|(_, new)| new.into())
+        .collect();
+
+    for (old, new) in pairs {
+        if old.id != new.id || old.title != new.title || old.status != new.status {
+            diff.changed.push(ChangedTask {
+                before: old.into(),
+                after: new.into(),
+            });
+        }
+    }
+    diff.added.sort_by_key(|t| t.id);
+    diff.removed.sort_by_key(|t| t.id);
+    diff.changed.sort_by_key(|c| c.after.id);
+    diff
+}
+
+/// Per-room differences from `before` to `after`, for rooms that differ, ordered by room ID.
+/// A room present on one side only shows up with all its tasks added or removed.
+pub fn diff_storage(before: &StorageData, after: &StorageData) -> Vec<RoomDiff> {
+    let rooms: BTreeSet<&OwnedRoomId> = before
+        .todo_lists
+        .keys()
+        .chain(after.todo_lists.keys())
+        .collect();
+    rooms
+        .into_iter()
+        .map(|room_id| {
+            let tasks = |data: &StorageData| {
+                data.todo_lists
+                    .get(room_id)
+                    .map(|list| list.tasks.clone())
+                    .unwrap_or_default()
+            };
+            diff_room(room_id, &tasks(before), &tasks(after))
+        })
+        .filter(|diff| !diff.is_empty())
+        .collect()
+}
+
+/// Read a save file from any path, verifying its checksum sidecar if there is one
+pub fn read_storage_file(path: &Path) -> Result<StorageData> {
+    let content =
+        std::fs::read(path).with_context(|| format!("Failed to read save file: {:?}", path))?;
+    let sidecar = checksum_path(path);
+    if sidecar.exists() {
+        let expected = std::fs::read_to_string(&sidecar)
+            .with_context(|| format!("Failed to read checksum file: {:?}", sidecar))?;
+        if sha256_hex(&content) != expected.trim() {
+            return Err(anyhow!(
+                "Checksum mismatch for {:?}; the file may be corrupted or modified",
+                path
+            ));
+        }
+    }
+    let content = String::from_utf8(content)
+        .with_context(|| format!("Save file is not valid UTF-8: {:?}", path))?;
+    let mut data = super::format::parse_storage(&content)
+        .with_context(|| format!("Failed to parse save file: {:?}", path))?;
+    data.migrate_legacy_lists();
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_management::RoomTaskList;
+
+    fn room(id: &str) -> OwnedRoomId {
+        OwnedRoomId::try_from(id).unwrap()
+    }
+
+    fn task(id: usize, title: &str, status: TaskStatus) -> Task {
+        let mut task = Task::new("@alice:example.org".to_owned(), id, title.to_owned());
+        task.status = status;
+        task
+    }
+
+    fn data(rooms: &[(&str, Vec<Task>)]) -> StorageData {
+        let mut data = StorageData::default();
+        for (room_id, tasks) in rooms {
+            let next_id = tasks.iter().map(|t| t.id).max().unwrap_or(0) + 1;
+            data.todo_lists.insert(
+                room(room_id),
+                RoomTaskList {
+                    next_id,
+                    tasks: tasks.clone(),
+                },
+            );
+        }
+        data
+    }
+
+    fn single_room(before: Vec<Task>, after: Vec<Task>) -> RoomDiff {
+        diff_room(&room("!a:example.org"), &before, &after)
+    }
+
+    #[test]
+    fn identical_data_has_no_diff() {
+        let tasks = vec![task(1, "One", TaskStatus::Pending)];
+        let a = data(&[("!a:example.org", tasks.clone())]);
+        let b = data(&[("!a:example.org", tasks)]);
+        assert!(diff_storage(&a, &b).is_empty());
+        assert!(diff_storage(&StorageData::default(), &StorageData::default()).is_empty());
+    }
+
+    #[test]
+    fn added_and_removed_tasks() {
+        let diff = single_room(
+            vec![
+                task(1, "Keep", TaskStatus::Pending),
+                task(2, "Gone", TaskStatus::Done),
+            ],
+            vec![
+                task(1, "Keep", TaskStatus::Pending),
+                task(3, "New", TaskStatus::Pending),
+            ],
+        );
+        assert_eq!((diff.tasks_before, diff.tasks_after), (2, 2));
+        assert_eq!(
+            diff.removed,
+            vec![TaskRef::from(&task(2, "Gone", TaskStatus::Done))]
+        );
+        assert_eq!(
+            diff.added,
+            vec![TaskRef::from(&task(3, "New", TaskStatus::Pending))]
+        );
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn status_and_title_changes_keyed_on_id() {
+        let diff = single_room(
+            vec![
+                task(1, "Write docs", TaskStatus::Pending),
+                task(2, "Old title", TaskStatus::Pending),
+            ],
+            vec![
+                task(1, "Write docs", TaskStatus::Done),
+                task(2, "New title", TaskStatus::Pending),
+            ],
+        );
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 2);
+        assert_eq!(diff.changed[0].before.status, TaskStatus::Pending);
+        assert_eq!(diff.changed[0].after.status, TaskStatus::Done);
+        assert_eq!(diff.changed[1].before.title, "Old title");
+        assert_eq!(diff.changed[1].after.title, "New title");
+        let rendered = diff.render();
+        assert!(rendered.contains("~ #1 Write docs: pending → done"));
+        assert!(rendered.contains("~ #2 New title: \"Old title\" → \"New title\""));
+    }
+
+    #[test]
+    fn renumbered_tasks_fall_back_to_title() {
+        let diff = single_room(
+            vec![task(4, "Ship release", TaskStatus::InProgress)],
+            vec![task(9, "ship release ", TaskStatus::Done)],
+        );
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.changed.len(), 1);
+        assert!(diff.render().contains("#4 → #9"));
+    }
+
+    #[test]
+    fn unrelated_tasks_are_not_paired() {
+        let diff = single_room(
+            vec![task(1, "First", TaskStatus::Pending)],
+            vec![task(2, "Second", TaskStatus::Pending)],
+        );
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.added.len(), 1);
+        assert!(diff.changed.is_empty());
+    }
+
+    #[test]
+    fn duplicate_titles_pair_once() {
+        let diff = single_room(
+            vec![
+                task(1, "Dup", TaskStatus::Pending),
+                task(2, "Dup", TaskStatus::Pending),
+            ],
+            vec![task(5, "Dup", TaskStatus::Pending)],
+        );
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(diff.changed[0].before.id, 1);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].id, 2);
+        assert!(diff.added.is_empty());
+    }
+
+    #[test]
+    fn rooms_on_one_side_only() {
+        let before = data(&[
+            (
+                "!gone:example.org",
+                vec![task(1, "Old", TaskStatus::Pending)],
+            ),
+            (
+                "!same:example.org",
+                vec![task(1, "Same", TaskStatus::Pending)],
+            ),
+        ]);
+        let after = data(&[
+            (
+                "!new:example.org",
+                vec![
+                    task(1, "A", TaskStatus::Pending),
+                    task(2, "B", TaskStatus::Done),
+                ],
+            ),
+            (
+                "!same:example.org",
+                vec![task(1, "Same", TaskStatus::Pending)],
+            ),
+        ]);
+        let diffs = diff_storage(&before, &after);
+        assert_eq!(diffs.len(), 2);
+        assert_eq!(diffs[0].room_id, room("!gone:example.org"));
+        assert_eq!((diffs[0].tasks_before, diffs[0].tasks_after), (1, 0));
+        assert_eq!(diffs[0].removed.len(), 1);
+        assert_eq!(diffs[1].room_id, room("!new:example.org"));
+        assert_eq!((diffs[1].tasks_before, diffs[1].tasks_after), (0, 2));
+        assert_eq!(diffs[1].added.len(), 2);
+    }
+
+    #[test]
+    fn empty_room_list_is_not_a_difference() {
+        let before = data(&[("!empty:example.org", vec![])]);
+        assert!(diff_storage(&before, &StorageData::default()).is_empty());
+    }
+}
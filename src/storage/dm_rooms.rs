@@ -0,0 +1,45 @@
+use matrix_sdk::ruma::OwnedRoomId;
+use std::collections::HashMap;
+use tracing::info;
+
+use super::StorageManager;
+use super::cold_history::sanitize_room_id;
+use super::per_room::ROOMS_DIR;
+
+impl StorageManager {
+    /// The user whose private list lives in `room_id`, if it is a DM room
+    pub async fn dm_owner(&self, room_id: &OwnedRoomId) -> Option<String> {
+        self.dm_rooms.lock().await.get(room_id).cloned()
+    }
+
+    /// The DM room holding `user`'s private list
+    pub async fn dm_room_of(&self, user: &str) -> Option<OwnedRoomId> {
+        self.dm_rooms
+            .lock()
+            .await
+            .iter()
+            .find(|(_, owner)| owner.as_str() == user)
+            .map(|(room_id, _)| room_id.clone())
+    }
+
+    pub async fn register_dm_room(&self, room_id: &OwnedRoomId, owner: &str) {
+        self.dm_rooms
+            .lock()
+            .await
+            .insert(room_id.clone(), owner.to_owned());
+        info!(room_id = %room_id, owner, "Registered private DM list");
+    }
+
+    /// Owners of DM rooms keyed by their per-room file name, as listed by `list_saved_files`
+    pub async fn dm_room_files(&self) -> HashMap<String, String> {
+        self.dm_rooms
+            .lock()
+            .await
+            .iter()
+            .map(|(room_id, owner)| {
+                let file = format!("{}/{}.json", ROOMS_DIR, sanitize_room_id(room_id));
+                (file, owner.clone())
+            })
+            .collect()
+    }
+}
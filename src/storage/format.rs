@@ -0,0 +1,245 @@
+//! On-disk encodings of `StorageData` for the timestamped save files.
+
+use anyhow::{Context, Result};
+use matrix_sdk::ruma::OwnedRoomId;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+
+use super::StorageData;
+use super::personal::PersonalData;
+use super::settings::RoomSettings;
+use crate::task_management::{RoomTaskList, Task};
+
+/// How `StorageManager::save` writes the save file. Loading accepts any of them.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+#[allow(clippy::enum_variant_names)]
+pub enum StorageFormat {
+    /// Indented JSON, the historical format
+    #[default]
+    JsonPretty,
+    /// JSON on a single line
+    JsonCompact,
+    /// One JSON object per room and line, for tools that stream the file
+    JsonLines,
+}
+
+/// One line of a JSON Lines save file. Parts a room doesn't have are left out,
+/// so the file reads back into exactly the same `StorageData`.
+#[derive(Debug, Serialize, Deserialize)]
+struct RoomLine {
+    room_id: OwnedRoomId,
+    /// Only set for rooms with a task list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    next_id: Option<usize>,
+    #[serde(default)]
+    tasks: Vec<Task>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    settings: Option<RoomSettings>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    admins: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    active: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    archived: Option<Vec<Task>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    aliases: Option<HashMap<String, String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    dm_owner: Option<String>,
+}
+
+pub fn serialize_storage(data: &StorageData, format: StorageFormat) -> Result<String> {
+    Ok(match format {
+        StorageFormat::JsonPretty => serde_json::to_string_pretty(data)?,
+        StorageFormat::JsonCompact => serde_json::to_string(data)?,
+        StorageFormat::JsonLines => {
+            let mut out = String::new();
+            for line in room_lines(data) {
+                out.push_str(&serde_json::to_string(&line)?);
+                out.push('\n');
+            }
+            // Personal lists follow the rooms, by user so the output is stable
+            let users: BTreeSet<&String> = data.personal_lists.keys().collect();
+            for user in users {
+                let list = &data.personal_lists[user];
+                let line = PersonalData {
+                    user_id: user.clone(),
+                    next_id: list.next_id,
+                    tasks: list.tasks.clone(),
+                };
+                out.push_str(&serde_json::to_string(&line)?);
+                out.push('\n');
+            }
+            out
+        }
+    })
+}
+
+/// Parse a save file in any `StorageFormat`: as a JSON document first, then as
+/// JSON Lines. Legacy lists are not migrated here.
+pub fn parse_storage(content: &str) -> Result<StorageData> {
+    let json_error = match serde_json::from_str(content) {
+        Ok(data) => return Ok(data),
+        Err(e) => e,
+    };
+    parse_json_lines(content)
+        .with_context(|| format!("Not a JSON save file ({}) nor a JSON Lines one", json_error))
+}
+
+// Rooms in ID order so the same data always gives the same file
+fn room_lines(data: &StorageData) -> Vec<RoomLine> {
+    let rooms: BTreeSet<&OwnedRoomId> = data
+        .todo_lists
+        .keys()
+        .chain(data.room_settings.keys())
+        .chain(data.admins.keys())
+        .chain(data.active_rooms.iter())
+        .chain(data.archived.keys())
+        .chain(data.aliases.keys())
+        .chain(data.dm_rooms.keys())
+        .collect();
+    rooms
+        .into_iter()
+        .map(|room_id| {
+            let list = data.todo_lists.get(room_id);
+            RoomLine {
+                room_id: room_id.clone(),
+                next_id: list.map(|l| l.next_id),
+                tasks: list.map(|l| l.tasks.clone()).unwrap_or_default(),
+                settings: data.room_settings.get(room_id).cloned(),
+                admins: data.admins.get(room_id).cloned(),
+                active: data.active_rooms.contains(room_id),
+                archived: data.archived.get(room_id).cloned(),
+                aliases: data.aliases.get(room_id).cloned(),
+                dm_owner: data.dm_rooms.get(room_id).cloned(),
+            }
+        })
+        .collect()
+}
+
+/// A JSON Lines line holds either a room or a user's personal list
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum Line {
+    Room(Box<RoomLine>),
+    Personal(PersonalData),
+}
+
+fn parse_json_lines(content: &str) -> Result<StorageData> {
+    let mut data = StorageData::default();
+    for (idx, line) in content.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let room = match serde_json::from_str(line)
+            .with_context(|| format!("Invalid line {}", idx + 1))?
+        {
+            Line::Room(room) => room,
+            Line::Personal(personal) => {
+                let (user, list) = personal.into_list();
+                data.personal_lists.insert(user, list);
+                continue;
+            }
+        };
+        let room_id = room.room_id;
+        if let Some(next_id) = room.next_id {
+            data.todo_lists
+                .insert(room_id.clone(), RoomTaskList::new(room.tasks, next_id));
+        }
+        if let Some(settings) = room.settings {
+            data.room_settings.insert(room_id.clone(), settings);
+        }
+        if let Some(admins) = room.admins {
+            data.admins.insert(room_id.clone(), admins);
+        }
+        if room.active {
+            data.active_rooms.insert(room_id.clone());
+        }
+        if let Some(archived) = room.archived {
+            data.archived.insert(room_id.clone(), archived);
+        }
+        if let Some(aliases) = room.aliases {
+            data.aliases.insert(room_id.clone(), aliases);
+        }
+        if let Some(owner) = room.dm_owner {
+            data.dm_rooms.insert(room_id, owner);
+        }
+    }
+    Ok(data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn room(id: &str) -> OwnedRoomId {
+        OwnedRoomId::try_from(id).unwrap()
+    }
+
+    fn sample() -> StorageData {
+        let mut data = StorageData::default();
+        let mut task = Task::new("@alice:example.org".to_owned(), 3, "Ship it".to_owned());
+        task.description = Some("First line\nsecond line".to_owned());
+        data.todo_lists.insert(
+            room("!a:example.org"),
+            RoomTaskList::new(vec![task.clone()], 4),
+        );
+        data.todo_lists
+            .insert(room("!empty:example.org"), RoomTaskList::new(vec![], 1));
+        data.room_settings
+            .insert(room("!a:example.org"), RoomSettings::default());
+        data.admins
+            .insert(room("!b:example.org"), vec!["@bob:example.org".to_owned()]);
+        data.active_rooms.insert(room("!c:example.org"));
+        data.archived.insert(room("!a:example.org"), vec![task]);
+        data.aliases.insert(
+            room("!b:example.org"),
+            HashMap::from([("t".to_owned(), "list".to_owned())]),
+        );
+        data.dm_rooms
+            .insert(room("!dm:example.org"), "@carol:example.org".to_owned());
+        let personal = Task::new("@carol:example.org".to_owned(), 1, "Dentist".to_owned());
+        data.personal_lists.insert(
+            "@carol:example.org".to_owned(),
+            RoomTaskList::new(vec![personal], 2),
+        );
+        data
+    }
+
+    #[test]
+    fn every_format_round_trips() {
+        let data = sample();
+        let expected = serde_json::to_value(&data).unwrap();
+        for format in [
+            StorageFormat::JsonPretty,
+            StorageFormat::JsonCompact,
+            StorageFormat::JsonLines,
+        ] {
+            let content = serialize_storage(&data, format).unwrap();
+            let parsed = parse_storage(&content).unwrap();
+            assert_eq!(
+                serde_json::to_value(&parsed).unwrap(),
+                expected,
+                "{:?}",
+                format
+            );
+        }
+    }
+
+    #[test]
+    fn json_lines_has_one_room_per_line() {
+        let content = serialize_storage(&sample(), StorageFormat::JsonLines).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert!(lines[5].starts_with(r#"{"user_id":"@carol:example.org""#));
+        assert!(lines[0].starts_with(r#"{"room_id":"!a:example.org""#));
+        let compact = serialize_storage(&sample(), StorageFormat::JsonCompact).unwrap();
+        assert_eq!(compact.lines().count(), 1);
+    }
+
+    #[test]
+    fn garbage_is_rejected() {
+        assert!(parse_storage("{\"todo_lists\": ").is_err());
+        assert!(parse_storage("not json\n").is_err());
+    }
+}
@@ -0,0 +1,316 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Days, Utc};
+use matrix_sdk::ruma::OwnedRoomId;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use tracing::{debug, warn};
+
+use super::StorageManager;
+use crate::matrix_integration::media::MediaDeleter;
+
+// Uploads outlive save files and sessions, so the registry has its own file
+pub const MEDIA_FILE: &str = "media.json";
+
+/// Oldest age `!bot media prune <days>` accepts, a hundred years
+pub const MAX_MEDIA_AGE_DAYS: u32 = 36500;
+
+/// One file the bot uploaded to the homeserver
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MediaRecord {
+    pub mxc_uri: String,
+    pub room_id: OwnedRoomId,
+    /// What the upload was for, e.g. `export`
+    pub purpose: String,
+    pub filename: String,
+    pub uploaded_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct MediaRegistry {
+    #[serde(default)]
+    pub uploads: Vec<MediaRecord>,
+}
+
+impl MediaRegistry {
+    /// Take out a room's uploads older than `days`, keeping everything else in the registry.
+    /// A cutoff before the start of the calendar matches nothing.
+    pub fn take_older_than(
+        &mut self,
+        room_id: &OwnedRoomId,
+        days: u32,
+        now: DateTime<Utc>,
+    ) -> Vec<MediaRecord> {
+        let Some(cutoff) = now.checked_sub_days(Days::new(days.into())) else {
+            return Vec::new();
+        };
+        let (expired, kept) = std::mem::take(&mut self.uploads)
+            .into_iter()
+            .partition(|record| &record.room_id == room_id && record.uploaded_at < cutoff);
+        self.uploads = kept;
+        expired
+    }
+}
+
+/// What pruning a room's old uploads did
+#[derive(Debug, PartialEq, Eq)]
+pub enum MediaPrune {
+    /// No upload was old enough
+    NothingExpired,
+    /// The homeserver can't delete media, so this many records were only forgotten
+    Forgotten(usize),
+    /// Deleted from the homeserver; the failed ones stay recorded for a later retry
+    Deleted { deleted: usize, failed: usize },
+}
+
+impl StorageManager {
+    fn media_path(&self) -> PathBuf {
+        self.data_dir.join(MEDIA_FILE)
+    }
+
+    pub async fn load_media_registry(&self) -> Result<MediaRegistry> {
+        let path = self.media_path();
+        if !path.exists() {
+            return Ok(MediaRegistry::default());
+        }
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read media registry: {:?}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse media registry: {:?}", path))
+    }
+
+    pub async fn save_media_registry(&self, registry: &MediaRegistry) -> Result<()> {
+        let path = self.media_path();
+        tokio::fs::write(&path, serde_json::to_string_pretty(registry)?)
+            .await
+            .with_context(|| format!("Failed to write media registry: {:?}", path))?;
+        debug!(file_path = %path.display(), uploads = registry.uploads.len(), "Saved media registry");
+        Ok(())
+    }
+
+    pub async fn record_media_upload(&self, record: MediaRecord) -> Result<()> {
+        let mut registry = self.load_media_registry().await?;
+        registry.uploads.push(record);
+        self.save_media_registry(&registry).await
+    }
+
+    /// Delete a room's uploads older than `days` from the homeserver and the registry
+    pub async fn prune_media(
+        &self,
+        deleter: &dyn MediaDeleter,
+        room_id: &OwnedRoomId,
+        days: u32,
+        now: DateTime<Utc>,
+    ) -> Result<MediaPrune> {
+        let mut registry = self.load_media_registry().await?;
+        let expired = registry.take_older_than(room_id, days, now);
+        if expired.is_empty() {
+            return Ok(MediaPrune::NothingExpired);
+        }
+
+        let outcome = if deleter.can_delete().await {
+            let mut deleted = 0;
+            let mut failed = 0;
+            for record in expired {
+                match deleter.delete(&record.mxc_uri).await {
+                    Ok(()) => deleted += 1,
+                    Err(e) => {
+                        warn!(mxc_uri = %record.mxc_uri, "Failed to delete upload: {}", e);
+                        failed += 1;
+                        // Keep the record so a later prune can retry
+                        registry.uploads.push(record);
+                    }
+                }
+            }
+            MediaPrune::Deleted { deleted, failed }
+        } else {
+            MediaPrune::Forgotten(expired.len())
+        };
+        self.save_media_registry(&registry).await?;
+        Ok(outcome)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_storage;
+    use anyhow::anyhow;
+    use async_trait::async_trait;
+    use chrono::Duration;
+    use std::sync::Mutex;
+
+    /// Homeserver stand-in that records deletions and fails for the given URIs
+    #[derive(Default)]
+    struct MockDeleter {
+        unsupported: bool,
+        failing: Vec<String>,
+        deleted: Mutex<Vec<String>>,
+    }
+
+    #[async_trait]
+    impl MediaDeleter for MockDeleter {
+        async fn can_delete(&self) -> bool {
+            !self.unsupported
+        }
+
+        async fn delete(&self, mxc_uri: &str) -> Result<()> {
+            if self.failing.iter().any(|uri| uri == mxc_uri) {
+                return Err(anyhow!("500 Internal Server Error"));
+            }
+            self.deleted.lock().unwrap().push(mxc_uri.to_owned());
+            Ok(())
+        }
+    }
+
+    fn room(id: &str) -> OwnedRoomId {
+        id.try_into().unwrap()
+    }
+
+    fn upload(
+        room_id: &OwnedRoomId,
+        media_id: &str,
+        age_days: i64,
+        now: DateTime<Utc>,
+    ) -> MediaRecord {
+        MediaRecord {
+            mxc_uri: format!("mxc://example.org/{}", media_id),
+            room_id: room_id.clone(),
+            purpose: "export".to_owned(),
+            filename: format!("{}.csv", media_id),
+            uploaded_at: now - Duration::days(age_days),
+            size: 42,
+        }
+    }
+
+    /// Storage whose registry holds two old and one new upload in `room_id`,
+    /// and an old one in another room
+    async fn storage_with_uploads(room_id: &OwnedRoomId, now: DateTime<Utc>) -> StorageManager {
+        let storage = test_storage(10);
+        std::fs::create_dir_all(&storage.data_dir).unwrap();
+        for record in [
+            upload(room_id, "old1", 40, now),
+            upload(room_id, "old2", 31, now),
+            upload(room_id, "new", 2, now),
+            upload(&room("!other:example.org"), "elsewhere", 90, now),
+        ] {
+            storage.record_media_upload(record).await.unwrap();
+        }
+        storage
+    }
+
+    fn media_ids(registry: &MediaRegistry) -> Vec<&str> {
+        registry
+            .uploads
+            .iter()
+            .map(|record| record.mxc_uri.trim_start_matches("mxc://example.org/"))
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn uploads_are_recorded_across_loads() {
+        let now = Utc::now();
+        let room_id = room("!room:example.org");
+        let storage = storage_with_uploads(&room_id, now).await;
+        let registry = storage.load_media_registry().await.unwrap();
+        assert_eq!(media_ids(&registry), ["old1", "old2", "new", "elsewhere"]);
+        assert_eq!(registry.uploads[0], upload(&room_id, "old1", 40, now));
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn pruning_deletes_only_the_rooms_old_uploads() {
+        let now = Utc::now();
+        let room_id = room("!room:example.org");
+        let storage = storage_with_uploads(&room_id, now).await;
+        let deleter = MockDeleter::default();
+
+        let outcome = storage
+            .prune_media(&deleter, &room_id, 30, now)
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome,
+            MediaPrune::Deleted {
+                deleted: 2,
+                failed: 0
+            }
+        );
+        assert_eq!(
+            *deleter.deleted.lock().unwrap(),
+            ["mxc://example.org/old1", "mxc://example.org/old2"]
+        );
+        let registry = storage.load_media_registry().await.unwrap();
+        assert_eq!(media_ids(&registry), ["new", "elsewhere"]);
+
+        let outcome = storage
+            .prune_media(&deleter, &room_id, 30, now)
+            .await
+            .unwrap();
+        assert_eq!(outcome, MediaPrune::NothingExpired);
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn an_age_past_the_calendar_prunes_nothing() {
+        let now = Utc::now();
+        let room_id = room("!room:example.org");
+        let storage = storage_with_uploads(&room_id, now).await;
+        let deleter = MockDeleter::default();
+
+        let outcome = storage
+            .prune_media(&deleter, &room_id, u32::MAX, now)
+            .await
+            .unwrap();
+        assert_eq!(outcome, MediaPrune::NothingExpired);
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn failed_deletions_stay_recorded_for_a_retry() {
+        let now = Utc::now();
+        let room_id = room("!room:example.org");
+        let storage = storage_with_uploads(&room_id, now).await;
+        let deleter = MockDeleter {
+            failing: vec!["mxc://example.org/old2".to_owned()],
+            ..Default::default()
+        };
+
+        let outcome = storage
+            .prune_media(&deleter, &room_id, 30, now)
+            .await
+            .unwrap();
+        assert_eq!(
+            outcome,
+            MediaPrune::Deleted {
+                deleted: 1,
+                failed: 1
+            }
+        );
+        let registry = storage.load_media_registry().await.unwrap();
+        assert_eq!(media_ids(&registry), ["new", "elsewhere", "old2"]);
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn without_delete_support_records_are_only_forgotten() {
+        let now = Utc::now();
+        let room_id = room("!room:example.org");
+        let storage = storage_with_uploads(&room_id, now).await;
+        let deleter = MockDeleter {
+            unsupported: true,
+            ..Default::default()
+        };
+
+        let outcome = storage
+            .prune_media(&deleter, &room_id, 30, now)
+            .await
+            .unwrap();
+        assert_eq!(outcome, MediaPrune::Forgotten(2));
+        assert!(deleter.deleted.lock().unwrap().is_empty());
+        let registry = storage.load_media_registry().await.unwrap();
+        assert_eq!(media_ids(&registry), ["new", "elsewhere"]);
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+}
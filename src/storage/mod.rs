@@ -1,52 +1,279 @@
 use anyhow::{Context, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
 use matrix_sdk::ruma::OwnedRoomId;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
-use std::{collections::HashMap, path::PathBuf, sync::Arc};
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
 use tokio::sync::Mutex;
 use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use crate::task_management::Task;
+use crate::task_management::{RoomTaskList, Task};
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
+pub mod active_rooms;
+pub mod admins;
+pub mod aliases;
+pub mod archive;
+pub mod atomic;
+pub mod checksum;
+pub mod cold_history;
+pub mod csv_export;
+pub mod debounce;
+pub mod diff;
+pub mod dm_rooms;
+pub mod format;
+pub mod media;
+pub mod per_room;
+pub mod personal;
+pub mod reindex;
+pub mod save_schedule;
+pub mod session_id;
+pub mod settings;
+pub mod templates;
+
+use format::StorageFormat;
+use settings::RoomSettings;
+
+/// Copy of the most recent timestamped save, for tools that want a stable name
+pub const LATEST_FILE: &str = "latest.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct StorageData {
-    pub todo_lists: HashMap<OwnedRoomId, Vec<Task>>,
+    pub todo_lists: HashMap<OwnedRoomId, RoomTaskList>,
+    #[serde(default)]
+    pub room_settings: HashMap<OwnedRoomId, RoomSettings>,
+    // Counters from before they moved into `RoomTaskList`; read for migration only
+    #[serde(default, skip_serializing)]
+    pub next_ids: HashMap<OwnedRoomId, usize>,
+    #[serde(default)]
+    pub admins: HashMap<OwnedRoomId, Vec<String>>,
+    #[serde(default)]
+    pub active_rooms: HashSet<OwnedRoomId>,
+    // Done and closed tasks moved out of the active lists with `!archive`
+    #[serde(default)]
+    pub archived: HashMap<OwnedRoomId, Vec<Task>>,
+    // Per-room command aliases, alias -> built-in command
+    #[serde(default)]
+    pub aliases: HashMap<OwnedRoomId, HashMap<String, String>>,
+    // Private DM rooms, room -> the user the list belongs to
+    #[serde(default)]
+    pub dm_rooms: HashMap<OwnedRoomId, String>,
+    // `!personal` lists, keyed by the user's MXID
+    #[serde(default)]
+    pub personal_lists: HashMap<String, RoomTaskList>,
+    // (old ID, new ID) of the tasks `migrate_legacy_lists` renumbered, per room
+    #[serde(skip)]
+    pub renumbered: HashMap<OwnedRoomId, Vec<(usize, usize)>>,
+}
+
+impl StorageData {
+    /// Upgrade rooms stored as a plain task array. If the file had a `next_ids`
+    /// counter for the room its IDs are already stable and the counter is kept.
+    /// Files written before stable IDs have neither, and their task IDs may be
+    /// duplicated or stale because closing a task shifted positions. Users addressed
+    /// those tasks by list position, so renumber them to exactly that and start the
+    /// counter after the last one.
+    pub fn migrate_legacy_lists(&mut self) {
+        let next_ids = std::mem::take(&mut self.next_ids);
+        for (room_id, list) in self.todo_lists.iter_mut() {
+            if list.next_id != 0 {
+                continue;
+            }
+            if let Some(&next_id) = next_ids.get(room_id) {
+                list.next_id = next_id;
+                continue;
+            }
+            let mut moves = Vec::new();
+            for (idx, task) in list.iter_mut().enumerate() {
+                moves.push((task.id, idx + 1));
+                task.id = idx + 1;
+            }
+            self.renumbered.insert(room_id.clone(), moves);
+            list.next_id = list.len() + 1;
+            info!(
+                room_id = %room_id,
+                task_count = list.len(),
+                "Migrated legacy positional task IDs to stable IDs"
+            );
+        }
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct StorageManager {
     pub data_dir: PathBuf,
     pub session_id: Uuid,
-    pub todo_lists: Arc<Mutex<HashMap<OwnedRoomId, Vec<Task>>>>,
+    pub todo_lists: Arc<Mutex<HashMap<OwnedRoomId, RoomTaskList>>>,
+    pub room_settings: Arc<Mutex<HashMap<OwnedRoomId, RoomSettings>>>,
+    pub admins: Arc<Mutex<HashMap<OwnedRoomId, Vec<String>>>>,
+    // Rooms activated with `!bot activate`, on top of those from `--active-room`
+    pub active_rooms: Arc<Mutex<HashSet<OwnedRoomId>>>,
+    // Tasks moved out of the active lists with `!archive`, newest last
+    pub archived: Arc<Mutex<HashMap<OwnedRoomId, Vec<Task>>>>,
+    pub aliases: Arc<Mutex<HashMap<OwnedRoomId, HashMap<String, String>>>>,
+    // Rooms holding a user's private list, room -> owner
+    pub dm_rooms: Arc<Mutex<HashMap<OwnedRoomId, String>>>,
+    // Lists that follow a user across rooms, keyed by MXID
+    pub personal_lists: Arc<Mutex<HashMap<String, RoomTaskList>>>,
+    // Bumped on every save and load, lets caches detect that tasks may have changed
+    pub generation: Arc<AtomicU64>,
     pub filename_pattern: Regex,
+    // Same save file names, written by any session
+    pub all_files_pattern: Regex,
+    pub history_offload_days: Option<u32>,
+    // Write one file per room under `rooms/` instead of one file for everything
+    pub per_room: bool,
+    // When set, mutations only mark their room dirty and a background flush writes them
+    pub save_debounce: Option<Duration>,
+    pub dirty_rooms: Arc<Mutex<HashSet<OwnedRoomId>>>,
+    // Room files that failed to load; kept as they are instead of being overwritten
+    pub unreadable_room_files: Arc<Mutex<HashSet<PathBuf>>>,
+    // Timestamped save files kept by `prune_old_saves`, 0 keeps all of them
+    pub max_saved_files: usize,
+    // Encoding of new save files; loading detects it from the content
+    pub storage_format: StorageFormat,
 }
 
 impl StorageManager {
-    pub fn new(data_dir: PathBuf, session_id: Uuid) -> Result<Self> {
+    pub fn new(
+        data_dir: PathBuf,
+        session_id: Uuid,
+        history_offload_days: Option<u32>,
+        per_room: bool,
+        save_debounce: Option<Duration>,
+        max_saved_files: usize,
+        storage_format: StorageFormat,
+    ) -> Result<Self> {
         if !data_dir.exists() {
             std::fs::create_dir_all(&data_dir)
                 .with_context(|| format!("Failed to create data directory: {:?}", data_dir))?;
         }
-        let filename_pattern = Regex::new(&format!(
-            r"^{}_{}_[0-9]{{4}}-[0-9]{{2}}-[0-9]{{2}}_[0-9]{{2}}-[0-9]{{2}}-[0-9]{{2}}Z\\.json$",
-            regex::escape(env!("CARGO_PKG_NAME")),
-            regex::escape(&session_id.to_string())
-        ))?;
+        // Whatever an interrupted write left behind is incomplete
+        for dir in [
+            data_dir.clone(),
+            data_dir.join(per_room::ROOMS_DIR),
+            data_dir.join(personal::PERSONAL_DIR),
+        ] {
+            atomic::remove_leftover_tmp_files(&dir);
+        }
+        let filename_pattern = save_file_pattern(&regex::escape(&session_id.to_string()))?;
+        let all_files_pattern = save_file_pattern(ANY_SESSION)?;
         Ok(Self {
             data_dir,
             session_id,
             todo_lists: Arc::new(Mutex::new(HashMap::new())),
+            room_settings: Arc::new(Mutex::new(HashMap::new())),
+            admins: Arc::new(Mutex::new(HashMap::new())),
+            active_rooms: Arc::new(Mutex::new(HashSet::new())),
+            archived: Arc::new(Mutex::new(HashMap::new())),
+            aliases: Arc::new(Mutex::new(HashMap::new())),
+            dm_rooms: Arc::new(Mutex::new(HashMap::new())),
+            personal_lists: Arc::new(Mutex::new(HashMap::new())),
+            generation: Arc::new(AtomicU64::new(0)),
             filename_pattern,
+            all_files_pattern,
+            history_offload_days,
+            per_room,
+            save_debounce,
+            dirty_rooms: Arc::new(Mutex::new(HashSet::new())),
+            unreadable_room_files: Arc::new(Mutex::new(HashSet::new())),
+            max_saved_files,
+            storage_format,
         })
     }
 
+    /// Copy of the current in-memory state in its serialized form
+    pub async fn snapshot(&self) -> StorageData {
+        let todo_lists = self.todo_lists.lock().await;
+        StorageData {
+            todo_lists: todo_lists.clone(),
+            room_settings: self.room_settings.lock().await.clone(),
+            next_ids: HashMap::new(),
+            admins: self.admins.lock().await.clone(),
+            active_rooms: self.active_rooms.lock().await.clone(),
+            archived: self.archived.lock().await.clone(),
+            aliases: self.aliases.lock().await.clone(),
+            dm_rooms: self.dm_rooms.lock().await.clone(),
+            personal_lists: self.personal_lists.lock().await.clone(),
+            renumbered: HashMap::new(),
+        }
+    }
+
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::SeqCst)
+    }
+
+    /// Name for an export of a room, like the save files with the room in place
+    /// of the session
+    pub fn export_filename(room_id: &OwnedRoomId, extension: &str) -> String {
+        let room: String = room_id
+            .as_str()
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+            .collect();
+        format!(
+            "{}_export_{}_{}.{}",
+            env!("CARGO_PKG_NAME"),
+            room.trim_matches('-'),
+            Utc::now().format("%Y-%m-%d_%H-%M-%SZ"),
+            extension
+        )
+    }
+
+    /// Write an export of a room next to the save files, see `export_filename`.
+    /// Returns the file name.
+    pub async fn write_export(
+        &self,
+        room_id: &OwnedRoomId,
+        extension: &str,
+        content: &str,
+    ) -> Result<String> {
+        let filename = Self::export_filename(room_id, extension);
+        let filepath = self.data_dir.join(&filename);
+        tokio::fs::write(&filepath, content)
+            .await
+            .with_context(|| format!("Failed to write export file: {:?}", filepath))?;
+        info!(room_id = %room_id, file_path = %filepath.display(), "Wrote room export");
+        Ok(filename)
+    }
+
     pub async fn save(&self) -> Result<String> {
+        self.save_at(Utc::now()).await
+    }
+
+    /// `save` with `current_time` naming the save file
+    async fn save_at(&self, current_time: DateTime<Utc>) -> Result<String> {
         debug!(session_id = %self.session_id, "Starting task storage save operation");
+        self.generation.fetch_add(1, Ordering::SeqCst);
+
+        let mut todo_lists = self.todo_lists.lock().await;
+
+        // Opt-in: keep the save file small by moving old history to the cold store
+        if let Err(e) = self.offload_history(&mut todo_lists).await {
+            error!(
+                session_id = %self.session_id,
+                error = %e,
+                "Failed to offload old task history; saving it in the main file instead"
+            );
+        }
+
+        if self.per_room {
+            drop(todo_lists);
+            let room_count = self.save_all_rooms().await.inspect_err(|e| {
+                error!(session_id = %self.session_id, error = %e, "Failed to write room files");
+            })?;
+            info!(session_id = %self.session_id, room_count, "Saved all rooms to per-room files");
+            return Ok(format!("{}/", per_room::ROOMS_DIR));
+        }
 
-        let todo_lists = self.todo_lists.lock().await;
-        let current_time = Utc::now();
         let filename = format!(
             "{}_{}_{}.json",
             env!("CARGO_PKG_NAME"),
@@ -70,9 +297,18 @@ impl StorageManager {
 
         let data = StorageData {
             todo_lists: todo_lists.clone(),
+            room_settings: self.room_settings.lock().await.clone(),
+            next_ids: HashMap::new(),
+            admins: self.admins.lock().await.clone(),
+            active_rooms: self.active_rooms.lock().await.clone(),
+            archived: self.archived.lock().await.clone(),
+            aliases: self.aliases.lock().await.clone(),
+            dm_rooms: self.dm_rooms.lock().await.clone(),
+            personal_lists: self.personal_lists.lock().await.clone(),
+            renumbered: HashMap::new(),
         };
 
-        let json_data = match serde_json::to_string_pretty(&data) {
+        let json_data = match format::serialize_storage(&data, self.storage_format) {
             Ok(json) => json,
             Err(e) => {
                 error!(
@@ -80,11 +316,12 @@ impl StorageManager {
                     error = %e,
                     "Failed to serialize task data to JSON"
                 );
-                return Err(e.into());
+                return Err(e);
             }
         };
 
-        match tokio::fs::write(&filepath, json_data).await {
+        let write_result = self.write_save_file(&filepath, &json_data).await;
+        match write_result {
             Ok(_) => {
                 info!(
                     session_id = %self.session_id,
@@ -94,6 +331,11 @@ impl StorageManager {
                     room_count,
                     "Successfully saved todo lists to file"
                 );
+                drop(todo_lists);
+                // A failed cleanup leaves extra files behind, the save itself succeeded
+                if let Err(e) = self.prune_old_saves(&filename).await {
+                    warn!(session_id = %self.session_id, error = %e, "Failed to prune old save files");
+                }
                 Ok(filename)
             }
             Err(e) => {
@@ -113,24 +355,94 @@ impl StorageManager {
     }
 
     pub async fn load(&self, filename: &str) -> Result<bool> {
+        self.load_matching(filename, &self.filename_pattern).await
+    }
+
+    /// Like `load`, but also accepts save files written by other sessions
+    pub async fn load_any_session(&self, filename: &str) -> Result<bool> {
+        self.load_matching(filename, &self.all_files_pattern).await
+    }
+
+    async fn load_matching(&self, filename: &str, pattern: &Regex) -> Result<bool> {
         debug!(session_id = %self.session_id, filename, "Starting task storage load operation");
 
+        if let Some(room_file) = filename.strip_prefix(&format!("{}/", per_room::ROOMS_DIR)) {
+            return self.load_room_file(room_file).await;
+        }
+
+        let Some(data) = self.read_save_file_matching(filename, pattern).await? else {
+            return Ok(false);
+        };
+        let filepath = self.data_dir.join(filename);
+
+        for (room_id, moves) in &data.renumbered {
+            self.rekey_cold_history(room_id, moves).await?;
+        }
+        let mut todo_lists = self.todo_lists.lock().await;
+        *todo_lists = data.todo_lists;
+        *self.room_settings.lock().await = data.room_settings;
+        *self.admins.lock().await = data.admins;
+        *self.active_rooms.lock().await = data.active_rooms;
+        *self.archived.lock().await = data.archived;
+        *self.aliases.lock().await = data.aliases;
+        *self.dm_rooms.lock().await = data.dm_rooms;
+        *self.personal_lists.lock().await = data.personal_lists;
+        self.generation.fetch_add(1, Ordering::SeqCst);
+
+        let task_count = todo_lists
+            .iter()
+            .fold(0, |acc, (_, tasks)| acc + tasks.len());
+        let room_count = todo_lists.len();
+
+        info!(
+            session_id = %self.session_id,
+            file_path = %filepath.display(),
+            task_count,
+            room_count,
+            "Successfully loaded todo lists from file"
+        );
+        drop(todo_lists);
+
+        self.backfill_from_cold_history().await?;
+        Ok(true)
+    }
+
+    /// Write a save file and its checksum atomically, then refresh `LATEST_FILE`
+    async fn write_save_file(&self, filepath: &std::path::Path, json_data: &str) -> Result<()> {
+        for path in [filepath.to_path_buf(), self.data_dir.join(LATEST_FILE)] {
+            checksum::write_with_checksum(&path, json_data.as_bytes()).await?;
+        }
+        Ok(())
+    }
+
+    /// Read and verify a save file without applying it. `None` if the file is
+    /// missing, has an unexpected name or fails checksum verification.
+    pub async fn read_save_file(&self, filename: &str) -> Result<Option<StorageData>> {
+        self.read_save_file_matching(filename, &self.filename_pattern)
+            .await
+    }
+
+    async fn read_save_file_matching(
+        &self,
+        filename: &str,
+        pattern: &Regex,
+    ) -> Result<Option<StorageData>> {
         let filepath = self.data_dir.join(filename);
         if !filepath.exists() {
             warn!(session_id = %self.session_id, file_path = %filepath.display(), "Attempted to load non-existent file");
-            return Ok(false);
+            return Ok(None);
         }
 
-        if !self.filename_pattern.is_match(filename) {
+        if filename != LATEST_FILE && !pattern.is_match(filename) {
             warn!(
                 session_id = %self.session_id,
                 filename,
                 "Rejected loading file with invalid filename pattern"
             );
-            return Ok(false);
+            return Ok(None);
         }
 
-        info!(session_id = %self.session_id, file_path = %filepath.display(), "Loading task data from file");
+        info!(session_id = %self.session_id, file_path = %filepath.display(), "Reading task data file");
 
         let file_content = match tokio::fs::read_to_string(&filepath).await {
             Ok(content) => content,
@@ -145,7 +457,17 @@ impl StorageManager {
             }
         };
 
-        let data: StorageData = match serde_json::from_str(&file_content) {
+        if let Err(e) = checksum::verify_checksum(&filepath, file_content.as_bytes()).await {
+            warn!(
+                session_id = %self.session_id,
+                file_path = %filepath.display(),
+                error = %e,
+                "Refusing to load save file that failed checksum verification"
+            );
+            return Ok(None);
+        }
+
+        let mut data = match format::parse_storage(&file_content) {
             Ok(parsed) => parsed,
             Err(e) => {
                 error!(
@@ -154,30 +476,30 @@ impl StorageManager {
                     error = %e,
                     "Failed to parse task data from JSON"
                 );
-                return Err(e.into());
+                return Err(e);
             }
         };
 
-        let mut todo_lists = self.todo_lists.lock().await;
-        *todo_lists = data.todo_lists;
-
-        let task_count = todo_lists
-            .iter()
-            .fold(0, |acc, (_, tasks)| acc + tasks.len());
-        let room_count = todo_lists.len();
+        data.migrate_legacy_lists();
+        Ok(Some(data))
+    }
 
-        info!(
-            session_id = %self.session_id,
-            file_path = %filepath.display(),
-            task_count,
-            room_count,
-            "Successfully loaded todo lists from file"
-        );
+    /// This session's save files oldest first, then the room files in per-room mode
+    pub fn list_saved_files(&self) -> Result<Vec<String>> {
+        let mut files = self.list_matching_files(&self.filename_pattern)?;
+        // Room files have no timestamp, they follow the single-file saves
+        if self.per_room {
+            files.extend(self.list_room_files()?);
+        }
+        Ok(files)
+    }
 
-        Ok(true)
+    /// Save files of every session, oldest first. Room files are left out.
+    pub fn list_all_saved_files(&self) -> Result<Vec<String>> {
+        self.list_matching_files(&self.all_files_pattern)
     }
 
-    pub fn list_saved_files(&self) -> Result<Vec<String>> {
+    fn list_matching_files(&self, pattern: &Regex) -> Result<Vec<String>> {
         debug!(session_id = %self.session_id, data_dir = %self.data_dir.display(), "Listing saved task files");
 
         let mut valid_files = Vec::new();
@@ -209,23 +531,20 @@ impl StorageManager {
             };
 
             let path = entry.path();
-            if path.is_file() {
-                if let Some(filename) = path.file_name().and_then(|s| s.to_str()) {
-                    if self.filename_pattern.is_match(filename) {
-                        debug!(file_name = %filename, "Found valid task file");
-                        valid_files.push(filename.to_owned());
-                    } else {
-                        debug!(file_name = %filename, "Ignoring non-matching file");
-                    }
+            // Only top-level files are considered, so the archive/ subdirectory is never listed
+            if path.is_file()
+                && let Some(filename) = path.file_name().and_then(|s| s.to_str())
+            {
+                if pattern.is_match(filename) {
+                    debug!(file_name = %filename, "Found valid task file");
+                    valid_files.push(filename.to_owned());
+                } else {
+                    debug!(file_name = %filename, "Ignoring non-matching file");
                 }
             }
         }
 
-        valid_files.sort_by(|a, b| {
-            let a_timestamp = a.chars().rev().skip(5).take(19).collect::<String>();
-            let b_timestamp = b.chars().rev().skip(5).take(19).collect::<String>();
-            a_timestamp.cmp(&b_timestamp)
-        });
+        valid_files.sort_by(|a, b| save_file_timestamp(a).cmp(save_file_timestamp(b)));
 
         info!(
             session_id = %self.session_id,
@@ -235,4 +554,290 @@ impl StorageManager {
 
         Ok(valid_files)
     }
+
+    /// Delete the oldest timestamped save files (and their checksums) beyond
+    /// `max_saved_files`, never `just_written`. Returns how many files were removed.
+    pub async fn prune_old_saves(&self, just_written: &str) -> Result<usize> {
+        // Sorted by their timestamp, oldest first
+        let files: Vec<String> = self
+            .list_saved_files()?
+            .into_iter()
+            .filter(|f| !f.starts_with(per_room::ROOMS_DIR))
+            .collect();
+        let expired = files_to_prune(&files, self.max_saved_files, just_written);
+        for filename in &expired {
+            let path = self.data_dir.join(filename);
+            tokio::fs::remove_file(&path)
+                .await
+                .with_context(|| format!("Failed to remove old save file: {:?}", path))?;
+            if let Err(e) = tokio::fs::remove_file(checksum::checksum_path(&path)).await
+                && e.kind() != std::io::ErrorKind::NotFound
+            {
+                warn!(file_path = %path.display(), error = %e, "Failed to remove checksum of old save file");
+            }
+            debug!(file_name = %filename, "Pruned old save file");
+        }
+        if !expired.is_empty() {
+            info!(
+                removed = expired.len(),
+                kept = self.max_saved_files,
+                "Pruned old save files"
+            );
+        }
+        Ok(expired.len())
+    }
+
+    /// Load the most recent single-file save that passes verification, falling back to
+    /// older ones when a newer file is corrupted, then to `LATEST_FILE`. Returns the
+    /// file that was loaded.
+    pub async fn load_latest_valid(&self) -> Result<Option<String>> {
+        let files = self.list_saved_files()?;
+        self.load_newest_of(&files, &self.filename_pattern).await
+    }
+
+    /// `load_latest_valid` over the save files of every session, for startup
+    /// where the new session has not written anything yet
+    pub async fn load_latest_valid_any_session(&self) -> Result<Option<String>> {
+        let files = self.list_all_saved_files()?;
+        self.load_newest_of(&files, &self.all_files_pattern).await
+    }
+
+    async fn load_newest_of(&self, files: &[String], pattern: &Regex) -> Result<Option<String>> {
+        for filename in files
+            .iter()
+            .rev()
+            .filter(|f| !f.starts_with(per_room::ROOMS_DIR))
+        {
+            match self.load_matching(filename, pattern).await {
+                Ok(true) => return Ok(Some(filename.clone())),
+                Ok(false) => {
+                    warn!(filename = %filename, "Skipping save file that could not be loaded")
+                }
+                Err(e) => warn!(filename = %filename, error = %e, "Skipping unreadable save file"),
+            }
+        }
+        // Last resort when every timestamped file is gone or broken
+        if self.data_dir.join(LATEST_FILE).exists()
+            && self.load_matching(LATEST_FILE, pattern).await?
+        {
+            return Ok(Some(LATEST_FILE.to_owned()));
+        }
+        Ok(None)
+    }
+}
+
+const SAVE_TIMESTAMP_LEN: usize = "YYYY-MM-DD_HH-MM-SSZ".len();
+
+/// The `YYYY-MM-DD_HH-MM-SSZ` part of a save file name, which sorts chronologically
+fn save_file_timestamp(filename: &str) -> &str {
+    let stem = filename.strip_suffix(".json").unwrap_or(filename);
+    stem.get(stem.len().saturating_sub(SAVE_TIMESTAMP_LEN)..)
+        .unwrap_or(stem)
+}
+
+/// The oldest of `files` (sorted oldest first) beyond the newest `keep`, leaving
+/// out `just_written`. `keep` 0 keeps everything.
+fn files_to_prune<'a>(files: &'a [String], keep: usize, just_written: &str) -> Vec<&'a String> {
+    if keep == 0 {
+        return Vec::new();
+    }
+    let excess = files.len().saturating_sub(keep);
+    files[..excess]
+        .iter()
+        .filter(|f| f.as_str() != just_written)
+        .collect()
+}
+
+/// Regex fragment matching any session ID
+const ANY_SESSION: &str = "[0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12}";
+
+/// `<app>_<session>_<UTC timestamp>.json`, `session` being a regex fragment
+fn save_file_pattern(session: &str) -> Result<Regex> {
+    Ok(Regex::new(&format!(
+        r"^{}_{}_[0-9]{{4}}-[0-9]{{2}}-[0-9]{{2}}_[0-9]{{2}}-[0-9]{{2}}-[0-9]{{2}}Z\.json$",
+        regex::escape(env!("CARGO_PKG_NAME")),
+        session
+    ))?)
+}
+
+/// A storage manager for tests, in a new directory under the system temp dir
+#[cfg(test)]
+pub(crate) fn test_storage(max_saved_files: usize) -> StorageManager {
+    let data_dir = std::env::temp_dir().join(format!("asmith-storage-{}", Uuid::new_v4()));
+    StorageManager::new(
+        data_dir,
+        Uuid::new_v4(),
+        None,
+        false,
+        None,
+        max_saved_files,
+        Default::default(),
+    )
+    .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn save_name(session: &str, timestamp: &str) -> String {
+        format!("{}_{}_{}.json", env!("CARGO_PKG_NAME"), session, timestamp)
+    }
+
+    #[test]
+    fn save_file_pattern_matches_only_save_file_names() {
+        let session = "5e1f3a0c-0000-4000-8000-000000000000";
+        let pattern = save_file_pattern(&regex::escape(session)).unwrap();
+        let valid = save_name(session, "2024-05-01_10-00-59Z");
+        assert!(pattern.is_match(&valid));
+
+        let app = env!("CARGO_PKG_NAME");
+        for invalid in [
+            // The escaped dot once matched a backslash and any character here
+            format!("{}_{}_2024-05-01_10-00-59Z\\xjson", app, session),
+            format!("{}_{}_2024-05-01_10-00-59Zxjson", app, session),
+            format!("{}.gz", valid),
+            format!("{}_{}_2024-05-01_10-00-59Z.json.gz", app, session),
+            format!("{}_{}_2024-05-01_10-00-59.json", app, session),
+            format!("{}_{}_2024-05-01.json", app, session),
+            format!("other_{}_2024-05-01_10-00-59Z.json", session),
+            format!("x{}", valid),
+            save_name(
+                "0badcafe-0000-4000-8000-000000000000",
+                "2024-05-01_10-00-59Z",
+            ),
+            LATEST_FILE.to_owned(),
+        ] {
+            assert!(!pattern.is_match(&invalid), "{}", invalid);
+        }
+
+        // Any session's files match the pattern `load_any_session` uses
+        let any = save_file_pattern(ANY_SESSION).unwrap();
+        assert!(any.is_match(&save_name(
+            "0badcafe-0000-4000-8000-000000000000",
+            "2024-05-01_10-00-59Z"
+        )));
+        assert!(!any.is_match(&save_name("not-a-uuid", "2024-05-01_10-00-59Z")));
+    }
+
+    #[test]
+    fn temporary_files_are_not_save_files() {
+        let session = "5e1f3a0c-0000-4000-8000-000000000000";
+        let pattern = save_file_pattern(&regex::escape(session)).unwrap();
+        let name = save_name(session, "2024-05-01_10-00-59Z");
+        assert!(pattern.is_match(&name));
+        assert!(!pattern.is_match(&format!("{}.tmp", name)));
+    }
+
+    #[tokio::test]
+    async fn saving_prunes_down_to_max_saved_files() {
+        let storage = test_storage(3);
+        let start = Utc::now();
+        let mut written = Vec::new();
+        for minute in 0..5 {
+            let at = start + chrono::Duration::minutes(minute);
+            written.push(storage.save_at(at).await.unwrap());
+        }
+
+        assert_eq!(storage.list_saved_files().unwrap(), written[2..]);
+        let mut on_disk: Vec<String> = std::fs::read_dir(&storage.data_dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .filter(|name| name != LATEST_FILE && !name.starts_with(LATEST_FILE))
+            .collect();
+        on_disk.sort();
+        // Each kept save has its checksum; the pruned ones lost theirs too
+        let mut expected: Vec<String> = written[2..]
+            .iter()
+            .flat_map(|f| {
+                let sidecar = checksum::checksum_path(std::path::Path::new(f));
+                [f.clone(), sidecar.to_string_lossy().into_owned()]
+            })
+            .collect();
+        expected.sort();
+        assert_eq!(on_disk, expected);
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+
+    async fn add_task(storage: &StorageManager, room_id: &OwnedRoomId, title: &str) {
+        let mut todo_lists = storage.todo_lists.lock().await;
+        let tasks = todo_lists.entry(room_id.clone()).or_default();
+        let id = tasks.allocate_id();
+        tasks.push(crate::task_management::Task::new(
+            "@alice:example.org".into(),
+            id,
+            title.into(),
+        ));
+    }
+
+    async fn task_count(storage: &StorageManager, room_id: &OwnedRoomId) -> usize {
+        storage.todo_lists.lock().await[room_id].len()
+    }
+
+    /// Cut a save file in half without touching its checksum
+    fn corrupt(storage: &StorageManager, filename: &str) {
+        let path = storage.data_dir.join(filename);
+        let data = std::fs::read(&path).unwrap();
+        std::fs::write(&path, &data[..data.len() / 2]).unwrap();
+    }
+
+    #[tokio::test]
+    async fn corrupted_saves_fall_back_to_older_ones_then_latest() {
+        let storage = test_storage(0);
+        let room_id = OwnedRoomId::try_from("!room:example.org").unwrap();
+        let start = Utc::now();
+        add_task(&storage, &room_id, "First").await;
+        let older = storage.save_at(start).await.unwrap();
+        add_task(&storage, &room_id, "Second").await;
+        let newest = storage
+            .save_at(start + chrono::Duration::minutes(1))
+            .await
+            .unwrap();
+
+        corrupt(&storage, &newest);
+        assert_eq!(
+            storage.load_latest_valid().await.unwrap(),
+            Some(older.clone())
+        );
+        assert_eq!(task_count(&storage, &room_id).await, 1);
+
+        // latest.json holds a copy of the newest save
+        corrupt(&storage, &older);
+        assert_eq!(
+            storage.load_latest_valid().await.unwrap(),
+            Some(LATEST_FILE.to_owned())
+        );
+        assert_eq!(task_count(&storage, &room_id).await, 2);
+
+        corrupt(&storage, LATEST_FILE);
+        assert_eq!(storage.load_latest_valid().await.unwrap(), None);
+        std::fs::remove_dir_all(&storage.data_dir).unwrap();
+    }
+
+    #[test]
+    fn pruning_keeps_the_newest_files_and_the_one_just_written() {
+        let mut files: Vec<String> = [
+            "2024-05-01_10-00-59Z",
+            "2024-05-01_09-59-00Z",
+            "2024-04-30_23-00-00Z",
+            "2024-05-02_00-00-01Z",
+            "2024-05-01_10-01-00Z",
+        ]
+        .iter()
+        .map(|ts| save_name("5e1f3a0c-0000-4000-8000-000000000000", ts))
+        .collect();
+        files.sort_by(|a, b| save_file_timestamp(a).cmp(save_file_timestamp(b)));
+        assert!(files[0].contains("2024-04-30_23-00-00Z"));
+        assert!(files[4].contains("2024-05-02_00-00-01Z"));
+
+        let pruned = files_to_prune(&files, 2, &files[4]);
+        assert_eq!(pruned, vec![&files[0], &files[1], &files[2]]);
+
+        // A clock that went backwards can make the new file look old, it still stays
+        let pruned = files_to_prune(&files, 2, &files[0]);
+        assert_eq!(pruned, vec![&files[1], &files[2]]);
+
+        assert!(files_to_prune(&files, 0, &files[4]).is_empty());
+        assert!(files_to_prune(&files, 10, &files[4]).is_empty());
+    }
 }
@@ -0,0 +1,387 @@
+use anyhow::{Context, Result, anyhow};
+use matrix_sdk::ruma::OwnedRoomId;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use tracing::{debug, error, info, warn};
+
+use super::cold_history::sanitize_room_id;
+use super::settings::RoomSettings;
+use super::{StorageManager, checksum};
+use crate::task_management::{RoomTaskList, Task};
+
+// One file per room lives here when per-room storage is enabled
+pub const ROOMS_DIR: &str = "rooms";
+
+/// Everything stored for a single room in per-room mode
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct RoomData {
+    pub room_id: OwnedRoomId,
+    pub tasks: Vec<Task>,
+    #[serde(default)]
+    pub settings: RoomSettings,
+    #[serde(default)]
+    pub next_id: Option<usize>,
+    #[serde(default)]
+    pub admins: Vec<String>,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub archived: Vec<Task>,
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    // Set when the room is a user's private DM list
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dm_owner: Option<String>,
+}
+
+impl StorageManager {
+    pub fn rooms_dir(&self) -> PathBuf {
+        self.data_dir.join(ROOMS_DIR)
+    }
+
+    fn room_file_path(&self, room_id: &OwnedRoomId) -> PathBuf {
+        self.rooms_dir()
+            .join(format!("{}.json", sanitize_room_id(room_id)))
+    }
+
+    /// Everything stored for `room_id`, from the in-memory state
+    pub async fn room_data(&self, room_id: &OwnedRoomId) -> RoomData {
+        let list = self
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .cloned()
+            .unwrap_or_default();
+        RoomData {
+            room_id: room_id.clone(),
+            next_id: Some(list.next_id),
+            tasks: list.tasks,
+            settings: self.room_settings(room_id).await,
+            admins: self.room_admins(room_id).await,
+            active: self.active_rooms.lock().await.contains(room_id),
+            archived: self
+                .archived
+                .lock()
+                .await
+                .get(room_id)
+                .cloned()
+                .unwrap_or_default(),
+            aliases: self.room_aliases(room_id).await,
+            dm_owner: self.dm_owner(room_id).await,
+        }
+    }
+
+    /// Write one room's file via a temporary file so a crash never leaves it half written.
+    /// A file that failed to load is refused until it is fixed and loaded, or removed,
+    /// so the room's saved tasks aren't replaced by the empty in-memory state.
+    async fn write_room_file(&self, data: &RoomData) -> Result<()> {
+        let path = self.room_file_path(&data.room_id);
+        {
+            let mut unreadable = self.unreadable_room_files.lock().await;
+            if unreadable.contains(&path) {
+                if path.exists() {
+                    return Err(anyhow!(
+                        "Room file {:?} failed to load and is kept as it is; fix it and load it with !bot load, or remove it",
+                        path
+                    ));
+                }
+                unreadable.remove(&path);
+            }
+        }
+        tokio::fs::create_dir_all(self.rooms_dir()).await?;
+        let json = serde_json::to_string_pretty(data)?;
+        checksum::write_with_checksum(&path, json.as_bytes())
+            .await
+            .with_context(|| format!("Failed to save room file: {:?}", path))?;
+        debug!(room_id = %data.room_id, file_path = %path.display(), "Saved room file");
+        Ok(())
+    }
+
+    /// Persist the state of a single room after it changed. With a save debounce the
+    /// room is only marked dirty and written by the next flush. Otherwise, in per-room
+    /// mode only that room's file is rewritten, and in single-file mode this is a full `save`.
+    pub async fn save_room(&self, room_id: &OwnedRoomId) -> Result<()> {
+        if self.save_debounce.is_some() {
+            self.mark_dirty(room_id).await;
+            return Ok(());
+        }
+        if !self.per_room {
+            return self.save().await.map(|_| ());
+        }
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.write_room(room_id).await
+    }
+
+    /// Rewrite one room's file from the in-memory state
+    pub(super) async fn write_room(&self, room_id: &OwnedRoomId) -> Result<()> {
+        self.offload_room_history(room_id).await;
+        let data = self.room_data(room_id).await;
+        self.write_room_file(&data).await.inspect_err(|e| {
+            error!(room_id = %room_id, error = %e, "Failed to save room file");
+        })
+    }
+
+    async fn offload_room_history(&self, room_id: &OwnedRoomId) {
+        let mut todo_lists = self.todo_lists.lock().await;
+        let Some(tasks) = todo_lists.remove(room_id) else {
+            return;
+        };
+        let mut single = HashMap::from([(room_id.clone(), tasks)]);
+        if let Err(e) = self.offload_history(&mut single).await {
+            error!(room_id = %room_id, error = %e, "Failed to offload old task history");
+        }
+        todo_lists.extend(single);
+    }
+
+    /// Write every known room to its own file, returns the number of rooms written
+    pub(super) async fn save_all_rooms(&self) -> Result<usize> {
+        let mut room_ids: Vec<OwnedRoomId> = self.todo_lists.lock().await.keys().cloned().collect();
+        let mut other_rooms: Vec<OwnedRoomId> =
+            self.room_settings.lock().await.keys().cloned().collect();
+        other_rooms.extend(self.active_rooms.lock().await.iter().cloned());
+        other_rooms.extend(self.archived.lock().await.keys().cloned());
+        other_rooms.extend(self.aliases.lock().await.keys().cloned());
+        other_rooms.extend(self.dm_rooms.lock().await.keys().cloned());
+        for room_id in other_rooms {
+            if !room_ids.contains(&room_id) {
+                room_ids.push(room_id);
+            }
+        }
+        for room_id in &room_ids {
+            let data = self.room_data(room_id).await;
+            self.write_room_file(&data).await?;
+        }
+        let users: Vec<String> = self.personal_lists.lock().await.keys().cloned().collect();
+        for user in &users {
+            self.write_personal_file(user).await?;
+        }
+        Ok(room_ids.len())
+    }
+
+    /// Put one room's stored data in memory, replacing what was there for that room
+    pub(super) async fn apply_room_data(&self, data: RoomData) {
+        let room_id = data.room_id;
+        // allocate_id keeps IDs ahead of existing tasks even without a stored counter
+        let list = RoomTaskList::new(data.tasks, data.next_id.unwrap_or(1));
+        self.todo_lists.lock().await.insert(room_id.clone(), list);
+        self.room_settings
+            .lock()
+            .await
+            .insert(room_id.clone(), data.settings);
+        let mut active_rooms = self.active_rooms.lock().await;
+        if data.active {
+            active_rooms.insert(room_id.clone());
+        } else {
+            active_rooms.remove(&room_id);
+        }
+        drop(active_rooms);
+        let mut archived = self.archived.lock().await;
+        if data.archived.is_empty() {
+            archived.remove(&room_id);
+        } else {
+            archived.insert(room_id.clone(), data.archived);
+        }
+        drop(archived);
+        let mut aliases = self.aliases.lock().await;
+        if data.aliases.is_empty() {
+            aliases.remove(&room_id);
+        } else {
+            aliases.insert(room_id.clone(), data.aliases);
+        }
+        drop(aliases);
+        let mut dm_rooms = self.dm_rooms.lock().await;
+        match data.dm_owner {
+            Some(owner) => dm_rooms.insert(room_id.clone(), owner),
+            None => dm_rooms.remove(&room_id),
+        };
+        drop(dm_rooms);
+        self.admins.lock().await.insert(room_id, data.admins);
+        self.generation.fetch_add(1, Ordering::SeqCst);
+    }
+
+    pub(super) async fn read_room_file(&self, path: &PathBuf) -> Result<RoomData> {
+        let content = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("Failed to read room file: {:?}", path))?;
+        checksum::verify_checksum(path, content.as_bytes()).await?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse room file: {:?}", path))
+    }
+
+    /// Names of the room files as accepted by `load`, e.g. `rooms/example_org_abc.json`
+    pub fn list_room_files(&self) -> Result<Vec<String>> {
+        let rooms_dir = self.rooms_dir();
+        if !rooms_dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&rooms_dir)? {
+            let path = entry?.path();
+            if path.is_file()
+                && path.extension().and_then(|e| e.to_str()) == Some("json")
+                && let Some(name) = path.file_name().and_then(|s| s.to_str())
+            {
+                files.push(format!("{}/{}", ROOMS_DIR, name));
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    /// Reload a single room from its file, leaving all other rooms untouched
+    pub(super) async fn load_room_file(&self, name: &str) -> Result<bool> {
+        // Only plain file names inside rooms/, never a path that escapes it
+        if name.contains(['/', '\\']) || name.starts_with('.') || !name.ends_with(".json") {
+            warn!(
+                filename = name,
+                "Rejected loading room file with invalid name"
+            );
+            return Ok(false);
+        }
+        let path = self.rooms_dir().join(name);
+        if !path.exists() {
+            warn!(file_path = %path.display(), "Attempted to load non-existent room file");
+            return Ok(false);
+        }
+        let data = self.read_room_file(&path).await?;
+        self.unreadable_room_files.lock().await.remove(&path);
+        info!(room_id = %data.room_id, file_path = %path.display(), "Loaded room file");
+        self.apply_room_data(data).await;
+        Ok(true)
+    }
+
+    /// Load every room file. A file that fails to load leaves its room empty until
+    /// it is fixed, see `write_room_file`.
+    /// Returns the number of rooms loaded.
+    pub async fn load_rooms(&self) -> Result<usize> {
+        self.load_personal_files().await?;
+        let rooms_dir = self.rooms_dir();
+        if !rooms_dir.exists() {
+            return Ok(0);
+        }
+        let mut loaded = 0;
+        for entry in std::fs::read_dir(&rooms_dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            match self.read_room_file(&path).await {
+                Ok(data) => {
+                    self.apply_room_data(data).await;
+                    loaded += 1;
+                }
+                Err(e) => {
+                    error!(
+                        file_path = %path.display(),
+                        "Room file failed to load, keeping it and refusing to overwrite it: {:#}",
+                        e
+                    );
+                    self.unreadable_room_files.lock().await.insert(path);
+                }
+            }
+        }
+        info!(rooms = loaded, "Loaded per-room save files");
+        Ok(loaded)
+    }
+
+    /// Startup load for per-room mode. The first time it runs over a data directory
+    /// written in the single-file format, the most recent save file is loaded and
+    /// split into room files.
+    pub async fn load_or_migrate_rooms(&self) -> Result<()> {
+        if self.rooms_dir().exists() {
+            self.load_rooms().await?;
+            return Ok(());
+        }
+
+        // The save files were written by earlier runs, so any session will do
+        let legacy_files = self.list_all_saved_files()?;
+        let Some(latest) = legacy_files.last() else {
+            info!("No saved state found, starting with empty per-room storage");
+            return Ok(());
+        };
+        info!(file = %latest, "Migrating single-file save to per-room storage");
+        if self.load_any_session(latest).await? {
+            let rooms = self.save_all_rooms().await?;
+            info!(rooms, "Migrated to per-room storage");
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::test_storage;
+
+    #[tokio::test]
+    async fn room_data_holds_only_that_room() {
+        let storage = test_storage(10);
+        for (room, title) in [
+            ("!ours:example.org", "Buy milk"),
+            ("!theirs:example.org", "Secret plan"),
+        ] {
+            let room_id: OwnedRoomId = room.try_into().unwrap();
+            let mut todo_lists = storage.todo_lists.lock().await;
+            let tasks = todo_lists.entry(room_id).or_default();
+            let id = tasks.allocate_id();
+            tasks.push(Task::new("@alice:example.org".into(), id, title.into()));
+        }
+
+        let ours: OwnedRoomId = "!ours:example.org".try_into().unwrap();
+        let json = serde_json::to_string(&storage.room_data(&ours).await).unwrap();
+        assert!(json.contains("Buy milk"));
+        assert!(!json.contains("Secret plan"));
+        assert!(!json.contains("theirs"));
+    }
+
+    async fn storage_with_task(title: &str) -> (StorageManager, OwnedRoomId) {
+        let storage = test_storage(10);
+        let room_id: OwnedRoomId = "!ours:example.org".try_into().unwrap();
+        let mut todo_lists = storage.todo_lists.lock().await;
+        let tasks = todo_lists.entry(room_id.clone()).or_default();
+        let id = tasks.allocate_id();
+        tasks.push(Task::new("@alice:example.org".into(), id, title.into()));
+        drop(todo_lists);
+        (storage, room_id)
+    }
+
+    #[tokio::test]
+    async fn rewriting_a_room_file_never_leaves_a_mismatched_checksum() {
+        let (storage, room_id) = storage_with_task("Buy milk").await;
+        storage.write_room(&room_id).await.unwrap();
+        let path = storage.room_file_path(&room_id);
+        assert!(checksum::checksum_path(&path).exists());
+
+        // A crash right after the new data lands leaves it without a sidecar,
+        // which loads unverified instead of failing the checksum
+        tokio::fs::remove_file(checksum::checksum_path(&path))
+            .await
+            .unwrap();
+        storage.todo_lists.lock().await.clear();
+        assert_eq!(storage.load_rooms().await.unwrap(), 1);
+        assert!(storage.todo_lists.lock().await.contains_key(&room_id));
+    }
+
+    #[tokio::test]
+    async fn a_room_file_that_fails_to_load_is_not_overwritten() {
+        let (storage, room_id) = storage_with_task("Buy milk").await;
+        storage.write_room(&room_id).await.unwrap();
+        let path = storage.room_file_path(&room_id);
+        tokio::fs::write(checksum::checksum_path(&path), "0".repeat(64))
+            .await
+            .unwrap();
+        let saved = tokio::fs::read_to_string(&path).await.unwrap();
+
+        storage.todo_lists.lock().await.clear();
+        assert_eq!(storage.load_rooms().await.unwrap(), 0);
+        assert!(storage.write_room(&room_id).await.is_err());
+        assert_eq!(tokio::fs::read_to_string(&path).await.unwrap(), saved);
+
+        // Once the file is removed the room can be saved again
+        tokio::fs::remove_file(&path).await.unwrap();
+        storage.write_room(&room_id).await.unwrap();
+    }
+}
@@ -0,0 +1,106 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::atomic::Ordering;
+use tracing::{debug, error, info, warn};
+
+use super::{StorageManager, checksum};
+use crate::task_management::{RoomTaskList, Task};
+
+// Personal lists get one file per user here in per-room mode
+pub const PERSONAL_DIR: &str = "personal";
+
+/// A user's personal list as stored in its own file or JSON Lines line
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PersonalData {
+    pub user_id: String,
+    pub next_id: usize,
+    pub tasks: Vec<Task>,
+}
+
+impl PersonalData {
+    pub fn into_list(self) -> (String, RoomTaskList) {
+        (self.user_id, RoomTaskList::new(self.tasks, self.next_id))
+    }
+}
+
+impl StorageManager {
+    pub fn personal_dir(&self) -> PathBuf {
+        self.data_dir.join(PERSONAL_DIR)
+    }
+
+    fn personal_file_path(&self, user: &str) -> PathBuf {
+        let name: String = user
+            .chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+            .collect();
+        self.personal_dir().join(format!("{}.json", name))
+    }
+
+    /// Persist a user's personal list right away: its own file in per-room mode,
+    /// otherwise a full save. Personal lists don't wait for the save debounce.
+    pub async fn save_personal(&self, user: &str) -> Result<()> {
+        if !self.per_room {
+            return self.save().await.map(|_| ());
+        }
+        self.generation.fetch_add(1, Ordering::SeqCst);
+        self.write_personal_file(user).await.inspect_err(|e| {
+            error!(user, error = %e, "Failed to save personal list");
+        })
+    }
+
+    pub(super) async fn write_personal_file(&self, user: &str) -> Result<()> {
+        let list = self
+            .personal_lists
+            .lock()
+            .await
+            .get(user)
+            .cloned()
+            .unwrap_or_default();
+        let data = PersonalData {
+            user_id: user.to_owned(),
+            next_id: list.next_id,
+            tasks: list.tasks,
+        };
+        let path = self.personal_file_path(user);
+        tokio::fs::create_dir_all(self.personal_dir()).await?;
+        let json = serde_json::to_string_pretty(&data)?;
+        checksum::write_with_checksum(&path, json.as_bytes())
+            .await
+            .with_context(|| format!("Failed to save personal file: {:?}", path))?;
+        debug!(user, file_path = %path.display(), "Saved personal list");
+        Ok(())
+    }
+
+    /// Load every personal file, skipping unreadable ones. Returns how many loaded.
+    pub(super) async fn load_personal_files(&self) -> Result<usize> {
+        let dir = self.personal_dir();
+        if !dir.exists() {
+            return Ok(0);
+        }
+        let mut loaded = 0;
+        for entry in std::fs::read_dir(&dir)? {
+            let path = entry?.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let data = async {
+                let content = tokio::fs::read_to_string(&path).await?;
+                checksum::verify_checksum(&path, content.as_bytes()).await?;
+                anyhow::Ok(serde_json::from_str::<PersonalData>(&content)?)
+            };
+            match data.await {
+                Ok(data) => {
+                    let (user, list) = data.into_list();
+                    self.personal_lists.lock().await.insert(user, list);
+                    loaded += 1;
+                }
+                Err(e) => {
+                    warn!(file_path = %path.display(), "Skipping unreadable personal file: {:#}", e)
+                }
+            }
+        }
+        info!(users = loaded, "Loaded personal lists");
+        Ok(loaded)
+    }
+}
@@ -0,0 +1,76 @@
+use matrix_sdk::ruma::OwnedRoomId;
+use std::collections::HashSet;
+use std::sync::atomic::Ordering;
+use tracing::{info, warn};
+
+use super::StorageManager;
+
+/// What `StorageManager::reindex` repaired in a room
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ReindexReport {
+    /// (old ID, new ID) for tasks that had an ID already taken in the room, or 0
+    pub renumbered: Vec<(usize, usize)>,
+    /// The ID counter was behind the highest ID and was moved past it
+    pub counter_fixed: Option<(usize, usize)>,
+}
+
+impl ReindexReport {
+    pub fn is_clean(&self) -> bool {
+        self.renumbered.is_empty() && self.counter_fixed.is_none()
+    }
+}
+
+impl StorageManager {
+    /// Repair a room's task IDs: give duplicate or zero IDs fresh ones (the first task
+    /// keeps a shared ID) and move the ID counter past the highest ID in use.
+    /// The room is not saved here.
+    pub async fn reindex(&self, room_id: &OwnedRoomId) -> ReindexReport {
+        let mut report = ReindexReport::default();
+        let mut todo_lists = self.todo_lists.lock().await;
+        let Some(list) = todo_lists.get_mut(room_id) else {
+            return report;
+        };
+
+        let max_id = list.iter().map(|t| t.id).max().unwrap_or(0);
+        if list.next_id <= max_id {
+            let fixed = max_id + 1;
+            report.counter_fixed = Some((list.next_id, fixed));
+            list.next_id = fixed;
+        }
+
+        let mut seen = HashSet::new();
+        let mut next_id = list.next_id;
+        for task in list.iter_mut() {
+            if task.id != 0 && seen.insert(task.id) {
+                continue;
+            }
+            report.renumbered.push((task.id, next_id));
+            task.id = next_id;
+            seen.insert(next_id);
+            next_id += 1;
+        }
+        list.next_id = next_id;
+        // A shared ID stays with the task that kept it, and so does its history
+        let moves: Vec<(usize, usize)> = report
+            .renumbered
+            .iter()
+            .filter(|(old, _)| !list.iter().any(|t| t.id == *old))
+            .copied()
+            .collect();
+        drop(todo_lists);
+        if let Err(e) = self.rekey_cold_history(room_id, &moves).await {
+            warn!(room_id = %room_id, error = %e, "Failed to move offloaded history of renumbered tasks");
+        }
+
+        if !report.is_clean() {
+            self.generation.fetch_add(1, Ordering::SeqCst);
+            info!(
+                room_id = %room_id,
+                renumbered = report.renumbered.len(),
+                counter_fixed = report.counter_fixed.is_some(),
+                "Reindexed room tasks"
+            );
+        }
+        report
+    }
+}
@@ -0,0 +1,47 @@
+use chrono::{DateTime, Duration, NaiveTime, Utc};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// UTC time of the daily save, changed at runtime by `!bot schedule`
+pub type SharedSaveSchedule = Arc<Mutex<Option<NaiveTime>>>;
+
+/// `HH:MM` in UTC
+pub fn parse_save_time(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value.trim(), "%H:%M").ok()
+}
+
+/// The first time after `now` the clock shows `time`
+pub fn next_save_at(now: DateTime<Utc>, time: NaiveTime) -> DateTime<Utc> {
+    let today = now.date_naive().and_time(time).and_utc();
+    if today > now {
+        today
+    } else {
+        today + Duration::days(1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn next_save_is_later_today_or_tomorrow() {
+        let at = |s: &str| DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc);
+        let three = parse_save_time("03:00").unwrap();
+        assert_eq!(
+            next_save_at(at("2024-05-01T02:59:00Z"), three),
+            at("2024-05-01T03:00:00Z")
+        );
+        // Right at the time the save has just happened, the next one is tomorrow
+        assert_eq!(
+            next_save_at(at("2024-05-01T03:00:00Z"), three),
+            at("2024-05-02T03:00:00Z")
+        );
+        assert_eq!(
+            next_save_at(at("2024-12-31T23:00:00Z"), three),
+            at("2025-01-01T03:00:00Z")
+        );
+        assert_eq!(parse_save_time("25:00"), None);
+        assert_eq!(parse_save_time("3pm"), None);
+    }
+}
@@ -0,0 +1,62 @@
+use anyhow::{Context, Result};
+use std::path::Path;
+use tracing::info;
+use uuid::Uuid;
+
+use super::atomic::write_atomic;
+
+/// File in the data directory holding the storage session ID across restarts
+pub const SESSION_ID_FILE: &str = "storage_session_id";
+
+/// The storage session ID saved in `data_dir`, or a new one written there.
+/// Save file names carry it, so reusing it keeps earlier saves discoverable.
+pub async fn load_or_create(data_dir: &Path) -> Result<Uuid> {
+    let path = data_dir.join(SESSION_ID_FILE);
+    match tokio::fs::read_to_string(&path).await {
+        Ok(content) => Uuid::parse_str(content.trim())
+            .with_context(|| format!("Invalid storage session ID in {:?}", path)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            let session_id = Uuid::new_v4();
+            write_atomic(&path, session_id.to_string().as_bytes()).await?;
+            info!(session_id = %session_id, "Created new storage session ID");
+            Ok(session_id)
+        }
+        Err(e) => Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::StorageManager;
+
+    fn storage(dir: &Path, session_id: Uuid) -> StorageManager {
+        StorageManager::new(
+            dir.to_owned(),
+            session_id,
+            None,
+            false,
+            None,
+            0,
+            Default::default(),
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn saves_from_one_run_are_listed_in_the_next() {
+        let dir = std::env::temp_dir().join(format!("asmith-session-{}", Uuid::new_v4()));
+        tokio::fs::create_dir_all(&dir).await.unwrap();
+
+        let first_run = load_or_create(&dir).await.unwrap();
+        let saved = storage(&dir, first_run).save().await.unwrap();
+
+        let next_run = load_or_create(&dir).await.unwrap();
+        assert_eq!(next_run, first_run);
+        assert_eq!(
+            storage(&dir, next_run).list_saved_files().unwrap(),
+            vec![saved]
+        );
+        tokio::fs::remove_dir_all(&dir).await.unwrap();
+    }
+}
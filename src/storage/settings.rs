@@ -0,0 +1,180 @@
+use chrono::{Duration, NaiveDate, Utc};
+use matrix_sdk::ruma::OwnedRoomId;
+use serde::{Deserialize, Serialize};
+
+use super::StorageManager;
+use crate::task_management::list_view::ListSort;
+
+/// How `!list` decorates each task with quick actions in its HTML body
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum QuickActionStyle {
+    #[default]
+    Off,
+    Commands,
+    Links,
+}
+
+impl QuickActionStyle {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "off" => Some(Self::Off),
+            "commands" => Some(Self::Commands),
+            "links" => Some(Self::Links),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Off => "off",
+            Self::Commands => "commands",
+            Self::Links => "links",
+        }
+    }
+}
+
+/// Which kind of message body the bot sends in a room
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ResponseFormat {
+    /// Plain text only, the HTML body is dropped
+    Plain,
+    /// Plain text with an HTML body when one is available
+    #[default]
+    Html,
+    /// The plain text body rendered as Markdown
+    Markdown,
+}
+
+impl ResponseFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "plain" => Some(Self::Plain),
+            "html" => Some(Self::Html),
+            "markdown" | "md" => Some(Self::Markdown),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Plain => "plain",
+            Self::Html => "html",
+            Self::Markdown => "markdown",
+        }
+    }
+}
+
+/// How messages are worded and decorated
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderStyle {
+    /// Emoji, Markdown emphasis and an HTML body
+    #[default]
+    Standard,
+    /// Screen-reader friendly: no decorative emoji or markup, plain text only
+    Plain,
+}
+
+impl RenderStyle {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "standard" | "default" => Some(Self::Standard),
+            "plain" => Some(Self::Plain),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Standard => "standard",
+            Self::Plain => "plain",
+        }
+    }
+}
+
+/// Per-room preferences, persisted alongside the task lists
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RoomSettings {
+    #[serde(default)]
+    pub quick_actions: QuickActionStyle,
+    #[serde(default)]
+    pub response_format: ResponseFormat,
+    /// Mirror the task list into `io.asmith.tasklist` state events
+    #[serde(default)]
+    pub board_mirror: bool,
+    #[serde(default)]
+    pub render_style: RenderStyle,
+    /// The room's timezone as an offset from UTC, used for dates in templates
+    #[serde(default)]
+    pub utc_offset_minutes: i32,
+    /// Order of `!list`, set with `!list sort`
+    #[serde(default)]
+    pub list_sort: ListSort,
+    /// Users who don't want to watch the tasks they create or get assigned
+    #[serde(default)]
+    pub auto_watch_opt_out: Vec<String>,
+}
+
+impl RoomSettings {
+    /// Current date in the room's timezone
+    pub fn today(&self) -> NaiveDate {
+        (Utc::now() + Duration::minutes(self.utc_offset_minutes.into())).date_naive()
+    }
+}
+
+/// Parse a UTC offset like `+02:00`, `-5`, `+0530` or `UTC` into minutes
+pub fn parse_utc_offset(value: &str) -> Option<i32> {
+    let value = value.trim();
+    let value = value
+        .strip_prefix("UTC")
+        .or_else(|| value.strip_prefix("utc"))
+        .unwrap_or(value);
+    if value.is_empty() {
+        return Some(0);
+    }
+    let (sign, digits) = match value.chars().next()? {
+        '+' => (1, &value[1..]),
+        '-' => (-1, &value[1..]),
+        _ => (1, value),
+    };
+    let (hours, minutes) = match digits.split_once(':') {
+        Some((h, m)) => (h.parse::<i32>().ok()?, m.parse::<i32>().ok()?),
+        None if digits.len() == 4 => (digits[..2].parse().ok()?, digits[2..].parse().ok()?),
+        None => (digits.parse().ok()?, 0),
+    };
+    if hours > 14 || minutes >= 60 {
+        return None;
+    }
+    Some(sign * (hours * 60 + minutes))
+}
+
+pub fn format_utc_offset(minutes: i32) -> String {
+    let sign = if minutes < 0 { '-' } else { '+' };
+    format!(
+        "UTC{}{:02}:{:02}",
+        sign,
+        minutes.abs() / 60,
+        minutes.abs() % 60
+    )
+}
+
+impl StorageManager {
+    pub async fn room_settings(&self, room_id: &OwnedRoomId) -> RoomSettings {
+        self.room_settings
+            .lock()
+            .await
+            .get(room_id)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    pub async fn update_room_settings<F>(&self, room_id: &OwnedRoomId, update: F)
+    where
+        F: FnOnce(&mut RoomSettings),
+    {
+        let mut settings = self.room_settings.lock().await;
+        update(settings.entry(room_id.clone()).or_default());
+    }
+}
@@ -0,0 +1,66 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use tracing::debug;
+
+use super::StorageManager;
+
+// Templates are shared by all rooms, so they live outside the per-session save files
+pub const TEMPLATES_FILE: &str = "templates.json";
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct TemplateStore {
+    /// Template name -> task titles
+    #[serde(default)]
+    pub templates: BTreeMap<String, Vec<String>>,
+    /// Template applied to rooms the bot joins, if any
+    #[serde(default)]
+    pub on_join: Option<String>,
+}
+
+const MAX_TEMPLATE_NAME_LEN: usize = 64;
+
+/// Template names are ASCII letters, digits and dashes
+pub fn validate_template_name(name: &str) -> Result<(), String> {
+    if name.is_empty() {
+        return Err("Template names cannot be empty.".to_owned());
+    }
+    if name.len() > MAX_TEMPLATE_NAME_LEN {
+        return Err(format!(
+            "Template names can be at most {} characters long.",
+            MAX_TEMPLATE_NAME_LEN
+        ));
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err("Template names may only contain letters, digits and dashes.".to_owned());
+    }
+    Ok(())
+}
+
+impl StorageManager {
+    fn templates_path(&self) -> PathBuf {
+        self.data_dir.join(TEMPLATES_FILE)
+    }
+
+    pub async fn load_templates(&self) -> Result<TemplateStore> {
+        let path = self.templates_path();
+        if !path.exists() {
+            return Ok(TemplateStore::default());
+        }
+        let content = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("Failed to read templates file: {:?}", path))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse templates file: {:?}", path))
+    }
+
+    pub async fn save_templates(&self, store: &TemplateStore) -> Result<()> {
+        let path = self.templates_path();
+        tokio::fs::write(&path, serde_json::to_string_pretty(store)?)
+            .await
+            .with_context(|| format!("Failed to write templates file: {:?}", path))?;
+        debug!(file_path = %path.display(), "Saved templates");
+        Ok(())
+    }
+}
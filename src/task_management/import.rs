@@ -0,0 +1,185 @@
+//! Parsing of `!import` payloads: a JSON array of titles or a Markdown checklist,
+//! and of the JSON task objects taken by `!bot import`.
+
+use serde::Deserialize;
+
+use super::{Task, TaskStatus};
+
+/// Most tasks a single `!import` may create
+pub const MAX_IMPORT_TASKS: usize = 100;
+
+/// One entry of a `!bot import` payload
+#[derive(Debug, Clone, Deserialize)]
+pub struct TaskImport {
+    pub title: String,
+    #[serde(default)]
+    pub status: Option<String>,
+    #[serde(default)]
+    pub logs: Option<Vec<String>>,
+}
+
+impl TaskImport {
+    /// The task as created by `creator`, with the entry's status and logs applied.
+    /// Statuses are checked by `parse_task_imports`, one that doesn't parse is ignored.
+    pub fn into_task(self, creator: &str, id: usize) -> Task {
+        let mut task = Task::new(creator.to_owned(), id, self.title.trim().to_owned());
+        for log in self.logs.unwrap_or_default() {
+            task.add_log(creator.to_owned(), log);
+        }
+        if let Some(status) = self.status.as_deref().and_then(TaskStatus::parse)
+            && status != task.status
+        {
+            task.set_status(creator.to_owned(), status, Some("imported"));
+        }
+        task
+    }
+}
+
+/// Entries of a `!bot import` payload: a JSON array of `{"title", "status", "logs"}`
+/// objects. Every entry needs a title and a known status, if it has one.
+pub fn parse_task_imports(payload: &str) -> Result<Vec<TaskImport>, String> {
+    let payload = payload.trim();
+    if payload.is_empty() {
+        return Err("Nothing to import.".to_owned());
+    }
+    let entries: Vec<TaskImport> = serde_json::from_str(payload)
+        .map_err(|e| format!("Invalid JSON, expected an array of task objects: {}", e))?;
+    if entries.is_empty() {
+        return Err("The array has no tasks.".to_owned());
+    }
+    for (idx, entry) in entries.iter().enumerate() {
+        if entry.title.trim().is_empty() {
+            return Err(format!("Entry {} has an empty title.", idx + 1));
+        }
+        if let Some(status) = &entry.status
+            && TaskStatus::parse(status).is_none()
+        {
+            return Err(format!(
+                "Entry {} has an unknown status '{}'.",
+                idx + 1,
+                status
+            ));
+        }
+    }
+    Ok(entries)
+}
+
+/// Titles from a JSON array of strings, or from the top-level items of a Markdown
+/// list such as the one `!export md` writes. Headings, text and nested bullets are skipped.
+pub fn parse_import_payload(payload: &str) -> Result<Vec<String>, String> {
+    let payload = payload.trim();
+    if payload.is_empty() {
+        return Err("Nothing to import.".to_owned());
+    }
+    let titles = if payload.starts_with('[') {
+        serde_json::from_str::<Vec<String>>(payload)
+            .map_err(|e| format!("Invalid JSON, expected an array of task titles: {}", e))?
+    } else {
+        payload.lines().filter_map(markdown_item).collect()
+    };
+    let titles: Vec<String> = titles
+        .into_iter()
+        .map(|t| t.trim().to_owned())
+        .filter(|t| !t.is_empty())
+        .collect();
+    if titles.is_empty() {
+        return Err("No tasks found. Send a JSON array of titles or a Markdown list.".to_owned());
+    }
+    Ok(titles)
+}
+
+/// Title of a top-level Markdown list item, without checkbox, `#id` prefix or escapes
+fn markdown_item(line: &str) -> Option<String> {
+    if line.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let item = if let Some(rest) = line
+        .strip_prefix("- ")
+        .or_else(|| line.strip_prefix("* "))
+        .or_else(|| line.strip_prefix("+ "))
+    {
+        rest
+    } else {
+        let (number, rest) = line.split_once(". ")?;
+        if number.is_empty() || !number.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        rest
+    };
+    let item = ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find_map(|checkbox| item.strip_prefix(checkbox))
+        .unwrap_or(item);
+    // `!export md` writes `#<id> <title>`
+    let item = match item.strip_prefix('#').and_then(|r| r.split_once(' ')) {
+        Some((id, title)) if id.chars().all(|c| c.is_ascii_digit()) => title,
+        _ => item,
+    };
+    Some(unescape_markdown(item))
+}
+
+fn unescape_markdown(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => unescaped.extend(chars.next()),
+            c => unescaped.push(c),
+        }
+    }
+    unescaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_json_array() {
+        let titles = parse_import_payload(r#"["Buy milk", " ", "Call Bob"]"#).unwrap();
+        assert_eq!(titles, vec!["Buy milk", "Call Bob"]);
+    }
+
+    #[test]
+    fn parses_markdown_export() {
+        let payload = "# To-Do List\n\n## Pending\n\n- [ ] #3 Fix \\*urgent\\* bug\n    - a log entry\n- [x] Ship 1\\.4\n2. Numbered item";
+        let titles = parse_import_payload(payload).unwrap();
+        assert_eq!(
+            titles,
+            vec!["Fix *urgent* bug", "Ship 1.4", "Numbered item"]
+        );
+    }
+
+    #[test]
+    fn task_imports_apply_status_and_logs() {
+        let entries = parse_task_imports(
+            r#"[{"title": "Ship", "status": "in-progress", "logs": ["a", "b"]}, {"title": "Plain"}]"#,
+        )
+        .unwrap();
+        let task = entries[0].clone().into_task("@bot:example.org", 4);
+        assert_eq!((task.id, task.title.as_str()), (4, "Ship"));
+        assert_eq!(task.status, TaskStatus::InProgress);
+        let logs: Vec<&str> = task.logs.iter().map(|log| log.text.as_str()).collect();
+        assert_eq!(logs, vec!["a", "b"]);
+        assert_eq!(task.creator, "@bot:example.org");
+        let plain = entries[1].clone().into_task("@bot:example.org", 5);
+        assert_eq!(plain.status, TaskStatus::Pending);
+        assert!(plain.logs.is_empty());
+    }
+
+    #[test]
+    fn task_imports_reject_bad_entries() {
+        assert!(parse_task_imports("[]").is_err());
+        assert!(parse_task_imports(r#"["just a title"]"#).is_err());
+        assert!(parse_task_imports(r#"[{"title": " "}]"#).is_err());
+        let err = parse_task_imports(r#"[{"title": "A"}, {"title": "B", "status": "later"}]"#)
+            .unwrap_err();
+        assert_eq!(err, "Entry 2 has an unknown status 'later'.");
+    }
+
+    #[test]
+    fn rejects_payload_without_tasks() {
+        assert!(parse_import_payload("just some text").is_err());
+        assert!(parse_import_payload("[1, 2]").is_err());
+    }
+}
@@ -0,0 +1,251 @@
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::{Task, TaskStatus};
+
+/// Which tasks a `!list` shows
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ListFilter {
+    /// Everything but closed tasks
+    #[default]
+    Open,
+    All,
+    Status(TaskStatus),
+}
+
+impl ListFilter {
+    /// `all` or a status keyword such as `pending` or `in-progress`
+    pub fn parse(input: &str) -> Option<Self> {
+        if input.trim().eq_ignore_ascii_case("all") {
+            return Some(ListFilter::All);
+        }
+        TaskStatus::parse(input).map(ListFilter::Status)
+    }
+
+    /// The `!list` invocation showing this filter, for page hints
+    pub fn command(&self) -> String {
+        match self {
+            ListFilter::Open => "!list".to_owned(),
+            ListFilter::All => "!list all".to_owned(),
+            ListFilter::Status(status) => format!("!list {}", status),
+        }
+    }
+
+    pub fn shows(&self, task: &Task) -> bool {
+        match self {
+            ListFilter::Open => task.status != TaskStatus::Closed,
+            ListFilter::All => true,
+            ListFilter::Status(status) => task.status == *status,
+        }
+    }
+}
+
+/// Order of the tasks in `!list`, remembered per room by `!list sort`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListSort {
+    /// Grouped by status, in the room's order within a group
+    #[default]
+    Status,
+    /// The room's order as set with `!move`, top first
+    Priority,
+    /// Oldest first
+    Age,
+    /// Earliest due date first, tasks without one last
+    Due,
+    /// By assignee, unassigned tasks last
+    Assignee,
+}
+
+impl ListSort {
+    pub const ALL: [ListSort; 5] = [
+        ListSort::Status,
+        ListSort::Priority,
+        ListSort::Age,
+        ListSort::Due,
+        ListSort::Assignee,
+    ];
+
+    pub fn parse(input: &str) -> Option<Self> {
+        let input = input.trim();
+        Self::ALL
+            .into_iter()
+            .find(|sort| sort.as_str().eq_ignore_ascii_case(input))
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ListSort::Status => "status",
+            ListSort::Priority => "priority",
+            ListSort::Age => "age",
+            ListSort::Due => "due",
+            ListSort::Assignee => "assignee",
+        }
+    }
+
+    /// Sort tasks given in the room's order. Ties keep that order for `Status`
+    /// and go by ID otherwise.
+    pub fn sort(&self, tasks: &mut [&Task]) {
+        match self {
+            ListSort::Status => tasks.sort_by_key(|t| t.status.list_rank()),
+            ListSort::Priority => {}
+            ListSort::Age => tasks.sort_by_key(|t| (t.created_at, t.id)),
+            ListSort::Due => {
+                tasks.sort_by(|a, b| last_if_none(a.due_date, b.due_date).then(a.id.cmp(&b.id)))
+            }
+            ListSort::Assignee => tasks.sort_by(|a, b| {
+                last_if_none(a.assignee.as_ref(), b.assignee.as_ref()).then(a.id.cmp(&b.id))
+            }),
+        }
+    }
+}
+
+// Ascending, with `None` after every value
+fn last_if_none<T: Ord>(a: Option<T>, b: Option<T>) -> Ordering {
+    match (a, b) {
+        (Some(a), Some(b)) => a.cmp(&b),
+        (a, b) => a.is_none().cmp(&b.is_none()),
+    }
+}
+
+/// Task counts per status in list order, leaving out statuses without tasks
+fn status_counts(tasks: &[Task]) -> Vec<(TaskStatus, usize)> {
+    TaskStatus::LIST_ORDER
+        .iter()
+        .map(|status| {
+            (
+                *status,
+                tasks.iter().filter(|t| t.status == *status).count(),
+            )
+        })
+        .filter(|(_, count)| *count > 0)
+        .collect()
+}
+
+/// `12 pending · 3 in-progress · 5 done`, empty for no tasks
+pub fn status_summary(tasks: &[Task]) -> String {
+    status_counts(tasks)
+        .iter()
+        .map(|(status, count)| format!("{} {}", count, status))
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+/// `status_summary` as a table with a column per status
+pub fn status_summary_html(tasks: &[Task]) -> String {
+    let counts = status_counts(tasks);
+    if counts.is_empty() {
+        return String::new();
+    }
+    let (headers, cells): (Vec<String>, Vec<String>) = counts
+        .iter()
+        .map(|(status, count)| {
+            (
+                format!("<th>{} {}</th>", status.emoji(), status),
+                format!("<td>{}</td>", count),
+            )
+        })
+        .unzip();
+    format!(
+        "<table><tr>{}</tr><tr>{}</tr></table>",
+        headers.concat(),
+        cells.concat()
+    )
+}
+
+/// The last `!list` posted in a room and what it currently shows
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListView {
+    pub event_id: OwnedEventId,
+    pub page: usize,
+    pub filter: ListFilter,
+    pub text: String,
+    pub html: Option<String>,
+}
+
+/// In-memory list views per room, edited in place after task changes. Not
+/// persisted, after a restart the next `!list` starts a new view.
+#[derive(Debug, Clone, Default)]
+pub struct ListViews(Arc<Mutex<HashMap<OwnedRoomId, ListView>>>);
+
+impl ListViews {
+    pub async fn get(&self, room_id: &OwnedRoomId) -> Option<ListView> {
+        self.0.lock().await.get(room_id).cloned()
+    }
+
+    pub async fn set(&self, room_id: &OwnedRoomId, view: ListView) {
+        self.0.lock().await.insert(room_id.clone(), view);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tasks_with(statuses: &[TaskStatus]) -> Vec<Task> {
+        statuses
+            .iter()
+            .enumerate()
+            .map(|(idx, status)| {
+                let mut task = Task::new("@alice:example.org".to_owned(), idx + 1, "t".to_owned());
+                task.status = *status;
+                task
+            })
+            .collect()
+    }
+
+    #[test]
+    fn summary_counts_statuses_in_list_order() {
+        let tasks = tasks_with(&[
+            TaskStatus::Pending,
+            TaskStatus::Done,
+            TaskStatus::Pending,
+            TaskStatus::InProgress,
+        ]);
+        assert_eq!(status_summary(&tasks), "1 in-progress · 2 pending · 1 done");
+        assert_eq!(
+            status_summary_html(&tasks),
+            "<table><tr><th>🚧 in-progress</th><th>⏳ pending</th><th>✅ done</th></tr>\
+             <tr><td>1</td><td>2</td><td>1</td></tr></table>"
+        );
+        assert_eq!(status_summary(&[]), "");
+        assert_eq!(status_summary_html(&[]), "");
+    }
+
+    #[test]
+    fn filters_parse_status_keywords() {
+        assert_eq!(ListFilter::parse("ALL"), Some(ListFilter::All));
+        assert_eq!(
+            ListFilter::parse("in_progress"),
+            Some(ListFilter::Status(TaskStatus::InProgress))
+        );
+        assert_eq!(ListFilter::parse("urgent"), None);
+        assert_eq!(ListFilter::Status(TaskStatus::Done).command(), "!list done");
+    }
+
+    #[test]
+    fn sorts_break_ties_by_id() {
+        let mut tasks = tasks_with(&[TaskStatus::Done, TaskStatus::Pending, TaskStatus::Pending]);
+        tasks[0].due_date = chrono::NaiveDate::from_ymd_opt(2024, 5, 2);
+        tasks[2].due_date = chrono::NaiveDate::from_ymd_opt(2024, 5, 1);
+        tasks[1].assignee = Some("@zoe:example.org".to_owned());
+        tasks[0].assignee = Some("@bob:example.org".to_owned());
+        // Listed in the room's order, which isn't the ID order
+        let manual = [&tasks[2], &tasks[0], &tasks[1]];
+        let ids = |sort: ListSort| {
+            let mut sorted = manual.to_vec();
+            sort.sort(&mut sorted);
+            sorted.iter().map(|t| t.id).collect::<Vec<_>>()
+        };
+        assert_eq!(ids(ListSort::Status), [3, 2, 1]);
+        assert_eq!(ids(ListSort::Priority), [3, 1, 2]);
+        assert_eq!(ids(ListSort::Due), [3, 1, 2]);
+        assert_eq!(ids(ListSort::Assignee), [1, 2, 3]);
+        assert_eq!(ListSort::parse("Due"), Some(ListSort::Due));
+        assert_eq!(ListSort::parse("size"), None);
+    }
+}
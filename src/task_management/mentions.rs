@@ -0,0 +1,56 @@
+//! Matrix user IDs mentioned in a task's title and logs, see `!notify`.
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::Task;
+
+// Localpart up to the colon, then the server name. Whitespace ends a mention so
+// "@bob and @alice:example.org" doesn't read as one ID.
+static MENTION_PATTERN: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r"@[^:\s]+:[a-zA-Z0-9.-]+").expect("valid mention pattern"));
+
+impl Task {
+    /// User IDs mentioned in the title or logs, first mention first, each once
+    pub fn mention_users(&self) -> Vec<String> {
+        let texts = std::iter::once(&self.title).chain(self.logs.iter().map(|log| &log.text));
+        let mut users: Vec<String> = Vec::new();
+        for text in texts {
+            for found in MENTION_PATTERN.find_iter(text) {
+                // A mention ending a sentence keeps the full stop out of the server name
+                let user = found.as_str().trim_end_matches('.');
+                if !users.iter().any(|u| u == user) {
+                    users.push(user.to_owned());
+                }
+            }
+        }
+        users
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_management::TaskLog;
+
+    #[test]
+    fn mentions_come_from_title_and_logs_once_each() {
+        let mut task = Task::new(
+            "@carol:example.org".into(),
+            1,
+            "Review with @bob:example.org".into(),
+        );
+        for text in [
+            "Blocked on @alice:matrix.org.",
+            "Asked @bob:example.org and @dave:example.org again",
+            "mail me at bob@example.org",
+        ] {
+            task.logs
+                .push(TaskLog::new("@carol:example.org".into(), text.into()));
+        }
+        assert_eq!(
+            task.mention_users(),
+            ["@bob:example.org", "@alice:matrix.org", "@dave:example.org"]
+        );
+    }
+}
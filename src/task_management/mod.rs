@@ -1,16 +1,54 @@
-use chrono::Utc;
-use matrix_sdk::ruma::OwnedRoomId;
-use serde::{Deserialize, Serialize};
+use chrono::{DateTime, Days, Duration, Months, NaiveDate, Utc};
+use matrix_sdk::ruma::{OwnedEventId, OwnedRoomId, UserId};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tracing::{debug, error, info, instrument, warn};
 
+pub mod import;
+pub mod list_view;
+pub mod mentions;
+pub mod personal;
+pub mod reminders;
+pub mod room_list;
+pub mod snooze;
+pub mod stats;
+pub mod task_log;
+pub mod template_vars;
+pub mod time_spent;
+pub mod transfer;
+pub mod undo;
+pub mod watchers;
+
+use list_view::{ListFilter, ListSort, ListView, ListViews, status_summary, status_summary_html};
+pub use room_list::RoomTaskList;
+pub use stats::RoomStats;
+pub use task_log::TaskLog;
+use time_spent::{format_time_spent, parse_time_spent};
+use undo::{UndoAction, UndoEntry, UndoHistory};
+use watchers::mention_watchers;
+
 // --- TaskEvent Constants ---
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub enum TaskEvent {
     Created,
     StatusUpdated,
-    LogAdded,
     TitleEdited,
+    TagAdded,
+    RecurrenceUpdated,
+    Moved,
+    SpunOff,
+    Assigned,
+    EstimateUpdated,
+    Archived,
+    Restored,
+    Reopened,
+    DependencyAdded,
+    TimeLogged,
+    Rescheduled,
+    Snoozed,
+    MovedFrom,
+    CopiedFrom,
 }
 
 impl TaskEvent {
@@ -18,21 +56,388 @@ impl TaskEvent {
         match self {
             TaskEvent::Created => "Created task",
             TaskEvent::StatusUpdated => "Updated status",
-            TaskEvent::LogAdded => "Added log",
             TaskEvent::TitleEdited => "Edited title",
+            TaskEvent::TagAdded => "Added tag",
+            TaskEvent::RecurrenceUpdated => "Updated recurrence",
+            TaskEvent::Moved => "Moved",
+            TaskEvent::SpunOff => "Spun off task",
+            TaskEvent::Assigned => "Changed assignee",
+            TaskEvent::EstimateUpdated => "Updated estimate",
+            TaskEvent::Archived => "Archived",
+            TaskEvent::Restored => "Restored from archive",
+            TaskEvent::Reopened => "Reopened task",
+            TaskEvent::DependencyAdded => "Added dependency",
+            TaskEvent::Rescheduled => "Rescheduled",
+            TaskEvent::TimeLogged => "Logged time",
+            TaskEvent::Snoozed => "Snoozed",
+            TaskEvent::MovedFrom => "Moved from",
+            TaskEvent::CopiedFrom => "Copied from",
         }
     }
 }
 
 // --- Task Struct ---
+// `remote = "Self"` keeps the derived impls as inherent functions, the trait impls
+// below wrap them to fill in timestamps that older save files don't have
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(remote = "Self")]
 pub struct Task {
     pub id: usize,
     pub title: String,
-    pub status: String,
-    pub logs: Vec<String>,
+    /// Lines after the first of a multi-line `!add`
+    #[serde(default)]
+    pub description: Option<String>,
+    pub status: TaskStatus,
+    pub logs: Vec<TaskLog>,
     pub internal_logs: Vec<(String, String, String)>, // (timestamp, user, log)
     pub creator: String,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[serde(default)]
+    pub due_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub recurrence: Option<Recurrence>,
+    /// Source of a task created with `!spin`: (task id, 1-based log number)
+    #[serde(default)]
+    pub spun_off_from: Option<(usize, usize)>,
+    /// Matrix user ID of the person responsible for the task
+    #[serde(default)]
+    pub assignee: Option<String>,
+    #[serde(default)]
+    pub estimate_hours: Option<f64>,
+    /// IDs of tasks in the same room that have to be finished first
+    #[serde(default)]
+    pub depends_on: Vec<usize>,
+    /// Total of the time logged with `!spent`
+    #[serde(default, with = "time_spent::serde_secs")]
+    pub time_spent: std::time::Duration,
+    /// Due date the last reminder was sent for, a new due date gets a new reminder
+    #[serde(default)]
+    pub reminded_for: Option<NaiveDate>,
+    /// Hidden from `!list` until then, see `!snooze`
+    #[serde(default)]
+    pub snoozed_until: Option<DateTime<Utc>>,
+    #[serde(default = "unknown_timestamp")]
+    pub created_at: DateTime<Utc>,
+    /// When the task was last marked done or closed, `None` while it is open
+    #[serde(default)]
+    pub completed_at: Option<DateTime<Utc>>,
+    /// Users mentioned in the confirmations of changes to the task, see `!watch`
+    #[serde(default)]
+    pub watchers: Vec<String>,
+}
+
+// Placeholder for a missing `created_at`, replaced while deserializing
+fn unknown_timestamp() -> DateTime<Utc> {
+    DateTime::<Utc>::MIN_UTC
+}
+
+impl Serialize for Task {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        Task::serialize(self, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Task {
+    /// Tasks saved before the timestamp fields existed get them from their history:
+    /// created at the first entry, completed at the last status change. Without a
+    /// usable history the creation time is the time of loading.
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut task = Task::deserialize(deserializer)?;
+        if task.created_at == unknown_timestamp() {
+            task.created_at = task
+                .internal_logs
+                .first()
+                .and_then(|(timestamp, _, _)| stats::parse_log_timestamp(timestamp))
+                .unwrap_or_else(Utc::now);
+        }
+        if task.completed_at.is_none() {
+            task.completed_at = task.finished_at();
+        }
+        Ok(task)
+    }
+}
+
+/// Where a task is in its lifecycle. Stored as a lowercase string so save files
+/// written when the status was free-form ("pending", "done", "closed") still load.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum TaskStatus {
+    #[default]
+    Pending,
+    InProgress,
+    Blocked,
+    Done,
+    Closed,
+}
+
+impl TaskStatus {
+    /// Order of the groups in `!list`: active work first, finished work last
+    pub const LIST_ORDER: [TaskStatus; 5] = [
+        TaskStatus::InProgress,
+        TaskStatus::Blocked,
+        TaskStatus::Pending,
+        TaskStatus::Done,
+        TaskStatus::Closed,
+    ];
+
+    pub fn parse(input: &str) -> Option<Self> {
+        match input
+            .trim()
+            .to_lowercase()
+            .replace(['_', ' '], "-")
+            .as_str()
+        {
+            "pending" => Some(TaskStatus::Pending),
+            "in-progress" | "inprogress" | "started" => Some(TaskStatus::InProgress),
+            "blocked" => Some(TaskStatus::Blocked),
+            "done" => Some(TaskStatus::Done),
+            "closed" => Some(TaskStatus::Closed),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Pending => "pending",
+            TaskStatus::InProgress => "in-progress",
+            TaskStatus::Blocked => "blocked",
+            TaskStatus::Done => "done",
+            TaskStatus::Closed => "closed",
+        }
+    }
+
+    pub fn emoji(&self) -> &'static str {
+        match self {
+            TaskStatus::Pending => "⏳",
+            TaskStatus::InProgress => "🚧",
+            TaskStatus::Blocked => "⛔",
+            TaskStatus::Done => "✅",
+            TaskStatus::Closed => "✖️",
+        }
+    }
+
+    /// Heading for this status' group in `!list`
+    pub fn label(&self) -> &'static str {
+        match self {
+            TaskStatus::Pending => "Pending",
+            TaskStatus::InProgress => "In progress",
+            TaskStatus::Blocked => "Blocked",
+            TaskStatus::Done => "Done",
+            TaskStatus::Closed => "Closed",
+        }
+    }
+
+    fn list_rank(&self) -> usize {
+        TaskStatus::LIST_ORDER
+            .iter()
+            .position(|s| s == self)
+            .unwrap_or(TaskStatus::LIST_ORDER.len())
+    }
+}
+
+impl std::fmt::Display for TaskStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for TaskStatus {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for TaskStatus {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        // Losing a whole save file over one odd status would be worse than resetting it
+        Ok(TaskStatus::parse(&value).unwrap_or_else(|| {
+            warn!(status = %value, "Unknown task status in saved data, treating it as pending");
+            TaskStatus::Pending
+        }))
+    }
+}
+
+/// How often a recurring task comes back after being marked done
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Recurrence {
+    Daily,
+    Weekly,
+    Monthly,
+    EveryDays(u32),
+}
+
+/// Longest interval `every N days` accepts, ten years
+pub const MAX_RECURRENCE_DAYS: u32 = 3650;
+
+impl Recurrence {
+    /// Parse `daily`, `weekly`, `monthly` or `every N days`, N up to `MAX_RECURRENCE_DAYS`
+    pub fn parse(input: &str) -> Option<Self> {
+        let words: Vec<String> = input.split_whitespace().map(|w| w.to_lowercase()).collect();
+        match words.iter().map(String::as_str).collect::<Vec<&str>>()[..] {
+            ["daily"] => Some(Recurrence::Daily),
+            ["weekly"] => Some(Recurrence::Weekly),
+            ["monthly"] => Some(Recurrence::Monthly),
+            ["every", n, "day" | "days"] => match n.parse::<u32>() {
+                Ok(1) => Some(Recurrence::Daily),
+                Ok(n) if (2..=MAX_RECURRENCE_DAYS).contains(&n) => Some(Recurrence::EveryDays(n)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            Recurrence::Daily => "daily".to_owned(),
+            Recurrence::Weekly => "weekly".to_owned(),
+            Recurrence::Monthly => "monthly".to_owned(),
+            Recurrence::EveryDays(n) => format!("every {} days", n),
+        }
+    }
+
+    /// The next due date after `from`, None past the last representable date.
+    /// Monthly clamps to the end of shorter months.
+    pub fn advance(&self, from: NaiveDate) -> Option<NaiveDate> {
+        match self {
+            Recurrence::Daily => from.checked_add_days(Days::new(1)),
+            Recurrence::Weekly => from.checked_add_days(Days::new(7)),
+            Recurrence::Monthly => from.checked_add_months(Months::new(1)),
+            Recurrence::EveryDays(n) => from.checked_add_days(Days::new((*n).into())),
+        }
+    }
+}
+
+pub const MAX_TAG_LENGTH: usize = 32;
+/// Where `!move` puts a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MoveTarget {
+    Top,
+    Bottom,
+    /// 1-based position in the room's list
+    Position(usize),
+}
+
+impl MoveTarget {
+    pub fn parse(input: &str) -> Option<Self> {
+        match input.to_lowercase().as_str() {
+            "top" => Some(MoveTarget::Top),
+            "bottom" => Some(MoveTarget::Bottom),
+            other => other.parse().ok().map(MoveTarget::Position),
+        }
+    }
+}
+
+/// Tasks shown per `!list` page
+pub const LIST_PAGE_SIZE: usize = 20;
+
+/// Room left in a `!list` event for the title, group headings and footer
+const LIST_FRAME_BYTES: usize = 1024;
+
+/// Ranges of `lines` per `!list` page: up to `LIST_PAGE_SIZE` lines, fewer when
+/// the page wouldn't fit in one event with each line in both bodies and its
+/// quick actions from `actions` in the HTML one.
+fn paginate_lines(lines: &[String], actions: &[String]) -> Vec<std::ops::Range<usize>> {
+    let budget = MAX_EVENT_CONTENT_BYTES - LIST_FRAME_BYTES;
+    let mut pages = Vec::new();
+    let mut start = 0;
+    let mut bytes = 0;
+    for (idx, line) in lines.iter().enumerate() {
+        // Plain line plus newline, HTML line with its actions plus `<br>`
+        let size = 2 * line.len() + actions.get(idx).map_or(0, String::len) + 5;
+        if idx > start && (idx - start == LIST_PAGE_SIZE || bytes + size > budget) {
+            pages.push(start..idx);
+            start = idx;
+            bytes = 0;
+        }
+        bytes += size;
+    }
+    if start < lines.len() {
+        pages.push(start..lines.len());
+    }
+    pages
+}
+
+/// Size of each code block `!export md` sends; escaping for HTML can grow a
+/// part a few times over, so this stays well below the event limit
+const MARKDOWN_EXPORT_PART_BYTES: usize = 8 * 1024;
+
+/// Archived tasks shown by `!archive list` without a count
+pub const ARCHIVE_LIST_DEFAULT: usize = 10;
+
+/// Title used to spot duplicates: lowercased, with whitespace runs collapsed to one space
+pub fn normalize_title(title: &str) -> String {
+    title
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Split a multi-line `!add` into the title (first line) and a description
+/// (the remaining lines), which is None when there are no further lines
+pub fn split_title_description(text: &str) -> (String, Option<String>) {
+    let text = text.trim();
+    let (title, rest) = text.split_once('\n').unwrap_or((text, ""));
+    let description = rest.trim();
+    (
+        title.trim().to_owned(),
+        (!description.is_empty()).then(|| description.to_owned()),
+    )
+}
+
+/// Characters of a log or title quoted in a task's history
+const HISTORY_EXCERPT_CHARS: usize = 30;
+
+/// Characters that belong to the character before them: combining marks,
+/// variation selectors, emoji skin tones and the zero width joiner
+fn extends_previous(c: char) -> bool {
+    matches!(c,
+        '\u{0300}'..='\u{036F}'
+        | '\u{1AB0}'..='\u{1AFF}'
+        | '\u{1DC0}'..='\u{1DFF}'
+        | '\u{20D0}'..='\u{20FF}'
+        | '\u{FE20}'..='\u{FE2F}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{1F3FB}'..='\u{1F3FF}'
+        | '\u{E0020}'..='\u{E007F}'
+        | '\u{200D}'
+    )
+}
+
+/// First `max_chars` characters of `text` followed by `...` when it is longer.
+/// Never cuts inside a UTF-8 sequence, and keeps combining marks and joined
+/// emoji sequences together with the character they belong to.
+fn truncate_text(text: &str, max_chars: usize) -> String {
+    let mut end = 0;
+    let mut after_joiner = false;
+    for (count, (index, c)) in text.char_indices().enumerate() {
+        if count >= max_chars && !after_joiner && !extends_previous(c) {
+            return format!("{}...", &text[..end]);
+        }
+        after_joiner = c == '\u{200D}';
+        end = index + c.len_utf8();
+    }
+    text.to_owned()
+}
+
+/// Normalize a user-supplied tag: strip a leading `#` and lowercase it.
+/// Returns an error message suitable for the user when the tag is invalid.
+pub fn normalize_tag(raw: &str) -> Result<String, String> {
+    let tag = raw.trim().trim_start_matches('#').to_lowercase();
+    if tag.is_empty() {
+        return Err("Tag cannot be empty.".to_owned());
+    }
+    if tag.chars().any(char::is_whitespace) {
+        return Err("Tags cannot contain whitespace.".to_owned());
+    }
+    if tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(format!(
+            "Tags can be at most {} characters long.",
+            MAX_TAG_LENGTH
+        ));
+    }
+    Ok(tag)
 }
 
 impl Task {
@@ -40,10 +445,24 @@ impl Task {
         let mut task = Task {
             id,
             title,
-            status: "pending".to_owned(),
+            description: None,
+            status: TaskStatus::Pending,
             logs: Vec::new(),
             internal_logs: Vec::new(),
             creator: sender.clone(),
+            tags: Vec::new(),
+            due_date: None,
+            recurrence: None,
+            spun_off_from: None,
+            assignee: None,
+            estimate_hours: None,
+            depends_on: Vec::new(),
+            time_spent: std::time::Duration::ZERO,
+            reminded_for: None,
+            snoozed_until: None,
+            created_at: Utc::now(),
+            completed_at: None,
+            watchers: Vec::new(),
         };
         task.add_internal_log(sender, TaskEvent::Created, None);
         task
@@ -65,54 +484,260 @@ impl Task {
     }
 
     pub fn add_log(&mut self, sender: String, log: String) {
-        self.logs.push(log.clone());
-        let truncated_log = if log.len() > 30 {
-            format!("'{}...'", &log[..30])
-        } else {
-            format!("'{}'", log)
-        };
-        self.add_internal_log(sender, TaskEvent::LogAdded, Some(truncated_log));
+        self.logs.push(TaskLog::new(sender, log));
+    }
+
+    /// Change the status, recording the transition (and why, if given) in the history
+    pub fn set_status(&mut self, sender: String, status: TaskStatus, reason: Option<&str>) {
+        let old_status = self.status;
+        self.status = status;
+        self.completed_at = matches!(status, TaskStatus::Done | TaskStatus::Closed).then(Utc::now);
+        let mut info = format!("from '{}' to '{}'", old_status, status);
+        if let Some(reason) = reason {
+            info.push_str(&format!(": {}", reason));
+        }
+        self.add_internal_log(sender, TaskEvent::StatusUpdated, Some(info));
+    }
+
+    /// When a done or closed task got that status, from the last status change in its
+    /// history. Fills in `completed_at` for tasks saved before it existed.
+    fn finished_at(&self) -> Option<DateTime<Utc>> {
+        self.finished_at_in(&self.internal_logs)
+    }
+
+    fn finished_at_in(&self, history: &[(String, String, String)]) -> Option<DateTime<Utc>> {
+        if !matches!(self.status, TaskStatus::Done | TaskStatus::Closed) {
+            return None;
+        }
+        let status_updated = TaskEvent::StatusUpdated.to_string_readable();
+        history
+            .iter()
+            .rev()
+            .find(|(_, _, action)| action.starts_with(status_updated))
+            .and_then(|(timestamp, _, _)| stats::parse_log_timestamp(timestamp))
+    }
+
+    /// Correct the timestamps filled in while loading from `offloaded`, the history
+    /// entries moved to the cold store before the task's own
+    pub fn backfill_timestamps(&mut self, offloaded: &[(String, String, String)]) {
+        if let Some(first) = offloaded
+            .first()
+            .and_then(|(timestamp, _, _)| stats::parse_log_timestamp(timestamp))
+            && first < self.created_at
+        {
+            self.created_at = first;
+        }
+        if self.completed_at.is_none() {
+            self.completed_at = self.finished_at_in(offloaded);
+        }
     }
 
-    pub fn set_status(&mut self, sender: String, status: String) {
-        let old_status = self.status.clone();
-        self.status = status.clone();
+    /// Back to pending after being done or closed
+    pub fn reopen(&mut self, sender: String) {
+        let old_status = self.status;
+        self.status = TaskStatus::Pending;
+        self.completed_at = None;
         self.add_internal_log(
             sender,
-            TaskEvent::StatusUpdated,
-            Some(format!("from '{}' to '{}'", old_status, status)),
+            TaskEvent::Reopened,
+            Some(format!("was '{}'", old_status)),
         );
     }
 
     pub fn set_title(&mut self, sender: String, title: String) {
-        let old_title = self.title.clone();
-        self.title = title.clone();
-        let truncated_old_title = if old_title.len() > 30 {
-            format!("'{}...'", &old_title[..30])
-        } else {
-            format!("'{}'", old_title)
-        };
-        let truncated_new_title = if title.len() > 30 {
-            format!("'{}...'", &title[..30])
-        } else {
-            format!("'{}'", title)
-        };
+        let info = format!(
+            "from '{}' to '{}'",
+            truncate_text(&self.title, HISTORY_EXCERPT_CHARS),
+            truncate_text(&title, HISTORY_EXCERPT_CHARS)
+        );
+        self.title = title;
+        self.add_internal_log(sender, TaskEvent::TitleEdited, Some(info));
+    }
+
+    /// Add an already normalized tag; returns false if the task already had it
+    pub fn add_tag(&mut self, sender: String, tag: String) -> bool {
+        if self.tags.contains(&tag) {
+            return false;
+        }
+        self.tags.push(tag.clone());
+        self.add_internal_log(sender, TaskEvent::TagAdded, Some(format!("#{}", tag)));
+        true
+    }
+
+    pub fn set_assignee(&mut self, sender: String, assignee: Option<String>) {
+        let info = assignee.clone().unwrap_or_else(|| "nobody".to_owned());
+        self.assignee = assignee;
+        self.add_internal_log(sender, TaskEvent::Assigned, Some(info));
+    }
+
+    pub fn set_estimate(&mut self, sender: String, hours: Option<f64>) {
+        self.estimate_hours = hours;
+        let info = hours.map_or("none".to_owned(), |h| format!("{}h", h));
+        self.add_internal_log(sender, TaskEvent::EstimateUpdated, Some(info));
+    }
+
+    /// Add to the time spent, recording who logged it
+    pub fn log_time(&mut self, sender: String, spent: std::time::Duration) {
+        self.time_spent += spent;
         self.add_internal_log(
             sender,
-            TaskEvent::TitleEdited,
+            TaskEvent::TimeLogged,
             Some(format!(
-                "from {} to {}",
-                truncated_old_title, truncated_new_title
+                "{} (total {})",
+                format_time_spent(spent),
+                format_time_spent(self.time_spent)
             )),
         );
     }
 
-    pub fn show_details(&self) -> String {
-        let mut details = vec![format!("**[{}] {}**", self.status, self.title)];
-        details.push(format!("Created by: {}", self.creator));
+    pub fn set_recurrence(&mut self, sender: String, recurrence: Option<Recurrence>) {
+        self.recurrence = recurrence;
+        let info = recurrence.map_or("off".to_owned(), |r| r.describe());
+        self.add_internal_log(sender, TaskEvent::RecurrenceUpdated, Some(info));
+    }
+
+    /// Next occurrence of a recurring task, due one period after the current due
+    /// date (or after today when the task had none). None if the task doesn't recur.
+    pub fn next_occurrence(&self, sender: String, id: usize) -> Option<Task> {
+        let recurrence = self.recurrence?;
+        let base = self.due_date.unwrap_or_else(|| Utc::now().date_naive());
+        let due_date = recurrence.advance(base)?;
+        let mut task = Task {
+            id,
+            title: self.title.clone(),
+            description: self.description.clone(),
+            status: TaskStatus::Pending,
+            logs: Vec::new(),
+            internal_logs: Vec::new(),
+            creator: self.creator.clone(),
+            tags: self.tags.clone(),
+            due_date: Some(due_date),
+            recurrence: Some(recurrence),
+            spun_off_from: None,
+            assignee: self.assignee.clone(),
+            estimate_hours: self.estimate_hours,
+            depends_on: Vec::new(),
+            time_spent: std::time::Duration::ZERO,
+            reminded_for: None,
+            snoozed_until: None,
+            created_at: Utc::now(),
+            completed_at: None,
+            watchers: self.watchers.clone(),
+        };
+        task.add_internal_log(
+            sender,
+            TaskEvent::Created,
+            Some(format!("spawned by recurrence of task #{}", self.id)),
+        );
+        Some(task)
+    }
+
+    /// Next occurrence of a recurring task that was just completed, with the
+    /// hand-over noted in this task's history. The schedule moves to the copy,
+    /// so completing this task again doesn't spawn another one. None if the
+    /// next due date is out of range.
+    pub fn regenerate(&mut self, sender: String, id: usize) -> Option<Task> {
+        let next = self.next_occurrence(sender.clone(), id)?;
+        self.recurrence = None;
+        let due = next
+            .due_date
+            .map(|d| d.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        self.add_internal_log(
+            sender,
+            TaskEvent::Rescheduled,
+            Some(format!("as task #{} due {}", id, due)),
+        );
+        Some(next)
+    }
+
+    /// Field lines shared by the plain text and HTML details, without the title.
+    /// `dependents` are the IDs of tasks that depend on this one.
+    fn detail_fields(&self, dependents: &[usize]) -> Vec<String> {
+        let mut fields = vec![
+            format!("Created by: {}", self.creator),
+            format!("Created: {}", self.created_at.format("%Y-%m-%d %H:%M UTC")),
+        ];
+        if let Some(completed_at) = self.completed_at {
+            fields.push(format!(
+                "Completed: {}",
+                completed_at.format("%Y-%m-%d %H:%M UTC")
+            ));
+        }
+
+        if !self.tags.is_empty() {
+            let tags = self
+                .tags
+                .iter()
+                .map(|t| format!("#{}", t))
+                .collect::<Vec<String>>()
+                .join(" ");
+            fields.push(format!("Tags: {}", tags));
+        }
+
+        if let Some(assignee) = &self.assignee {
+            fields.push(format!("Assigned to: {}", assignee));
+        }
+
+        if let Some(hours) = self.estimate_hours {
+            fields.push(format!("Estimate: {}h", hours));
+        }
+
+        if !self.time_spent.is_zero() {
+            fields.push(format!(
+                "Time spent: {}",
+                format_time_spent(self.time_spent)
+            ));
+        }
+
+        if let Some(due_date) = self.due_date {
+            fields.push(format!("Due: {}", due_date.format("%Y-%m-%d")));
+        }
+
+        if let Some(recurrence) = self.recurrence {
+            fields.push(format!("Repeats: {}", recurrence.describe()));
+        }
+
+        if let Some((source_id, log_number)) = self.spun_off_from {
+            fields.push(format!(
+                "Spun off from: task #{} (log {})",
+                source_id, log_number
+            ));
+        }
+
+        if !self.depends_on.is_empty() {
+            fields.push(format!("Depends on: {}", task_refs(&self.depends_on)));
+        }
+        if !dependents.is_empty() {
+            fields.push(format!("Required by: {}", task_refs(dependents)));
+        }
+
+        if !self.watchers.is_empty() {
+            fields.push(format!("Watchers: {}", self.watchers.join(", ")));
+        }
+
+        let mentioned = self.mention_users();
+        if !mentioned.is_empty() {
+            fields.push(format!("Mentioned users: {}", mentioned.join(", ")));
+        }
+        fields
+    }
+
+    /// History entries as `[<timestamp>] <user> — <action>`
+    fn history_lines(&self) -> impl Iterator<Item = String> + '_ {
+        self.internal_logs
+            .iter()
+            .map(|(timestamp, user, action)| format!("[{}] {} — {}", timestamp, user, action))
+    }
+
+    pub fn show_details(&self, dependents: &[usize]) -> String {
+        let mut details = vec![self.to_string_short()];
+        details.extend(self.description.clone());
+        details.extend(self.detail_fields(dependents));
 
         if !self.logs.is_empty() {
             details.push("\n**Logs:**".to_owned());
+            // Numbered, `!spin` refers to logs by position
             for (i, log) in self.logs.iter().enumerate() {
                 details.push(format!("{}. {}", i + 1, log));
             }
@@ -120,53 +745,377 @@ impl Task {
 
         if !self.internal_logs.is_empty() {
             details.push("\n**History:**".to_owned());
-            for (timestamp, user, action) in &self.internal_logs {
-                details.push(format!("• {} - {}: {}", timestamp, user, action));
-            }
+            details.extend(self.history_lines().map(|line| format!("• {}", line)));
         }
         details.join("\n")
     }
 
+    /// HTML counterpart of `show_details`, with logs and history as lists
+    pub fn show_details_html(&self, dependents: &[usize]) -> String {
+        let mut html = format!(
+            "{} <b>[{}] {}</b>",
+            self.status.emoji(),
+            self.status,
+            escape_html(&self.title)
+        );
+        if let Some(description) = &self.description {
+            html.push_str(&format!(
+                "<br>{}",
+                escape_html(description).replace('\n', "<br>")
+            ));
+        }
+        for field in self.detail_fields(dependents) {
+            html.push_str(&format!("<br>{}", escape_html(&field)));
+        }
+
+        if !self.logs.is_empty() {
+            html.push_str("<p><b>Logs:</b></p><ol>");
+            for log in &self.logs {
+                html.push_str(&format!("<li>{}</li>", escape_html(&log.to_string())));
+            }
+            html.push_str("</ol>");
+        }
+
+        if !self.internal_logs.is_empty() {
+            html.push_str("<p><b>History:</b></p><ul>");
+            for line in self.history_lines() {
+                html.push_str(&format!("<li>{}</li>", escape_html(&line)));
+            }
+            html.push_str("</ul>");
+        }
+        html
+    }
+
+    /// Checklist entry for the Markdown export, with the task's logs as nested bullets
+    pub fn to_markdown(&self) -> String {
+        let checkbox = match self.status {
+            TaskStatus::Done | TaskStatus::Closed => "[x]",
+            _ => "[ ]",
+        };
+        let mut entry = format!(
+            "- {} #{} {}",
+            checkbox,
+            self.id,
+            escape_markdown(&self.title)
+        );
+        if !self.tags.is_empty() {
+            let tags = self
+                .tags
+                .iter()
+                .map(|t| format!("`#{}`", t))
+                .collect::<Vec<String>>()
+                .join(" ");
+            entry.push_str(&format!(" {}", tags));
+        }
+        if let Some(due_date) = self.due_date {
+            entry.push_str(&format!(" (due {})", due_date.format("%Y-%m-%d")));
+        }
+        if let Some(assignee) = &self.assignee {
+            entry.push_str(&format!(" (assigned to {})", escape_markdown(assignee)));
+        }
+        for log in &self.logs {
+            entry.push_str(&format!("\n    - {}", escape_markdown(&log.to_string())));
+        }
+        entry
+    }
+
+    /// `⏳ **[pending] Title** (3d)`, the age being the time since creation
     pub fn to_string_short(&self) -> String {
-        format!("**[{}] {}**", self.status, self.title)
+        format!(
+            "{} **[{}] {}** ({})",
+            self.status.emoji(),
+            self.status,
+            self.title,
+            format_age(Utc::now() - self.created_at)
+        )
+    }
+}
+
+/// Largest whole unit of an age: `3d`, `5h` or `12m`
+fn format_age(age: Duration) -> String {
+    if age.num_days() > 0 {
+        format!("{}d", age.num_days())
+    } else if age.num_hours() > 0 {
+        format!("{}h", age.num_hours())
+    } else {
+        format!("{}m", age.num_minutes().max(0))
+    }
+}
+
+/// `#1, #2` style list of task IDs
+fn task_refs(ids: &[usize]) -> String {
+    ids.iter()
+        .map(|id| format!("#{}", id))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// HTML snippet appended to a `!list` line offering copyable/clickable task actions.
+/// Only ever used in the HTML body so the plain-text fallback stays uncluttered.
+pub fn quick_actions_html(
+    style: QuickActionStyle,
+    room_id: &OwnedRoomId,
+    task_id: usize,
+) -> String {
+    let actions = ["done", "details"];
+    match style {
+        QuickActionStyle::Off => String::new(),
+        QuickActionStyle::Commands => {
+            let snippets = actions
+                .iter()
+                .map(|action| format!("<code>!{} {}</code>", action, task_id))
+                .collect::<Vec<String>>()
+                .join(" ");
+            format!(" — {}", snippets)
+        }
+        QuickActionStyle::Links => {
+            // matrix: URIs open the room's composer in supporting clients, the command
+            // rides along for those that prefill it; the link text is the command
+            let room_uri = format!(
+                "matrix:roomid/{}?action=chat",
+                room_id.as_str().trim_start_matches('!')
+            );
+            let links = actions
+                .iter()
+                .map(|action| {
+                    let command = format!("!{} {}", action, task_id);
+                    let encoded: String =
+                        url::form_urlencoded::byte_serialize(command.as_bytes()).collect();
+                    format!(
+                        "<a href=\"{}&amp;command={}\">{}</a>",
+                        room_uri, encoded, command
+                    )
+                })
+                .collect::<Vec<String>>()
+                .join(" ");
+            format!(" — {}", links)
+        }
+    }
+}
+
+/// A parsed `!search` query: quoted phrases stay together, `status:<value>` filters
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SearchQuery {
+    pub terms: Vec<String>,
+    pub status: Option<String>,
+}
+
+impl SearchQuery {
+    pub fn parse(input: &str) -> Self {
+        let mut query = SearchQuery::default();
+        let mut tokens = Vec::new();
+        let mut current = String::new();
+        let mut in_quotes = false;
+
+        for c in input.chars() {
+            match c {
+                '"' => {
+                    in_quotes = !in_quotes;
+                    if !in_quotes && !current.is_empty() {
+                        tokens.push(std::mem::take(&mut current));
+                    }
+                }
+                c if c.is_whitespace() && !in_quotes => {
+                    if !current.is_empty() {
+                        tokens.push(std::mem::take(&mut current));
+                    }
+                }
+                c => current.push(c),
+            }
+        }
+        if !current.is_empty() {
+            tokens.push(current);
+        }
+
+        for token in tokens {
+            match token.strip_prefix("status:") {
+                Some(status) if !status.is_empty() => query.status = Some(status.to_lowercase()),
+                _ => query.terms.push(token.to_lowercase()),
+            }
+        }
+        query
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.terms.is_empty() && self.status.is_none()
+    }
+
+    /// Every term must appear in the title, a log entry, or the creator
+    pub fn matches(&self, task: &Task) -> bool {
+        if let Some(status) = &self.status
+            && TaskStatus::parse(status) != Some(task.status)
+        {
+            return false;
+        }
+        let title = task.title.to_lowercase();
+        let creator = task.creator.to_lowercase();
+        let logs: Vec<String> = task.logs.iter().map(|l| l.text.to_lowercase()).collect();
+        self.terms.iter().all(|term| {
+            title.contains(term) || creator.contains(term) || logs.iter().any(|l| l.contains(term))
+        })
+    }
+
+    /// Byte range of the first case-insensitive occurrence of any term in `text`
+    fn find_term(&self, text: &str) -> Option<(usize, usize)> {
+        for (start, _) in text.char_indices() {
+            for term in &self.terms {
+                let mut wanted = term.chars().peekable();
+                let mut end = start;
+                for c in text[start..].chars() {
+                    if wanted.peek().is_none() {
+                        break;
+                    }
+                    if !c.to_lowercase().all(|l| wanted.next_if_eq(&l).is_some()) {
+                        break;
+                    }
+                    end += c.len_utf8();
+                }
+                if wanted.peek().is_none() && end > start {
+                    return Some((start, end));
+                }
+            }
+        }
+        None
+    }
+
+    /// Short excerpt of the title or log entry that matched, as plain text and as
+    /// HTML with every matching term highlighted
+    pub fn snippet(&self, task: &Task) -> Option<(String, String)> {
+        const CONTEXT_CHARS: usize = 30;
+
+        let source = std::iter::once(&task.title)
+            .chain(task.logs.iter().map(|log| &log.text))
+            .find(|text| self.find_term(text).is_some())?;
+        let (first_start, first_end) = self.find_term(source)?;
+
+        let before: String = {
+            let chars: Vec<char> = source[..first_start].chars().collect();
+            let skip = chars.len().saturating_sub(CONTEXT_CHARS);
+            chars[skip..].iter().collect()
+        };
+        let from = first_start - before.len();
+        let to = source[first_end..]
+            .char_indices()
+            .nth(CONTEXT_CHARS)
+            .map_or(source.len(), |(i, _)| first_end + i);
+        let excerpt = &source[from..to];
+        let prefix = if from > 0 { "…" } else { "" };
+        let suffix = if to < source.len() { "…" } else { "" };
+
+        let mut html = String::new();
+        let mut rest = excerpt;
+        while let Some((start, end)) = self.find_term(rest) {
+            html.push_str(&escape_html(&rest[..start]));
+            html.push_str(&format!("<mark>{}</mark>", escape_html(&rest[start..end])));
+            rest = &rest[end..];
+        }
+        html.push_str(&escape_html(rest));
+
+        Some((
+            format!("{}{}{}", prefix, excerpt, suffix),
+            format!("{}{}{}", prefix, html, suffix),
+        ))
     }
 }
 
+/// Open work assigned to one user, see `TodoList::workload`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Workload {
+    pub user: String,
+    pub open_tasks: usize,
+    pub estimate_hours: f64,
+}
+
 // --- TodoList Struct ---
 #[derive(Clone)]
 pub struct TodoList {
     message_sender: Arc<dyn crate::messaging::MessageSender>,
     pub storage: Arc<StorageManager>,
+    pub undo: UndoHistory,
+    list_views: ListViews,
+    // Most tasks a room's list may hold, closed ones included
+    max_tasks_per_room: usize,
 }
 
-use crate::messaging::MessageSender;
+use crate::messaging::render::{escape_html, escape_markdown};
+use crate::messaging::split::split_text;
+use crate::messaging::{EditOutcome, MAX_EVENT_CONTENT_BYTES, MessageSender};
 use crate::storage::StorageManager;
+use crate::storage::settings::QuickActionStyle;
+use crate::storage::templates::validate_template_name;
 use anyhow::Result;
 
 impl TodoList {
-    pub fn new(message_sender: Arc<dyn MessageSender>, storage: Arc<StorageManager>) -> Self {
+    pub fn new(
+        message_sender: Arc<dyn MessageSender>,
+        storage: Arc<StorageManager>,
+        max_tasks_per_room: usize,
+    ) -> Self {
         Self {
             message_sender,
             storage,
+            undo: UndoHistory::default(),
+            list_views: ListViews::default(),
+            max_tasks_per_room,
         }
     }
 
+    /// Error for adding `adding` tasks to a list of `current` when that breaks the cap
+    fn room_full_message(&self, current: usize, adding: usize) -> Option<String> {
+        (current + adding > self.max_tasks_per_room).then(|| {
+            format!(
+                "⚠️ Error: This room's list holds {} of at most {} tasks. Use `!archive` to move finished tasks out, or `!bot cleartasks` to start over.",
+                current, self.max_tasks_per_room
+            )
+        })
+    }
+
     #[instrument(skip(self), fields(room_id = %room_id))]
     pub async fn add_task(
         &self,
         room_id: &OwnedRoomId,
         sender: String,
         task_title: String,
+        force: bool,
     ) -> Result<()> {
         debug!(user = %sender, "Starting add task operation");
+        let (task_title, description) = split_title_description(&task_title);
+        let auto_watch = self.auto_watches(room_id, &sender).await;
 
         // Create a lock on the todo lists and get the current task list for the room (or a new one)
         let mut todo_lists_lock = self.storage.todo_lists.lock().await;
         let room_tasks = todo_lists_lock.entry(room_id.clone()).or_default();
 
-        // Get the next task ID and create a new task
-        let next_id = room_tasks.len() + 1;
-        let task = Task::new(sender.clone(), next_id, task_title.clone());
+        // Two people adding the same thing shouldn't produce two open tasks
+        let normalized = normalize_title(&task_title);
+        if !force
+            && let Some(existing) = room_tasks.iter().find(|t| {
+                !matches!(t.status, TaskStatus::Done | TaskStatus::Closed)
+                    && normalize_title(&t.title) == normalized
+            })
+        {
+            let message = format!(
+                "⚠️ Task already exists as #{} ({}): {}\nUse `!add --force <title>` to add it anyway.",
+                existing.id, existing.status, existing.title
+            );
+            drop(todo_lists_lock);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
+        if let Some(message) = self.room_full_message(room_tasks.len(), 1) {
+            drop(todo_lists_lock);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
+        // Allocate a stable ID that is never reused in this room, even after closes
+        let next_id = room_tasks.allocate_id();
+        let mut task = Task::new(sender.clone(), next_id, task_title.clone());
+        task.description = description;
+        if auto_watch {
+            task.watch(&sender);
+        }
 
         info!(
             user = %sender,
@@ -191,7 +1140,17 @@ impl TodoList {
         self.send_matrix_message(room_id, &message, None).await?;
 
         debug!("Saving updated task list");
-        match self.storage.save().await {
+        drop(todo_lists_lock);
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(
+                    format!("add of task {}", next_id),
+                    vec![UndoAction::RemoveTasks(vec![next_id])],
+                ),
+            )
+            .await;
+        match self.storage.save_room(room_id).await {
             Ok(_) => {
                 info!(
                     user = %sender,
@@ -215,76 +1174,354 @@ impl TodoList {
         Ok(())
     }
 
-    pub async fn list_tasks(&self, room_id: &OwnedRoomId) -> Result<()> {
+    /// One page (1-based) of `!list` as plain text and HTML, showing the tasks `filter`
+    /// lets through. Notices such as an empty list have no HTML.
+    async fn render_list(
+        &self,
+        room_id: &OwnedRoomId,
+        page: usize,
+        filter: ListFilter,
+    ) -> (String, Option<String>) {
+        let settings = self.storage.room_settings(room_id).await;
         let todo_lists = self.storage.todo_lists.lock().await;
-        let tasks = todo_lists.get(room_id);
+        let now = Utc::now();
+        let mut snoozed = 0;
+        let tasks = todo_lists.get(room_id).map(|tasks| {
+            let mut tasks = tasks
+                .iter()
+                .filter(|t| filter.shows(t))
+                .filter(|t| {
+                    let hidden = filter != ListFilter::All && t.is_snoozed(now);
+                    snoozed += usize::from(hidden);
+                    !hidden
+                })
+                .collect::<Vec<&Task>>();
+            settings.list_sort.sort(&mut tasks);
+            tasks
+        });
+
+        // Snoozed tasks only show up as a count
+        let snoozed_note =
+            (snoozed > 0).then(|| format!("({} snoozed — !list snoozed to view)", snoozed));
 
         if let Some(tasks) = tasks {
             if tasks.is_empty() {
-                let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
-                self.send_matrix_message(room_id, message, None).await?;
-                return Ok(());
+                let message = match filter {
+                    ListFilter::All => {
+                        "ℹ️ Info: There are no tasks in this room's to-do list.".to_owned()
+                    }
+                    ListFilter::Open => "ℹ️ Info: There are no open tasks in this room's to-do list. Use `!list all` to include closed ones.".to_owned(),
+                    ListFilter::Status(status) => format!(
+                        "ℹ️ Info: There are no {} tasks in this room's to-do list.",
+                        status
+                    ),
+                };
+                let message = match &snoozed_note {
+                    Some(note) => format!("{}\n{}", message, note),
+                    None => message,
+                };
+                return (message, None);
+            }
+
+            // Lines first, so pages can be cut where the event would get too big
+            let lines: Vec<String> = tasks
+                .iter()
+                .map(|task| {
+                    let mut line = format!("{}. {}", task.id, task.to_string_short());
+                    // Finished tasks aren't waiting on anything any more
+                    if !matches!(task.status, TaskStatus::Done | TaskStatus::Closed) {
+                        let waiting_on: Vec<usize> = todo_lists
+                            .get(room_id)
+                            .map(|list| list.unfinished_dependencies(task.id))
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|t| t.id)
+                            .collect();
+                        if !waiting_on.is_empty() {
+                            line.push_str(&format!(" ⛓️ waiting on {}", task_refs(&waiting_on)));
+                        }
+                    }
+                    line
+                })
+                .collect();
+            let actions: Vec<String> = tasks
+                .iter()
+                .map(|task| quick_actions_html(settings.quick_actions, room_id, task.id))
+                .collect();
+            let page_ranges = paginate_lines(&lines, &actions);
+            let pages = page_ranges.len();
+            if page == 0 || page > pages {
+                let message = format!(
+                    "❌ Error: Page {} doesn't exist. The list has {} page(s).",
+                    page, pages
+                );
+                return (message, None);
             }
 
             let mut response = String::new();
-            for (idx, task) in tasks.iter().enumerate() {
-                response.push_str(&format!("{}. {}\n", idx + 1, task.to_string_short()));
+            let mut html_response = String::new();
+            // Status headings only make sense while tasks are grouped by status
+            let grouped = settings.list_sort == ListSort::Status;
+            if !grouped {
+                let heading = format!("Sorted by {}:", settings.list_sort.as_str());
+                response.push_str(&format!("{}\n", heading));
+                html_response.push_str(&format!("<b>{}</b><br>", heading));
+            }
+            let mut current_group = None;
+            for idx in page_ranges[page - 1].clone() {
+                let (task, line) = (tasks[idx], &lines[idx]);
+                if grouped && current_group != Some(task.status) {
+                    current_group = Some(task.status);
+                    let heading = format!("{} {}:", task.status.emoji(), task.status.label());
+                    response.push_str(&format!("{}\n", heading));
+                    html_response.push_str(&format!("<b>{}</b><br>", heading));
+                }
+                html_response.push_str(line);
+                html_response.push_str(&actions[idx]);
+                html_response.push_str("<br>");
+                response.push_str(line);
+                response.push('\n');
             }
 
-            let message = format!("📋 Room To-Do List:\n{}", response);
-            let html_message = format!("📋 Room To-Do List:<br>{}", response.replace('\n', "<br>"));
-            self.send_matrix_message(room_id, &message, Some(html_message))
-                .await?;
+            let footer = if pages == 1 {
+                String::new()
+            } else if page < pages {
+                format!(
+                    "Page {}/{} — use {} {} for more",
+                    page,
+                    pages,
+                    filter.command(),
+                    page + 1
+                )
+            } else {
+                format!("Page {}/{}", page, pages)
+            };
+
+            let footer = [Some(footer), snoozed_note]
+                .into_iter()
+                .flatten()
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("\n");
+            // The summary counts the whole room, whatever the filter shows
+            let room_tasks = todo_lists
+                .get(room_id)
+                .map(|list| list.as_slice())
+                .unwrap_or_default();
+            let summary = status_summary(room_tasks);
+            let message = if footer.is_empty() {
+                format!("📋 Room To-Do List:\n{}{}", response, summary)
+            } else {
+                format!("📋 Room To-Do List:\n{}{}\n{}", response, footer, summary)
+            };
+            let html_footer = format!(
+                "{}{}",
+                footer.replace('\n', "<br>"),
+                status_summary_html(room_tasks)
+            );
+            let html_message = format!("📋 Room To-Do List:<br>{}{}", html_response, html_footer);
+            (message, Some(html_message))
         } else {
             let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
-            self.send_matrix_message(room_id, message, None).await?;
+            (message.to_owned(), None)
         }
+    }
+
+    /// Post a page of the list. A page that lists tasks becomes the room's list
+    /// view, which `refresh_list_view` keeps up to date.
+    pub async fn list_tasks(
+        &self,
+        room_id: &OwnedRoomId,
+        page: usize,
+        filter: ListFilter,
+    ) -> Result<()> {
+        let (text, html) = self.render_list(room_id, page, filter).await;
+        let event_id = self
+            .send_matrix_message(room_id, &text, html.clone())
+            .await?;
+        if html.is_some() {
+            let view = ListView {
+                event_id,
+                page,
+                filter,
+                text,
+                html,
+            };
+            self.list_views.set(room_id, view).await;
+        }
+        Ok(())
+    }
+
+    /// `!list sort <key>`: remember the room's list order and post the list in it
+    pub async fn sort_list(&self, room_id: &OwnedRoomId, key: &str) -> Result<()> {
+        let Some(sort) = ListSort::parse(key) else {
+            let options = ListSort::ALL.map(|s| s.as_str()).join(", ");
+            let message = match key.trim() {
+                "" => format!("⚠️ Error: Usage: !list sort <key>, one of: {}.", options),
+                key => format!("⚠️ Error: Unknown sort '{}'. Use one of: {}.", key, options),
+            };
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        self.storage
+            .update_room_settings(room_id, |settings| settings.list_sort = sort)
+            .await;
+        self.storage.save_room(room_id).await?;
+        self.list_tasks(room_id, 1, ListFilter::Open).await
+    }
+
+    /// Edit the room's last `!list` to match the current tasks, if it changed.
+    /// A redacted list is posted again and becomes the new view.
+    pub async fn refresh_list_view(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let Some(mut view) = self.list_views.get(room_id).await else {
+            return Ok(());
+        };
+        let (text, html) = self.render_list(room_id, view.page, view.filter).await;
+        if text == view.text && html == view.html {
+            return Ok(());
+        }
+        let outcome = self
+            .message_sender
+            .edit_message(room_id, &view.event_id, &text, html.clone())
+            .await?;
+        if let EditOutcome::Resent(event_id) = outcome {
+            debug!(room_id = %room_id, "List view was gone, posted it again");
+            view.event_id = event_id;
+        }
+        view.text = text;
+        view.html = html;
+        self.list_views.set(room_id, view).await;
         Ok(())
     }
 
-    #[instrument(skip(self), fields(room_id = %room_id, task_id = task_number))]
+    #[instrument(skip(self), fields(room_id = %room_id))]
     pub async fn done_task(
         &self,
         room_id: &OwnedRoomId,
         sender: String,
-        task_number: usize,
+        task_id: usize,
+        force: bool,
+        note: Option<String>,
     ) -> Result<()> {
         debug!(user = %sender, "Starting mark task as done operation");
 
         let mut todo_lists = self.storage.todo_lists.lock().await;
         let tasks = todo_lists.entry(room_id.clone()).or_default();
 
-        if task_number > 0 && task_number <= tasks.len() {
-            let task = &mut tasks[task_number - 1];
+        if tasks
+            .find(task_id)
+            .is_some_and(|task| task.status == TaskStatus::Done)
+        {
+            drop(todo_lists);
+            let message = format!("ℹ️ Info: Task {} is already done.", task_id);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
+        let blockers = tasks.unfinished_dependencies(task_id);
+        if !force && !blockers.is_empty() {
+            let mut lines = vec![format!(
+                "⚠️ Task {} is waiting on {} unfinished task(s):",
+                task_id,
+                blockers.len()
+            )];
+            lines.extend(
+                blockers
+                    .iter()
+                    .map(|t| format!("• #{} [{}] {}", t.id, t.status, t.title)),
+            );
+            lines.push(format!(
+                "Use `!done {} force` to mark it done anyway.",
+                task_id
+            ));
+            drop(todo_lists);
+            self.send_matrix_message(room_id, &lines.join("\n"), None)
+                .await?;
+            return Ok(());
+        }
+
+        if let Some(task) = tasks.find_mut(task_id) {
             let task_title = task.title.clone();
 
             info!(
                 user = %sender,
                 room_id = %room_id,
-                task_id = task_number,
+                task_id = task_id,
                 title = %task_title,
                 "Marking task as done"
             );
 
-            task.set_status(sender.clone(), "done".to_string());
+            let mut undo_actions = vec![UndoAction::RestoreTasks(vec![task.clone()])];
+            task.set_status(sender.clone(), TaskStatus::Done, None);
 
-            let message = format!("✅ Task {} marked as done: **{}**", task_number, task.title);
-            let html_message = format!(
+            let mut message = format!("✅ Task {} marked as done: **{}**", task_id, task.title);
+            let mut html_message = format!(
                 "✅ Task {} marked as done: <b>{}</b>",
-                task_number, task.title
+                task_id,
+                escape_html(&task.title)
             );
+            if let Some(note) = note {
+                message.push_str(&format!("\n📝 {}", note));
+                html_message.push_str(&format!("<br>📝 {}", escape_html(&note)));
+                task.add_log(sender.clone(), note);
+            }
+            let watchers = task.watchers.clone();
+
+            // Recurring tasks come back as a fresh pending copy
+            if task.recurrence.is_some() {
+                let next_id = tasks.allocate_id();
+                let next = tasks
+                    .find_mut(task_id)
+                    .and_then(|task| task.regenerate(sender.clone(), next_id));
+                if let Some(next) = next {
+                    let due = next
+                        .due_date
+                        .map(|d| d.format("%Y-%m-%d").to_string())
+                        .unwrap_or_default();
+                    info!(
+                        room_id = %room_id,
+                        task_id = task_id,
+                        next_task_id = next_id,
+                        due_date = %due,
+                        "Rescheduled recurring task"
+                    );
+                    message.push_str(&format!(
+                        "\n🔁 Rescheduled as task {}, due {}",
+                        next_id, due
+                    ));
+                    html_message.push_str(&format!(
+                        "<br>🔁 Rescheduled as task {}, due {}",
+                        next_id, due
+                    ));
+                    tasks.push(next);
+                    undo_actions.push(UndoAction::RemoveTasks(vec![next_id]));
+                } else {
+                    warn!(room_id = %room_id, task_id = task_id, "Next due date of recurring task is out of range");
+                    message.push_str("\n⚠️ Not rescheduled: the next due date is out of range.");
+                    html_message
+                        .push_str("<br>⚠️ Not rescheduled: the next due date is out of range.");
+                }
+            }
+            mention_watchers(&watchers, &sender, &mut message, &mut html_message);
 
             debug!("Sending confirmation message to room");
             self.send_matrix_message(room_id, &message, Some(html_message))
                 .await?;
 
             debug!("Saving updated task list");
-            match self.storage.save().await {
+            drop(todo_lists);
+            self.undo
+                .push(
+                    room_id,
+                    UndoEntry::new(format!("done of task {}", task_id), undo_actions),
+                )
+                .await;
+            match self.storage.save_room(room_id).await {
                 Ok(_) => {
                     info!(
                         user = %sender,
                         room_id = %room_id,
-                        task_id = task_number,
+                        task_id = task_id,
                         "Successfully saved task status change"
                     );
                 }
@@ -292,7 +1529,7 @@ impl TodoList {
                     error!(
                         user = %sender,
                         room_id = %room_id,
-                        task_id = task_number,
+                        task_id = task_id,
                         error = %e,
                         "Failed to save task list after marking task as done"
                     );
@@ -303,62 +1540,169 @@ impl TodoList {
             warn!(
                 user = %sender,
                 room_id = %room_id,
-                task_id = task_number,
+                task_id = task_id,
                 "Attempted to mark non-existent task as done"
             );
 
-            let message = format!("❌ Error: Task {} doesn't exist.", task_number);
+            let message = format!("❌ Error: Task {} doesn't exist.", task_id);
             self.send_matrix_message(room_id, &message, None).await?;
         }
 
         Ok(())
     }
 
-    pub async fn close_task(
+    /// Mark several tasks done at once; one confirmation and one save for the batch
+    pub async fn done_tasks(
         &self,
         room_id: &OwnedRoomId,
         sender: String,
-        task_number: usize,
+        task_ids: &[usize],
+        force: bool,
     ) -> Result<()> {
-        let mut todo_lists = self.storage.todo_lists.lock().await;
-        let tasks = todo_lists.get_mut(room_id);
+        self.bulk_status_change(room_id, sender, task_ids, TaskStatus::Done, force)
+            .await
+    }
 
-        if let Some(tasks) = tasks {
-            if tasks.is_empty() {
-                let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
-                self.send_matrix_message(room_id, message, None).await?;
-                return Ok(());
-            }
+    /// Close several tasks at once; one confirmation and one save for the batch
+    pub async fn close_tasks(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_ids: &[usize],
+    ) -> Result<()> {
+        self.bulk_status_change(room_id, sender, task_ids, TaskStatus::Closed, true)
+            .await
+    }
 
-            if task_number > 0 && task_number <= tasks.len() {
-                let mut task = tasks.remove(task_number - 1);
-                task.set_status(sender, "closed".to_owned());
+    async fn bulk_status_change(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_ids: &[usize],
+        status: TaskStatus,
+        force: bool,
+    ) -> Result<()> {
+        let mut changed = Vec::new();
+        let mut unchanged = Vec::new();
+        let mut missing = Vec::new();
+        let mut waiting = Vec::new();
+        let mut rescheduled = Vec::new();
+        let mut out_of_range = Vec::new();
+        let mut previous = Vec::new();
+        let mut created = Vec::new();
+        let mut watchers = Vec::new();
+        {
+            let mut todo_lists = self.storage.todo_lists.lock().await;
+            let tasks = todo_lists.entry(room_id.clone()).or_default();
+            for &task_id in task_ids {
+                let Some(task) = tasks.find_mut(task_id) else {
+                    missing.push(task_id.to_string());
+                    continue;
+                };
+                if task.status == status {
+                    unchanged.push(task_id.to_string());
+                    continue;
+                }
+                if status == TaskStatus::Done && !force {
+                    let blockers = tasks.unfinished_dependencies(task_id);
+                    if !blockers.is_empty() {
+                        let ids: Vec<usize> = blockers.iter().map(|t| t.id).collect();
+                        waiting.push(format!("{} (on {})", task_id, task_refs(&ids)));
+                        continue;
+                    }
+                }
+                let task = tasks.find_mut(task_id).unwrap();
+                previous.push(task.clone());
+                task.set_status(sender.clone(), status, None);
+                changed.push(format!("{}. {}", task_id, task.title));
+                watchers.extend(task.watchers.iter().cloned());
 
-                let message = format!("✖️ Task Closed: **{}**", task.to_string_short());
-                let html_message = format!("✖️ Task Closed: <b>{}</b>", task.to_string_short());
-                self.send_matrix_message(room_id, &message, Some(html_message))
-                    .await?;
-                self.storage.save().await?;
-            } else {
-                let message = format!(
-                    "❌ Error: Invalid task number: {}. Use `!list` to see valid numbers.",
-                    task_number
-                );
-                self.send_matrix_message(room_id, &message, None).await?;
+                // Recurring tasks come back as a fresh pending copy, as with a single !done
+                if status == TaskStatus::Done && task.recurrence.is_some() {
+                    let next_id = tasks.allocate_id();
+                    let next = tasks
+                        .find_mut(task_id)
+                        .and_then(|task| task.regenerate(sender.clone(), next_id));
+                    match next {
+                        Some(next) => {
+                            rescheduled.push(format!("{} → {}", task_id, next_id));
+                            tasks.push(next);
+                            created.push(next_id);
+                        }
+                        None => out_of_range.push(task_id.to_string()),
+                    }
+                }
             }
-        } else {
-            let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
-            self.send_matrix_message(room_id, message, None).await?;
+        }
+
+        info!(
+            user = %sender,
+            room_id = %room_id,
+            status = %status,
+            changed = changed.len(),
+            unchanged = unchanged.len(),
+            missing = missing.len(),
+            waiting = waiting.len(),
+            "Applied bulk status change"
+        );
+
+        let mut lines = Vec::new();
+        if !changed.is_empty() {
+            lines.push(format!(
+                "{} Marked {} task(s) as {}:",
+                status.emoji(),
+                changed.len(),
+                status
+            ));
+            lines.extend(changed.iter().map(|t| format!("• {}", t)));
+        }
+        if !rescheduled.is_empty() {
+            lines.push(format!("🔁 Rescheduled: {}", rescheduled.join(", ")));
+        }
+        if !out_of_range.is_empty() {
+            lines.push(format!(
+                "⚠️ Not rescheduled, the next due date is out of range: {}",
+                out_of_range.join(", ")
+            ));
+        }
+        if !unchanged.is_empty() {
+            lines.push(format!("ℹ️ Already {}: {}", status, unchanged.join(", ")));
+        }
+        if !missing.is_empty() {
+            lines.push(format!("❌ Not found: {}", missing.join(", ")));
+        }
+        if !waiting.is_empty() {
+            lines.push(format!(
+                "⛓️ Waiting on unfinished tasks: {}. Add `force` to mark them anyway.",
+                waiting.join(", ")
+            ));
+        }
+        let mut message = lines.join("\n");
+        let mut html_message = lines.join("<br>");
+        mention_watchers(&watchers, &sender, &mut message, &mut html_message);
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+
+        if !changed.is_empty() {
+            let description = format!("{} of {} task(s)", status, changed.len());
+            let actions = vec![
+                UndoAction::RestoreTasks(previous),
+                UndoAction::RemoveTasks(created),
+            ];
+            self.undo
+                .push(room_id, UndoEntry::new(description, actions))
+                .await;
+            self.storage.save_room(room_id).await?;
         }
         Ok(())
     }
 
-    pub async fn log_task(
+    pub async fn close_task(
         &self,
         room_id: &OwnedRoomId,
         sender: String,
-        task_number: usize,
-        log_content: String,
+        task_id: usize,
+        note: Option<String>,
     ) -> Result<()> {
         let mut todo_lists = self.storage.todo_lists.lock().await;
         let tasks = todo_lists.get_mut(room_id);
@@ -370,29 +1714,41 @@ impl TodoList {
                 return Ok(());
             }
 
-            if task_number > 0 && task_number <= tasks.len() {
-                let task = &mut tasks[task_number - 1];
-                task.add_log(sender, log_content.clone());
+            // Closed tasks stay in the list, hidden from `!list`, so they can be reopened
+            if let Some(task) = tasks.find_mut(task_id) {
+                if task.status == TaskStatus::Closed {
+                    let message = format!("ℹ️ Info: Task {} is already closed.", task_id);
+                    self.send_matrix_message(room_id, &message, None).await?;
+                    return Ok(());
+                }
+                let previous = task.clone();
+                task.set_status(sender.clone(), TaskStatus::Closed, None);
 
-                let message = format!(
-                    "📝 Log Added to Task #{}:\nLog: '{}'\n\nCurrent Task Details:\n{}",
-                    task_number,
-                    log_content,
-                    task.show_details()
-                );
-                let html_message = format!(
-                    "📝 Log Added to Task #{}:<br>Log: '{}'<<br><br><b>Current Task Details:</b><br>{}",
-                    task_number,
-                    log_content,
-                    task.show_details().replace('\n', "<br>")
-                );
+                let mut message = format!("✖️ Task Closed: **{}**", task.to_string_short());
+                let mut html_message = format!("✖️ Task Closed: <b>{}</b>", task.to_string_short());
+                if let Some(note) = note {
+                    message.push_str(&format!("\n📝 {}", note));
+                    html_message.push_str(&format!("<br>📝 {}", escape_html(&note)));
+                    task.add_log(sender.clone(), note);
+                }
+                mention_watchers(&task.watchers, &sender, &mut message, &mut html_message);
                 self.send_matrix_message(room_id, &message, Some(html_message))
                     .await?;
-                self.storage.save().await?;
+                drop(todo_lists);
+                self.undo
+                    .push(
+                        room_id,
+                        UndoEntry::new(
+                            format!("close of task {}", task_id),
+                            vec![UndoAction::RestoreTasks(vec![previous])],
+                        ),
+                    )
+                    .await;
+                self.storage.save_room(room_id).await?;
             } else {
                 let message = format!(
-                    "❌ Error: Invalid task number: {}. Use `!list` to see valid numbers.",
-                    task_number
+                    "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                    task_id
                 );
                 self.send_matrix_message(room_id, &message, None).await?;
             }
@@ -403,28 +1759,57 @@ impl TodoList {
         Ok(())
     }
 
-    pub async fn details_task(&self, room_id: &OwnedRoomId, task_number: usize) -> Result<()> {
-        let todo_lists = self.storage.todo_lists.lock().await;
-        let tasks = todo_lists.get(room_id);
+    pub async fn reopen_task(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+    ) -> Result<()> {
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let tasks = todo_lists.get_mut(room_id);
 
         if let Some(tasks) = tasks {
-            if tasks.is_empty() {
-                let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
-                self.send_matrix_message(room_id, message, None).await?;
-                return Ok(());
-            }
+            if let Some(task) = tasks.find_mut(task_id) {
+                if !matches!(task.status, TaskStatus::Done | TaskStatus::Closed) {
+                    let message = format!(
+                        "ℹ️ Info: Task {} is '{}', only done or closed tasks can be reopened.",
+                        task_id, task.status
+                    );
+                    self.send_matrix_message(room_id, &message, None).await?;
+                    return Ok(());
+                }
+
+                info!(
+                    user = %sender,
+                    room_id = %room_id,
+                    task_id = task_id,
+                    from_status = %task.status,
+                    "Reopening task"
+                );
+                let previous = task.clone();
+                task.reopen(sender.clone());
 
-            if task_number > 0 && task_number <= tasks.len() {
-                let task = &tasks[task_number - 1];
-                let details = task.show_details();
-                let message = format!("🔍 Task Details:\n{}", details);
-                let html_message = format!("🔍 Task Details:<br>{}", details.replace('\n', "<br>"));
+                let mut message = format!("🔄 Task {} reopened: **{}**", task_id, task.title);
+                let mut html_message =
+                    format!("🔄 Task {} reopened: <b>{}</b>", task_id, task.title);
+                mention_watchers(&task.watchers, &sender, &mut message, &mut html_message);
                 self.send_matrix_message(room_id, &message, Some(html_message))
                     .await?;
+                drop(todo_lists);
+                self.undo
+                    .push(
+                        room_id,
+                        UndoEntry::new(
+                            format!("reopen of task {}", task_id),
+                            vec![UndoAction::RestoreTasks(vec![previous])],
+                        ),
+                    )
+                    .await;
+                self.storage.save_room(room_id).await?;
             } else {
                 let message = format!(
-                    "❌ Error: Invalid task number: {}. Use `!list` to see valid numbers.",
-                    task_number
+                    "❌ Error: Invalid task ID: {}. Use `!list all` to see valid IDs.",
+                    task_id
                 );
                 self.send_matrix_message(room_id, &message, None).await?;
             }
@@ -435,62 +1820,3492 @@ impl TodoList {
         Ok(())
     }
 
-    // Use MessageSender trait to send messages without directly depending on Matrix SDK
-    pub async fn send_matrix_message(
+    /// Mark a task as being worked on
+    pub async fn start_task(
         &self,
         room_id: &OwnedRoomId,
-        message: &str,
-        html_message: Option<String>,
+        sender: String,
+        task_id: usize,
     ) -> Result<()> {
-        self.message_sender
-            .send_response(room_id, message, html_message)
+        self.change_status(room_id, sender, task_id, TaskStatus::InProgress, None)
             .await
     }
 
-    pub async fn edit_task(
+    /// Mark a task as blocked, keeping the reason in its history
+    pub async fn block_task(
         &self,
         room_id: &OwnedRoomId,
         sender: String,
-        task_number: usize,
-        new_title: String,
+        task_id: usize,
+        reason: Option<String>,
+    ) -> Result<()> {
+        self.change_status(
+            room_id,
+            sender,
+            task_id,
+            TaskStatus::Blocked,
+            reason.as_deref(),
+        )
+        .await
+    }
+
+    async fn change_status(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        status: TaskStatus,
+        reason: Option<&str>,
     ) -> Result<()> {
         let mut todo_lists = self.storage.todo_lists.lock().await;
-        let tasks = todo_lists.get_mut(room_id);
+        let Some(task) = todo_lists
+            .get_mut(room_id)
+            .and_then(|tasks| tasks.find_mut(task_id))
+        else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
 
-        if let Some(tasks) = tasks {
-            if tasks.is_empty() {
-                let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
-                self.send_matrix_message(room_id, message, None).await?;
+        if task.status == status {
+            let message = format!("ℹ️ Info: Task {} is already {}.", task_id, status);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
+        info!(
+            user = %sender,
+            room_id = %room_id,
+            task_id = task_id,
+            from_status = %task.status,
+            to_status = %status,
+            "Changing task status"
+        );
+        let previous = task.clone();
+        task.set_status(sender.clone(), status, reason);
+
+        let mut message = format!(
+            "{} Task {} is now {}: **{}**",
+            status.emoji(),
+            task_id,
+            status.label().to_lowercase(),
+            task.title
+        );
+        let mut html_message = format!(
+            "{} Task {} is now {}: <b>{}</b>",
+            status.emoji(),
+            task_id,
+            status.label().to_lowercase(),
+            escape_html(&task.title)
+        );
+        if let Some(reason) = reason {
+            message.push_str(&format!("\nReason: {}", reason));
+            html_message.push_str(&format!("<br>Reason: {}", escape_html(reason)));
+        }
+        mention_watchers(&task.watchers, &sender, &mut message, &mut html_message);
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        drop(todo_lists);
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(
+                    format!("{} of task {}", status.label().to_lowercase(), task_id),
+                    vec![UndoAction::RestoreTasks(vec![previous])],
+                ),
+            )
+            .await;
+        self.storage.save_room(room_id).await?;
+        Ok(())
+    }
+
+    /// `!import <payload>`: create tasks from a JSON array or Markdown list of titles
+    pub async fn import_command(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        payload: &str,
+    ) -> Result<()> {
+        let titles = match import::parse_import_payload(payload) {
+            Ok(titles) => titles,
+            Err(reason) => {
+                let message = format!(
+                    "❌ Error: {} Usage: !import <JSON array or Markdown list>",
+                    reason
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
                 return Ok(());
             }
+        };
+        if titles.len() > import::MAX_IMPORT_TASKS {
+            let message = format!(
+                "❌ Error: The payload has {} tasks, at most {} can be imported at once.",
+                titles.len(),
+                import::MAX_IMPORT_TASKS
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+        self.import_tasks(room_id, sender, titles).await
+    }
 
-            if task_number > 0 && task_number <= tasks.len() {
-                let task = &mut tasks[task_number - 1];
-                let old_title = task.title.clone();
-                task.set_title(sender, new_title.clone());
+    /// Create a pending task for each title not already in the room (compared
+    /// case-insensitively), then report how many were added and skipped
+    pub async fn import_tasks(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        titles: Vec<String>,
+    ) -> Result<()> {
+        let mut added = Vec::new();
+        let mut skipped = 0;
+        {
+            let mut todo_lists = self.storage.todo_lists.lock().await;
+            let tasks = todo_lists.entry(room_id.clone()).or_default();
+            let mut known: HashSet<String> =
+                tasks.iter().map(|t| normalize_title(&t.title)).collect();
+            let new_titles: Vec<String> = titles
+                .into_iter()
+                .filter(|title| {
+                    let new = known.insert(normalize_title(title));
+                    skipped += usize::from(!new);
+                    new
+                })
+                .collect();
+            if let Some(message) = self.room_full_message(tasks.len(), new_titles.len()) {
+                drop(todo_lists);
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            }
+            for title in new_titles {
+                let id = tasks.allocate_id();
+                tasks.push(Task::new(sender.clone(), id, title));
+                added.push(id);
+            }
+        }
+
+        info!(user = %sender, room_id = %room_id, added = added.len(), skipped, "Imported tasks");
+        let message = format!(
+            "📥 Imported {} task(s), skipped {} already in the list.",
+            added.len(),
+            skipped
+        );
+        self.send_matrix_message(room_id, &message, None).await?;
+        if !added.is_empty() {
+            let description = format!("import of {} task(s)", added.len());
+            self.undo
+                .push(
+                    room_id,
+                    UndoEntry::new(description, vec![UndoAction::RemoveTasks(added)]),
+                )
+                .await;
+            self.storage.save_room(room_id).await?;
+        }
+        Ok(())
+    }
+
+    /// Append fresh pending tasks for each title. Returns how many were created,
+    /// or `None` after replying that the room has no space for them.
+    pub async fn apply_template_titles(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        titles: &[String],
+    ) -> Result<Option<usize>> {
+        let mut added = Vec::new();
+        {
+            let mut todo_lists = self.storage.todo_lists.lock().await;
+            let tasks = todo_lists.entry(room_id.clone()).or_default();
+            if let Some(message) = self.room_full_message(tasks.len(), titles.len()) {
+                drop(todo_lists);
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(None);
+            }
+            for title in titles {
+                let id = tasks.allocate_id();
+                tasks.push(Task::new(sender.clone(), id, title.clone()));
+                added.push(id);
+            }
+        }
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(
+                    format!("template of {} task(s)", added.len()),
+                    vec![UndoAction::RemoveTasks(added)],
+                ),
+            )
+            .await;
+        self.storage.save_room(room_id).await?;
+        Ok(Some(titles.len()))
+    }
+
+    /// `!template save <name>`: store the room's task titles as a template
+    pub async fn save_template(&self, room_id: &OwnedRoomId, name: &str) -> Result<()> {
+        if let Err(reason) = validate_template_name(name) {
+            let message = format!("❌ Error: Invalid template name '{}'. {}", name, reason);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+        let titles = self
+            .storage
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .map(|list| {
+                list.iter()
+                    .map(|t| t.title.clone())
+                    .collect::<Vec<String>>()
+            })
+            .unwrap_or_default();
+        if titles.is_empty() {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list to save.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+
+        let mut store = self.storage.load_templates().await?;
+        let count = titles.len();
+        let replaced = store.templates.insert(name.to_owned(), titles).is_some();
+        self.storage.save_templates(&store).await?;
+        info!(room_id = %room_id, template = %name, count, replaced, "Saved template");
+        let message = format!(
+            "💾 Template '{}' {} with {} task(s).",
+            name,
+            if replaced { "updated" } else { "saved" },
+            count
+        );
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    pub async fn list_templates(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let store = self.storage.load_templates().await?;
+        let message = if store.templates.is_empty() {
+            "ℹ️ Info: No templates saved yet. Use !template save <name> to create one.".to_owned()
+        } else {
+            let lines = store
+                .templates
+                .iter()
+                .map(|(name, titles)| {
+                    let on_join = if store.on_join.as_deref() == Some(name.as_str()) {
+                        " (applied on join)"
+                    } else {
+                        ""
+                    };
+                    format!("• {} — {} task(s){}", name, titles.len(), on_join)
+                })
+                .collect::<Vec<String>>();
+            format!("📋 Templates:\n{}", lines.join("\n"))
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    pub async fn delete_template(&self, room_id: &OwnedRoomId, name: &str) -> Result<()> {
+        let mut store = self.storage.load_templates().await?;
+        if store.templates.remove(name).is_none() {
+            let message = format!("❌ Error: Template '{}' doesn't exist.", name);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+        let mut message = format!("🗑️ Template '{}' deleted.", name);
+        if store.on_join.as_deref() == Some(name) {
+            store.on_join = None;
+            message.push_str(" It is no longer applied to rooms the bot joins.");
+        }
+        self.storage.save_templates(&store).await?;
+        info!(room_id = %room_id, template = %name, "Deleted template");
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
 
+    /// `!template apply <name> [key=value ...]`: fill the template's placeholders and
+    /// add its tasks. Nothing is added unless every placeholder can be filled.
+    pub async fn apply_template(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        name: &str,
+        args: &[&str],
+    ) -> Result<()> {
+        let store = self.storage.load_templates().await?;
+        let Some(titles) = store.templates.get(name) else {
+            let available = store
+                .templates
+                .keys()
+                .cloned()
+                .collect::<Vec<String>>()
+                .join(", ");
+            let message = format!(
+                "❌ Error: Template '{}' doesn't exist. Available templates: {}",
+                name,
+                if available.is_empty() {
+                    "none"
+                } else {
+                    &available
+                }
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        let vars = match template_vars::parse_assignments(args.iter().copied()) {
+            Ok(vars) => vars,
+            Err(reason) => {
                 let message = format!(
-                    "✏️ Task Edited: Task #{} title changed:\nFrom: {}\nTo: {}",
-                    task_number, old_title, new_title
-                );
-                let html_message = format!(
-                    "✏️ Task Edited: Task #{} title changed:<br><b>From:</b> {}<br><b>To:</b> {}",
-                    task_number, old_title, new_title
+                    "❌ Error: {}. Usage: !template apply <name> [key=value ...]",
+                    reason
                 );
-                self.send_matrix_message(room_id, &message, Some(html_message))
-                    .await?;
-                self.storage.save().await?;
-            } else {
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            }
+        };
+        let today = self.storage.room_settings(room_id).await.today();
+        let titles = match template_vars::substitute_all(titles, &vars, today) {
+            Ok(titles) => titles,
+            Err(missing) => {
                 let message = format!(
-                    "❌ Error: Invalid task number: {}. Use `!list` to see valid numbers.",
-                    task_number
+                    "❌ Error: Template '{}' needs values for: {}. Pass them as key=value.",
+                    name,
+                    missing.join(", ")
                 );
                 self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
             }
-        } else {
-            let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
-            self.send_matrix_message(room_id, message, None).await?;
+        };
+
+        let Some(count) = self.apply_template_titles(room_id, sender, &titles).await? else {
+            return Ok(());
+        };
+        let message = format!(
+            "📋 Applied template '{}': {} task(s) added. Use !list to see them.",
+            name, count
+        );
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// Apply the configured on-join template to a room that has no tasks yet
+    pub async fn apply_join_template(&self, room_id: &OwnedRoomId, sender: String) -> Result<()> {
+        let store = self.storage.load_templates().await?;
+        let Some(name) = store.on_join else {
+            return Ok(());
+        };
+        let has_tasks = self
+            .storage
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .is_some_and(|tasks| !tasks.is_empty());
+        if has_tasks {
+            debug!(room_id = %room_id, "Room already has tasks, skipping join template");
+            return Ok(());
         }
+        let Some(titles) = store.templates.get(&name) else {
+            warn!(template = %name, "On-join template no longer exists");
+            return Ok(());
+        };
+
+        // Only date placeholders can be filled here, nobody passed any variables
+        let today = self.storage.room_settings(room_id).await.today();
+        let titles = match template_vars::substitute_all(titles, &HashMap::new(), today) {
+            Ok(titles) => titles,
+            Err(missing) => {
+                warn!(template = %name, missing = ?missing, "On-join template needs variables, skipping it");
+                return Ok(());
+            }
+        };
+        let Some(count) = self.apply_template_titles(room_id, sender, &titles).await? else {
+            return Ok(());
+        };
+        info!(room_id = %room_id, template = %name, count, "Applied on-join template");
+        let message = format!(
+            "📋 Applied template '{}': {} task(s) added. Use !list to see them.",
+            name, count
+        );
+        self.send_matrix_message(room_id, &message, None).await?;
         Ok(())
     }
+
+    /// Move a task (by stable ID) to a 1-based position in the room's list
+    pub async fn move_task(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        target: MoveTarget,
+    ) -> Result<()> {
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let Some(tasks) = todo_lists.get_mut(room_id).filter(|t| !t.is_empty()) else {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        };
+
+        let Some(from) = tasks.iter().position(|t| t.id == task_id) else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+
+        let to = match target {
+            MoveTarget::Top => 0,
+            MoveTarget::Bottom => tasks.len() - 1,
+            MoveTarget::Position(position) if (1..=tasks.len()).contains(&position) => position - 1,
+            MoveTarget::Position(position) => {
+                let message = format!(
+                    "❌ Error: Invalid position: {}. Use a position between 1 and {}.",
+                    position,
+                    tasks.len()
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            }
+        };
+
+        let previous = tasks.tasks.clone();
+        let mut task = tasks.remove(from);
+        task.add_internal_log(
+            sender,
+            TaskEvent::Moved,
+            Some(format!("from position {} to {}", from + 1, to + 1)),
+        );
+        tasks.insert(to, task);
+
+        // Show the moved task with its new neighbours
+        let snippet = tasks
+            .iter()
+            .enumerate()
+            .skip(to.saturating_sub(1))
+            .take(3)
+            .map(|(idx, t)| {
+                let marker = if idx == to { "➡️ " } else { "" };
+                format!("{}{}. {}", marker, t.id, t.to_string_short())
+            })
+            .collect::<Vec<String>>();
+        let message = format!(
+            "↕️ Task {} moved to position {}:\n{}",
+            task_id,
+            to + 1,
+            snippet.join("\n")
+        );
+        let html_message = format!(
+            "↕️ Task {} moved to position {}:<br>{}",
+            task_id,
+            to + 1,
+            snippet.join("<br>")
+        );
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        drop(todo_lists);
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(
+                    format!("move of task {}", task_id),
+                    vec![UndoAction::RestoreList(previous)],
+                ),
+            )
+            .await;
+        self.storage.save_room(room_id).await?;
+        Ok(())
+    }
+
+    /// Create a new task from log `log_number` (1-based) of task `task_id`
+    pub async fn spinoff_from_log(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        log_number: usize,
+    ) -> Result<()> {
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let Some(tasks) = todo_lists.get_mut(room_id) else {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        };
+
+        let Some(source) = tasks.find(task_id) else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        let Some(title) = log_number
+            .checked_sub(1)
+            .and_then(|idx| source.logs.get(idx))
+            .map(|log| log.text.clone())
+        else {
+            let message = format!(
+                "❌ Error: Task {} has no log {}. It has {} log(s), see `!details {}`.",
+                task_id,
+                log_number,
+                source.logs.len(),
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+
+        let new_id = tasks.allocate_id();
+        let mut task = Task::new(sender.clone(), new_id, title.clone());
+        task.spun_off_from = Some((task_id, log_number));
+        // Replace the generic creation entry with one that names the source
+        task.internal_logs.clear();
+        task.add_internal_log(
+            sender.clone(),
+            TaskEvent::Created,
+            Some(format!(
+                "spun off from task #{} log {}",
+                task_id, log_number
+            )),
+        );
+        tasks.push(task);
+        let mut undo_actions = vec![UndoAction::RemoveTasks(vec![new_id])];
+        if let Some(source) = tasks.find_mut(task_id) {
+            undo_actions.push(UndoAction::RestoreTasks(vec![source.clone()]));
+            source.add_internal_log(
+                sender.clone(),
+                TaskEvent::SpunOff,
+                Some(format!("#{} from log {}", new_id, log_number)),
+            );
+        }
+
+        info!(
+            user = %sender,
+            room_id = %room_id,
+            task_id = task_id,
+            new_task_id = new_id,
+            "Spun off task from log"
+        );
+        let message = format!(
+            "🌱 Task {} created from log {} of task {}: **{}**",
+            new_id, log_number, task_id, title
+        );
+        let html_message = format!(
+            "🌱 Task {} created from log {} of task {}: <b>{}</b>",
+            new_id, log_number, task_id, title
+        );
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        drop(todo_lists);
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(format!("spin-off of task {}", new_id), undo_actions),
+            )
+            .await;
+        self.storage.save_room(room_id).await?;
+        Ok(())
+    }
+
+    pub async fn log_task(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        log_content: String,
+    ) -> Result<()> {
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let tasks = todo_lists.get_mut(room_id);
+
+        if let Some(tasks) = tasks {
+            if tasks.is_empty() {
+                let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+                self.send_matrix_message(room_id, message, None).await?;
+                return Ok(());
+            }
+
+            let dependents = tasks.dependents(task_id);
+            if let Some(task) = tasks.find_mut(task_id) {
+                let previous = task.clone();
+                task.add_log(sender.clone(), log_content.clone());
+
+                let mut message = format!(
+                    "📝 Log Added to Task #{}:\nLog: '{}'\n\nCurrent Task Details:\n{}",
+                    task_id,
+                    log_content,
+                    task.show_details(&dependents)
+                );
+                let mut html_message = format!(
+                    "📝 Log Added to Task #{}:<br>Log: '{}'<br><br><b>Current Task Details:</b><br>{}",
+                    task_id,
+                    escape_html(&log_content),
+                    task.show_details_html(&dependents)
+                );
+                mention_watchers(&task.watchers, &sender, &mut message, &mut html_message);
+                self.send_matrix_message(room_id, &message, Some(html_message))
+                    .await?;
+                drop(todo_lists);
+                self.undo
+                    .push(
+                        room_id,
+                        UndoEntry::new(
+                            format!("log on task {}", task_id),
+                            vec![UndoAction::RestoreTasks(vec![previous])],
+                        ),
+                    )
+                    .await;
+                self.storage.save_room(room_id).await?;
+            } else {
+                let message = format!(
+                    "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                    task_id
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+            }
+        } else {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+            self.send_matrix_message(room_id, message, None).await?;
+        }
+        Ok(())
+    }
+
+    /// `!archive`: move the room's done and closed tasks out of the active list
+    pub async fn archive_tasks(&self, room_id: &OwnedRoomId, sender: String) -> Result<()> {
+        let moved = {
+            let mut todo_lists = self.storage.todo_lists.lock().await;
+            let mut moved = Vec::new();
+            let mut previous = Vec::new();
+            if let Some(list) = todo_lists.get_mut(room_id) {
+                previous = list.tasks.clone();
+                let active;
+                (moved, active) = std::mem::take(&mut list.tasks)
+                    .into_iter()
+                    .partition(|t| matches!(t.status, TaskStatus::Done | TaskStatus::Closed));
+                list.tasks = active;
+            }
+            for task in &mut moved {
+                task.add_internal_log(sender.clone(), TaskEvent::Archived, None);
+            }
+            if !moved.is_empty() {
+                let mut archived = self.storage.archived.lock().await;
+                let room_archive = archived.entry(room_id.clone()).or_default();
+                let previous_archive = room_archive.clone();
+                room_archive.extend(moved.iter().cloned());
+                drop(archived);
+                drop(todo_lists);
+                self.undo
+                    .push(
+                        room_id,
+                        UndoEntry::new(
+                            format!("archive of {} task(s)", moved.len()),
+                            vec![
+                                UndoAction::RestoreList(previous),
+                                UndoAction::RestoreArchive(previous_archive),
+                            ],
+                        ),
+                    )
+                    .await;
+            }
+            moved
+        };
+
+        if moved.is_empty() {
+            let message = "ℹ️ Info: There are no done or closed tasks to archive.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+        info!(user = %sender, room_id = %room_id, archived = moved.len(), "Archived tasks");
+        let message = format!(
+            "🗄️ Archived {} done or closed task(s). Use `!archive list` to browse them.",
+            moved.len()
+        );
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await
+    }
+
+    /// `!archive list [n]`: the most recently archived tasks, newest first
+    pub async fn list_archived(&self, room_id: &OwnedRoomId, count: usize) -> Result<()> {
+        let (lines, total) = {
+            let archived = self.storage.archived.lock().await;
+            let tasks = archived.get(room_id).map(Vec::as_slice).unwrap_or_default();
+            let lines = tasks
+                .iter()
+                .rev()
+                .take(count)
+                .map(|t| format!("{}. {}", t.id, t.to_string_short()))
+                .collect::<Vec<String>>();
+            (lines, tasks.len())
+        };
+
+        if lines.is_empty() {
+            let message = "ℹ️ Info: This room has no archived tasks.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+        let message = format!(
+            "🗄️ Archived tasks ({} of {}):\n{}\n\nRestore one with `!archive restore <id>`.",
+            lines.len(),
+            total,
+            lines.join("\n")
+        );
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// `!archive restore <id>`: put an archived task back in the active list
+    pub async fn restore_archived(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+    ) -> Result<()> {
+        let restored = {
+            let mut todo_lists = self.storage.todo_lists.lock().await;
+            let mut archived = self.storage.archived.lock().await;
+            let Some(pos) = archived
+                .get(room_id)
+                .and_then(|tasks| tasks.iter().rposition(|t| t.id == task_id))
+            else {
+                drop(archived);
+                drop(todo_lists);
+                let message = format!("❌ Error: No archived task with ID {}.", task_id);
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            };
+            let current = todo_lists.get(room_id).map_or(0, |list| list.len());
+            if let Some(message) = self.room_full_message(current, 1) {
+                drop(archived);
+                drop(todo_lists);
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            }
+            let room_archive = archived.get_mut(room_id).expect("room checked above");
+            let previous_archive = room_archive.clone();
+            let mut task = room_archive.remove(pos);
+            if room_archive.is_empty() {
+                archived.remove(room_id);
+            }
+            let list = todo_lists.entry(room_id.clone()).or_default();
+            // IDs are never reused, but a reindex may have handed this one out again
+            if list.find(task.id).is_some() {
+                task.id = list.allocate_id();
+            }
+            task.add_internal_log(sender.clone(), TaskEvent::Restored, None);
+            list.push(task.clone());
+            drop(archived);
+            drop(todo_lists);
+            self.undo
+                .push(
+                    room_id,
+                    UndoEntry::new(
+                        format!("restore of task {}", task.id),
+                        vec![
+                            UndoAction::RemoveTasks(vec![task.id]),
+                            UndoAction::RestoreArchive(previous_archive),
+                        ],
+                    ),
+                )
+                .await;
+            task
+        };
+
+        info!(user = %sender, room_id = %room_id, task_id = restored.id, "Restored archived task");
+        let message = format!(
+            "♻️ Restored task {} from the archive: {}",
+            restored.id,
+            restored.to_string_short()
+        );
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await
+    }
+
+    pub async fn details_task(&self, room_id: &OwnedRoomId, task_id: usize) -> Result<()> {
+        let todo_lists = self.storage.todo_lists.lock().await;
+        let tasks = todo_lists.get(room_id);
+
+        if let Some(tasks) = tasks {
+            if tasks.is_empty() {
+                let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+                self.send_matrix_message(room_id, message, None).await?;
+                return Ok(());
+            }
+
+            if let Some(task) = tasks.find(task_id) {
+                let dependents = tasks.dependents(task_id);
+                let message = format!("🔍 Task Details:\n{}", task.show_details(&dependents));
+                let html_message = format!(
+                    "🔍 Task Details:<br>{}",
+                    task.show_details_html(&dependents)
+                );
+                self.send_matrix_message(room_id, &message, Some(html_message))
+                    .await?;
+            } else {
+                let message = format!(
+                    "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                    task_id
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+            }
+        } else {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+            self.send_matrix_message(room_id, message, None).await?;
+        }
+        Ok(())
+    }
+
+    pub async fn add_tag(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        raw_tag: String,
+    ) -> Result<()> {
+        let tag = match normalize_tag(&raw_tag) {
+            Ok(tag) => tag,
+            Err(reason) => {
+                let message = format!("❌ Error: Invalid tag '{}'. {}", raw_tag, reason);
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            }
+        };
+
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let tasks = todo_lists.get_mut(room_id);
+
+        if let Some(tasks) = tasks {
+            if let Some(task) = tasks.find_mut(task_id) {
+                let previous = task.clone();
+                if task.add_tag(sender, tag.clone()) {
+                    let message = format!("🏷️ Task #{} tagged #{}", task_id, tag);
+                    let html_message = format!("🏷️ Task #{} tagged <code>#{}</code>", task_id, tag);
+                    self.send_matrix_message(room_id, &message, Some(html_message))
+                        .await?;
+                    drop(todo_lists);
+                    self.undo
+                        .push(
+                            room_id,
+                            UndoEntry::new(
+                                format!("tag of task {}", task_id),
+                                vec![UndoAction::RestoreTasks(vec![previous])],
+                            ),
+                        )
+                        .await;
+                    self.storage.save_room(room_id).await?;
+                } else {
+                    let message = format!("ℹ️ Info: Task #{} is already tagged #{}.", task_id, tag);
+                    self.send_matrix_message(room_id, &message, None).await?;
+                }
+            } else {
+                let message = format!(
+                    "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                    task_id
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+            }
+        } else {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+            self.send_matrix_message(room_id, message, None).await?;
+        }
+        Ok(())
+    }
+
+    /// Record that `task_id` can't be finished before `other_id`. Rejected when
+    /// `other_id` already depends on `task_id`, directly or through other tasks.
+    pub async fn add_dependency(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        other_id: usize,
+    ) -> Result<()> {
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let tasks = todo_lists.entry(room_id.clone()).or_default();
+
+        let message = if let Some(missing) = [task_id, other_id]
+            .into_iter()
+            .find(|&id| tasks.find(id).is_none())
+        {
+            format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                missing
+            )
+        } else if task_id == other_id {
+            "❌ Error: A task can't depend on itself.".to_owned()
+        } else if tasks.find(task_id).unwrap().depends_on.contains(&other_id) {
+            format!(
+                "ℹ️ Info: Task #{} already depends on task #{}.",
+                task_id, other_id
+            )
+        } else if let Some(path) = tasks.dependency_path(other_id, task_id) {
+            let chain = path
+                .iter()
+                .map(|id| format!("#{}", id))
+                .collect::<Vec<String>>()
+                .join(" → ");
+            format!(
+                "❌ Error: Task #{} can't depend on #{}, that would create a cycle: {} → #{}",
+                task_id, other_id, chain, other_id
+            )
+        } else {
+            let task = tasks.find_mut(task_id).unwrap();
+            let previous = task.clone();
+            task.depends_on.push(other_id);
+            task.add_internal_log(
+                sender.clone(),
+                TaskEvent::DependencyAdded,
+                Some(format!("on task #{}", other_id)),
+            );
+            let other_title = tasks.find(other_id).unwrap().title.clone();
+            info!(
+                user = %sender,
+                room_id = %room_id,
+                task_id = task_id,
+                depends_on = other_id,
+                "Added task dependency"
+            );
+            drop(todo_lists);
+
+            let message = format!(
+                "🔗 Task #{} now depends on task #{}: {}",
+                task_id, other_id, other_title
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            self.undo
+                .push(
+                    room_id,
+                    UndoEntry::new(
+                        format!("dependency of task {}", task_id),
+                        vec![UndoAction::RestoreTasks(vec![previous])],
+                    ),
+                )
+                .await;
+            return self.storage.save_room(room_id).await;
+        };
+        drop(todo_lists);
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// Assign a task to `@user:server`, to the sender with `me`, or to nobody with `off`
+    pub async fn assign_task(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        raw_user: &str,
+    ) -> Result<()> {
+        let raw_user = raw_user.trim();
+        let assignee = if raw_user.eq_ignore_ascii_case("off") {
+            None
+        } else if raw_user.eq_ignore_ascii_case("me") {
+            Some(sender.clone())
+        } else if let Ok(user_id) = UserId::parse(raw_user) {
+            Some(user_id.to_string())
+        } else {
+            let message = format!(
+                "❌ Error: Invalid user '{}'. Use a full Matrix ID like @user:server, me or off.",
+                raw_user
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+
+        let auto_watch = match &assignee {
+            Some(user) => self.auto_watches(room_id, user).await,
+            None => false,
+        };
+
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let Some(task) = todo_lists
+            .get_mut(room_id)
+            .and_then(|tasks| tasks.find_mut(task_id))
+        else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        let previous = task.clone();
+        task.set_assignee(sender, assignee.clone());
+        if let Some(user) = &assignee
+            && auto_watch
+        {
+            task.watch(user);
+        }
+        let message = match assignee {
+            Some(user) => format!("👤 Task #{} assigned to {}", task_id, user),
+            None => format!("👤 Task #{} is no longer assigned", task_id),
+        };
+        drop(todo_lists);
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(
+                    format!("assignment of task {}", task_id),
+                    vec![UndoAction::RestoreTasks(vec![previous])],
+                ),
+            )
+            .await;
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await
+    }
+
+    /// Set the estimated effort of a task in hours, or clear it with `off`
+    pub async fn estimate_task(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        raw_hours: &str,
+    ) -> Result<()> {
+        let raw_hours = raw_hours.trim();
+        let hours = if raw_hours.eq_ignore_ascii_case("off") {
+            None
+        } else {
+            match raw_hours.trim_end_matches(['h', 'H']).parse::<f64>() {
+                Ok(hours) if hours.is_finite() && hours > 0.0 => Some(hours),
+                _ => {
+                    let message = format!(
+                        "❌ Error: Invalid estimate '{}'. Use a positive number of hours, e.g. 1.5, or off.",
+                        raw_hours
+                    );
+                    self.send_matrix_message(room_id, &message, None).await?;
+                    return Ok(());
+                }
+            }
+        };
+
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let Some(task) = todo_lists
+            .get_mut(room_id)
+            .and_then(|tasks| tasks.find_mut(task_id))
+        else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        let previous = task.clone();
+        task.set_estimate(sender, hours);
+        let message = match hours {
+            Some(hours) => format!("⏱️ Task #{} estimated at {}h", task_id, hours),
+            None => format!("⏱️ Task #{} no longer has an estimate", task_id),
+        };
+        drop(todo_lists);
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(
+                    format!("estimate of task {}", task_id),
+                    vec![UndoAction::RestoreTasks(vec![previous])],
+                ),
+            )
+            .await;
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await
+    }
+
+    /// `!spent <id> <duration>` adds logged time, `!spent <id>` shows the total
+    pub async fn spent_command(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        raw_duration: &str,
+    ) -> Result<()> {
+        let raw_duration = raw_duration.trim();
+        let spent = if raw_duration.is_empty() {
+            None
+        } else {
+            match parse_time_spent(raw_duration) {
+                Ok(spent) => Some(spent),
+                Err(reason) => {
+                    let message = format!(
+                        "❌ Error: Invalid time '{}'. {} Use e.g. 90m, 1h30m or 2d.",
+                        raw_duration, reason
+                    );
+                    self.send_matrix_message(room_id, &message, None).await?;
+                    return Ok(());
+                }
+            }
+        };
+
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let Some(task) = todo_lists
+            .get_mut(room_id)
+            .and_then(|tasks| tasks.find_mut(task_id))
+        else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            drop(todo_lists);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        let Some(spent) = spent else {
+            let message = format!(
+                "🕒 Task #{} has {} logged.",
+                task_id,
+                format_time_spent(task.time_spent)
+            );
+            drop(todo_lists);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+
+        let previous = task.clone();
+        task.log_time(sender.clone(), spent);
+        let message = format!(
+            "🕒 Logged {} on task #{}, {} in total",
+            format_time_spent(spent),
+            task_id,
+            format_time_spent(task.time_spent)
+        );
+        info!(
+            user = %sender,
+            room_id = %room_id,
+            task_id = task_id,
+            spent_secs = spent.as_secs(),
+            "Logged time on task"
+        );
+        drop(todo_lists);
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(
+                    format!("time logged on task {}", task_id),
+                    vec![UndoAction::RestoreTasks(vec![previous])],
+                ),
+            )
+            .await;
+        self.storage.save_room(room_id).await
+    }
+
+    pub async fn recur_task(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        raw_rule: String,
+    ) -> Result<()> {
+        let recurrence = if raw_rule.trim().eq_ignore_ascii_case("off") {
+            None
+        } else {
+            match Recurrence::parse(&raw_rule) {
+                Some(recurrence) => Some(recurrence),
+                None => {
+                    let message = format!(
+                        "❌ Error: Invalid recurrence '{}'. Use daily, weekly, monthly, every N days (N up to {}) or off.",
+                        raw_rule.trim(),
+                        MAX_RECURRENCE_DAYS
+                    );
+                    self.send_matrix_message(room_id, &message, None).await?;
+                    return Ok(());
+                }
+            }
+        };
+
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let tasks = todo_lists.get_mut(room_id);
+
+        if let Some(tasks) = tasks {
+            if let Some(task) = tasks.find_mut(task_id) {
+                let previous = task.clone();
+                task.set_recurrence(sender, recurrence);
+                let message = match recurrence {
+                    Some(r) => format!("🔁 Task #{} now repeats {}", task_id, r.describe()),
+                    None => format!("🔁 Task #{} no longer repeats", task_id),
+                };
+                self.send_matrix_message(room_id, &message, None).await?;
+                drop(todo_lists);
+                self.undo
+                    .push(
+                        room_id,
+                        UndoEntry::new(
+                            format!("recurrence of task {}", task_id),
+                            vec![UndoAction::RestoreTasks(vec![previous])],
+                        ),
+                    )
+                    .await;
+                self.storage.save_room(room_id).await?;
+            } else {
+                let message = format!(
+                    "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                    task_id
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+            }
+        } else {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+            self.send_matrix_message(room_id, message, None).await?;
+        }
+        Ok(())
+    }
+
+    /// All tasks in the room carrying `tag`
+    pub async fn filter_by_tag(&self, room_id: &OwnedRoomId, tag: &str) -> Vec<Task> {
+        let todo_lists = self.storage.todo_lists.lock().await;
+        todo_lists
+            .get(room_id)
+            .map(|tasks| {
+                tasks
+                    .iter()
+                    .filter(|task| task.tags.iter().any(|t| t == tag))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    pub async fn filter_tasks(&self, room_id: &OwnedRoomId, raw_tag: String) -> Result<()> {
+        let tag = match normalize_tag(&raw_tag) {
+            Ok(tag) => tag,
+            Err(reason) => {
+                let message = format!("❌ Error: Invalid tag '{}'. {}", raw_tag, reason);
+                self.send_matrix_message(room_id, &message, None).await?;
+                return Ok(());
+            }
+        };
+
+        let tasks = self.filter_by_tag(room_id, &tag).await;
+        if tasks.is_empty() {
+            let message = format!("ℹ️ Info: No tasks tagged #{} in this room.", tag);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
+        let mut response = String::new();
+        for task in &tasks {
+            response.push_str(&format!("{}. {}\n", task.id, task.to_string_short()));
+        }
+
+        let message = format!("🏷️ Tasks tagged #{}:\n{}", tag, response);
+        let html_message = format!(
+            "🏷️ Tasks tagged #{}:<br>{}",
+            escape_html(&tag),
+            escape_html(&response).replace('\n', "<br>")
+        );
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        Ok(())
+    }
+
+    /// `!mine [status]`: tasks the sender created or is assigned to. Without a
+    /// status closed tasks are left out, like in `!list`.
+    pub async fn list_my_tasks(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: &str,
+        status: Option<TaskStatus>,
+    ) -> Result<()> {
+        let tasks: Vec<Task> = self
+            .storage
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .map(|list| {
+                list.iter()
+                    .filter(|task| {
+                        task.creator == sender || task.assignee.as_deref() == Some(sender)
+                    })
+                    .filter(|task| match status {
+                        Some(status) => task.status == status,
+                        None => task.status != TaskStatus::Closed,
+                    })
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default();
+        let scope = status.map(|s| format!("{} ", s)).unwrap_or_default();
+        if tasks.is_empty() {
+            let message = format!("ℹ️ Info: No {}tasks for {} in this room.", scope, sender);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
+        let mut response = String::new();
+        for task in &tasks {
+            response.push_str(&format!("{}. {}\n", task.id, task.to_string_short()));
+        }
+        let heading = format!("🙋 {} {}task(s) for {}:", tasks.len(), scope, sender);
+        let message = format!("{}\n{}", heading, response);
+        let html_message = format!(
+            "{}<br>{}",
+            escape_html(&heading),
+            response.replace('\n', "<br>")
+        );
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        Ok(())
+    }
+
+    /// Task counts for a room, computed while holding the `todo_lists` lock.
+    /// Activity includes the history offloaded to the cold store.
+    pub async fn room_stats(&self, room_id: &OwnedRoomId) -> RoomStats {
+        let cold = self
+            .storage
+            .read_cold_history(room_id)
+            .await
+            .unwrap_or_else(|e| {
+                warn!(room_id = %room_id, error = %e, "Failed to read offloaded history for stats");
+                Default::default()
+            });
+        let todo_lists = self.storage.todo_lists.lock().await;
+        let Some(tasks) = todo_lists.get(room_id) else {
+            return RoomStats::default();
+        };
+        let mut stats = RoomStats::from_tasks(tasks);
+        for task in tasks.iter() {
+            if let Some(history) = cold.get(&task.id) {
+                stats.count_activity(history);
+            }
+        }
+        stats
+    }
+
+    pub async fn stats_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let stats = self.room_stats(room_id).await;
+        if stats.total == 0 {
+            let message = "ℹ️ Info: There are no tasks in this room yet.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+        let (message, html_message) = stats.render("Room statistics");
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        Ok(())
+    }
+
+    /// The room's tasks as a Markdown document with one section per status
+    pub async fn export_markdown(&self, room_id: &OwnedRoomId) -> String {
+        let tasks = self
+            .storage
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .map(|list| list.tasks.clone())
+            .unwrap_or_default();
+
+        let mut sections = vec![format!(
+            "# To-Do List\n\nExported {}",
+            Utc::now().format("%Y-%m-%d %H:%M UTC")
+        )];
+        for status in TaskStatus::LIST_ORDER {
+            let entries = tasks
+                .iter()
+                .filter(|t| t.status == status)
+                .map(Task::to_markdown)
+                .collect::<Vec<String>>();
+            if !entries.is_empty() {
+                sections.push(format!("## {}\n\n{}", status.label(), entries.join("\n")));
+            }
+        }
+        let mut document = sections.join("\n\n");
+        document.push('\n');
+        document
+    }
+
+    pub async fn export_command(&self, room_id: &OwnedRoomId, format: &str) -> Result<()> {
+        let format = format.trim().to_lowercase();
+        if format != "md" && format != "csv" {
+            let message = "⚠️ Usage: !export md|csv";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+        let is_empty = self
+            .storage
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .is_none_or(|list| list.is_empty());
+        if is_empty {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list to export.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+
+        if format == "csv" {
+            let (filename, rows) = self.storage.export_csv(room_id).await?;
+            let message = format!("📤 CSV export of {} task(s) saved as {}", rows, filename);
+            self.send_matrix_message(room_id, &message, None).await?;
+            let data = tokio::fs::read(self.storage.data_dir.join(&filename)).await?;
+            return self
+                .message_sender
+                .send_file(room_id, "export", &filename, "text/csv", data)
+                .await;
+        }
+
+        let markdown = self.export_markdown(room_id).await;
+        let saved_as = match self.storage.write_export(room_id, "md", &markdown).await {
+            Ok(filename) => format!("saved as {}", filename),
+            Err(e) => {
+                // The room still gets the export even if the copy on disk failed
+                error!(room_id = %room_id, error = %e, "Failed to write Markdown export file");
+                "not saved to disk".to_owned()
+            }
+        };
+
+        // Each part is its own code block so no fence is cut in half
+        let parts = split_text(&markdown, MARKDOWN_EXPORT_PART_BYTES);
+        if parts.len() > 1 {
+            debug!(
+                bytes = markdown.len(),
+                parts = parts.len(),
+                "Markdown export exceeds one event, sending it in parts"
+            );
+        }
+        for (i, part) in parts.iter().enumerate() {
+            let heading = if parts.len() == 1 {
+                format!("📤 Markdown export ({})", saved_as)
+            } else {
+                format!(
+                    "📤 Markdown export ({}), part {}/{}",
+                    saved_as,
+                    i + 1,
+                    parts.len()
+                )
+            };
+            let message = format!("{}\n```markdown\n{}\n```", heading, part);
+            let html = format!(
+                "{}<pre><code class=\"language-markdown\">{}</code></pre>",
+                escape_html(&heading),
+                escape_html(part)
+            );
+            self.send_matrix_message(room_id, &message, Some(html))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Open tasks per assignee, busiest first, plus the number of open unassigned tasks
+    pub async fn workload(&self, room_id: &OwnedRoomId) -> (Vec<Workload>, usize) {
+        let todo_lists = self.storage.todo_lists.lock().await;
+        let mut by_user: Vec<Workload> = Vec::new();
+        let mut unassigned = 0;
+        for task in todo_lists.get(room_id).map(|l| l.as_slice()).unwrap_or(&[]) {
+            if matches!(task.status, TaskStatus::Done | TaskStatus::Closed) {
+                continue;
+            }
+            let Some(assignee) = &task.assignee else {
+                unassigned += 1;
+                continue;
+            };
+            let entry = match by_user.iter().position(|w| &w.user == assignee) {
+                Some(index) => &mut by_user[index],
+                None => {
+                    by_user.push(Workload {
+                        user: assignee.clone(),
+                        open_tasks: 0,
+                        estimate_hours: 0.0,
+                    });
+                    by_user.last_mut().expect("just pushed")
+                }
+            };
+            entry.open_tasks += 1;
+            entry.estimate_hours += task.estimate_hours.unwrap_or(0.0);
+        }
+        by_user.sort_by(|a, b| {
+            b.open_tasks
+                .cmp(&a.open_tasks)
+                .then_with(|| a.user.cmp(&b.user))
+        });
+        (by_user, unassigned)
+    }
+
+    pub async fn workload_command(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let (by_user, unassigned) = self.workload(room_id).await;
+        if by_user.is_empty() && unassigned == 0 {
+            let message = "ℹ️ Info: There are no open tasks in this room.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+
+        let mut lines = Vec::new();
+        let mut html_lines = Vec::new();
+        for (rank, entry) in by_user.iter().enumerate() {
+            let estimate = if entry.estimate_hours > 0.0 {
+                format!(", ~{}h estimated", entry.estimate_hours)
+            } else {
+                String::new()
+            };
+            lines.push(format!(
+                "{}. {} — {} open task(s){}",
+                rank + 1,
+                entry.user,
+                entry.open_tasks,
+                estimate
+            ));
+            html_lines.push(format!(
+                "<li><b>{}</b> — {} open task(s){}</li>",
+                escape_html(&entry.user),
+                entry.open_tasks,
+                estimate
+            ));
+        }
+        let footer = format!("Unassigned: {} open task(s)", unassigned);
+        let message = format!("👥 Workload:\n{}\n{}", lines.join("\n"), footer);
+        let html_message = format!(
+            "👥 <b>Workload</b><ol>{}</ol>{}",
+            html_lines.join(""),
+            footer
+        );
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn list_tags(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let tags = {
+            let todo_lists = self.storage.todo_lists.lock().await;
+            let mut tags: Vec<String> = todo_lists
+                .get(room_id)
+                .map(|tasks| tasks.iter().flat_map(|t| t.tags.iter().cloned()).collect())
+                .unwrap_or_default();
+            tags.sort();
+            tags.dedup();
+            tags
+        };
+
+        if tags.is_empty() {
+            let message = "ℹ️ Info: No tags are in use in this room.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+
+        let tags_list = tags
+            .iter()
+            .map(|t| format!("#{}", t))
+            .collect::<Vec<String>>()
+            .join(" ");
+        let message = format!("🏷️ Tags in use:\n{}", tags_list);
+        let html_message = format!("🏷️ Tags in use:<br>{}", tags_list);
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        Ok(())
+    }
+
+    /// Tasks in the room matching a `!search` query. The room's tasks are copied
+    /// first so the scan itself runs without holding the `todo_lists` lock.
+    pub async fn search_tasks(&self, room_id: &OwnedRoomId, query: &SearchQuery) -> Vec<Task> {
+        let tasks = {
+            let todo_lists = self.storage.todo_lists.lock().await;
+            todo_lists.get(room_id).cloned().unwrap_or_default()
+        };
+        tasks
+            .tasks
+            .into_iter()
+            .filter(|t| query.matches(t))
+            .collect()
+    }
+
+    pub async fn search_command(&self, room_id: &OwnedRoomId, raw_query: &str) -> Result<()> {
+        let query = SearchQuery::parse(raw_query);
+        if query.is_empty() {
+            let message =
+                "⚠️ Error: Missing search query. Format: !search \"some words\" status:pending";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        }
+
+        let tasks = self.search_tasks(room_id, &query).await;
+        if tasks.is_empty() {
+            let message = format!(
+                "🔎 No matches: no tasks in this room match '{}'.",
+                raw_query.trim()
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        }
+
+        let mut response = String::new();
+        let mut html_response = String::new();
+        for task in &tasks {
+            response.push_str(&format!("{}. {}\n", task.id, task.to_string_short()));
+            html_response.push_str(&format!(
+                "{}. {}<br>",
+                task.id,
+                escape_html(&task.to_string_short())
+            ));
+            // Matches on the creator or status alone have no text excerpt to show
+            if let Some((snippet, html_snippet)) = query.snippet(task) {
+                response.push_str(&format!("   ↳ {}\n", snippet));
+                html_response.push_str(&format!("&nbsp;&nbsp;&nbsp;↳ <i>{}</i><br>", html_snippet));
+            }
+        }
+
+        let message = format!("🔎 Search results ({}):\n{}", tasks.len(), response);
+        let html_message = format!("🔎 Search results ({}):<br>{}", tasks.len(), html_response);
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        Ok(())
+    }
+
+    pub async fn history_task(&self, room_id: &OwnedRoomId, task_id: usize) -> Result<()> {
+        let task = {
+            let todo_lists = self.storage.todo_lists.lock().await;
+            todo_lists
+                .get(room_id)
+                .and_then(|tasks| tasks.find(task_id))
+                .cloned()
+        };
+
+        let Some(task) = task else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+
+        // Reads offloaded entries from the cold store (if any) followed by recent ones
+        let history = self.storage.task_history(room_id, &task).await?;
+        let lines = history
+            .iter()
+            .map(|(timestamp, user, action)| format!("• {} - {}: {}", timestamp, user, action))
+            .collect::<Vec<String>>()
+            .join("\n");
+
+        let message = format!("📜 History of Task #{}:\n{}", task_id, lines);
+        let html_message = format!(
+            "📜 History of Task #{}:<br>{}",
+            task_id,
+            escape_html(&lines).replace('\n', "<br>")
+        );
+        self.send_matrix_message(room_id, &message, Some(html_message))
+            .await?;
+        Ok(())
+    }
+
+    /// Revert the most recent recorded mutation in the room
+    pub async fn undo_command(&self, room_id: &OwnedRoomId, sender: String) -> Result<()> {
+        let Some(entry) = self.undo.pop(room_id).await else {
+            let message = "ℹ️ Info: Nothing to undo in this room.";
+            self.send_matrix_message(room_id, message, None).await?;
+            return Ok(());
+        };
+        let description = entry.description.clone();
+        {
+            let mut todo_lists = self.storage.todo_lists.lock().await;
+            let mut archived = self.storage.archived.lock().await;
+            let mut archive = archived.remove(room_id).unwrap_or_default();
+            entry.apply(todo_lists.entry(room_id.clone()).or_default(), &mut archive);
+            if !archive.is_empty() {
+                archived.insert(room_id.clone(), archive);
+            }
+        }
+        info!(user = %sender, room_id = %room_id, undone = %description, "Undid task mutation");
+        let message = format!("↩️ Reverted: {}", description);
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await
+    }
+
+    // Use MessageSender trait to send messages without directly depending on Matrix SDK
+    pub async fn send_matrix_message(
+        &self,
+        room_id: &OwnedRoomId,
+        message: &str,
+        html_message: Option<String>,
+    ) -> Result<OwnedEventId> {
+        self.message_sender
+            .send_reply(room_id, message, html_message)
+            .await
+    }
+
+    pub async fn edit_task(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        new_title: String,
+    ) -> Result<()> {
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let tasks = todo_lists.get_mut(room_id);
+
+        if let Some(tasks) = tasks {
+            if tasks.is_empty() {
+                let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+                self.send_matrix_message(room_id, message, None).await?;
+                return Ok(());
+            }
+
+            if let Some(task) = tasks.find_mut(task_id) {
+                let previous = task.clone();
+                let old_title = task.title.clone();
+                task.set_title(sender.clone(), new_title.clone());
+
+                let mut message = format!(
+                    "✏️ Task Edited: Task #{} title changed:\nFrom: {}\nTo: {}",
+                    task_id, old_title, new_title
+                );
+                let mut html_message = format!(
+                    "✏️ Task Edited: Task #{} title changed:<br><b>From:</b> {}<br><b>To:</b> {}",
+                    task_id, old_title, new_title
+                );
+                mention_watchers(&task.watchers, &sender, &mut message, &mut html_message);
+                self.send_matrix_message(room_id, &message, Some(html_message))
+                    .await?;
+                drop(todo_lists);
+                self.undo
+                    .push(
+                        room_id,
+                        UndoEntry::new(
+                            format!("edit of task {}", task_id),
+                            vec![UndoAction::RestoreTasks(vec![previous])],
+                        ),
+                    )
+                    .await;
+                self.storage.save_room(room_id).await?;
+            } else {
+                let message = format!(
+                    "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                    task_id
+                );
+                self.send_matrix_message(room_id, &message, None).await?;
+            }
+        } else {
+            let message = "ℹ️ Info: There are no tasks in this room's to-do list.";
+            self.send_matrix_message(room_id, message, None).await?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::mock::MockMessageSender;
+    use uuid::Uuid;
+
+    struct Fixture {
+        todo: TodoList,
+        sender: Arc<MockMessageSender>,
+        storage: Arc<StorageManager>,
+        room_id: OwnedRoomId,
+        data_dir: std::path::PathBuf,
+    }
+
+    impl Fixture {
+        fn new() -> Self {
+            Self::with_history_offload(None)
+        }
+
+        fn with_history_offload(days: Option<u32>) -> Self {
+            let data_dir = std::env::temp_dir().join(format!("asmith-test-{}", Uuid::new_v4()));
+            let storage = Arc::new(
+                StorageManager::new(
+                    data_dir.clone(),
+                    Uuid::new_v4(),
+                    days,
+                    false,
+                    None,
+                    0,
+                    Default::default(),
+                )
+                .unwrap(),
+            );
+            let sender = Arc::new(MockMessageSender::new());
+            Self {
+                todo: TodoList::new(
+                    sender.clone(),
+                    storage.clone(),
+                    crate::config::DEFAULT_MAX_TASKS_PER_ROOM,
+                ),
+                sender,
+                storage,
+                room_id: OwnedRoomId::try_from("!room:example.org").unwrap(),
+                data_dir,
+            }
+        }
+
+        fn last_message(&self) -> crate::messaging::mock::SentMessage {
+            self.sender.sent_messages().pop().expect("no message sent")
+        }
+
+        /// Timestamped save files, `latest.json` being a copy of the newest
+        fn save_files(&self) -> usize {
+            std::fs::read_dir(&self.data_dir)
+                .unwrap()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.path().extension().is_some_and(|ext| ext == "json"))
+                .filter(|e| e.file_name() != crate::storage::LATEST_FILE)
+                .count()
+        }
+
+        async fn task(&self, id: usize) -> Task {
+            self.storage.todo_lists.lock().await[&self.room_id]
+                .find(id)
+                .cloned()
+                .expect("task exists")
+        }
+    }
+
+    impl Drop for Fixture {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_dir_all(&self.data_dir);
+        }
+    }
+
+    #[tokio::test]
+    async fn add_task_confirms_and_saves() {
+        let f = Fixture::new();
+        f.todo
+            .add_task(
+                &f.room_id,
+                "@alice:example.org".into(),
+                "Buy milk".into(),
+                false,
+            )
+            .await
+            .unwrap();
+
+        let message = f.last_message();
+        assert_eq!(message.room_id, f.room_id);
+        assert!(message.text.contains("Task 1 added by @alice:example.org"));
+        assert!(message.text.contains("Buy milk"));
+        assert_eq!(f.task(1).await.title, "Buy milk");
+        assert_eq!(f.save_files(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_matrix_message_returns_event_id() {
+        let f = Fixture::new();
+        let first = f
+            .todo
+            .send_matrix_message(&f.room_id, "plain", None)
+            .await
+            .unwrap();
+        let second = f
+            .todo
+            .send_matrix_message(&f.room_id, "rich", Some("<b>rich</b>".into()))
+            .await
+            .unwrap();
+
+        assert_ne!(first, second);
+        assert_eq!(f.sender.returned_ids(), vec![first, second.clone()]);
+        assert_eq!(f.last_message().event_id, second);
+    }
+
+    #[tokio::test]
+    async fn replies_go_to_the_thread_of_the_command() {
+        let f = Fixture::new();
+        let root = OwnedEventId::try_from("$command:example.org").unwrap();
+        crate::messaging::THREAD_ROOT
+            .scope(
+                root.clone(),
+                f.todo.add_task(
+                    &f.room_id,
+                    "@alice:example.org".into(),
+                    "Threaded".into(),
+                    false,
+                ),
+            )
+            .await
+            .unwrap();
+        assert_eq!(f.last_message().thread_root, Some(root));
+
+        f.todo
+            .list_tasks(&f.room_id, 1, ListFilter::Open)
+            .await
+            .unwrap();
+        assert_eq!(f.last_message().thread_root, None);
+    }
+
+    #[tokio::test]
+    async fn list_view_is_edited_after_changes() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Standup notes".into(), false)
+            .await
+            .unwrap();
+        f.todo
+            .list_tasks(&f.room_id, 1, ListFilter::Open)
+            .await
+            .unwrap();
+        let list_id = f.last_message().event_id;
+
+        // Nothing changed, nothing to edit
+        f.todo.refresh_list_view(&f.room_id).await.unwrap();
+        assert!(f.sender.edits().is_empty());
+
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        let sent = f.sender.sent_messages().len();
+        f.todo.refresh_list_view(&f.room_id).await.unwrap();
+        let edits = f.sender.edits();
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].event_id, list_id);
+        assert!(edits[0].text.contains("✅ Done:"));
+        assert_eq!(f.sender.sent_messages().len(), sent);
+
+        // A redacted list is posted again and later edits go to the new one
+        f.sender.redact(&list_id);
+        f.todo
+            .add_task(&f.room_id, alice, "Retro".into(), false)
+            .await
+            .unwrap();
+        f.todo.refresh_list_view(&f.room_id).await.unwrap();
+        let reposted = f.last_message();
+        assert!(reposted.text.contains("Retro"));
+        assert_eq!(f.sender.edits().len(), 1);
+        assert_eq!(
+            f.todo.list_views.get(&f.room_id).await.unwrap().event_id,
+            reposted.event_id
+        );
+    }
+
+    #[tokio::test]
+    async fn done_task_marks_done_and_saves() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Buy milk".into(), false)
+            .await
+            .unwrap();
+        let saves = f.storage.generation();
+        f.todo
+            .done_task(&f.room_id, alice, 1, false, None)
+            .await
+            .unwrap();
+
+        let message = f.last_message();
+        assert!(message.text.contains("Task 1 marked as done"));
+        assert!(message.html.unwrap().contains("<b>Buy milk</b>"));
+        assert_eq!(f.task(1).await.status, TaskStatus::Done);
+        assert!(f.storage.generation() > saves);
+    }
+
+    #[tokio::test]
+    async fn done_task_reports_missing_task() {
+        let f = Fixture::new();
+        f.todo
+            .done_task(&f.room_id, "@alice:example.org".into(), 7, false, None)
+            .await
+            .unwrap();
+
+        assert_eq!(f.last_message().text, "❌ Error: Task 7 doesn't exist.");
+        assert_eq!(f.save_files(), 0);
+    }
+
+    #[tokio::test]
+    async fn edit_task_changes_title_and_saves() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Buy milk".into(), false)
+            .await
+            .unwrap();
+        let saves = f.storage.generation();
+        f.todo
+            .edit_task(&f.room_id, alice, 1, "Buy oat milk".into())
+            .await
+            .unwrap();
+
+        let message = f.last_message();
+        assert!(message.text.contains("From: Buy milk\nTo: Buy oat milk"));
+        assert_eq!(f.task(1).await.title, "Buy oat milk");
+        assert!(f.storage.generation() > saves);
+    }
+
+    #[tokio::test]
+    async fn list_tasks_groups_by_status_and_hides_closed() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Buy milk", "Call Bob", "Old chore"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        f.todo.close_task(&f.room_id, alice, 3, None).await.unwrap();
+        let saves = f.storage.generation();
+
+        f.todo
+            .list_tasks(&f.room_id, 1, ListFilter::Open)
+            .await
+            .unwrap();
+
+        let message = f.last_message();
+        assert!(message.text.starts_with("📋 Room To-Do List:"));
+        let pending = message.text.find("Pending:").unwrap();
+        let done = message.text.find("Done:").unwrap();
+        assert!(pending < done, "open work is listed before finished work");
+        assert!(message.text.contains("2. ⏳ **[pending] Call Bob**"));
+        assert!(message.text.contains("1. ✅ **[done] Buy milk**"));
+        assert!(!message.text.contains("Old chore"));
+        assert!(message.html.is_some());
+        // Listing is read-only
+        assert_eq!(f.storage.generation(), saves);
+    }
+
+    #[tokio::test]
+    async fn done_and_close_log_an_optional_note() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Ship it", "Old idea", "Plain"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+
+        f.todo
+            .done_task(
+                &f.room_id,
+                alice.clone(),
+                1,
+                false,
+                Some("merged in #42".into()),
+            )
+            .await
+            .unwrap();
+        let message = f.last_message();
+        assert!(message.text.ends_with("**Ship it**\n📝 merged in #42"));
+        assert!(message.html.unwrap().ends_with("<br>📝 merged in #42"));
+        let task = f.task(1).await;
+        assert_eq!(task.status, TaskStatus::Done);
+        assert_eq!(task.logs[0].text, "merged in #42");
+        assert_eq!(task.logs[0].author.as_deref(), Some("@alice:example.org"));
+
+        f.todo
+            .close_task(&f.room_id, alice.clone(), 2, Some("<won't do>".into()))
+            .await
+            .unwrap();
+        let message = f.last_message();
+        assert!(message.text.ends_with("\n📝 <won't do>"));
+        assert!(message.html.unwrap().ends_with("<br>📝 &lt;won't do&gt;"));
+        assert_eq!(f.task(2).await.logs[0].text, "<won't do>");
+
+        f.todo
+            .done_task(&f.room_id, alice, 3, false, None)
+            .await
+            .unwrap();
+        assert!(!f.last_message().text.contains("📝"));
+        assert!(f.task(3).await.logs.is_empty());
+    }
+
+    #[tokio::test]
+    async fn archive_moves_finished_tasks_and_restore_brings_them_back() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Buy milk", "Call Bob"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        f.todo
+            .archive_tasks(&f.room_id, alice.clone())
+            .await
+            .unwrap();
+
+        assert!(
+            f.last_message()
+                .text
+                .contains("Archived 1 done or closed task(s)")
+        );
+        assert!(
+            f.storage.todo_lists.lock().await[&f.room_id]
+                .find(1)
+                .is_none()
+        );
+        assert_eq!(f.storage.snapshot().await.archived[&f.room_id].len(), 1);
+
+        f.todo.restore_archived(&f.room_id, alice, 1).await.unwrap();
+        let restored = f.task(1).await;
+        assert_eq!(restored.status, TaskStatus::Done);
+        assert!(restored.internal_logs.len() > 2);
+        assert!(f.storage.archived.lock().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn undo_reverts_archive_and_restore() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Buy milk", "Call Bob"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        f.todo
+            .archive_tasks(&f.room_id, alice.clone())
+            .await
+            .unwrap();
+        f.todo
+            .restore_archived(&f.room_id, alice.clone(), 1)
+            .await
+            .unwrap();
+
+        f.todo
+            .undo_command(&f.room_id, alice.clone())
+            .await
+            .unwrap();
+        assert_eq!(f.last_message().text, "↩️ Reverted: restore of task 1");
+        assert!(
+            f.storage.todo_lists.lock().await[&f.room_id]
+                .find(1)
+                .is_none()
+        );
+        assert_eq!(f.storage.archived.lock().await[&f.room_id][0].id, 1);
+
+        f.todo.undo_command(&f.room_id, alice).await.unwrap();
+        assert_eq!(f.last_message().text, "↩️ Reverted: archive of 1 task(s)");
+        assert_eq!(f.task(1).await.status, TaskStatus::Done);
+        assert!(f.storage.archived.lock().await.is_empty());
+    }
+
+    #[test]
+    fn completion_time_follows_status_and_old_tasks_are_backfilled() {
+        let mut task = Task::new("@alice:example.org".into(), 1, "Ship".into());
+        assert!(task.to_string_short().ends_with("** (0m)"));
+        task.set_status("@bob:example.org".into(), TaskStatus::Done, None);
+        assert!(task.completed_at.is_some());
+        assert!(task.show_details(&[]).contains("\nCompleted: "));
+        task.reopen("@bob:example.org".into());
+        assert_eq!(task.completed_at, None);
+
+        // Saved before the fields existed: both come from the history
+        task.set_status("@bob:example.org".into(), TaskStatus::Closed, None);
+        task.internal_logs[0].0 = "2024-05-01 09:30:00".into();
+        task.internal_logs[3].0 = "2024-05-04 08:00:00".into();
+        let mut json = serde_json::to_value(&task).unwrap();
+        let fields = json.as_object_mut().unwrap();
+        fields.remove("created_at");
+        fields.remove("completed_at");
+        let loaded: Task = serde_json::from_value(json).unwrap();
+        assert_eq!(loaded.created_at.to_string(), "2024-05-01 09:30:00 UTC");
+        assert_eq!(
+            loaded.completed_at.unwrap().to_string(),
+            "2024-05-04 08:00:00 UTC"
+        );
+        assert_eq!(format_age(Duration::hours(80)), "3d");
+    }
+
+    #[test]
+    fn show_details_formats_history() {
+        let mut task = Task::new("@alice:example.org".into(), 3, "Fix <b>bug</b>".into());
+        task.internal_logs[0].0 = "2024-05-01 09:30:00".into();
+        task.set_status("@bob:example.org".into(), TaskStatus::Done, None);
+        task.reopen("@bob:example.org".into());
+        task.internal_logs[1].0 = "2024-05-02 10:00:00".into();
+        task.internal_logs[2].0 = "2024-05-03 11:15:00".into();
+
+        let details = task.show_details(&[]);
+        assert!(details.contains(
+            "**History:**\n\
+            • [2024-05-01 09:30:00] @alice:example.org — Created task\n\
+            • [2024-05-02 10:00:00] @bob:example.org — Updated status: from 'pending' to 'done'\n\
+            • [2024-05-03 11:15:00] @bob:example.org — Reopened task: was 'done'"
+        ));
+
+        let html = task.show_details_html(&[]);
+        assert!(html.starts_with("⏳ <b>[pending] Fix &lt;b&gt;bug&lt;/b&gt;</b>"));
+        assert!(
+            html.contains("<ul><li>[2024-05-01 09:30:00] @alice:example.org — Created task</li>")
+        );
+        assert!(html.ends_with(
+            "<li>[2024-05-03 11:15:00] @bob:example.org — Reopened task: was 'done'</li></ul>"
+        ));
+    }
+
+    #[test]
+    fn finished_at_is_the_last_status_change() {
+        let mut task = Task::new("@alice:example.org".into(), 1, "Old".into());
+        assert_eq!(task.finished_at(), None);
+        task.set_status("@bob:example.org".into(), TaskStatus::Done, None);
+        task.set_status("@bob:example.org".into(), TaskStatus::Closed, None);
+        task.internal_logs[1].0 = "2024-05-02 10:00:00".into();
+        task.internal_logs[2].0 = "2024-05-03 11:15:00".into();
+        let expected = NaiveDate::from_ymd_opt(2024, 5, 3)
+            .and_then(|d| d.and_hms_opt(11, 15, 0))
+            .map(|t| t.and_utc());
+        assert_eq!(task.finished_at(), expected);
+
+        task.reopen("@bob:example.org".into());
+        assert_eq!(task.finished_at(), None);
+    }
+
+    #[test]
+    fn normalize_title_ignores_case_and_spacing() {
+        assert_eq!(normalize_title("  Buy   MILK\t"), "buy milk");
+        assert_eq!(normalize_title("Ünïcödé  ÉCOLE"), "ünïcödé école");
+        assert_eq!(normalize_title("🚀 Launch\n 🎉"), "🚀 launch 🎉");
+        assert_ne!(normalize_title("Straße"), normalize_title("Strasse"));
+    }
+
+    #[tokio::test]
+    async fn add_task_rejects_open_duplicate_unless_forced() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Buy milk".into(), false)
+            .await
+            .unwrap();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), " buy  MILK ".into(), false)
+            .await
+            .unwrap();
+        assert!(
+            f.last_message()
+                .text
+                .starts_with("⚠️ Task already exists as #1 (pending)")
+        );
+
+        f.todo
+            .add_task(&f.room_id, alice, "buy milk".into(), true)
+            .await
+            .unwrap();
+        assert_eq!(f.task(2).await.title, "buy milk");
+    }
+
+    #[tokio::test]
+    async fn dependencies_block_done_and_reject_cycles() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Design", "Build", "Ship"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .add_dependency(&f.room_id, alice.clone(), 3, 2)
+            .await
+            .unwrap();
+        f.todo
+            .add_dependency(&f.room_id, alice.clone(), 2, 1)
+            .await
+            .unwrap();
+        assert_eq!(f.task(3).await.depends_on, vec![2]);
+
+        f.todo
+            .add_dependency(&f.room_id, alice.clone(), 1, 3)
+            .await
+            .unwrap();
+        assert!(f.last_message().text.contains("cycle: #3 → #2 → #1 → #3"));
+        assert!(f.task(1).await.depends_on.is_empty());
+
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 2, false, None)
+            .await
+            .unwrap();
+        assert!(f.last_message().text.contains("• #1 [pending] Design"));
+        assert_eq!(f.task(2).await.status, TaskStatus::Pending);
+
+        f.todo
+            .list_tasks(&f.room_id, 1, ListFilter::Open)
+            .await
+            .unwrap();
+        assert!(
+            f.last_message()
+                .text
+                .contains("Build** (0m) ⛓️ waiting on #1")
+        );
+
+        f.todo
+            .done_task(&f.room_id, alice, 2, true, None)
+            .await
+            .unwrap();
+        assert_eq!(f.task(2).await.status, TaskStatus::Done);
+        let list = &f.storage.todo_lists.lock().await[&f.room_id];
+        assert_eq!(list.dependents(2), vec![3]);
+        assert!(list.unfinished_dependencies(3).is_empty());
+    }
+
+    #[tokio::test]
+    async fn undo_reverts_in_reverse_order_until_empty() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Buy milk".into(), false)
+            .await
+            .unwrap();
+        f.todo
+            .start_task(&f.room_id, alice.clone(), 1)
+            .await
+            .unwrap();
+        f.todo
+            .edit_task(&f.room_id, alice.clone(), 1, "Buy oat milk".into())
+            .await
+            .unwrap();
+
+        f.todo
+            .undo_command(&f.room_id, alice.clone())
+            .await
+            .unwrap();
+        assert_eq!(f.last_message().text, "↩️ Reverted: edit of task 1");
+        assert_eq!(f.task(1).await.title, "Buy milk");
+        assert_eq!(f.task(1).await.status, TaskStatus::InProgress);
+
+        f.todo
+            .undo_command(&f.room_id, alice.clone())
+            .await
+            .unwrap();
+        assert_eq!(f.last_message().text, "↩️ Reverted: in progress of task 1");
+        assert_eq!(f.task(1).await.status, TaskStatus::Pending);
+
+        f.todo
+            .undo_command(&f.room_id, alice.clone())
+            .await
+            .unwrap();
+        f.todo.undo_command(&f.room_id, alice).await.unwrap();
+        assert_eq!(
+            f.last_message().text,
+            "ℹ️ Info: Nothing to undo in this room."
+        );
+        assert!(f.storage.todo_lists.lock().await[&f.room_id].is_empty());
+    }
+
+    #[test]
+    fn truncate_text_respects_characters() {
+        assert_eq!(truncate_text("short", 30), "short");
+        assert_eq!(truncate_text("exactly", 7), "exactly");
+        assert_eq!(truncate_text("abcdef", 3), "abc...");
+        // Multi-byte characters around the cut used to panic
+        assert_eq!(truncate_text("日本語のタスクを書く", 4), "日本語の...");
+        assert_eq!(truncate_text("🚀🚀🚀🚀", 2), "🚀🚀...");
+        // Combining accents, skin tones and ZWJ sequences stay whole
+        assert_eq!(truncate_text("cafe\u{301} au lait", 4), "cafe\u{301}...");
+        assert_eq!(truncate_text("hi 👍🏽 there", 4), "hi 👍🏽...");
+        assert_eq!(
+            truncate_text("ok 👨\u{200D}👩\u{200D}👧 family", 4),
+            "ok 👨\u{200D}👩\u{200D}👧..."
+        );
+    }
+
+    #[test]
+    fn history_excerpts_of_non_ascii_text_do_not_panic() {
+        let alice = "@alice:example.org".to_owned();
+        // Byte 30 falls inside a character in each of these
+        let emoji = "🎉".repeat(40);
+        let cjk = "漢字".repeat(20);
+        let combining = "e\u{301}".repeat(20);
+        let mut task = Task::new(alice.clone(), 1, "a".repeat(29) + "é and more");
+        for text in [&emoji, &cjk, &combining] {
+            task.add_log(alice.clone(), text.clone());
+            task.set_title(alice.clone(), text.clone());
+        }
+        let texts: Vec<&str> = task.logs.iter().map(|log| log.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            vec![emoji.as_str(), cjk.as_str(), combining.as_str()]
+        );
+        // Logs carry their own attribution and no longer show up in the history
+        let (_, _, action) = &task.internal_logs[1];
+        assert_eq!(
+            action,
+            &format!(
+                "Edited title: from '{}é...' to '{}...'",
+                "a".repeat(29),
+                "🎉".repeat(30)
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn multi_line_add_keeps_description() {
+        let f = Fixture::new();
+        f.todo
+            .add_task(
+                &f.room_id,
+                "@alice:example.org".into(),
+                "Buy milk\nFrom the corner shop\nTwo litres\n".into(),
+                false,
+            )
+            .await
+            .unwrap();
+
+        let task = f.task(1).await;
+        assert_eq!(task.title, "Buy milk");
+        assert_eq!(
+            task.description.as_deref(),
+            Some("From the corner shop\nTwo litres")
+        );
+        assert!(
+            task.show_details(&[])
+                .contains("Buy milk** (0m)\nFrom the corner shop\nTwo litres\n")
+        );
+        assert!(
+            task.show_details_html(&[])
+                .contains("<br>From the corner shop<br>Two litres")
+        );
+        assert_eq!(
+            split_title_description("  One line  "),
+            ("One line".to_owned(), None)
+        );
+    }
+
+    #[tokio::test]
+    async fn export_markdown_renders_checklist_and_writes_file() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Buy milk", "Call Bob"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .assign_task(&f.room_id, alice.clone(), 1, "me")
+            .await
+            .unwrap();
+        f.todo
+            .done_task(&f.room_id, alice, 2, false, None)
+            .await
+            .unwrap();
+
+        let markdown = f.todo.export_markdown(&f.room_id).await;
+        assert!(markdown.contains("- [ ] #1 Buy milk (assigned to @alice:example\\.org)"));
+        assert!(markdown.contains("## Done\n\n- [x] #2 Call Bob"));
+
+        f.todo.export_command(&f.room_id, "md").await.unwrap();
+        let message = f.last_message();
+        assert!(message.text.contains("```markdown\n# To-Do List"));
+        assert!(
+            message
+                .html
+                .unwrap()
+                .contains("<pre><code class=\"language-markdown\">")
+        );
+        let exports = std::fs::read_dir(&f.data_dir)
+            .unwrap()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.path().extension().is_some_and(|ext| ext == "md"))
+            .count();
+        assert_eq!(exports, 1);
+    }
+
+    #[tokio::test]
+    async fn mine_lists_created_and_assigned_tasks() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        let bob = "@bob:example.org".to_owned();
+        for (creator, title) in [(&alice, "Mine"), (&bob, "Given to me"), (&bob, "Not mine")] {
+            f.todo
+                .add_task(&f.room_id, creator.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .assign_task(&f.room_id, bob.clone(), 2, &alice)
+            .await
+            .unwrap();
+
+        f.todo
+            .list_my_tasks(&f.room_id, &alice, None)
+            .await
+            .unwrap();
+        let text = f.last_message().text;
+        assert!(text.starts_with("🙋 2 task(s) for @alice:example.org:"));
+        assert!(text.contains("1. ") && text.contains("2. ") && !text.contains("Not mine"));
+
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 2, false, None)
+            .await
+            .unwrap();
+        f.todo
+            .list_my_tasks(&f.room_id, &alice, Some(TaskStatus::Done))
+            .await
+            .unwrap();
+        let text = f.last_message().text;
+        assert!(text.starts_with("🙋 1 done task(s) for @alice:example.org:"));
+        assert!(text.contains("Given to me") && !text.contains("1. "));
+    }
+
+    #[tokio::test]
+    async fn personal_list_follows_the_user_across_rooms() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        let dm_room = OwnedRoomId::try_from("!dm:example.org").unwrap();
+        f.storage.register_dm_room(&dm_room, &alice).await;
+
+        f.todo
+            .personal_command(&dm_room, alice.clone(), "add Renew passport")
+            .await
+            .unwrap();
+        let text = f.last_message().text;
+        assert_eq!(text, "✅ Personal task 1 added: **Renew passport**");
+
+        f.todo
+            .personal_command(&f.room_id, alice.clone(), "done 1")
+            .await
+            .unwrap();
+        let text = f.last_message().text;
+        assert!(text.starts_with("✅ Personal task 1 marked as done"));
+        assert!(text.contains("This room can see your personal list"));
+
+        f.todo
+            .personal_command(&f.room_id, "@bob:example.org".into(), "list")
+            .await
+            .unwrap();
+        assert!(
+            f.last_message()
+                .text
+                .starts_with("ℹ️ Info: Your personal list is empty.")
+        );
+        // Room lists are untouched and the personal list is saved with everything else
+        assert!(f.storage.todo_lists.lock().await.get(&f.room_id).is_none());
+        let saved = f.storage.snapshot().await.personal_lists;
+        assert_eq!(saved[&alice].tasks[0].status, TaskStatus::Done);
+        assert!(f.save_files() > 0);
+    }
+
+    #[tokio::test]
+    async fn moveto_and_copyto_hand_tasks_to_another_room() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        let other = OwnedRoomId::try_from("!other:example.org").unwrap();
+        for title in ["Design", "Handoff"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .add_dependency(&f.room_id, alice.clone(), 2, 1)
+            .await
+            .unwrap();
+
+        f.todo
+            .transfer_task(&f.room_id, &other, alice.clone(), 2, false)
+            .await
+            .unwrap();
+        let sent = f.sender.sent_messages();
+        let [.., to_source, to_target] = sent.as_slice() else {
+            panic!("expected a message to each room");
+        };
+        assert_eq!(to_source.room_id, f.room_id);
+        assert_eq!(
+            to_source.text,
+            "📤 Task #2 moved to !other:example.org as #1: **Handoff**"
+        );
+        assert_eq!(to_target.room_id, other);
+        assert_eq!(
+            to_target.text,
+            "📥 Task #1 moved here from !room:example.org by @alice:example.org: **Handoff**"
+        );
+        {
+            let todo_lists = f.storage.todo_lists.lock().await;
+            assert!(todo_lists[&f.room_id].find(2).is_none());
+            let moved = todo_lists[&other].find(1).unwrap();
+            assert!(moved.depends_on.is_empty());
+            assert_eq!(moved.internal_logs[0].2, "Created task");
+            assert!(
+                moved
+                    .internal_logs
+                    .last()
+                    .unwrap()
+                    .2
+                    .ends_with("Moved from: task #2 in !room:example.org")
+            );
+        }
+
+        f.todo
+            .transfer_task(&f.room_id, &other, alice, 1, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            f.last_message().text,
+            "📥 Task #2 copied here from !room:example.org by @alice:example.org: **Design**"
+        );
+        assert_eq!(f.task(1).await.title, "Design");
+    }
+
+    #[tokio::test]
+    async fn list_sort_is_remembered_per_room() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Later", "Sooner", "Whenever"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        {
+            let mut todo_lists = f.todo.storage.todo_lists.lock().await;
+            let tasks = todo_lists.get_mut(&f.room_id).unwrap();
+            tasks.find_mut(1).unwrap().due_date = NaiveDate::from_ymd_opt(2030, 2, 1);
+            tasks.find_mut(2).unwrap().due_date = NaiveDate::from_ymd_opt(2030, 1, 1);
+        }
+
+        f.todo.sort_list(&f.room_id, "size").await.unwrap();
+        assert!(
+            f.last_message()
+                .text
+                .contains("Unknown sort 'size'. Use one of: status, priority, age, due, assignee.")
+        );
+
+        f.todo.sort_list(&f.room_id, "due").await.unwrap();
+        f.todo
+            .list_tasks(&f.room_id, 1, ListFilter::Open)
+            .await
+            .unwrap();
+        let list = f.last_message().text;
+        assert!(list.contains("Sorted by due:\n2. ⏳ **[pending] Sooner**"));
+        assert!(list.contains("1. ⏳ **[pending] Later** (0m)\n3. "));
+        assert_eq!(
+            f.todo.storage.room_settings(&f.room_id).await.list_sort,
+            ListSort::Due
+        );
+    }
+
+    #[tokio::test]
+    async fn snoozed_tasks_leave_the_list_until_they_wake() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Now", "Later"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .snooze_task(&f.room_id, alice.clone(), 2, "2d")
+            .await
+            .unwrap();
+        assert!(
+            f.last_message()
+                .text
+                .starts_with("😴 Task #2 snoozed until")
+        );
+        f.todo
+            .snooze_task(&f.room_id, alice.clone(), 1, "yesterday")
+            .await
+            .unwrap();
+        assert!(f.last_message().text.contains("Invalid snooze time"));
+
+        f.todo
+            .list_tasks(&f.room_id, 1, ListFilter::Open)
+            .await
+            .unwrap();
+        let list = f.last_message().text;
+        assert!(list.contains("Now") && !list.contains("Later"));
+        assert!(list.contains("(1 snoozed — !list snoozed to view)\n2 pending"));
+        f.todo.list_snoozed_tasks(&f.room_id).await.unwrap();
+        assert!(f.last_message().text.contains("2. Later — back "));
+
+        // Nothing wakes early, a past snooze time brings the task back
+        assert_eq!(f.todo.wake_snoozed_tasks().await.unwrap(), 0);
+        f.storage
+            .todo_lists
+            .lock()
+            .await
+            .get_mut(&f.room_id)
+            .unwrap()
+            .find_mut(2)
+            .unwrap()
+            .snoozed_until = Some(Utc::now() - Duration::minutes(1));
+        assert_eq!(f.todo.wake_snoozed_tasks().await.unwrap(), 1);
+        let sent = f.sender.sent_messages();
+        assert!(
+            sent.iter()
+                .any(|m| m.text == "⏰ Task #2 is back: **Later**")
+        );
+        assert_eq!(f.task(2).await.snoozed_until, None);
+    }
+
+    #[tokio::test]
+    async fn due_tasks_are_reminded_once_per_due_date() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Overdue", "Tomorrow", "Next month", "Finished"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        let today = Utc::now().date_naive();
+        {
+            let mut lists = f.storage.todo_lists.lock().await;
+            let tasks = lists.get_mut(&f.room_id).unwrap();
+            tasks.find_mut(1).unwrap().due_date = Some(today - Duration::days(2));
+            let tomorrow = tasks.find_mut(2).unwrap();
+            tomorrow.due_date = Some(today + Duration::days(1));
+            tomorrow.assignee = Some("@bob:example.org".to_owned());
+            tasks.find_mut(3).unwrap().due_date = Some(today + Duration::days(30));
+            let finished = tasks.find_mut(4).unwrap();
+            finished.due_date = Some(today);
+            finished.set_status(alice.clone(), TaskStatus::Done, None);
+        }
+
+        let before = f.sender.sent_messages().len();
+        assert_eq!(
+            f.todo
+                .send_due_reminders(Duration::hours(24))
+                .await
+                .unwrap(),
+            2
+        );
+        let reminder = f.last_message().text;
+        assert!(reminder.contains("Task 1 **Overdue** is overdue since"));
+        assert!(reminder.contains("Task 2 **Tomorrow** is due"));
+        assert!(reminder.contains("assigned to @bob:example.org"));
+        assert!(!reminder.contains("Next month") && !reminder.contains("Finished"));
+
+        // Nothing new until a due date changes
+        assert_eq!(
+            f.todo
+                .send_due_reminders(Duration::hours(24))
+                .await
+                .unwrap(),
+            0
+        );
+        assert_eq!(f.sender.sent_messages().len(), before + 1);
+        f.storage
+            .todo_lists
+            .lock()
+            .await
+            .get_mut(&f.room_id)
+            .unwrap()
+            .find_mut(1)
+            .unwrap()
+            .due_date = Some(today);
+        assert_eq!(
+            f.todo
+                .send_due_reminders(Duration::hours(24))
+                .await
+                .unwrap(),
+            1
+        );
+    }
+
+    #[tokio::test]
+    async fn done_recurring_task_comes_back_with_tags_and_assignee() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Water plants".into(), false)
+            .await
+            .unwrap();
+        {
+            let mut lists = f.storage.todo_lists.lock().await;
+            let task = lists.get_mut(&f.room_id).unwrap().find_mut(1).unwrap();
+            task.tags.push("home".to_owned());
+            task.assignee = Some(alice.clone());
+            task.due_date = NaiveDate::from_ymd_opt(2024, 5, 1);
+            task.set_recurrence(alice.clone(), Some(Recurrence::Weekly));
+        }
+
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        assert!(
+            f.last_message()
+                .text
+                .contains("🔁 Rescheduled as task 2, due 2024-05-08")
+        );
+
+        let done = f.task(1).await;
+        assert_eq!(done.status, TaskStatus::Done);
+        let (_, _, action) = done.internal_logs.last().unwrap();
+        assert_eq!(action, "Rescheduled: as task #2 due 2024-05-08");
+
+        let next = f.task(2).await;
+        assert_eq!(next.status, TaskStatus::Pending);
+        assert_eq!(next.tags, vec!["home".to_owned()]);
+        assert_eq!(next.assignee.as_deref(), Some("@alice:example.org"));
+        assert_eq!(next.recurrence, Some(Recurrence::Weekly));
+        assert!(
+            next.internal_logs[0]
+                .2
+                .contains("spawned by recurrence of task #1")
+        );
+    }
+
+    #[tokio::test]
+    async fn done_twice_makes_one_copy_of_a_recurring_task() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Water plants".into(), false)
+            .await
+            .unwrap();
+        f.todo
+            .recur_task(&f.room_id, alice.clone(), 1, "weekly".into())
+            .await
+            .unwrap();
+        for _ in 0..2 {
+            f.todo
+                .done_task(&f.room_id, alice.clone(), 1, false, None)
+                .await
+                .unwrap();
+        }
+        assert_eq!(f.last_message().text, "ℹ️ Info: Task 1 is already done.");
+
+        let tasks = f.storage.todo_lists.lock().await[&f.room_id].clone();
+        let pending: Vec<usize> = tasks
+            .iter()
+            .filter(|task| task.status == TaskStatus::Pending)
+            .map(|task| task.id)
+            .collect();
+        assert_eq!(pending, [2]);
+        // The schedule moved to the copy
+        assert_eq!(tasks.find(1).unwrap().recurrence, None);
+        assert_eq!(tasks.find(2).unwrap().recurrence, Some(Recurrence::Weekly));
+    }
+
+    #[test]
+    fn recurrence_intervals_are_bounded() {
+        assert_eq!(
+            Recurrence::parse("every 3650 days"),
+            Some(Recurrence::EveryDays(3650))
+        );
+        assert_eq!(Recurrence::parse("every 3651 days"), None);
+        assert_eq!(Recurrence::parse("every 4000000000 days"), None);
+
+        let today = NaiveDate::from_ymd_opt(2024, 1, 31).unwrap();
+        assert_eq!(
+            Recurrence::Monthly.advance(today),
+            NaiveDate::from_ymd_opt(2024, 2, 29)
+        );
+        assert_eq!(Recurrence::EveryDays(3650).advance(NaiveDate::MAX), None);
+        assert_eq!(Recurrence::Daily.advance(NaiveDate::MAX), None);
+    }
+
+    #[tokio::test]
+    async fn a_due_date_past_the_calendar_is_not_rescheduled() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Forever".into(), false)
+            .await
+            .unwrap();
+        {
+            let mut lists = f.storage.todo_lists.lock().await;
+            let task = lists.get_mut(&f.room_id).unwrap().find_mut(1).unwrap();
+            task.due_date = Some(NaiveDate::MAX);
+            task.set_recurrence(alice.clone(), Some(Recurrence::EveryDays(3650)));
+        }
+
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        assert!(
+            f.last_message()
+                .text
+                .contains("⚠️ Not rescheduled: the next due date is out of range.")
+        );
+        assert_eq!(f.task(1).await.status, TaskStatus::Done);
+        assert_eq!(f.storage.todo_lists.lock().await[&f.room_id].len(), 1);
+    }
+
+    #[tokio::test]
+    async fn done_reply_escapes_the_title() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "<img src=x> & co".into(), false)
+            .await
+            .unwrap();
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        let html = f.last_message().html.unwrap();
+        assert!(
+            html.starts_with("✅ Task 1 marked as done: <b>&lt;img src=x&gt; &amp; co</b>"),
+            "{html}"
+        );
+    }
+
+    #[tokio::test]
+    async fn block_reply_escapes_the_title_and_reason() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "<b>Deploy</b>".into(), false)
+            .await
+            .unwrap();
+        f.todo
+            .block_task(&f.room_id, alice, 1, Some("waiting on <i>ops</i>".into()))
+            .await
+            .unwrap();
+        let html = f.last_message().html.unwrap();
+        assert!(
+            html.contains(
+                "<b>&lt;b&gt;Deploy&lt;/b&gt;</b><br>Reason: waiting on &lt;i&gt;ops&lt;/i&gt;"
+            ),
+            "{html}"
+        );
+    }
+
+    #[tokio::test]
+    async fn full_room_refuses_templates_and_archive_restores() {
+        let f = Fixture::new();
+        let todo = TodoList::new(f.sender.clone(), f.storage.clone(), 2);
+        let alice = "@alice:example.org".to_owned();
+        for title in ["One", "Two"] {
+            todo.add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        todo.done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        todo.archive_tasks(&f.room_id, alice.clone()).await.unwrap();
+
+        let titles = vec!["Three".to_owned(), "Four".to_owned()];
+        let added = todo
+            .apply_template_titles(&f.room_id, alice.clone(), &titles)
+            .await
+            .unwrap();
+        assert_eq!(added, None);
+        assert!(f.last_message().text.contains("holds 1 of at most 2 tasks"));
+
+        todo.add_task(&f.room_id, alice.clone(), "Three".into(), false)
+            .await
+            .unwrap();
+        todo.restore_archived(&f.room_id, alice, 1).await.unwrap();
+        assert!(f.last_message().text.contains("holds 2 of at most 2 tasks"));
+        assert_eq!(f.storage.archived.lock().await[&f.room_id].len(), 1);
+        assert_eq!(f.storage.todo_lists.lock().await[&f.room_id].len(), 2);
+    }
+
+    #[tokio::test]
+    async fn tag_filter_escapes_titles_in_html() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(
+                &f.room_id,
+                alice.clone(),
+                "<script>x</script>".into(),
+                false,
+            )
+            .await
+            .unwrap();
+        f.todo
+            .add_tag(&f.room_id, alice, 1, "web".into())
+            .await
+            .unwrap();
+        f.todo.filter_tasks(&f.room_id, "web".into()).await.unwrap();
+        let html = f.last_message().html.unwrap();
+        assert!(html.contains("&lt;script&gt;x&lt;/script&gt;"), "{html}");
+        assert!(!html.contains("<script>"), "{html}");
+    }
+
+    #[tokio::test]
+    async fn history_escapes_user_text_in_html() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Fix sink".into(), false)
+            .await
+            .unwrap();
+        f.todo
+            .block_task(
+                &f.room_id,
+                alice,
+                1,
+                Some("<a href=\"evil\">click</a>".into()),
+            )
+            .await
+            .unwrap();
+        f.todo.history_task(&f.room_id, 1).await.unwrap();
+        let html = f.last_message().html.unwrap();
+        assert!(
+            html.contains("&lt;a href=&quot;evil&quot;&gt;click&lt;/a&gt;"),
+            "{html}"
+        );
+    }
+
+    #[tokio::test]
+    async fn full_room_refuses_new_tasks() {
+        let f = Fixture::new();
+        let todo = TodoList::new(f.sender.clone(), f.storage.clone(), 2);
+        let alice = "@alice:example.org".to_owned();
+        for title in ["One", "Two", "Three"] {
+            todo.add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        let text = f.last_message().text;
+        assert!(text.starts_with("⚠️ Error: This room's list holds 2 of at most 2 tasks."));
+        assert!(text.contains("!archive"));
+        assert!(
+            f.storage.todo_lists.lock().await[&f.room_id]
+                .find(3)
+                .is_none()
+        );
+
+        todo.import_tasks(&f.room_id, alice, vec!["Four".into()])
+            .await
+            .unwrap();
+        assert!(
+            f.last_message()
+                .text
+                .starts_with("⚠️ Error: This room's list holds 2")
+        );
+    }
+
+    #[test]
+    fn list_pages_stay_within_one_event() {
+        let short = vec!["1. task".to_owned(); 45];
+        let ranges = paginate_lines(&short, &[]);
+        assert_eq!(ranges, vec![0..20, 20..40, 40..45]);
+
+        let long = vec!["x".repeat(10_000); 7];
+        let ranges = paginate_lines(&long, &[]);
+        assert_eq!(ranges, vec![0..3, 3..6, 6..7]);
+        assert!(paginate_lines(&[], &[]).is_empty());
+
+        // Quick actions only go into the HTML body, but they count
+        let actions = vec!["y".repeat(5_000); 7];
+        let ranges = paginate_lines(&long, &actions);
+        assert_eq!(ranges, vec![0..2, 2..4, 4..6, 6..7]);
+    }
+
+    #[tokio::test]
+    async fn list_html_for_each_quick_action_style() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Buy milk", "Call Bob"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        let mut rendered = Vec::new();
+        for style in [
+            QuickActionStyle::Off,
+            QuickActionStyle::Commands,
+            QuickActionStyle::Links,
+        ] {
+            f.storage
+                .update_room_settings(&f.room_id, |s| s.quick_actions = style)
+                .await;
+            let (text, html) = f.todo.render_list(&f.room_id, 1, ListFilter::Open).await;
+            rendered.push((text, html.unwrap()));
+        }
+        // The plain body never changes
+        assert!(rendered.iter().all(|(text, _)| *text == rendered[0].0));
+        let tasks = |actions: [&str; 2]| {
+            format!(
+                "📋 Room To-Do List:<br><b>⏳ Pending:</b><br>\
+                 1. ⏳ **[pending] Buy milk** (0m){}<br>\
+                 2. ⏳ **[pending] Call Bob** (0m){}<br>\
+                 <table><tr><th>⏳ pending</th></tr><tr><td>2</td></tr></table>",
+                actions[0], actions[1]
+            )
+        };
+        assert_eq!(rendered[0].1, tasks(["", ""]));
+        assert_eq!(
+            rendered[1].1,
+            tasks([
+                " — <code>!done 1</code> <code>!details 1</code>",
+                " — <code>!done 2</code> <code>!details 2</code>",
+            ])
+        );
+        let link = |command: &str, encoded: &str| {
+            format!(
+                "<a href=\"matrix:roomid/room:example.org?action=chat&amp;command={}\">{}</a>",
+                encoded, command
+            )
+        };
+        assert_eq!(
+            rendered[2].1,
+            tasks([
+                &format!(
+                    " — {} {}",
+                    link("!done 1", "%21done+1"),
+                    link("!details 1", "%21details+1")
+                ),
+                &format!(
+                    " — {} {}",
+                    link("!done 2", "%21done+2"),
+                    link("!details 2", "%21details+2")
+                ),
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn export_csv_writes_file_and_announces_it() {
+        let f = Fixture::new();
+        f.todo
+            .add_task(
+                &f.room_id,
+                "@alice:example.org".into(),
+                "Buy \"oat\" milk, 2L".into(),
+                false,
+            )
+            .await
+            .unwrap();
+
+        f.todo.export_command(&f.room_id, "csv").await.unwrap();
+        let sent = f.sender.sent_messages();
+        let announcement = &sent[sent.len() - 2].text;
+        assert!(announcement.starts_with("📤 CSV export of 1 task(s) saved as "));
+        let filename = f.last_message().text;
+        assert!(announcement.ends_with(&filename));
+
+        let csv = std::fs::read_to_string(f.data_dir.join(&filename)).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some(crate::storage::csv_export::CSV_HEADER));
+        assert!(
+            lines
+                .next()
+                .unwrap()
+                .starts_with("1,\"Buy \"\"oat\"\" milk, 2L\",pending,@alice:example.org,")
+        );
+    }
+
+    #[tokio::test]
+    async fn watchers_are_mentioned_when_a_task_changes() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        let bob = "@bob:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Fix the boiler".into(), false)
+            .await
+            .unwrap();
+        f.todo
+            .auto_watch_command(&f.room_id, &bob, false)
+            .await
+            .unwrap();
+        f.todo
+            .add_task(&f.room_id, bob.clone(), "Paint the fence".into(), false)
+            .await
+            .unwrap();
+        assert_eq!(f.task(1).await.watchers, vec![alice.clone()]);
+        assert!(f.task(2).await.watchers.is_empty());
+
+        // Changes by a watcher don't mention themselves
+        f.todo
+            .log_task(&f.room_id, alice.clone(), 1, "Called the plumber".into())
+            .await
+            .unwrap();
+        assert!(!f.last_message().text.contains("👀"));
+
+        f.todo
+            .watch_task(&f.room_id, bob.clone(), 1, true)
+            .await
+            .unwrap();
+        f.todo
+            .done_task(&f.room_id, bob.clone(), 1, false, None)
+            .await
+            .unwrap();
+        let message = f.last_message();
+        assert!(message.text.ends_with("\n👀 @alice:example.org"));
+        assert!(message.html.unwrap().ends_with(
+            "<br>👀 <a href=\"https://matrix.to/#/@alice:example.org\">@alice:example.org</a>"
+        ));
+
+        f.todo
+            .watch_task(&f.room_id, alice.clone(), 1, false)
+            .await
+            .unwrap();
+        assert_eq!(f.task(1).await.watchers, vec![bob]);
+    }
+
+    #[tokio::test]
+    async fn offloading_history_keeps_stats_and_timestamps() {
+        let f = Fixture::with_history_offload(Some(30));
+        let alice = "@alice:example.org".to_owned();
+        let bob = "@bob:example.org".to_owned();
+        f.todo
+            .add_task(&f.room_id, alice.clone(), "Old work".into(), false)
+            .await
+            .unwrap();
+        f.todo
+            .done_task(&f.room_id, bob.clone(), 1, false, None)
+            .await
+            .unwrap();
+        f.todo
+            .edit_task(&f.room_id, alice.clone(), 1, "Old work, renamed".into())
+            .await
+            .unwrap();
+        // All of it happened long before the offload threshold
+        {
+            let mut todo_lists = f.storage.todo_lists.lock().await;
+            let task = todo_lists.get_mut(&f.room_id).unwrap().find_mut(1).unwrap();
+            for (day, entry) in task.internal_logs.iter_mut().enumerate() {
+                entry.0 = format!("2024-01-0{} 10:00:00", day + 1);
+            }
+            task.created_at = stats::parse_log_timestamp("2024-01-01 10:00:00").unwrap();
+            task.completed_at = stats::parse_log_timestamp("2024-01-02 10:00:00");
+        }
+        let before = f.todo.room_stats(&f.room_id).await;
+        let task_before = f.task(1).await;
+
+        f.storage.save().await.unwrap();
+        assert_eq!(f.task(1).await.internal_logs.len(), 1);
+        assert!(f.storage.load_latest_valid().await.unwrap().is_some());
+
+        assert_eq!(f.todo.room_stats(&f.room_id).await, before);
+        let task = f.task(1).await;
+        assert_eq!(task.created_at, task_before.created_at);
+        assert_eq!(task.completed_at, task_before.completed_at);
+        assert_eq!(
+            f.storage.task_history(&f.room_id, &task).await.unwrap(),
+            task_before.internal_logs
+        );
+    }
+
+    #[tokio::test]
+    async fn reopened_tasks_survive_a_save_and_load() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Buy milk", "Call Bob", "Water plants"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        f.todo
+            .close_tasks(&f.room_id, alice.clone(), &[2])
+            .await
+            .unwrap();
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 3, false, None)
+            .await
+            .unwrap();
+        for id in [1, 2] {
+            f.todo
+                .reopen_task(&f.room_id, alice.clone(), id)
+                .await
+                .unwrap();
+        }
+        let before = [f.task(1).await, f.task(2).await, f.task(3).await];
+        for task in &before[..2] {
+            assert_eq!(task.status, TaskStatus::Pending);
+            assert_eq!(task.completed_at, None);
+            assert!(
+                task.internal_logs
+                    .last()
+                    .unwrap()
+                    .2
+                    .starts_with("Reopened task: was")
+            );
+        }
+        assert!(before[2].completed_at.is_some());
+
+        f.storage.save().await.unwrap();
+        f.storage.todo_lists.lock().await.clear();
+        assert!(f.storage.load_latest_valid().await.unwrap().is_some());
+
+        for task in before {
+            let loaded = f.task(task.id).await;
+            assert_eq!(loaded.status, task.status);
+            assert_eq!(loaded.completed_at, task.completed_at);
+            assert_eq!(loaded.internal_logs, task.internal_logs);
+        }
+        assert_eq!(
+            f.task(1).await.internal_logs.last().unwrap().2,
+            "Reopened task: was 'done'"
+        );
+        assert_eq!(
+            f.task(2).await.internal_logs.last().unwrap().2,
+            "Reopened task: was 'closed'"
+        );
+    }
+
+    #[tokio::test]
+    async fn room_stats_counts_every_status() {
+        let f = Fixture::new();
+        let (alice, bob) = ("@alice:example.org", "@bob:example.org");
+        {
+            let mut todo_lists = f.storage.todo_lists.lock().await;
+            let tasks = todo_lists.entry(f.room_id.clone()).or_default();
+            for (creator, status) in [
+                (alice, TaskStatus::Pending),
+                (alice, TaskStatus::Pending),
+                (bob, TaskStatus::InProgress),
+                (alice, TaskStatus::Blocked),
+                (bob, TaskStatus::Done),
+                (bob, TaskStatus::Done),
+                (alice, TaskStatus::Done),
+                (bob, TaskStatus::Closed),
+            ] {
+                let id = tasks.allocate_id();
+                let mut task = Task::new(creator.to_owned(), id, format!("Task {}", id));
+                task.status = status;
+                tasks.push(task);
+            }
+        }
+
+        let stats = f.todo.room_stats(&f.room_id).await;
+        assert_eq!(stats.total, 8);
+        assert_eq!(stats.pending, 2);
+        assert_eq!(stats.in_progress, 1);
+        assert_eq!(stats.blocked, 1);
+        assert_eq!(stats.done, 3);
+        assert_eq!(stats.closed, 1);
+        assert_eq!(
+            stats.by_creator,
+            HashMap::from([(alice.to_owned(), 4), (bob.to_owned(), 4)])
+        );
+
+        let other_room = OwnedRoomId::try_from("!other:example.org").unwrap();
+        assert_eq!(f.todo.room_stats(&other_room).await, RoomStats::default());
+    }
+
+    #[tokio::test]
+    async fn messages_in_the_default_and_plain_styles() {
+        let f = Fixture::new();
+        let alice = "@alice:example.org".to_owned();
+        for title in ["Buy milk", "Call Bob"] {
+            f.todo
+                .add_task(&f.room_id, alice.clone(), title.into(), false)
+                .await
+                .unwrap();
+        }
+        f.todo
+            .start_task(&f.room_id, alice.clone(), 2)
+            .await
+            .unwrap();
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 1, false, None)
+            .await
+            .unwrap();
+        f.todo
+            .list_tasks(&f.room_id, 1, ListFilter::All)
+            .await
+            .unwrap();
+        f.todo.details_task(&f.room_id, 2).await.unwrap();
+        f.todo.stats_command(&f.room_id).await.unwrap();
+        f.todo
+            .done_task(&f.room_id, alice.clone(), 9, false, None)
+            .await
+            .unwrap();
+
+        let task = f.task(2).await;
+        let created = task.created_at.format("%Y-%m-%d %H:%M UTC");
+        let (created_log, started_log) = (&task.internal_logs[0].0, &task.internal_logs[1].0);
+        let expected = [
+            (
+                "📝 Task 1 added by @alice:example.org:\n Buy milk".to_owned(),
+                "Task 1 added by @alice:example.org:\nBuy milk".to_owned(),
+            ),
+            (
+                "📝 Task 2 added by @alice:example.org:\n Call Bob".to_owned(),
+                "Task 2 added by @alice:example.org:\nCall Bob".to_owned(),
+            ),
+            (
+                "🚧 Task 2 is now in progress: **Call Bob**".to_owned(),
+                "Task 2 is now in progress: Call Bob".to_owned(),
+            ),
+            (
+                "✅ Task 1 marked as done: **Buy milk**".to_owned(),
+                "Task 1 marked as done: Buy milk".to_owned(),
+            ),
+            (
+                "📋 Room To-Do List:\n\
+                 🚧 In progress:\n\
+                 2. 🚧 **[in-progress] Call Bob** (0m)\n\
+                 ✅ Done:\n\
+                 1. ✅ **[done] Buy milk** (0m)\n\
+                 1 in-progress · 1 done"
+                    .to_owned(),
+                "Room To-Do List:\n\
+                 In progress:\n\
+                 2. [in-progress] Call Bob (0m)\n\
+                 Done:\n\
+                 1. [done] Buy milk (0m)\n\
+                 1 in-progress · 1 done"
+                    .to_owned(),
+            ),
+            (
+                format!(
+                    "🔍 Task Details:\n\
+                     🚧 **[in-progress] Call Bob** (0m)\n\
+                     Created by: @alice:example.org\n\
+                     Created: {created}\n\
+                     Watchers: @alice:example.org\n\
+                     \n\
+                     **History:**\n\
+                     • [{created_log}] @alice:example.org — Created task\n\
+                     • [{started_log}] @alice:example.org — Updated status: from 'pending' to 'in-progress'"
+                ),
+                format!(
+                    "Task Details:\n\
+                     [in-progress] Call Bob (0m)\n\
+                     Created by: @alice:example.org\n\
+                     Created: {created}\n\
+                     Watchers: @alice:example.org\n\
+                     \n\
+                     History:\n\
+                     - [{created_log}] @alice:example.org - Created task\n\
+                     - [{started_log}] @alice:example.org - Updated status: from 'pending' to 'in-progress'"
+                ),
+            ),
+            (
+                "📊 Room statistics\n\
+                 📋 Total: 2\n\
+                 ⏳ Pending: 0\n\
+                 🚧 In progress: 1\n\
+                 ⛔ Blocked: 0\n\
+                 ✅ Done: 1\n\
+                 ✖️ Closed: 0\n\
+                 \n\
+                 🗓️ Last 7 days: 2 created, 1 completed\n\
+                 ⏱️ Average time to done: 0m\n\
+                 🔥 Most active: @alice:example.org (4 actions)\n\
+                 \n\
+                 👤 Tasks by creator:\n\
+                 • @alice:example.org: 2"
+                    .to_owned(),
+                "Room statistics\n\
+                 Total: 2\n\
+                 Pending: 0\n\
+                 In progress: 1\n\
+                 Blocked: 0\n\
+                 Done: 1\n\
+                 Closed: 0\n\
+                 \n\
+                 Last 7 days: 2 created, 1 completed\n\
+                 Average time to done: 0m\n\
+                 Most active: @alice:example.org (4 actions)\n\
+                 \n\
+                 Tasks by creator:\n\
+                 - @alice:example.org: 2"
+                    .to_owned(),
+            ),
+            (
+                "❌ Error: Task 9 doesn't exist.".to_owned(),
+                "Error: Task 9 doesn't exist.".to_owned(),
+            ),
+        ];
+
+        let sent = f.sender.sent_messages();
+        assert_eq!(sent.len(), expected.len());
+        for (message, (styled, plain)) in sent.iter().zip(expected) {
+            assert_eq!(message.text, styled);
+            assert_eq!(crate::messaging::render::plain_style(&message.text), plain);
+        }
+    }
 }
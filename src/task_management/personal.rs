@@ -0,0 +1,98 @@
+//! `!personal`: a list per user that follows them into every room they share with the bot.
+
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedRoomId;
+use tracing::info;
+
+use super::{Task, TaskStatus, TodoList};
+
+const USAGE: &str = "⚠️ Error: Usage: !personal add <task>, !personal list or !personal done <id>";
+
+impl TodoList {
+    /// Run a `!personal` subcommand on the sender's own list. Outside the sender's
+    /// DM room the reply notes that everyone in the room can read it.
+    pub async fn personal_command(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        args: &str,
+    ) -> Result<()> {
+        let (subcommand, rest) = args
+            .trim()
+            .split_once(char::is_whitespace)
+            .unwrap_or((args.trim(), ""));
+        let mut message = match subcommand.to_lowercase().as_str() {
+            "add" => self.personal_add(&sender, rest.trim()).await?,
+            "list" | "" => self.personal_list(&sender).await,
+            "done" => match rest.trim().parse::<usize>() {
+                Ok(id) => self.personal_done(&sender, id).await?,
+                Err(_) => USAGE.to_owned(),
+            },
+            _ => USAGE.to_owned(),
+        };
+        if self.storage.dm_owner(room_id).await.as_deref() != Some(sender.as_str()) {
+            message.push_str(
+                "\n\n👀 This room can see your personal list. Use `!dm` to manage it in private.",
+            );
+        }
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    async fn personal_add(&self, sender: &str, title: &str) -> Result<String> {
+        if title.is_empty() {
+            return Ok(USAGE.to_owned());
+        }
+        let id = {
+            let mut lists = self.storage.personal_lists.lock().await;
+            let list = lists.entry(sender.to_owned()).or_default();
+            let id = list.allocate_id();
+            list.push(Task::new(sender.to_owned(), id, title.to_owned()));
+            id
+        };
+        info!(user = sender, task_id = id, "Added personal task");
+        self.storage.save_personal(sender).await?;
+        Ok(format!("✅ Personal task {} added: **{}**", id, title))
+    }
+
+    /// Open tasks first, closed ones left out like in `!list`
+    async fn personal_list(&self, sender: &str) -> String {
+        let lists = self.storage.personal_lists.lock().await;
+        let mut tasks: Vec<&Task> = lists
+            .get(sender)
+            .map(|list| {
+                list.iter()
+                    .filter(|t| t.status != TaskStatus::Closed)
+                    .collect()
+            })
+            .unwrap_or_default();
+        if tasks.is_empty() {
+            return "ℹ️ Info: Your personal list is empty.".to_owned();
+        }
+        tasks.sort_by_key(|t| t.status == TaskStatus::Done);
+        let lines: Vec<String> = tasks
+            .iter()
+            .map(|t| format!("{}. {}", t.id, t.to_string_short()))
+            .collect();
+        format!("📒 Personal list of {}:\n{}", sender, lines.join("\n"))
+    }
+
+    async fn personal_done(&self, sender: &str, id: usize) -> Result<String> {
+        let title = {
+            let mut lists = self.storage.personal_lists.lock().await;
+            let Some(task) = lists.get_mut(sender).and_then(|list| list.find_mut(id)) else {
+                return Ok(format!(
+                    "❌ Error: Invalid task ID: {}. Use `!personal list` to see valid IDs.",
+                    id
+                ));
+            };
+            task.set_status(sender.to_owned(), TaskStatus::Done, None);
+            task.title.clone()
+        };
+        self.storage.save_personal(sender).await?;
+        Ok(format!(
+            "✅ Personal task {} marked as done: **{}**",
+            id, title
+        ))
+    }
+}
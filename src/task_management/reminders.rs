@@ -0,0 +1,88 @@
+//! Reminders posted to a room when a task's due date comes close or has passed.
+
+use anyhow::Result;
+use chrono::{Duration, NaiveDate, Utc};
+use matrix_sdk::ruma::OwnedRoomId;
+use tracing::{error, info};
+
+use super::{Task, TaskStatus, TodoList};
+use crate::messaging::render::escape_html;
+
+impl Task {
+    /// Whether the task is due on or before `cutoff`, still open and not yet
+    /// reminded about for its current due date
+    pub fn needs_reminder(&self, cutoff: NaiveDate) -> bool {
+        match self.due_date {
+            Some(due) => {
+                due <= cutoff
+                    && !matches!(self.status, TaskStatus::Done | TaskStatus::Closed)
+                    && self.reminded_for != Some(due)
+            }
+            None => false,
+        }
+    }
+}
+
+impl TodoList {
+    /// Post one reminder per room for the tasks due within `lead` or overdue.
+    /// Each task is reminded once per due date. Returns how many tasks were reminded.
+    pub async fn send_due_reminders(&self, lead: Duration) -> Result<usize> {
+        let now = Utc::now();
+        let today = now.date_naive();
+        let cutoff = (now + lead).date_naive();
+
+        let mut reminders: Vec<(OwnedRoomId, Vec<String>, Vec<String>)> = Vec::new();
+        {
+            let mut todo_lists = self.storage.todo_lists.lock().await;
+            for (room_id, tasks) in todo_lists.iter_mut() {
+                let mut lines = Vec::new();
+                let mut html_lines = Vec::new();
+                for task in tasks.iter_mut().filter(|t| t.needs_reminder(cutoff)) {
+                    let Some(due) = task.due_date else { continue };
+                    task.reminded_for = Some(due);
+                    let when = if due < today {
+                        format!("is overdue since {}", due.format("%Y-%m-%d"))
+                    } else {
+                        format!("is due {}", due.format("%Y-%m-%d"))
+                    };
+                    let assignee = task
+                        .assignee
+                        .as_ref()
+                        .map(|a| format!(", assigned to {}", a))
+                        .unwrap_or_default();
+                    lines.push(format!(
+                        "- Task {} **{}** {}{}",
+                        task.id, task.title, when, assignee
+                    ));
+                    html_lines.push(format!(
+                        "<li>Task {} <b>{}</b> {}{}</li>",
+                        task.id,
+                        escape_html(&task.title),
+                        when,
+                        escape_html(&assignee)
+                    ));
+                }
+                if !lines.is_empty() {
+                    reminders.push((room_id.clone(), lines, html_lines));
+                }
+            }
+        }
+
+        let mut reminded = 0;
+        for (room_id, lines, html_lines) in reminders {
+            reminded += lines.len();
+            info!(room_id = %room_id, tasks = lines.len(), "Sending due date reminders");
+            let message = format!("⏰ Task reminders:\n{}", lines.join("\n"));
+            let html_message = format!("⏰ Task reminders:<ul>{}</ul>", html_lines.join(""));
+            // A room the bot can no longer post in shouldn't stop the others
+            if let Err(e) = self
+                .send_matrix_message(&room_id, &message, Some(html_message))
+                .await
+            {
+                error!(room_id = %room_id, error = %e, "Failed to send due date reminders");
+            }
+            self.storage.save_room(&room_id).await?;
+        }
+        Ok(reminded)
+    }
+}
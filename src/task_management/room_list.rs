@@ -0,0 +1,119 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use std::collections::HashSet;
+use std::ops::{Deref, DerefMut};
+
+use super::{Task, TaskStatus};
+
+/// A room's tasks together with the counter that hands out their stable IDs.
+/// Derefs to the task `Vec`, so lookups go by `task.id`, never by position.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RoomTaskList {
+    /// Next ID to hand out. 0 only for lists read from the old plain-array format,
+    /// which `StorageData::migrate_legacy_lists` fixes up on load.
+    pub next_id: usize,
+    pub tasks: Vec<Task>,
+}
+
+impl RoomTaskList {
+    pub fn new(tasks: Vec<Task>, next_id: usize) -> Self {
+        Self { next_id, tasks }
+    }
+
+    /// Hand out the next stable ID. IDs are monotonic and never reused; the
+    /// counter is kept ahead of any ID already present in the list.
+    pub fn allocate_id(&mut self) -> usize {
+        let max_existing = self.tasks.iter().map(|t| t.id).max().unwrap_or(0);
+        let id = self.next_id.max(max_existing + 1);
+        self.next_id = id + 1;
+        id
+    }
+
+    pub fn find(&self, task_id: usize) -> Option<&Task> {
+        self.tasks.iter().find(|t| t.id == task_id)
+    }
+
+    pub fn find_mut(&mut self, task_id: usize) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|t| t.id == task_id)
+    }
+
+    /// Tasks `task_id` depends on that are neither done nor closed. Dependencies
+    /// on tasks no longer in the list don't count.
+    pub fn unfinished_dependencies(&self, task_id: usize) -> Vec<&Task> {
+        let Some(task) = self.find(task_id) else {
+            return Vec::new();
+        };
+        task.depends_on
+            .iter()
+            .filter_map(|&id| self.find(id))
+            .filter(|t| !matches!(t.status, TaskStatus::Done | TaskStatus::Closed))
+            .collect()
+    }
+
+    /// IDs of the tasks that depend on `task_id`
+    pub fn dependents(&self, task_id: usize) -> Vec<usize> {
+        self.tasks
+            .iter()
+            .filter(|t| t.depends_on.contains(&task_id))
+            .map(|t| t.id)
+            .collect()
+    }
+
+    /// Chain of dependencies leading from `from` to `to`, both included, if there is one
+    pub fn dependency_path(&self, from: usize, to: usize) -> Option<Vec<usize>> {
+        let mut path = vec![from];
+        let mut visited = HashSet::new();
+        self.find_path(to, &mut path, &mut visited).then_some(path)
+    }
+
+    fn find_path(&self, to: usize, path: &mut Vec<usize>, visited: &mut HashSet<usize>) -> bool {
+        let current = *path.last().unwrap();
+        if current == to {
+            return true;
+        }
+        if !visited.insert(current) {
+            return false;
+        }
+        let Some(task) = self.find(current) else {
+            return false;
+        };
+        for &next in &task.depends_on {
+            path.push(next);
+            if self.find_path(to, path, visited) {
+                return true;
+            }
+            path.pop();
+        }
+        false
+    }
+}
+
+impl Deref for RoomTaskList {
+    type Target = Vec<Task>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tasks
+    }
+}
+
+impl DerefMut for RoomTaskList {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.tasks
+    }
+}
+
+impl<'de> Deserialize<'de> for RoomTaskList {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        // Older save files stored each room as a plain array of tasks
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Current { next_id: usize, tasks: Vec<Task> },
+            Legacy(Vec<Task>),
+        }
+
+        Ok(match Stored::deserialize(deserializer)? {
+            Stored::Current { next_id, tasks } => RoomTaskList::new(tasks, next_id),
+            Stored::Legacy(tasks) => RoomTaskList::new(tasks, 0),
+        })
+    }
+}
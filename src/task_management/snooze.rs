@@ -0,0 +1,192 @@
+//! `!snooze`: hide a task from `!list` until a later time.
+
+use anyhow::Result;
+use chrono::{DateTime, NaiveDate, Utc};
+use matrix_sdk::ruma::OwnedRoomId;
+use tracing::{error, info};
+
+use super::time_spent::parse_time_spent;
+use super::undo::{UndoAction, UndoEntry};
+use super::{Task, TaskEvent, TodoList};
+use crate::messaging::render::escape_html;
+
+/// How wake times are shown to users
+const WAKE_FORMAT: &str = "%Y-%m-%d %H:%M UTC";
+
+/// Parse a `!snooze` time: a duration such as `2d` or `3h`, or a `YYYY-MM-DD`
+/// date meaning midnight UTC. The result has to be in the future.
+pub fn parse_snooze_until(input: &str, now: DateTime<Utc>) -> Result<DateTime<Utc>, String> {
+    let until = match NaiveDate::parse_from_str(input.trim(), "%Y-%m-%d") {
+        Ok(date) => date.and_hms_opt(0, 0, 0).unwrap_or_default().and_utc(),
+        Err(_) => now + parse_time_spent(input)?,
+    };
+    if until <= now {
+        return Err(format!(
+            "{} is not in the future.",
+            until.format(WAKE_FORMAT)
+        ));
+    }
+    Ok(until)
+}
+
+impl Task {
+    /// Hidden from `!list` until the snooze time passes
+    pub fn is_snoozed(&self, now: DateTime<Utc>) -> bool {
+        self.snoozed_until.is_some_and(|until| until > now)
+    }
+
+    /// Snooze until a time, or wake the task up with `None`
+    pub fn snooze(&mut self, sender: String, until: Option<DateTime<Utc>>) {
+        self.snoozed_until = until;
+        let info = until.map_or("off".to_owned(), |u| {
+            format!("until {}", u.format(WAKE_FORMAT))
+        });
+        self.add_internal_log(sender, TaskEvent::Snoozed, Some(info));
+    }
+}
+
+impl TodoList {
+    /// `!snooze <id> <duration|date|off>`
+    pub async fn snooze_task(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        raw_until: &str,
+    ) -> Result<()> {
+        let raw_until = raw_until.trim();
+        let until = if raw_until.eq_ignore_ascii_case("off") {
+            None
+        } else {
+            match parse_snooze_until(raw_until, Utc::now()) {
+                Ok(until) => Some(until),
+                Err(e) => {
+                    let message = format!(
+                        "❌ Error: Invalid snooze time '{}': {} Use a duration such as 2d or 3h, a date like 2024-05-01, or off.",
+                        raw_until, e
+                    );
+                    self.send_matrix_message(room_id, &message, None).await?;
+                    return Ok(());
+                }
+            }
+        };
+
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let Some(task) = todo_lists
+            .get_mut(room_id)
+            .and_then(|tasks| tasks.find_mut(task_id))
+        else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        let previous = task.clone();
+        task.snooze(sender, until);
+        let message = match until {
+            Some(until) => format!(
+                "😴 Task #{} snoozed until {}",
+                task_id,
+                until.format(WAKE_FORMAT)
+            ),
+            None => format!("⏰ Task #{} is back", task_id),
+        };
+        drop(todo_lists);
+        self.undo
+            .push(
+                room_id,
+                UndoEntry::new(
+                    format!("snooze of task {}", task_id),
+                    vec![UndoAction::RestoreTasks(vec![previous])],
+                ),
+            )
+            .await;
+        self.send_matrix_message(room_id, &message, None).await?;
+        self.storage.save_room(room_id).await
+    }
+
+    /// `!list snoozed`: the hidden tasks, soonest to wake first
+    pub async fn list_snoozed_tasks(&self, room_id: &OwnedRoomId) -> Result<()> {
+        let now = Utc::now();
+        let mut snoozed: Vec<(DateTime<Utc>, String)> = self
+            .storage
+            .todo_lists
+            .lock()
+            .await
+            .get(room_id)
+            .map(|tasks| {
+                tasks
+                    .iter()
+                    .filter(|t| t.is_snoozed(now))
+                    .filter_map(|t| {
+                        let until = t.snoozed_until?;
+                        let line =
+                            format!("{}. {} — back {}", t.id, t.title, until.format(WAKE_FORMAT));
+                        Some((until, line))
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let message = if snoozed.is_empty() {
+            "ℹ️ Info: No tasks are snoozed in this room.".to_owned()
+        } else {
+            snoozed.sort_by_key(|(until, _)| *until);
+            let lines: Vec<String> = snoozed.into_iter().map(|(_, line)| line).collect();
+            format!("😴 Snoozed tasks:\n{}", lines.join("\n"))
+        };
+        self.send_matrix_message(room_id, &message, None).await?;
+        Ok(())
+    }
+
+    /// Clear snoozes whose time has passed and tell each room which tasks are
+    /// back. Returns how many tasks woke up.
+    pub async fn wake_snoozed_tasks(&self) -> Result<usize> {
+        let now = Utc::now();
+        let mut woken: Vec<(OwnedRoomId, Vec<(usize, String)>)> = Vec::new();
+        {
+            let mut todo_lists = self.storage.todo_lists.lock().await;
+            for (room_id, tasks) in todo_lists.iter_mut() {
+                let mut back = Vec::new();
+                for task in tasks.iter_mut() {
+                    if task.snoozed_until.is_some_and(|until| until <= now) {
+                        task.snoozed_until = None;
+                        back.push((task.id, task.title.clone()));
+                    }
+                }
+                if !back.is_empty() {
+                    woken.push((room_id.clone(), back));
+                }
+            }
+        }
+
+        let mut count = 0;
+        for (room_id, back) in woken {
+            count += back.len();
+            info!(room_id = %room_id, tasks = back.len(), "Snoozed tasks are back");
+            let message = back
+                .iter()
+                .map(|(id, title)| format!("⏰ Task #{} is back: **{}**", id, title))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let html_message = back
+                .iter()
+                .map(|(id, title)| {
+                    format!("⏰ Task #{} is back: <b>{}</b>", id, escape_html(title))
+                })
+                .collect::<Vec<_>>()
+                .join("<br>");
+            // A room the bot can no longer post in shouldn't stop the others
+            if let Err(e) = self
+                .send_matrix_message(&room_id, &message, Some(html_message))
+                .await
+            {
+                error!(room_id = %room_id, error = %e, "Failed to announce woken tasks");
+            }
+            self.storage.save_room(&room_id).await?;
+            self.refresh_list_view(&room_id).await?;
+        }
+        Ok(count)
+    }
+}
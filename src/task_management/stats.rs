@@ -0,0 +1,311 @@
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use std::collections::HashMap;
+
+use super::time_spent::format_time_spent;
+use super::{Task, TaskStatus};
+use crate::messaging::render::escape_html;
+
+/// Window for the "recent activity" counts
+pub const RECENT_DAYS: i64 = 7;
+
+pub(super) fn parse_log_timestamp(timestamp: &str) -> Option<DateTime<Utc>> {
+    NaiveDateTime::parse_from_str(timestamp, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .map(|t| t.and_utc())
+}
+
+/// Rough human duration such as `3d 4h` or `25m`
+fn format_duration(duration: Duration) -> String {
+    let minutes = duration.num_minutes().max(0);
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h", days, hours),
+    }
+}
+
+/// Task counts for one room, or for every room when merged
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct RoomStats {
+    pub total: usize,
+    pub pending: usize,
+    pub in_progress: usize,
+    pub blocked: usize,
+    pub done: usize,
+    pub closed: usize,
+    pub by_creator: HashMap<String, usize>,
+    /// Tasks created in the last `RECENT_DAYS` days
+    pub created_recently: usize,
+    /// Tasks marked done in the last `RECENT_DAYS` days
+    pub completed_recently: usize,
+    /// History entries per user
+    pub activity: HashMap<String, usize>,
+    /// Summed creation-to-done time of done tasks, and how many were summed
+    pub time_to_done: Duration,
+    pub time_to_done_count: usize,
+    /// Time logged with `!spent` across all tasks
+    pub time_spent: std::time::Duration,
+}
+
+impl RoomStats {
+    pub fn from_tasks(tasks: &[Task]) -> Self {
+        Self::from_tasks_at(tasks, Utc::now())
+    }
+
+    /// Stats with the recent-activity window ending at `now`
+    pub fn from_tasks_at(tasks: &[Task], now: DateTime<Utc>) -> Self {
+        let since = now - Duration::days(RECENT_DAYS);
+        let mut stats = RoomStats::default();
+        for task in tasks {
+            stats.count_activity(&task.internal_logs);
+            // Logs aren't part of the history, their authors count separately
+            for author in task.logs.iter().filter_map(|log| log.author.as_ref()) {
+                *stats.activity.entry(author.clone()).or_default() += 1;
+            }
+            if task.created_at >= since {
+                stats.created_recently += 1;
+            }
+            // `completed_at` is also set for closed tasks, only done ones count here
+            if task.status == TaskStatus::Done
+                && let Some(done_at) = task.completed_at
+            {
+                if done_at >= since {
+                    stats.completed_recently += 1;
+                }
+                stats.time_to_done += done_at - task.created_at;
+                stats.time_to_done_count += 1;
+            }
+
+            stats.total += 1;
+            match task.status {
+                TaskStatus::Pending => stats.pending += 1,
+                TaskStatus::InProgress => stats.in_progress += 1,
+                TaskStatus::Blocked => stats.blocked += 1,
+                TaskStatus::Done => stats.done += 1,
+                TaskStatus::Closed => stats.closed += 1,
+            }
+            *stats.by_creator.entry(task.creator.clone()).or_default() += 1;
+            stats.time_spent += task.time_spent;
+        }
+        stats
+    }
+
+    /// Count history entries that are no longer in the tasks, such as those
+    /// offloaded to the cold store
+    pub fn count_activity(&mut self, history: &[(String, String, String)]) {
+        for (_, user, _) in history {
+            *self.activity.entry(user.clone()).or_default() += 1;
+        }
+    }
+
+    pub fn merge(&mut self, other: &RoomStats) {
+        self.total += other.total;
+        self.pending += other.pending;
+        self.in_progress += other.in_progress;
+        self.blocked += other.blocked;
+        self.done += other.done;
+        self.closed += other.closed;
+        for (creator, count) in &other.by_creator {
+            *self.by_creator.entry(creator.clone()).or_default() += count;
+        }
+        self.created_recently += other.created_recently;
+        self.completed_recently += other.completed_recently;
+        for (user, count) in &other.activity {
+            *self.activity.entry(user.clone()).or_default() += count;
+        }
+        self.time_to_done += other.time_to_done;
+        self.time_to_done_count += other.time_to_done_count;
+        self.time_spent += other.time_spent;
+    }
+
+    /// Mean creation-to-done time over done tasks
+    pub fn average_time_to_done(&self) -> Option<Duration> {
+        let count = i32::try_from(self.time_to_done_count)
+            .ok()
+            .filter(|&c| c > 0)?;
+        Some(self.time_to_done / count)
+    }
+
+    /// User with the most history entries, ties broken alphabetically
+    pub fn most_active_user(&self) -> Option<(&String, &usize)> {
+        self.activity
+            .iter()
+            .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+    }
+
+    /// Creators ordered by task count, most active first
+    fn creators_by_count(&self) -> Vec<(&String, &usize)> {
+        let mut creators: Vec<(&String, &usize)> = self.by_creator.iter().collect();
+        creators.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+        creators
+    }
+
+    /// Plain text and HTML report headed by `title`
+    pub fn render(&self, title: &str) -> (String, String) {
+        let mut message = format!(
+            "📊 {}\n📋 Total: {}\n⏳ Pending: {}\n🚧 In progress: {}\n⛔ Blocked: {}\n✅ Done: {}\n✖️ Closed: {}",
+            title, self.total, self.pending, self.in_progress, self.blocked, self.done, self.closed
+        );
+        let mut html = format!(
+            "<h4>📊 {}</h4>\
+            <table>\
+            <tr><td>📋 Total</td><td>{}</td></tr>\
+            <tr><td>⏳ Pending</td><td>{}</td></tr>\
+            <tr><td>🚧 In progress</td><td>{}</td></tr>\
+            <tr><td>⛔ Blocked</td><td>{}</td></tr>\
+            <tr><td>✅ Done</td><td>{}</td></tr>\
+            <tr><td>✖️ Closed</td><td>{}</td></tr>\
+            </table>",
+            escape_html(title),
+            self.total,
+            self.pending,
+            self.in_progress,
+            self.blocked,
+            self.done,
+            self.closed
+        );
+
+        let average = self
+            .average_time_to_done()
+            .map(format_duration)
+            .unwrap_or_else(|| "n/a".to_owned());
+        message.push_str(&format!(
+            "\n\n🗓️ Last {} days: {} created, {} completed\n⏱️ Average time to done: {}",
+            RECENT_DAYS, self.created_recently, self.completed_recently, average
+        ));
+        html.push_str(&format!(
+            "<p>🗓️ Last {} days:</p>\
+            <table>\
+            <tr><td>🆕 Created</td><td>{}</td></tr>\
+            <tr><td>🏁 Completed</td><td>{}</td></tr>\
+            <tr><td>⏱️ Average time to done</td><td>{}</td></tr>\
+            </table>",
+            RECENT_DAYS, self.created_recently, self.completed_recently, average
+        ));
+        if !self.time_spent.is_zero() {
+            let spent = format_time_spent(self.time_spent);
+            message.push_str(&format!("\n🕒 Time logged: {}", spent));
+            html.push_str(&format!("<p>🕒 Time logged: {}</p>", spent));
+        }
+        if let Some((user, count)) = self.most_active_user() {
+            message.push_str(&format!("\n🔥 Most active: {} ({} actions)", user, count));
+            html.push_str(&format!(
+                "<p>🔥 Most active: {} ({} actions)</p>",
+                escape_html(user),
+                count
+            ));
+        }
+
+        if !self.by_creator.is_empty() {
+            message.push_str("\n\n👤 Tasks by creator:");
+            html.push_str(
+                "<p>👤 Tasks by creator:</p><table><tr><th>Creator</th><th>Tasks</th></tr>",
+            );
+            for (creator, count) in self.creators_by_count() {
+                message.push_str(&format!("\n• {}: {}", creator, count));
+                html.push_str(&format!(
+                    "<tr><td>{}</td><td>{}</td></tr>",
+                    escape_html(creator),
+                    count
+                ));
+            }
+            html.push_str("</table>");
+        }
+        (message, html)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn log(task: &mut Task, timestamp: &str, user: &str, action: &str) {
+        task.internal_logs
+            .push((timestamp.to_owned(), user.to_owned(), action.to_owned()));
+    }
+
+    #[test]
+    fn counts_recent_activity_and_time_to_done() {
+        let now = parse_log_timestamp("2024-05-20 12:00:00").unwrap();
+        let mut old = Task::new("@alice:example.org".to_owned(), 1, "Old".to_owned());
+        old.internal_logs.clear();
+        log(
+            &mut old,
+            "2024-05-01 12:00:00",
+            "@alice:example.org",
+            "Created task",
+        );
+        log(
+            &mut old,
+            "2024-05-18 12:00:00",
+            "@bob:example.org",
+            "Updated status: from 'pending' to 'done'",
+        );
+        old.status = TaskStatus::Done;
+        old.created_at = parse_log_timestamp("2024-05-01 12:00:00").unwrap();
+        old.completed_at = parse_log_timestamp("2024-05-18 12:00:00");
+
+        let mut fresh = Task::new("@bob:example.org".to_owned(), 2, "Fresh".to_owned());
+        fresh.internal_logs.clear();
+        log(
+            &mut fresh,
+            "2024-05-19 12:00:00",
+            "@bob:example.org",
+            "Created task",
+        );
+        log(
+            &mut fresh,
+            "2024-05-19 18:00:00",
+            "@bob:example.org",
+            "Updated status: from 'pending' to 'done'",
+        );
+        fresh.status = TaskStatus::Done;
+        fresh.created_at = parse_log_timestamp("2024-05-19 12:00:00").unwrap();
+        fresh.completed_at = parse_log_timestamp("2024-05-19 18:00:00");
+
+        let stats = RoomStats::from_tasks_at(&[old, fresh], now);
+        assert_eq!(stats.total, 2);
+        assert_eq!(stats.done, 2);
+        assert_eq!(stats.created_recently, 1);
+        assert_eq!(stats.completed_recently, 2);
+        assert_eq!(
+            stats.most_active_user(),
+            Some((&"@bob:example.org".to_owned(), &3))
+        );
+        // (17 days + 6 hours) / 2
+        assert_eq!(stats.average_time_to_done(), Some(Duration::hours(207)));
+    }
+
+    #[test]
+    fn times_come_from_the_timestamps_not_the_history() {
+        let now = parse_log_timestamp("2024-05-20 12:00:00").unwrap();
+        let mut task = Task::new("@alice:example.org".to_owned(), 1, "Task".to_owned());
+        task.internal_logs.clear();
+        task.status = TaskStatus::Done;
+        task.created_at = parse_log_timestamp("2024-05-19 12:00:00").unwrap();
+        task.completed_at = parse_log_timestamp("2024-05-19 14:00:00");
+
+        let stats = RoomStats::from_tasks_at(&[task.clone()], now);
+        assert_eq!(stats.created_recently, 1);
+        assert_eq!(stats.completed_recently, 1);
+        assert_eq!(stats.average_time_to_done(), Some(Duration::hours(2)));
+
+        // Closed tasks have a completion time but were never done
+        task.status = TaskStatus::Closed;
+        let stats = RoomStats::from_tasks_at(&[task], now);
+        assert_eq!(stats.completed_recently, 0);
+        assert_eq!(stats.average_time_to_done(), None);
+    }
+
+    #[test]
+    fn reopened_task_has_no_time_to_done() {
+        let mut task = Task::new("@alice:example.org".to_owned(), 1, "Task".to_owned());
+        task.set_status("@alice:example.org".to_owned(), TaskStatus::Done, None);
+        task.set_status("@alice:example.org".to_owned(), TaskStatus::Pending, None);
+        let stats = RoomStats::from_tasks(&[task]);
+        assert_eq!(stats.completed_recently, 0);
+        assert_eq!(stats.average_time_to_done(), None);
+        assert_eq!(format_duration(Duration::minutes(1500)), "1d 1h");
+    }
+}
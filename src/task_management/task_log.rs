@@ -0,0 +1,124 @@
+//! Comments added to a task with `!log`.
+
+use chrono::Utc;
+use serde::{Deserialize, Deserializer, Serialize};
+
+/// A log entry with who wrote it and when. Save files from before logs were
+/// attributed hold plain strings, those load without author and timestamp.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TaskLog {
+    /// UTC, in the `%Y-%m-%d %H:%M:%S` format of the task history
+    pub timestamp: Option<String>,
+    pub author: Option<String>,
+    pub text: String,
+}
+
+impl TaskLog {
+    pub fn new(author: String, text: String) -> Self {
+        Self {
+            timestamp: Some(Utc::now().format("%Y-%m-%d %H:%M:%S").to_string()),
+            author: Some(author),
+            text,
+        }
+    }
+
+    /// `<date> <time> <author>: ` to the minute, or nothing for legacy entries
+    pub fn attribution(&self) -> String {
+        let mut prefix = String::new();
+        if let Some(timestamp) = &self.timestamp {
+            prefix.push_str(timestamp.get(..16).unwrap_or(timestamp));
+            prefix.push(' ');
+        }
+        if let Some(author) = &self.author {
+            prefix.push_str(author);
+            prefix.push_str(": ");
+        }
+        prefix
+    }
+}
+
+impl std::fmt::Display for TaskLog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}{}", self.attribution(), self.text)
+    }
+}
+
+impl<'de> Deserialize<'de> for TaskLog {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Legacy(String),
+            Attributed {
+                #[serde(default)]
+                timestamp: Option<String>,
+                #[serde(default)]
+                author: Option<String>,
+                text: String,
+            },
+        }
+        Ok(match Stored::deserialize(deserializer)? {
+            Stored::Legacy(text) => TaskLog {
+                timestamp: None,
+                author: None,
+                text,
+            },
+            Stored::Attributed {
+                timestamp,
+                author,
+                text,
+            } => TaskLog {
+                timestamp,
+                author,
+                text,
+            },
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::task_management::Task;
+
+    #[test]
+    fn legacy_string_logs_still_load() {
+        let json = r#"{
+            "id": 1,
+            "title": "Old task",
+            "status": "pending",
+            "logs": ["written before attribution"],
+            "internal_logs": [["2024-05-01 10:00:00", "@alice:example.org", "Created task"]],
+            "creator": "@alice:example.org"
+        }"#;
+        let mut task: Task = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            task.logs,
+            vec![TaskLog {
+                timestamp: None,
+                author: None,
+                text: "written before attribution".to_owned(),
+            }]
+        );
+        assert_eq!(task.logs[0].to_string(), "written before attribution");
+
+        // Once saved again the file has both kinds, and reads back the same
+        task.add_log("@bob:example.org".to_owned(), "new note".to_owned());
+        let reloaded: Task = serde_json::from_str(&serde_json::to_string(&task).unwrap()).unwrap();
+        assert_eq!(reloaded.logs, task.logs);
+        assert_eq!(reloaded.logs[1].author.as_deref(), Some("@bob:example.org"));
+    }
+
+    #[test]
+    fn attributed_logs_show_author_and_minute() {
+        let log = TaskLog {
+            timestamp: Some("2024-05-01 10:22:45".to_owned()),
+            author: Some("@alice:example.org".to_owned()),
+            text: "did the thing".to_owned(),
+        };
+        assert_eq!(
+            log.to_string(),
+            "2024-05-01 10:22 @alice:example.org: did the thing"
+        );
+    }
+}
@@ -0,0 +1,242 @@
+//! Placeholder substitution for template task titles.
+//!
+//! Titles may contain `{{name}}` placeholders filled from `key=value` arguments, and
+//! date expressions `{{date}}`, `{{date+7d}}`, `{{date-2w}}` or `{{date+1m}}` computed
+//! from the room's current date. Anything not closed by `}}` is left as it is, and
+//! `\{{` writes a literal `{{`. Filled-in values are never expanded again.
+
+use chrono::{Days, Months, NaiveDate};
+use std::collections::{BTreeSet, HashMap};
+
+const DATE_VARIABLE: &str = "date";
+
+/// Parse `key=value` arguments. Keys are matched case-insensitively.
+pub fn parse_assignments<'a, I>(args: I) -> Result<HashMap<String, String>, String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut vars = HashMap::new();
+    for arg in args {
+        match arg.split_once('=') {
+            Some((key, value)) if !key.trim().is_empty() => {
+                vars.insert(key.trim().to_lowercase(), value.to_owned());
+            }
+            _ => return Err(format!("'{}' is not a key=value pair", arg)),
+        }
+    }
+    Ok(vars)
+}
+
+/// Evaluate `date`, `date+Nd`, `date-Nw`, `date+Nm`. None if `expr` is not a date expression.
+fn eval_date(expr: &str, today: NaiveDate) -> Option<Result<NaiveDate, String>> {
+    let rest = expr.strip_prefix(DATE_VARIABLE)?;
+    if rest.is_empty() {
+        return Some(Ok(today));
+    }
+    let (sign, amount) = match rest.chars().next()? {
+        '+' => (1, &rest[1..]),
+        '-' => (-1, &rest[1..]),
+        _ => return None,
+    };
+    let invalid = || format!("'{}' is not a valid date expression", expr);
+    let Some(unit) = amount.chars().last() else {
+        return Some(Err(invalid()));
+    };
+    let Ok(count) = amount[..amount.len() - unit.len_utf8()].parse::<u32>() else {
+        return Some(Err(invalid()));
+    };
+    let date = match (unit.to_ascii_lowercase(), sign) {
+        ('d', 1) => today.checked_add_days(Days::new(count.into())),
+        ('d', _) => today.checked_sub_days(Days::new(count.into())),
+        ('w', 1) => today.checked_add_days(Days::new(u64::from(count) * 7)),
+        ('w', _) => today.checked_sub_days(Days::new(u64::from(count) * 7)),
+        ('m', 1) => today.checked_add_months(Months::new(count)),
+        ('m', _) => today.checked_sub_months(Months::new(count)),
+        _ => return Some(Err(invalid())),
+    };
+    Some(date.ok_or_else(invalid))
+}
+
+/// Fill the placeholders of one title. On failure returns the missing variable
+/// names and any invalid date expressions.
+pub fn substitute(
+    title: &str,
+    vars: &HashMap<String, String>,
+    today: NaiveDate,
+) -> Result<String, Vec<String>> {
+    let mut output = String::with_capacity(title.len());
+    let mut problems = Vec::new();
+    let mut rest = title;
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            output.push_str(&rest[..start - 1]);
+            output.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        let Some(len) = rest[start + 2..].find("}}") else {
+            break;
+        };
+        output.push_str(&rest[..start]);
+        let name = rest[start + 2..start + 2 + len].trim().to_lowercase();
+        rest = &rest[start + 2 + len + 2..];
+
+        if let Some(value) = vars.get(&name) {
+            output.push_str(value);
+        } else if let Some(date) = eval_date(&name, today) {
+            match date {
+                Ok(date) => output.push_str(&date.format("%Y-%m-%d").to_string()),
+                Err(problem) => problems.push(problem),
+            }
+        } else {
+            problems.push(name);
+        }
+    }
+    output.push_str(rest);
+
+    if problems.is_empty() {
+        Ok(output)
+    } else {
+        Err(problems)
+    }
+}
+
+/// Fill every title of a template. On failure returns all problems across the
+/// titles, sorted and without duplicates.
+pub fn substitute_all(
+    titles: &[String],
+    vars: &HashMap<String, String>,
+    today: NaiveDate,
+) -> Result<Vec<String>, Vec<String>> {
+    let mut filled = Vec::with_capacity(titles.len());
+    let mut problems = BTreeSet::new();
+    for title in titles {
+        match substitute(title, vars, today) {
+            Ok(title) => filled.push(title),
+            Err(missing) => problems.extend(missing),
+        }
+    }
+    if problems.is_empty() {
+        Ok(filled)
+    } else {
+        Err(problems.into_iter().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn today() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()
+    }
+
+    fn vars(pairs: &[&str]) -> HashMap<String, String> {
+        parse_assignments(pairs.iter().copied()).unwrap()
+    }
+
+    #[test]
+    fn every_variable_is_filled_in() {
+        let vars = vars(&["Version=1.4", "owner=bob"]);
+        for (title, expected) in [
+            ("Release {{version}}", "Release 1.4"),
+            ("Release {{ VERSION }} by {{owner}}", "Release 1.4 by bob"),
+            ("Due {{date}}", "Due 2024-01-31"),
+            ("Due {{date+7d}}", "Due 2024-02-07"),
+            ("Due {{date-2d}}", "Due 2024-01-29"),
+            ("Due {{date+2w}}", "Due 2024-02-14"),
+            ("Due {{date-1w}}", "Due 2024-01-24"),
+            // Month arithmetic clamps to the end of shorter months
+            ("Due {{date+1m}}", "Due 2024-02-29"),
+            ("Due {{date-2M}}", "Due 2023-11-30"),
+            ("No placeholders", "No placeholders"),
+        ] {
+            assert_eq!(substitute(title, &vars, today()), Ok(expected.to_owned()));
+        }
+    }
+
+    #[test]
+    fn a_variable_named_like_a_date_wins() {
+        let vars = vars(&["date=tomorrow"]);
+        assert_eq!(
+            substitute("Ship {{date}}", &vars, today()),
+            Ok("Ship tomorrow".to_owned())
+        );
+    }
+
+    #[test]
+    fn unknown_variables_and_bad_dates_are_reported() {
+        let vars = vars(&["version=1.4"]);
+        assert_eq!(
+            substitute(
+                "{{version}} for {{Customer}} on {{date+7y}}",
+                &vars,
+                today()
+            ),
+            Err(vec![
+                "customer".to_owned(),
+                "'date+7y' is not a valid date expression".to_owned()
+            ])
+        );
+        for expr in ["date+", "date+d", "date-x7d"] {
+            assert_eq!(
+                substitute(&format!("{{{{{}}}}}", expr), &vars, today()),
+                Err(vec![format!("'{}' is not a valid date expression", expr)])
+            );
+        }
+        // A suffix that isn't +/- makes it an ordinary (missing) variable
+        assert_eq!(
+            substitute("{{dates}}", &vars, today()),
+            Err(vec!["dates".to_owned()])
+        );
+    }
+
+    #[test]
+    fn problems_across_titles_are_sorted_and_deduplicated() {
+        let titles = [
+            "{{b}} {{a}}".to_owned(),
+            "{{a}}".to_owned(),
+            "Fine".to_owned(),
+        ];
+        assert_eq!(
+            substitute_all(&titles, &HashMap::new(), today()),
+            Err(vec!["a".to_owned(), "b".to_owned()])
+        );
+        assert_eq!(
+            substitute_all(&titles, &vars(&["a=1", "b=2"]), today()),
+            Ok(vec!["2 1".to_owned(), "1".to_owned(), "Fine".to_owned()])
+        );
+    }
+
+    #[test]
+    fn escaped_and_unclosed_braces_stay_literal() {
+        let vars = vars(&["version=1.4", "note={{date}}"]);
+        for (title, expected) in [
+            ("\\{{version}} is {{version}}", "{{version}} is 1.4"),
+            ("Release {{version", "Release {{version"),
+            ("Release {version}", "Release {version}"),
+            ("Release }}version{{", "Release }}version{{"),
+            // Values are inserted as they are, not expanded again
+            ("Note: {{note}}", "Note: {{date}}"),
+        ] {
+            assert_eq!(substitute(title, &vars, today()), Ok(expected.to_owned()));
+        }
+    }
+
+    #[test]
+    fn assignments_need_a_key() {
+        assert_eq!(
+            vars(&[" Version =1.4", "empty="]),
+            HashMap::from([
+                ("version".to_owned(), "1.4".to_owned()),
+                ("empty".to_owned(), String::new())
+            ])
+        );
+        for arg in ["version", "=1.4"] {
+            assert_eq!(
+                parse_assignments([arg]),
+                Err(format!("'{}' is not a key=value pair", arg))
+            );
+        }
+    }
+}
@@ -0,0 +1,112 @@
+//! Durations for `!spent`: parsing `1h30m`-style input and showing totals.
+
+use std::time::Duration;
+
+/// Longest time a single `!spent` entry may log
+pub const MAX_TIME_ENTRY: Duration = Duration::from_secs(365 * 24 * 3600);
+
+/// Parse durations such as `90m`, `1h30m`, `1h 30m` or `2d`
+pub fn parse_time_spent(input: &str) -> Result<Duration, String> {
+    let compact: String = input.chars().filter(|c| !c.is_whitespace()).collect();
+    if compact.is_empty() {
+        return Err("No time given.".to_owned());
+    }
+    if compact.starts_with('-') {
+        return Err("Time spent can't be negative.".to_owned());
+    }
+
+    let mut total: u64 = 0;
+    let mut number = String::new();
+    for c in compact.chars() {
+        if c.is_ascii_digit() {
+            number.push(c);
+            continue;
+        }
+        let unit_secs = match c.to_ascii_lowercase() {
+            'd' => 24 * 3600,
+            'h' => 3600,
+            'm' => 60,
+            _ => return Err(format!("Unknown unit '{}', use d, h or m.", c)),
+        };
+        let value: u64 = number
+            .parse()
+            .map_err(|_| format!("Expected a number before '{}'.", c))?;
+        number.clear();
+        total = value
+            .checked_mul(unit_secs)
+            .and_then(|secs| total.checked_add(secs))
+            .ok_or_else(|| "That is more than a year.".to_owned())?;
+    }
+    if !number.is_empty() {
+        return Err(format!("Missing unit after {}, e.g. {}m.", number, number));
+    }
+
+    let duration = Duration::from_secs(total);
+    if duration.is_zero() {
+        Err("Time spent must be more than zero.".to_owned())
+    } else if duration > MAX_TIME_ENTRY {
+        Err("That is more than a year.".to_owned())
+    } else {
+        Ok(duration)
+    }
+}
+
+/// `2d 3h 15m`, leaving out leading zero parts; `0m` for nothing
+pub fn format_time_spent(duration: Duration) -> String {
+    let minutes = duration.as_secs() / 60;
+    let (days, hours, minutes) = (minutes / 1440, minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) => format!("{}m", minutes),
+        (0, _) => format!("{}h {}m", hours, minutes),
+        _ => format!("{}d {}h {}m", days, hours, minutes),
+    }
+}
+
+/// Serde helper storing a `Duration` as whole seconds
+pub mod serde_secs {
+    use serde::{Deserialize, Deserializer, Serializer};
+    use std::time::Duration;
+
+    pub fn serialize<S: Serializer>(duration: &Duration, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(duration.as_secs())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Duration, D::Error> {
+        u64::deserialize(deserializer).map(Duration::from_secs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_human_durations() {
+        assert_eq!(parse_time_spent("90m"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_time_spent("1h30m"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_time_spent("1h 30m"), Ok(Duration::from_secs(90 * 60)));
+        assert_eq!(parse_time_spent("2d"), Ok(Duration::from_secs(2 * 86400)));
+        assert_eq!(parse_time_spent("365d"), Ok(MAX_TIME_ENTRY));
+    }
+
+    #[test]
+    fn rejects_negative_zero_and_absurd_values() {
+        assert!(parse_time_spent("-1h").is_err());
+        assert!(parse_time_spent("0m").is_err());
+        assert!(parse_time_spent("366d").is_err());
+        assert!(parse_time_spent("99999999999999999999d").is_err());
+        assert!(parse_time_spent("30").is_err());
+        assert!(parse_time_spent("2w").is_err());
+        assert!(parse_time_spent("h").is_err());
+    }
+
+    #[test]
+    fn formats_totals() {
+        assert_eq!(format_time_spent(Duration::ZERO), "0m");
+        assert_eq!(format_time_spent(Duration::from_secs(90 * 60)), "1h 30m");
+        assert_eq!(
+            format_time_spent(Duration::from_secs(2 * 86400 + 3600 + 60)),
+            "2d 1h 1m"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! `!moveto` and `!copyto`: hand a task over to another room's list.
+
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedRoomId;
+use tracing::info;
+
+use super::undo::{UndoAction, UndoEntry};
+use super::{TaskEvent, TodoList};
+
+impl TodoList {
+    /// Append task `task_id` to `target`'s list under a new ID, with its history
+    /// and a note where it came from. A move also removes it from `source`.
+    /// The room checks (bot joined, sender a member) are up to the caller.
+    pub async fn transfer_task(
+        &self,
+        source: &OwnedRoomId,
+        target: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        copy: bool,
+    ) -> Result<()> {
+        let (verb, event) = if copy {
+            ("copied", TaskEvent::CopiedFrom)
+        } else {
+            ("moved", TaskEvent::MovedFrom)
+        };
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let Some(mut task) = todo_lists
+            .get(source)
+            .and_then(|tasks| tasks.find(task_id))
+            .cloned()
+        else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            drop(todo_lists);
+            self.send_matrix_message(source, &message, None).await?;
+            return Ok(());
+        };
+        let target_list = todo_lists.entry(target.clone()).or_default();
+        if target_list.len() >= self.max_tasks_per_room {
+            let message = format!(
+                "⚠️ Error: The list in {} already holds the maximum of {} tasks.",
+                target, self.max_tasks_per_room
+            );
+            drop(todo_lists);
+            self.send_matrix_message(source, &message, None).await?;
+            return Ok(());
+        }
+
+        let previous_target = target_list.tasks.clone();
+        let new_id = target_list.allocate_id();
+        task.id = new_id;
+        // IDs only mean something in the room they were handed out in
+        task.depends_on.clear();
+        task.spun_off_from = None;
+        task.add_internal_log(
+            sender.clone(),
+            event,
+            Some(format!("task #{} in {}", task_id, source)),
+        );
+        let title = task.title.clone();
+        target_list.push(task);
+
+        let previous_source = if copy {
+            None
+        } else {
+            todo_lists.get_mut(source).map(|tasks| {
+                let previous = tasks.tasks.clone();
+                tasks.retain(|t| t.id != task_id);
+                previous
+            })
+        };
+        drop(todo_lists);
+
+        info!(source = %source, target = %target, task_id, new_id, copy, "Transferred task");
+        self.undo
+            .push(
+                target,
+                UndoEntry::new(
+                    format!("task {} {} here", new_id, verb),
+                    vec![UndoAction::RestoreList(previous_target)],
+                ),
+            )
+            .await;
+        if let Some(previous) = previous_source {
+            self.undo
+                .push(
+                    source,
+                    UndoEntry::new(
+                        format!("move of task {}", task_id),
+                        vec![UndoAction::RestoreList(previous)],
+                    ),
+                )
+                .await;
+        }
+
+        let message = format!(
+            "📤 Task #{} {} to {} as #{}: **{}**",
+            task_id, verb, target, new_id, title
+        );
+        self.send_matrix_message(source, &message, None).await?;
+        let message = format!(
+            "📥 Task #{} {} here from {} by {}: **{}**",
+            new_id, verb, source, sender, title
+        );
+        self.send_matrix_message(target, &message, None).await?;
+        // One save covers both rooms
+        self.storage.save().await?;
+        self.refresh_list_view(target).await
+    }
+}
@@ -0,0 +1,76 @@
+use matrix_sdk::ruma::OwnedRoomId;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+use super::{RoomTaskList, Task};
+
+/// Undo entries kept per room; the oldest is dropped first
+pub const MAX_UNDO_ENTRIES: usize = 10;
+
+/// One step that reverts part of a task mutation
+#[derive(Debug, Clone)]
+pub enum UndoAction {
+    /// Remove tasks the mutation created
+    RemoveTasks(Vec<usize>),
+    /// Put back copies of tasks taken before the mutation changed them
+    RestoreTasks(Vec<Task>),
+    /// Put back the whole list the mutation replaced
+    RestoreList(Vec<Task>),
+    /// Put back the room's archive as it was before the mutation
+    RestoreArchive(Vec<Task>),
+}
+
+/// Inverse of one task mutation, with a description for the `!undo` reply
+#[derive(Debug, Clone)]
+pub struct UndoEntry {
+    pub description: String,
+    pub actions: Vec<UndoAction>,
+}
+
+impl UndoEntry {
+    pub fn new(description: impl Into<String>, actions: Vec<UndoAction>) -> Self {
+        Self {
+            description: description.into(),
+            actions,
+        }
+    }
+
+    /// Revert the mutation. The ID counter is left alone so undone IDs are never reused.
+    pub fn apply(self, list: &mut RoomTaskList, archive: &mut Vec<Task>) {
+        for action in self.actions {
+            match action {
+                UndoAction::RemoveTasks(ids) => list.retain(|t| !ids.contains(&t.id)),
+                UndoAction::RestoreTasks(tasks) => {
+                    for task in tasks {
+                        match list.find_mut(task.id) {
+                            Some(current) => *current = task,
+                            None => list.push(task),
+                        }
+                    }
+                }
+                UndoAction::RestoreList(tasks) => list.tasks = tasks,
+                UndoAction::RestoreArchive(tasks) => *archive = tasks,
+            }
+        }
+    }
+}
+
+/// In-memory undo stacks per room. Not persisted, a restart starts with empty history.
+#[derive(Debug, Clone, Default)]
+pub struct UndoHistory(Arc<Mutex<HashMap<OwnedRoomId, Vec<UndoEntry>>>>);
+
+impl UndoHistory {
+    pub async fn push(&self, room_id: &OwnedRoomId, entry: UndoEntry) {
+        let mut stacks = self.0.lock().await;
+        let stack = stacks.entry(room_id.clone()).or_default();
+        stack.push(entry);
+        if stack.len() > MAX_UNDO_ENTRIES {
+            stack.remove(0);
+        }
+    }
+
+    pub async fn pop(&self, room_id: &OwnedRoomId) -> Option<UndoEntry> {
+        self.0.lock().await.get_mut(room_id)?.pop()
+    }
+}
@@ -0,0 +1,146 @@
+//! `!watch`: users who get mentioned whenever a task changes.
+
+use anyhow::Result;
+use matrix_sdk::ruma::OwnedRoomId;
+
+use super::undo::{UndoAction, UndoEntry};
+use super::{Task, TodoList};
+use crate::messaging::render::escape_html;
+
+impl Task {
+    /// Add a watcher, false if they already watch the task
+    pub fn watch(&mut self, user: &str) -> bool {
+        if self.watchers.iter().any(|w| w == user) {
+            return false;
+        }
+        self.watchers.push(user.to_owned());
+        true
+    }
+
+    /// Remove a watcher, false if they weren't watching
+    pub fn unwatch(&mut self, user: &str) -> bool {
+        let before = self.watchers.len();
+        self.watchers.retain(|w| w != user);
+        self.watchers.len() != before
+    }
+}
+
+/// Append a line mentioning `watchers` to a confirmation, as Matrix pills in the
+/// HTML so their clients notify them. `actor` made the change and is left out.
+pub fn mention_watchers<'a>(
+    watchers: impl IntoIterator<Item = &'a String>,
+    actor: &str,
+    message: &mut String,
+    html_message: &mut String,
+) {
+    let mut users: Vec<&String> = Vec::new();
+    for user in watchers {
+        if user != actor && !users.contains(&user) {
+            users.push(user);
+        }
+    }
+    if users.is_empty() {
+        return;
+    }
+    let plain: Vec<&str> = users.iter().map(|u| u.as_str()).collect();
+    let pills: Vec<String> = users
+        .iter()
+        .map(|u| {
+            let user = escape_html(u);
+            format!("<a href=\"https://matrix.to/#/{}\">{}</a>", user, user)
+        })
+        .collect();
+    message.push_str(&format!("\n👀 {}", plain.join(", ")));
+    html_message.push_str(&format!("<br>👀 {}", pills.join(", ")));
+}
+
+impl TodoList {
+    /// `!watch <id>` and `!unwatch <id>` for the sender
+    pub async fn watch_task(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: String,
+        task_id: usize,
+        watch: bool,
+    ) -> Result<()> {
+        let mut todo_lists = self.storage.todo_lists.lock().await;
+        let Some(task) = todo_lists
+            .get_mut(room_id)
+            .and_then(|tasks| tasks.find_mut(task_id))
+        else {
+            let message = format!(
+                "❌ Error: Invalid task ID: {}. Use `!list` to see valid IDs.",
+                task_id
+            );
+            drop(todo_lists);
+            self.send_matrix_message(room_id, &message, None).await?;
+            return Ok(());
+        };
+        let previous = task.clone();
+        let changed = if watch {
+            task.watch(&sender)
+        } else {
+            task.unwatch(&sender)
+        };
+        drop(todo_lists);
+        let message = match (watch, changed) {
+            (true, true) => format!("👀 You're now watching task #{}", task_id),
+            (true, false) => format!("ℹ️ Info: You already watch task #{}.", task_id),
+            (false, true) => format!("🔕 You stopped watching task #{}", task_id),
+            (false, false) => format!("ℹ️ Info: You weren't watching task #{}.", task_id),
+        };
+        if changed {
+            let verb = if watch { "watch" } else { "unwatch" };
+            self.undo
+                .push(
+                    room_id,
+                    UndoEntry::new(
+                        format!("{} of task {}", verb, task_id),
+                        vec![UndoAction::RestoreTasks(vec![previous])],
+                    ),
+                )
+                .await;
+        }
+        self.send_matrix_message(room_id, &message, None).await?;
+        if changed {
+            self.storage.save_room(room_id).await?;
+        }
+        Ok(())
+    }
+
+    /// `!watch auto on|off`: whether the sender starts watching the tasks they
+    /// create or get assigned in this room
+    pub async fn auto_watch_command(
+        &self,
+        room_id: &OwnedRoomId,
+        sender: &str,
+        enabled: bool,
+    ) -> Result<()> {
+        self.storage
+            .update_room_settings(room_id, |settings| {
+                settings.auto_watch_opt_out.retain(|user| user != sender);
+                if !enabled {
+                    settings.auto_watch_opt_out.push(sender.to_owned());
+                }
+            })
+            .await;
+        let message = if enabled {
+            "👀 You'll watch the tasks you create or get assigned in this room."
+        } else {
+            "🔕 You won't automatically watch the tasks you create or get assigned in this room."
+        };
+        self.send_matrix_message(room_id, message, None).await?;
+        self.storage.save_room(room_id).await
+    }
+
+    /// Whether `user` should start watching tasks they create or get assigned
+    pub(super) async fn auto_watches(&self, room_id: &OwnedRoomId, user: &str) -> bool {
+        !self
+            .storage
+            .room_settings(room_id)
+            .await
+            .auto_watch_opt_out
+            .iter()
+            .any(|u| u == user)
+    }
+}